@@ -0,0 +1,42 @@
+//! Selects the platform, from the target vendor or a `platform-*` feature, and sets the
+//! `rrt0_platform` cfg that the crate is conditionally compiled on. The features serve targets
+//! whose vendor is not one of the platform names, like custom targets for new hardware, and are
+//! ignored on targets with an OS. Also puts the GBA linker script on the link search path.
+
+use std::env;
+
+/// Platform names, with the target vendor and feature selecting them
+const PLATFORMS: [(&str, &str, &str); 6] = [
+    ("n64", "nintendo64", "PLATFORM_N64"),
+    ("gba", "gba", "PLATFORM_GBA"),
+    ("n3ds", "nintendo3ds", "PLATFORM_N3DS"),
+    ("ps2", "ps2", "PLATFORM_PS2"),
+    ("dreamcast", "dreamcast", "PLATFORM_DREAMCAST"),
+    ("saturn", "saturn", "PLATFORM_SATURN"),
+];
+
+fn main() {
+    let vendor = env::var("CARGO_CFG_TARGET_VENDOR").unwrap_or_default();
+    let bare_metal = env::var("CARGO_CFG_TARGET_OS").map_or(true, |os| os == "none");
+    let selected: Vec<_> = PLATFORMS
+        .iter()
+        .filter(|(_, platform_vendor, feature)| {
+            vendor == *platform_vendor
+                || bare_metal && env::var_os(format!("CARGO_FEATURE_{}", feature)).is_some()
+        })
+        .collect();
+
+    match selected.as_slice() {
+        [] => (),
+        [(name, _, _)] => {
+            println!("cargo:rustc-cfg=rrt0_platform=\"{}\"", name);
+
+            // Programs link with the GBA linker script by name
+            if *name == "gba" {
+                let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+                println!("cargo:rustc-link-search={}/src/platforms/gba", dir);
+            }
+        }
+        _ => panic!("More than one platform selected"),
+    }
+}
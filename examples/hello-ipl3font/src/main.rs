@@ -2,11 +2,10 @@
 #![no_main]
 #![no_std]
 
-use n64lib::{ipl3font, vi};
+use n64lib::{color::Color, ipl3font, vi};
 
-// Colors are 5:5:5:1 RGB with a 16-bit color depth.
-#[allow(clippy::unusual_byte_groupings)]
-const WHITE: u16 = 0b11111_11111_11111_1;
+// The frame buffer uses 5:5:5:1 RGBA with a 16-bit color depth.
+const WHITE: u16 = Color::WHITE.to_rgba5551();
 
 #[no_mangle]
 fn main() {
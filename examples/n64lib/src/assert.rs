@@ -0,0 +1,65 @@
+//! Visible assertions.
+//!
+//! Without a development cartridge there is nowhere to print a failed assertion, so the console
+//! just stops. [`assert_screen!`](crate::assert_screen) paints the failure on screen before
+//! panicking instead.
+
+use crate::color::Color;
+use crate::gfx;
+use crate::ipl3font::{self, Rect};
+use crate::vi;
+use core::fmt::{self, Write};
+use rrt0::fmt::BufWriter;
+
+/// Margin around the failure message (in pixels)
+const MARGIN: usize = 16;
+
+/// Capacity of the failure message (in bytes)
+const MESSAGE_SIZE: usize = 256;
+
+/// Assert that a condition holds. On failure, the message is drawn on screen in white on red, and
+/// the program panics.
+///
+/// e.g. `assert_screen!(lives <= MAX_LIVES, "too many lives: {}", lives);`
+#[macro_export]
+macro_rules! assert_screen {
+    ($cond:expr $(,)?) => {
+        $crate::assert_screen!($cond, "{}", ::core::stringify!($cond))
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::assert::fail(
+                ::core::file!(),
+                ::core::line!(),
+                ::core::format_args!($($arg)+),
+            );
+        }
+    };
+}
+
+/// Draw an assertion failure on screen, display it, and panic.
+#[doc(hidden)]
+pub fn fail(file: &str, line: u32, message: fmt::Arguments<'_>) -> ! {
+    let mut buffer = [0; MESSAGE_SIZE];
+    let mut writer = BufWriter::new(&mut buffer);
+    let _ = write!(writer, "ASSERTION FAILED\n{}:{}\n{}", file, line, message);
+    let len = writer.as_str().len();
+
+    // The embedded font only has uppercase letters
+    let text = &mut buffer[..len];
+    text.make_ascii_uppercase();
+    let text = core::str::from_utf8(text).unwrap_or_default();
+
+    let mut surface = gfx::back_buffer();
+    surface.clear(Color::RED.to_rgba5551());
+    let rect = Rect {
+        x: MARGIN,
+        y: MARGIN,
+        width: vi::WIDTH - MARGIN * 2,
+        height: vi::HEIGHT - MARGIN * 2,
+    };
+    ipl3font::draw_str_wrapped(rect, Color::WHITE.to_rgba5551(), text);
+    vi::swap_buffer();
+
+    panic!("Assertion failed at {}:{}: {}", file, line, message);
+}
@@ -0,0 +1,69 @@
+//! Game loop backend.
+//!
+//! Runs the [game loop](rrt0::app) on the double buffered frame buffers of [`vi`](crate::vi), with
+//! the controller in port 1:
+//!
+//! ```text
+//! vi::init();
+//! rrt0::app::run(Game::new(), &mut N64::new());
+//! ```
+
+use crate::{controller, vi};
+use core::slice;
+use core::time::Duration;
+use rrt0::app::{Backend, Frame};
+use rrt0::time::Instant;
+
+/// The VI and the controller in port 1, as a [`Backend`].
+#[derive(Debug, Default)]
+pub struct N64 {
+    /// Time of the previous vertical blank
+    vsync: Option<Instant>,
+}
+
+impl N64 {
+    /// The VI must be set up with [`vi::init`] first.
+    pub fn new() -> Self {
+        Self { vsync: None }
+    }
+}
+
+impl Backend for N64 {
+    type Pixel = u16;
+
+    fn refresh_period(&self) -> Duration {
+        Duration::from_nanos(1_000_000_000 / vi::get_refresh_rate() as u64)
+    }
+
+    fn wait_vsync(&mut self) -> u32 {
+        // Leave the current vertical blank, if in one, then wait for the next
+        while vi::in_vblank() {}
+        vi::wait_for_ready();
+
+        // Vertical blanks since the previous one, counted from the time between them
+        let now = Instant::now();
+        let period = self.refresh_period().as_nanos();
+        let vsyncs = self.vsync.map_or(1, |vsync| {
+            (now.duration_since(vsync).as_nanos() + period / 2) / period
+        });
+        self.vsync = Some(now);
+
+        vsyncs.max(1) as u32
+    }
+
+    fn buttons(&mut self) -> u16 {
+        controller::read().map_or(0, |state| state.buttons)
+    }
+
+    fn frame(&mut self) -> Frame<'_, u16> {
+        // The back buffer is not shown, so nothing else uses it
+        let pixels =
+            unsafe { slice::from_raw_parts_mut(vi::next_buffer(), vi::WIDTH * vi::HEIGHT) };
+
+        Frame::new(pixels, vi::WIDTH, vi::HEIGHT)
+    }
+
+    fn swap(&mut self) {
+        vi::swap_buffer();
+    }
+}
@@ -0,0 +1,128 @@
+//! Color conversion and blending.
+//!
+//! [`Color`] holds 8-bit RGBA components and converts to and from the packed pixel formats used
+//! by the VI and [`gfx`](crate::gfx): 16-bit RGBA 5:5:5:1 and 32-bit RGBA 8:8:8:8.
+
+/// An RGBA color with 8 bits per component.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const TRANSPARENT: Self = Self::rgba(0x00, 0x00, 0x00, 0x00);
+    pub const BLACK: Self = Self::rgb(0x00, 0x00, 0x00);
+    pub const WHITE: Self = Self::rgb(0xFF, 0xFF, 0xFF);
+    pub const GRAY: Self = Self::rgb(0x80, 0x80, 0x80);
+    pub const RED: Self = Self::rgb(0xFF, 0x00, 0x00);
+    pub const GREEN: Self = Self::rgb(0x00, 0xFF, 0x00);
+    pub const BLUE: Self = Self::rgb(0x00, 0x00, 0xFF);
+    pub const YELLOW: Self = Self::rgb(0xFF, 0xFF, 0x00);
+    pub const CYAN: Self = Self::rgb(0x00, 0xFF, 0xFF);
+    pub const MAGENTA: Self = Self::rgb(0xFF, 0x00, 0xFF);
+
+    /// Create an opaque color.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 0xFF)
+    }
+
+    /// Create a color with alpha.
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Unpack a 16-bit RGBA 5:5:5:1 color.
+    pub const fn from_rgba5551(color: u16) -> Self {
+        const fn expand(c: u16) -> u8 {
+            let c = (c & 0x1F) as u8;
+            c << 3 | c >> 2
+        }
+
+        let a = if color & 0x0001 != 0 { 0xFF } else { 0x00 };
+
+        Self::rgba(
+            expand(color >> 11),
+            expand(color >> 6),
+            expand(color >> 1),
+            a,
+        )
+    }
+
+    /// Unpack a 32-bit RGBA 8:8:8:8 color.
+    pub const fn from_rgba8888(color: u32) -> Self {
+        let [r, g, b, a] = color.to_be_bytes();
+
+        Self::rgba(r, g, b, a)
+    }
+
+    /// Pack into a 16-bit RGBA 5:5:5:1 color. Alpha is rounded to the nearest bit.
+    pub const fn to_rgba5551(self) -> u16 {
+        let r = (self.r >> 3) as u16;
+        let g = (self.g >> 3) as u16;
+        let b = (self.b >> 3) as u16;
+        let a = (self.a >= 0x80) as u16;
+
+        r << 11 | g << 6 | b << 1 | a
+    }
+
+    /// Pack into a 32-bit RGBA 8:8:8:8 color.
+    pub const fn to_rgba8888(self) -> u32 {
+        u32::from_be_bytes([self.r, self.g, self.b, self.a])
+    }
+
+    /// Replace the alpha component.
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self::rgba(self.r, self.g, self.b, a)
+    }
+
+    /// Alpha blend this color over `dst` ("source over"). The result is opaque if `dst` is.
+    pub const fn blend_over(self, dst: Self) -> Self {
+        const fn mix(s: u8, d: u8, a: u8) -> u8 {
+            let (s, d, a) = (s as u16, d as u16, a as u16);
+            ((s * a + d * (255 - a) + 127) / 255) as u8
+        }
+
+        let a = self.a;
+        let out_a = a as u16 + (dst.a as u16 * (255 - a as u16) + 127) / 255;
+
+        Self::rgba(
+            mix(self.r, dst.r, a),
+            mix(self.g, dst.g, a),
+            mix(self.b, dst.b, a),
+            out_a as u8,
+        )
+    }
+
+    /// Linear interpolation between two colors, where `t` ranges from 0 (`self`) to 255 (`to`).
+    pub const fn lerp(self, to: Self, t: u8) -> Self {
+        const fn mix(a: u8, b: u8, t: u8) -> u8 {
+            let (a, b, t) = (a as i32, b as i32, t as i32);
+            (a + ((b - a) * t + 127 * (b - a).signum()) / 255) as u8
+        }
+
+        Self::rgba(
+            mix(self.r, to.r, t),
+            mix(self.g, to.g, t),
+            mix(self.b, to.b, t),
+            mix(self.a, to.a, t),
+        )
+    }
+}
+
+/// Convert a 16-bit RGBA 5:5:5:1 color to 32-bit RGBA 8:8:8:8.
+pub const fn rgba5551_to_rgba8888(color: u16) -> u32 {
+    Color::from_rgba5551(color).to_rgba8888()
+}
+
+/// Convert a 32-bit RGBA 8:8:8:8 color to 16-bit RGBA 5:5:5:1.
+pub const fn rgba8888_to_rgba5551(color: u32) -> u16 {
+    Color::from_rgba8888(color).to_rgba5551()
+}
+
+/// Pack two 16-bit pixels into one 32-bit word, e.g. to fill a frame buffer two pixels at a time.
+pub const fn pack_rgba5551_pair(left: u16, right: u16) -> u32 {
+    (left as u32) << 16 | right as u32
+}
@@ -0,0 +1,76 @@
+//! Controllers.
+//!
+//! Reads the controller in port 1 through the PIF, with SI DMA. Buttons are the
+//! [`BUTTON_*`](rrt0::app::BUTTON_A) bits of the game loop.
+
+use rrt0::mem::{map, DmaBuffer};
+use rrt0::mmio::SI;
+
+/// PIF command block reading the controller in port 1: a padding byte, the lengths to send and
+/// receive, the command, space for the response, the end marker, and the PIF control byte
+/// starting the transfer.
+const READ_BLOCK: [u8; 64] = {
+    let mut block = [0; 64];
+    let command = [0xFF, 0x01, 0x04, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE];
+    let mut i = 0;
+    while i < command.len() {
+        block[i] = command[i];
+        i += 1;
+    }
+    block[63] = 0x01;
+    block
+};
+
+/// Error bits set by the PIF in the receive length, e.g. when no controller is plugged in
+const RX_ERROR: u8 = 0xC0;
+
+/// Button bits that are not buttons: the reset flag and an unused bit
+const NOT_BUTTONS: u16 = 0x00C0;
+
+/// State of a controller.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct State {
+    /// Buttons held down
+    pub buttons: u16,
+    /// Analog stick position, right and up are positive
+    pub x: i8,
+    pub y: i8,
+}
+
+/// Read the controller in port 1. Returns `None` if none is plugged in.
+pub fn read() -> Option<State> {
+    let mut block = DmaBuffer::new(READ_BLOCK);
+    let addr = block.writeback_invalidate();
+
+    si_wait();
+    SI.dram_addr.write(addr);
+    SI.pif_ad_wr64b.write(map::PIF_RAM as u32);
+    si_wait();
+    SI.dram_addr.write(addr);
+    SI.pif_ad_rd64b.write(map::PIF_RAM as u32);
+    si_wait();
+
+    // Any bytes are a valid array of bytes
+    unsafe { block.invalidate() };
+
+    if block[2] & RX_ERROR != 0 {
+        return None;
+    }
+    // The analog stick is noisy in its low bits
+    rrt0::entropy::add_sample(u32::from_be_bytes([block[4], block[5], block[6], block[7]]));
+
+    Some(State {
+        buttons: u16::from_be_bytes([block[4], block[5]]) & !NOT_BUTTONS,
+        x: block[6] as i8,
+        y: block[7] as i8,
+    })
+}
+
+fn si_wait() {
+    loop {
+        let status = SI.status.read();
+        if !status.dma_busy() && !status.io_busy() {
+            break;
+        }
+    }
+}
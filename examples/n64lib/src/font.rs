@@ -0,0 +1,415 @@
+//! Bitmap font rendering.
+//!
+//! Provides the [`Font`] trait and text drawing shared by all fonts, along with [`BitmapFont`] for
+//! rendering user-provided fonts. See [`ipl3font`](crate::ipl3font) for the font embedded in the
+//! IPL3 bootcode. Text is laid out with [`rrt0::text`], like the text consoles of other platforms.
+
+use crate::vi;
+use rrt0::io;
+use rrt0::text::{Layout, Metrics};
+
+pub use crate::gfx::Rect;
+pub use rrt0::text::{Align, Extent};
+
+/// Default line spacing (in pixels)
+pub const LEADING: usize = 2;
+
+/// A run of text drawn in a single color
+#[derive(Clone, Copy, Debug)]
+pub struct Span<'a> {
+    pub color: u16,
+    pub text: &'a str,
+}
+
+/// A font that can be drawn with the text layout functions in this module.
+pub trait Font {
+    /// Glyph height (in pixels)
+    fn height(&self) -> usize;
+
+    /// Horizontal space between adjacent glyphs (in pixels)
+    fn spacing(&self) -> usize;
+
+    /// Vertical space between adjacent lines (in pixels)
+    fn leading(&self) -> usize {
+        LEADING
+    }
+
+    /// Width of the glyph for a character (in pixels)
+    fn glyph_width(&self, ch: char) -> usize;
+
+    /// Draw the glyph for a character into the back buffer with its top-left corner at the given
+    /// position. Pixels outside of the frame buffer are clipped.
+    ///
+    /// Characters that the font does not cover are drawn with a replacement glyph.
+    fn draw_glyph(&self, x: usize, y: usize, color: u16, ch: char);
+}
+
+/// Draw a string with its top-left corner at the given position.
+/// Newlines start a new line at the original `x` position. Text is not wrapped.
+///
+/// Returns the extent of the drawn text.
+pub fn draw_str_at<F: Font + ?Sized>(
+    font: &F,
+    x: usize,
+    y: usize,
+    color: u16,
+    string: &str,
+) -> Extent {
+    let mut layout = layout(font, None);
+    layout.push(string, |dx, dy, ch| {
+        font.draw_glyph(x + dx, y + dy, color, ch)
+    });
+
+    layout.extent()
+}
+
+/// Draw a string centered on the frame buffer.
+pub fn draw_str_centered<F: Font + ?Sized>(font: &F, color: u16, string: &str) -> Extent {
+    let extent = measure_str(font, string);
+    let x = vi::WIDTH.saturating_sub(extent.width) / 2;
+    let y = vi::HEIGHT.saturating_sub(extent.height) / 2;
+
+    draw_str_at(font, x, y, color, string)
+}
+
+/// Draw a string word wrapped to fit within a rectangle.
+/// Lines that do not fit within the rectangle's height are not drawn.
+///
+/// Returns the extent of the laid out text, which may be taller than the rectangle when the
+/// text overflows it.
+pub fn draw_str_wrapped<F: Font + ?Sized>(
+    font: &F,
+    rect: Rect,
+    color: u16,
+    string: &str,
+) -> Extent {
+    let mut layout = layout(font, Some(rect.width));
+    layout.push(string, |dx, dy, ch| {
+        if dy + font.height() <= rect.height {
+            font.draw_glyph(rect.x + dx, rect.y + dy, color, ch);
+        }
+    });
+
+    layout.extent()
+}
+
+/// Draw a string word wrapped to fit within a rectangle, with each line aligned within its width.
+/// Lines that do not fit within the rectangle's height are not drawn.
+///
+/// Returns the extent of the laid out text, like [`draw_str_wrapped`].
+pub fn draw_str_aligned<F: Font + ?Sized>(
+    font: &F,
+    rect: Rect,
+    align: Align,
+    color: u16,
+    string: &str,
+) -> Extent {
+    // Each line is measured as it is laid out, then drawn unwrapped at its offset, so its glyphs
+    // land at the same positions `Layout` computed
+    let mut layout = layout(font, Some(rect.width));
+    layout.lines(string, |line| {
+        if line.y + font.height() <= rect.height {
+            let x = rect.x + align.offset(rect.width, line.width) + line.x;
+            draw_str_at(font, x, rect.y + line.y, color, line.text);
+        }
+    });
+
+    layout.extent()
+}
+
+/// Draw a sequence of colored spans with its top-left corner at the given position.
+/// Each span continues where the previous one ended.
+///
+/// Returns the extent of the drawn text.
+pub fn draw_spans_at<F: Font + ?Sized>(font: &F, x: usize, y: usize, spans: &[Span<'_>]) -> Extent {
+    let mut layout = layout(font, None);
+    for span in spans {
+        layout.push(span.text, |dx, dy, ch| {
+            font.draw_glyph(x + dx, y + dy, span.color, ch)
+        });
+    }
+
+    layout.extent()
+}
+
+/// Draw a sequence of colored spans word wrapped to fit within a rectangle.
+/// Span boundaries are treated as possible line breaks.
+///
+/// Returns the extent of the laid out text, like [`draw_str_wrapped`].
+pub fn draw_spans_wrapped<F: Font + ?Sized>(font: &F, rect: Rect, spans: &[Span<'_>]) -> Extent {
+    let mut layout = layout(font, Some(rect.width));
+    for span in spans {
+        layout.push(span.text, |dx, dy, ch| {
+            if dy + font.height() <= rect.height {
+                font.draw_glyph(rect.x + dx, rect.y + dy, span.color, ch);
+            }
+        });
+    }
+
+    layout.extent()
+}
+
+/// Measure the extent of a string without drawing it.
+pub fn measure_str<F: Font + ?Sized>(font: &F, string: &str) -> Extent {
+    let mut layout = layout(font, None);
+    layout.push(string, |_, _, _| ());
+
+    layout.extent()
+}
+
+/// Measure the extent of a string word wrapped to the given width, without drawing it.
+pub fn measure_str_wrapped<F: Font + ?Sized>(font: &F, width: usize, string: &str) -> Extent {
+    let mut layout = layout(font, Some(width));
+    layout.push(string, |_, _, _| ());
+
+    layout.extent()
+}
+
+/// Measure the extent of a sequence of spans without drawing them.
+pub fn measure_spans<F: Font + ?Sized>(font: &F, spans: &[Span<'_>]) -> Extent {
+    let mut layout = layout(font, None);
+    for span in spans {
+        layout.push(span.text, |_, _, _| ());
+    }
+
+    layout.extent()
+}
+
+/// The metrics of a font, for laying out text with it.
+struct FontMetrics<'a, F: ?Sized>(&'a F);
+
+impl<F: Font + ?Sized> Metrics for FontMetrics<'_, F> {
+    fn height(&self) -> usize {
+        self.0.height()
+    }
+
+    fn spacing(&self) -> usize {
+        self.0.spacing()
+    }
+
+    fn leading(&self) -> usize {
+        self.0.leading()
+    }
+
+    fn glyph_width(&self, ch: char) -> usize {
+        self.0.glyph_width(ch)
+    }
+}
+
+/// Start laying out text in a font, optionally word wrapped to a width.
+fn layout<F: Font + ?Sized>(font: &F, max_width: Option<usize>) -> Layout<FontMetrics<'_, F>> {
+    Layout::new(FontMetrics(font), max_width)
+}
+
+/// Font header magic number
+const MAGIC: &[u8; 4] = b"BFNT";
+
+/// Font header size (in bytes)
+const HEADER_SIZE: usize = 9;
+
+/// Character range table entry size (in bytes)
+const RANGE_SIZE: usize = 6;
+
+/// Header flag: the range table is followed by a table of glyph widths
+const FLAG_VARIABLE_WIDTH: u8 = 0x01;
+
+/// Number of glyphs in a range table
+fn glyph_count(ranges: &[u8]) -> usize {
+    ranges
+        .chunks_exact(RANGE_SIZE)
+        .map(|range| u16::from_be_bytes([range[4], range[5]]) as usize)
+        .sum()
+}
+
+/// A 1-bit-per-pixel bitmap font in a simple packed format.
+///
+/// The format is a 9-byte header followed by a table of character ranges, an optional width
+/// table, and the glyph bitmaps. Multi-byte values are big-endian.
+///
+/// | Offset | Size       | Description                                                   |
+/// |--------|------------|---------------------------------------------------------------|
+/// | 0      | 4          | Magic number `BFNT`                                           |
+/// | 4      | 1          | Cell width (pixels)                                           |
+/// | 5      | 1          | Cell height (pixels)                                          |
+/// | 6      | 1          | Flags (bit 0: variable width)                                 |
+/// | 7      | 1          | Spacing between glyphs (pixels)                               |
+/// | 8      | 1          | Range count                                                   |
+/// | 9      | 6 * ranges | Character ranges: first code point (4 bytes), count (2 bytes) |
+/// | ...    | glyphs     | Glyph widths (pixels), only present for variable width fonts  |
+/// | ...    | ...        | Glyph bitmaps                                                 |
+///
+/// Glyphs are stored in range table order, so a font can cover e.g. ASCII, Latin-1 and Kana
+/// without storing the code points in between. Each glyph bitmap is `cell height` rows of
+/// `ceil(cell width / 8)` bytes, with the most significant bit of each row being the leftmost
+/// pixel. Glyphs in variable width fonts are left aligned in their cells.
+///
+/// Characters that the font does not cover fall back to their ASCII uppercase equivalent, then
+/// to U+FFFD REPLACEMENT CHARACTER, then to `?`. If none of these are present, nothing is drawn.
+///
+/// Font data must be in RDRAM, e.g. with `include_bytes!`, since it cannot be drawn from directly
+/// in the cartridge. Fonts in the ROM are read into a buffer with [`BitmapFont::read`].
+#[derive(Clone, Copy, Debug)]
+pub struct BitmapFont<'a> {
+    cell_width: usize,
+    height: usize,
+    spacing: usize,
+    ranges: &'a [u8],
+    widths: Option<&'a [u8]>,
+    bitmaps: &'a [u8],
+}
+
+impl<'a> BitmapFont<'a> {
+    /// Parse a font from its packed representation.
+    ///
+    /// Returns `None` if the header is invalid or the data is truncated.
+    pub fn new(data: &'a [u8]) -> Option<Self> {
+        let data = data.get(..Self::packed_size(data)?)?;
+        let (ranges, rest) = data[HEADER_SIZE..].split_at(data[8] as usize * RANGE_SIZE);
+
+        let (widths, bitmaps) = if data[6] & FLAG_VARIABLE_WIDTH != 0 {
+            let (widths, bitmaps) = rest.split_at(glyph_count(ranges));
+            (Some(widths), bitmaps)
+        } else {
+            (None, rest)
+        };
+
+        Some(Self {
+            cell_width: data[4] as usize,
+            height: data[5] as usize,
+            spacing: data[7] as usize,
+            ranges,
+            widths,
+            bitmaps,
+        })
+    }
+
+    /// Read a font from a stream, e.g. a file in the [`romfs`](rrt0::romfs), into a buffer and
+    /// parse it from there.
+    ///
+    /// Returns `None` if the header is invalid, the buffer is too small for the font, or reading
+    /// fails.
+    pub fn read<R: io::Read>(reader: &mut R, buffer: &'a mut [u8]) -> Option<Self> {
+        reader.read_exact(buffer.get_mut(..HEADER_SIZE)?).ok()?;
+        let ranges_end = HEADER_SIZE + buffer[8] as usize * RANGE_SIZE;
+        reader
+            .read_exact(buffer.get_mut(HEADER_SIZE..ranges_end)?)
+            .ok()?;
+
+        let size = Self::packed_size(&buffer[..ranges_end])?;
+        reader.read_exact(buffer.get_mut(ranges_end..size)?).ok()?;
+
+        let buffer: &'a [u8] = buffer;
+        Self::new(&buffer[..size])
+    }
+
+    /// Size of a packed font (in bytes), from its header and range table.
+    ///
+    /// Returns `None` if the header is invalid or the range table is truncated.
+    fn packed_size(data: &[u8]) -> Option<usize> {
+        let header = data.get(..HEADER_SIZE)?;
+        let cell_width = header[4] as usize;
+        let height = header[5] as usize;
+        if &header[..4] != MAGIC || cell_width == 0 || height == 0 {
+            return None;
+        }
+
+        let ranges = data.get(HEADER_SIZE..HEADER_SIZE + header[8] as usize * RANGE_SIZE)?;
+        let count = glyph_count(ranges);
+        let widths = match header[6] & FLAG_VARIABLE_WIDTH {
+            0 => 0,
+            _ => count,
+        };
+        let bitmaps = count.checked_mul(height * ((cell_width + 7) / 8))?;
+
+        (HEADER_SIZE + ranges.len() + widths).checked_add(bitmaps)
+    }
+
+    /// Bitmap size of one glyph (in bytes)
+    fn glyph_size(&self) -> usize {
+        self.height * self.stride()
+    }
+
+    /// Bitmap size of one glyph row (in bytes)
+    fn stride(&self) -> usize {
+        (self.cell_width + 7) / 8
+    }
+
+    /// Glyph index for a character, if the font covers it.
+    fn lookup(&self, ch: char) -> Option<usize> {
+        let code = ch as u32;
+        let mut base = 0;
+
+        for range in self.ranges.chunks_exact(RANGE_SIZE) {
+            let first = u32::from_be_bytes([range[0], range[1], range[2], range[3]]);
+            let count = u16::from_be_bytes([range[4], range[5]]) as usize;
+
+            if let Some(offset) = code.checked_sub(first) {
+                if (offset as usize) < count {
+                    return Some(base + offset as usize);
+                }
+            }
+            base += count;
+        }
+
+        None
+    }
+
+    /// Glyph index for a character, with fallbacks for characters the font does not cover.
+    fn index(&self, ch: char) -> Option<usize> {
+        self.lookup(ch)
+            .or_else(|| self.lookup(ch.to_ascii_uppercase()))
+            .or_else(|| self.lookup(char::REPLACEMENT_CHARACTER))
+            .or_else(|| self.lookup('?'))
+    }
+}
+
+impl Font for BitmapFont<'_> {
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn spacing(&self) -> usize {
+        self.spacing
+    }
+
+    fn glyph_width(&self, ch: char) -> usize {
+        match (self.widths, self.index(ch)) {
+            (Some(widths), Some(index)) => widths[index] as usize,
+            _ => self.cell_width,
+        }
+    }
+
+    fn draw_glyph(&self, x: usize, y: usize, color: u16, ch: char) {
+        let index = match self.index(ch) {
+            Some(index) => index,
+            None => return,
+        };
+
+        let frame_buffer = vi::next_buffer() as usize;
+        let width = self.glyph_width(ch).min(self.cell_width);
+        let stride = self.stride();
+        let glyph = &self.bitmaps[index * self.glyph_size()..][..self.glyph_size()];
+
+        for (row, bits) in glyph.chunks_exact(stride).enumerate() {
+            let yy = y + row;
+
+            // Bail if we're trying to draw outside of the frame buffer
+            if yy >= vi::HEIGHT {
+                return;
+            }
+
+            for col in 0..width {
+                let xx = x + col;
+                if bits[col / 8] & (0x80 >> (col % 8)) != 0 && xx < vi::WIDTH {
+                    // Put a pixel into the frame buffer
+                    let offset = (yy * vi::WIDTH + xx) * 2;
+                    let p = (frame_buffer + offset) as *mut u16;
+
+                    unsafe {
+                        *p = color;
+                    }
+                }
+            }
+        }
+    }
+}
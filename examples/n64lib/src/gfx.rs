@@ -0,0 +1,447 @@
+//! Software drawing primitives.
+//!
+//! Draws pixels, lines, rectangles, circles and sprites into 16-bit or 32-bit frame buffers with
+//! the CPU. All primitives are clipped to the surface, so coordinates may be negative or out of
+//! bounds.
+
+use crate::color::Color;
+use crate::vi;
+use core::{mem, slice};
+use rrt0::io;
+
+/// Pixel formats that can be drawn on a [`Surface`].
+///
+/// Implemented for `u16` (RGBA 5:5:5:1) and `u32` (RGBA 8:8:8:8).
+pub trait Pixel: Copy + PartialEq + private::Sealed {
+    /// Convert from a color.
+    fn from_color(color: Color) -> Self;
+
+    /// Convert to a color.
+    fn to_color(self) -> Color;
+
+    /// Whether the pixel is opaque when using 1-bit alpha.
+    fn is_opaque(self) -> bool;
+}
+
+impl Pixel for u16 {
+    fn from_color(color: Color) -> Self {
+        color.to_rgba5551()
+    }
+
+    fn to_color(self) -> Color {
+        Color::from_rgba5551(self)
+    }
+
+    fn is_opaque(self) -> bool {
+        self & 0x0001 != 0
+    }
+}
+
+impl Pixel for u32 {
+    fn from_color(color: Color) -> Self {
+        color.to_rgba8888()
+    }
+
+    fn to_color(self) -> Color {
+        Color::from_rgba8888(self)
+    }
+
+    fn is_opaque(self) -> bool {
+        self & 0xFF >= 0x80
+    }
+}
+
+mod private {
+    pub trait Sealed: Sized {
+        /// Convert from big-endian, the byte order of pixels in files, to native byte order.
+        fn to_native(self) -> Self;
+    }
+
+    impl Sealed for u16 {
+        fn to_native(self) -> Self {
+            u16::from_be(self)
+        }
+    }
+
+    impl Sealed for u32 {
+        fn to_native(self) -> Self {
+            u32::from_be(self)
+        }
+    }
+}
+
+/// A rectangular region of an image (in pixels)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// How transparent pixels in a sprite are determined when blitting.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Transparency<P: Pixel> {
+    /// All pixels are drawn.
+    Opaque,
+    /// Pixels matching the key color are not drawn.
+    ColorKey(P),
+    /// Pixels are drawn when their alpha channel is at least half opaque. See [`Pixel::is_opaque`].
+    Alpha,
+}
+
+/// A read-only image that can be blitted onto a [`Surface`].
+///
+/// Pixel data must be in RDRAM, since it cannot be drawn from directly in the cartridge. Sprites in
+/// the ROM are read into a buffer with [`Sprite::read`].
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite<'a, P: Pixel> {
+    pixels: &'a [P],
+    width: usize,
+    height: usize,
+}
+
+impl<'a, P: Pixel> Sprite<'a, P> {
+    /// Create a sprite from a buffer of `width * height` pixels in row-major order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is too small.
+    pub fn new(pixels: &'a [P], width: usize, height: usize) -> Self {
+        assert!(pixels.len() >= width * height, "Sprite buffer is too small");
+
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Read a sprite from a stream, e.g. a file in the [`romfs`](rrt0::romfs), into a buffer of at
+    /// least `width * height` pixels. The stream holds the pixels in row-major order, each in
+    /// big-endian byte order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is too small.
+    pub fn read<R: io::Read>(
+        reader: &mut R,
+        pixels: &'a mut [P],
+        width: usize,
+        height: usize,
+    ) -> Result<Self, io::ReadExactError<R::Error>> {
+        assert!(pixels.len() >= width * height, "Sprite buffer is too small");
+        let pixels = &mut pixels[..width * height];
+
+        // Both pixel formats are plain integers, valid for any bytes
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(pixels.as_mut_ptr().cast::<u8>(), mem::size_of_val(pixels))
+        };
+        reader.read_exact(bytes)?;
+        for pixel in pixels.iter_mut() {
+            *pixel = pixel.to_native();
+        }
+
+        Ok(Self::new(pixels, width, height))
+    }
+
+    /// Sprite width (in pixels)
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Sprite height (in pixels)
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Raw pixel data in row-major order.
+    pub fn pixels(&self) -> &'a [P] {
+        &self.pixels[..self.width * self.height]
+    }
+
+    /// The region covering the whole sprite.
+    pub fn bounds(&self) -> Rect {
+        Rect {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// A frame buffer (or any other image in RDRAM) that can be drawn on.
+pub struct Surface<'a, P: Pixel> {
+    pixels: &'a mut [P],
+    width: usize,
+    height: usize,
+}
+
+/// Get a surface for drawing on the back buffer.
+///
+/// The surface borrows the VI frame buffer directly. Drop it before calling [`vi::swap_buffer`].
+pub fn back_buffer() -> Surface<'static, u16> {
+    let pixels = unsafe { slice::from_raw_parts_mut(vi::next_buffer(), vi::WIDTH * vi::HEIGHT) };
+
+    Surface::new(pixels, vi::WIDTH, vi::HEIGHT)
+}
+
+impl<'a, P: Pixel> Surface<'a, P> {
+    /// Create a surface over a buffer of `width * height` pixels in row-major order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is too small.
+    pub fn new(pixels: &'a mut [P], width: usize, height: usize) -> Self {
+        assert!(
+            pixels.len() >= width * height,
+            "Surface buffer is too small"
+        );
+
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Surface width (in pixels)
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Surface height (in pixels)
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Raw pixel data in row-major order.
+    pub fn pixels(&self) -> &[P] {
+        &self.pixels[..self.width * self.height]
+    }
+
+    /// Mutable raw pixel data in row-major order.
+    pub fn pixels_mut(&mut self) -> &mut [P] {
+        &mut self.pixels[..self.width * self.height]
+    }
+
+    /// Borrow the surface as a sprite, e.g. to blit it onto another surface.
+    pub fn as_sprite(&self) -> Sprite<'_, P> {
+        Sprite::new(self.pixels, self.width, self.height)
+    }
+
+    /// Fill the entire surface with a single color.
+    pub fn clear(&mut self, color: P) {
+        self.pixels_mut().fill(color);
+    }
+
+    /// Get the color of a pixel, or `None` if it is outside of the surface.
+    pub fn pixel(&self, x: i32, y: i32) -> Option<P> {
+        self.offset(x, y).map(|offset| self.pixels[offset])
+    }
+
+    /// Set the color of a single pixel.
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: P) {
+        if let Some(offset) = self.offset(x, y) {
+            self.pixels[offset] = color;
+        }
+    }
+
+    /// Alpha blend a color over a single pixel.
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if let Some(offset) = self.offset(x, y) {
+            let dst = self.pixels[offset].to_color();
+            self.pixels[offset] = P::from_color(color.blend_over(dst));
+        }
+    }
+
+    /// Fill a horizontal span of pixels from `x0` to `x1` (inclusive) on row `y`.
+    pub fn hspan(&mut self, x0: i32, x1: i32, y: i32, color: P) {
+        if y < 0 || y >= self.height as i32 {
+            return;
+        }
+
+        let (x0, x1) = if x0 <= x1 { (x0, x1) } else { (x1, x0) };
+        let x0 = x0.max(0);
+        let x1 = x1.min(self.width as i32 - 1);
+        if x0 > x1 {
+            return;
+        }
+
+        let row = y as usize * self.width;
+        self.pixels[row + x0 as usize..=row + x1 as usize].fill(color);
+    }
+
+    /// Draw a line from `(x0, y0)` to `(x1, y1)` (inclusive) with Bresenham's algorithm.
+    pub fn line(&mut self, mut x0: i32, mut y0: i32, x1: i32, y1: i32, color: P) {
+        if y0 == y1 {
+            self.hspan(x0, x1, y0, color);
+            return;
+        }
+
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        loop {
+            self.set_pixel(x0, y0, color);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+
+            let e2 = error * 2;
+            if e2 >= dy {
+                error += dy;
+                x0 += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                y0 += sy;
+            }
+        }
+    }
+
+    /// Draw the outline of a rectangle.
+    pub fn rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: P) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let x1 = x + width as i32 - 1;
+        let y1 = y + height as i32 - 1;
+
+        self.hspan(x, x1, y, color);
+        self.hspan(x, x1, y1, color);
+        for yy in y + 1..y1 {
+            self.set_pixel(x, yy, color);
+            self.set_pixel(x1, yy, color);
+        }
+    }
+
+    /// Draw a filled rectangle.
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: P) {
+        if width == 0 {
+            return;
+        }
+
+        let x1 = x + width as i32 - 1;
+        let y0 = y.max(0);
+        let y1 = (y + height as i32).min(self.height as i32);
+        for yy in y0..y1 {
+            self.hspan(x, x1, yy, color);
+        }
+    }
+
+    /// Draw the outline of a circle with the midpoint algorithm.
+    pub fn circle(&mut self, cx: i32, cy: i32, radius: u32, color: P) {
+        self.midpoint_circle(radius, |surface, x, y| {
+            surface.set_pixel(cx + x, cy + y, color);
+            surface.set_pixel(cx - x, cy + y, color);
+            surface.set_pixel(cx + x, cy - y, color);
+            surface.set_pixel(cx - x, cy - y, color);
+            surface.set_pixel(cx + y, cy + x, color);
+            surface.set_pixel(cx - y, cy + x, color);
+            surface.set_pixel(cx + y, cy - x, color);
+            surface.set_pixel(cx - y, cy - x, color);
+        });
+    }
+
+    /// Draw a filled circle.
+    pub fn fill_circle(&mut self, cx: i32, cy: i32, radius: u32, color: P) {
+        self.midpoint_circle(radius, |surface, x, y| {
+            surface.hspan(cx - x, cx + x, cy + y, color);
+            surface.hspan(cx - x, cx + x, cy - y, color);
+            surface.hspan(cx - y, cx + y, cy + x, color);
+            surface.hspan(cx - y, cx + y, cy - x, color);
+        });
+    }
+
+    /// Draw a sprite with its top-left corner at the given position.
+    pub fn blit(&mut self, sprite: &Sprite<'_, P>, x: i32, y: i32, transparency: Transparency<P>) {
+        self.blit_region(sprite, sprite.bounds(), x, y, transparency);
+    }
+
+    /// Draw a region of a sprite (e.g. one frame of a sprite sheet) with its top-left corner at
+    /// the given position. The region is clipped to the bounds of the sprite.
+    pub fn blit_region(
+        &mut self,
+        sprite: &Sprite<'_, P>,
+        region: Rect,
+        x: i32,
+        y: i32,
+        transparency: Transparency<P>,
+    ) {
+        // Clip the source region to the sprite
+        let src_x = region.x.min(sprite.width);
+        let src_y = region.y.min(sprite.height);
+        let width = region.width.min(sprite.width - src_x) as i32;
+        let height = region.height.min(sprite.height - src_y) as i32;
+
+        // Clip the destination to the surface
+        let x0 = x.max(0);
+        let y0 = y.max(0);
+        let x1 = (x + width).min(self.width as i32);
+        let y1 = (y + height).min(self.height as i32);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+
+        let len = (x1 - x0) as usize;
+        let src_x = src_x + (x0 - x) as usize;
+        for yy in y0..y1 {
+            let src_row = (src_y + (yy - y) as usize) * sprite.width + src_x;
+            let dst_row = yy as usize * self.width + x0 as usize;
+            let src = &sprite.pixels[src_row..src_row + len];
+            let dst = &mut self.pixels[dst_row..dst_row + len];
+
+            match transparency {
+                Transparency::Opaque => dst.copy_from_slice(src),
+                Transparency::ColorKey(key) => {
+                    for (d, &s) in dst.iter_mut().zip(src) {
+                        if s != key {
+                            *d = s;
+                        }
+                    }
+                }
+                Transparency::Alpha => {
+                    for (d, &s) in dst.iter_mut().zip(src) {
+                        if s.is_opaque() {
+                            *d = s;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Walk one octant of a circle, calling `f` with each point relative to the center.
+    fn midpoint_circle(&mut self, radius: u32, mut f: impl FnMut(&mut Self, i32, i32)) {
+        let mut x = radius as i32;
+        let mut y = 0;
+        let mut error = 1 - x;
+
+        while x >= y {
+            f(self, x, y);
+
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Offset of a pixel in the buffer, or `None` if it is outside of the surface.
+    fn offset(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+
+        Some(y as usize * self.width + x as usize)
+    }
+}
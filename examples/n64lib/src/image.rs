@@ -0,0 +1,321 @@
+//! Image decoding.
+//!
+//! Decodes simple uncompressed BMP and TGA images (e.g. from `include_bytes!`) into [`Surface`]s.
+//! No allocator is required; pixels are decoded into a caller-provided buffer.
+//!
+//! Supported formats:
+//!
+//! - BMP: 24-bit and 32-bit, uncompressed (`BI_RGB`). The unused byte of 32-bit pixels is ignored.
+//! - TGA: 16-bit, 24-bit and 32-bit, uncompressed true-color (image type 2).
+
+use crate::color::Color;
+use crate::gfx::{Pixel, Surface};
+
+/// Errors that can occur while decoding an image.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImageError {
+    /// The data ends before the image does.
+    Truncated,
+    /// The data is not a BMP or TGA image, or uses a feature that is not supported.
+    Unsupported,
+    /// The pixel buffer is smaller than `width * height`.
+    BufferTooSmall,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Layout {
+    /// 16-bit little-endian `ARRRRRGG GGGBBBBB`
+    Argb1555,
+    /// 24-bit `B, G, R`
+    Bgr888,
+    /// 32-bit `B, G, R, X` with the last byte ignored
+    Bgrx8888,
+    /// 32-bit `B, G, R, A`
+    Bgra8888,
+}
+
+impl Layout {
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Argb1555 => 2,
+            Self::Bgr888 => 3,
+            Self::Bgrx8888 | Self::Bgra8888 => 4,
+        }
+    }
+
+    fn read<P: Pixel>(self, bytes: &[u8]) -> P {
+        let color = match self {
+            Self::Argb1555 => {
+                // Move the alpha bit from the top to the bottom
+                let p = u16::from_le_bytes([bytes[0], bytes[1]]);
+                Color::from_rgba5551(p << 1 | p >> 15)
+            }
+            Self::Bgr888 | Self::Bgrx8888 => Color::rgb(bytes[2], bytes[1], bytes[0]),
+            Self::Bgra8888 => Color::rgba(bytes[2], bytes[1], bytes[0], bytes[3]),
+        };
+
+        P::from_color(color)
+    }
+}
+
+/// A parsed BMP or TGA image, ready to be decoded.
+#[derive(Clone, Copy, Debug)]
+pub struct Image<'a> {
+    pixels: &'a [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    layout: Layout,
+    bottom_up: bool,
+    right_to_left: bool,
+}
+
+impl<'a> Image<'a> {
+    /// Parse the header of a BMP or TGA image.
+    pub fn parse(data: &'a [u8]) -> Result<Self, ImageError> {
+        if data.starts_with(b"BM") {
+            Self::parse_bmp(data)
+        } else {
+            Self::parse_tga(data)
+        }
+    }
+
+    /// Image width (in pixels)
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Image height (in pixels)
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Decode the image into a buffer of at least `width * height` pixels, converting to the
+    /// buffer's pixel format. Returns a surface over the decoded image.
+    pub fn decode_into<'b, P: Pixel>(
+        &self,
+        pixels: &'b mut [P],
+    ) -> Result<Surface<'b, P>, ImageError> {
+        match self.width.checked_mul(self.height) {
+            Some(len) if len <= pixels.len() => (),
+            _ => return Err(ImageError::BufferTooSmall),
+        }
+
+        let bpp = self.layout.bytes_per_pixel();
+        for y in 0..self.height {
+            let src_y = if self.bottom_up {
+                self.height - 1 - y
+            } else {
+                y
+            };
+            let src = &self.pixels[src_y * self.stride..][..self.width * bpp];
+            let dst = &mut pixels[y * self.width..][..self.width];
+
+            for (x, bytes) in src.chunks_exact(bpp).enumerate() {
+                let dst_x = if self.right_to_left {
+                    self.width - 1 - x
+                } else {
+                    x
+                };
+                dst[dst_x] = self.layout.read(bytes);
+            }
+        }
+
+        Ok(Surface::new(pixels, self.width, self.height))
+    }
+
+    fn parse_bmp(data: &'a [u8]) -> Result<Self, ImageError> {
+        if data.len() < 54 {
+            return Err(ImageError::Truncated);
+        }
+
+        let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ])
+        };
+
+        let offset = u32_at(10) as usize;
+        let width = u32_at(18) as i32;
+        let height = u32_at(22) as i32;
+        let planes = u16_at(26);
+        let bits = u16_at(28);
+        let compression = u32_at(30);
+
+        let layout = match (planes, bits, compression) {
+            (1, 24, 0) => Layout::Bgr888,
+            (1, 32, 0) => Layout::Bgrx8888,
+            _ => return Err(ImageError::Unsupported),
+        };
+        if width <= 0 || height == 0 {
+            return Err(ImageError::Unsupported);
+        }
+
+        let width = width as usize;
+        let stride = width
+            .checked_mul(layout.bytes_per_pixel())
+            .and_then(|row| row.checked_add(3))
+            .ok_or(ImageError::Unsupported)?
+            & !3;
+
+        Self::new(
+            data.get(offset..).ok_or(ImageError::Truncated)?,
+            width,
+            height.unsigned_abs() as usize,
+            stride,
+            layout,
+            height > 0,
+            false,
+        )
+    }
+
+    fn parse_tga(data: &'a [u8]) -> Result<Self, ImageError> {
+        if data.len() < 18 {
+            return Err(ImageError::Truncated);
+        }
+
+        let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]) as usize;
+
+        let id_length = data[0] as usize;
+        let color_map_type = data[1];
+        let image_type = data[2];
+        let color_map_length = u16_at(5);
+        let color_map_bits = data[7] as usize;
+        let width = u16_at(12);
+        let height = u16_at(14);
+        let bits = data[16];
+        let descriptor = data[17];
+        let alpha_bits = descriptor & 0x0F;
+
+        if color_map_type > 1 || image_type != 2 || width == 0 || height == 0 {
+            return Err(ImageError::Unsupported);
+        }
+
+        let layout = match (bits, alpha_bits) {
+            (16, _) => Layout::Argb1555,
+            (24, _) => Layout::Bgr888,
+            (32, 0) => Layout::Bgrx8888,
+            (32, _) => Layout::Bgra8888,
+            _ => return Err(ImageError::Unsupported),
+        };
+
+        // Skip the image ID and the (unused) color map
+        let mut offset = 18 + id_length;
+        if color_map_type == 1 {
+            offset += color_map_length * ((color_map_bits + 7) / 8);
+        }
+
+        Self::new(
+            data.get(offset..).ok_or(ImageError::Truncated)?,
+            width,
+            height,
+            width * layout.bytes_per_pixel(),
+            layout,
+            descriptor & 0x20 == 0,
+            descriptor & 0x10 != 0,
+        )
+    }
+
+    fn new(
+        pixels: &'a [u8],
+        width: usize,
+        height: usize,
+        stride: usize,
+        layout: Layout,
+        bottom_up: bool,
+        right_to_left: bool,
+    ) -> Result<Self, ImageError> {
+        let size = stride
+            .checked_mul(height - 1)
+            .and_then(|size| size.checked_add(width.checked_mul(layout.bytes_per_pixel())?))
+            .ok_or(ImageError::Unsupported)?;
+        if pixels.len() < size {
+            return Err(ImageError::Truncated);
+        }
+
+        Ok(Self {
+            pixels,
+            width,
+            height,
+            stride,
+            layout,
+            bottom_up,
+            right_to_left,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A BMP header for an image of the given size and bit depth, followed by room for 16 bytes
+    /// of pixels
+    fn bmp(width: i32, height: i32, bits: u16) -> [u8; 70] {
+        let mut data = [0; 70];
+        data[..2].copy_from_slice(b"BM");
+        data[10..14].copy_from_slice(&54_u32.to_le_bytes());
+        data[14..18].copy_from_slice(&40_u32.to_le_bytes());
+        data[18..22].copy_from_slice(&width.to_le_bytes());
+        data[22..26].copy_from_slice(&height.to_le_bytes());
+        data[26..28].copy_from_slice(&1_u16.to_le_bytes());
+        data[28..30].copy_from_slice(&bits.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn bmp_rows_are_bottom_up_and_padded() {
+        let mut data = bmp(2, 2, 24);
+        // Bottom row: blue, green; top row: red, white. Rows are padded to 4 bytes.
+        data[54..62].copy_from_slice(&[0xFF, 0, 0, 0, 0xFF, 0, 0, 0]);
+        data[62..70].copy_from_slice(&[0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0, 0]);
+
+        let mut pixels = [0_u32; 4];
+        Image::parse(&data)
+            .unwrap()
+            .decode_into(&mut pixels)
+            .unwrap();
+        assert_eq!(pixels, [0xFF0000FF, 0xFFFFFFFF, 0x0000FFFF, 0x00FF00FF]);
+    }
+
+    #[test]
+    fn tga_argb1555_moves_the_alpha_bit() {
+        let mut data = [0; 22];
+        data[2] = 2;
+        data[12..14].copy_from_slice(&2_u16.to_le_bytes());
+        data[14..16].copy_from_slice(&1_u16.to_le_bytes());
+        data[16] = 16;
+        // Top-left origin, 1 alpha bit
+        data[17] = 0x21;
+        data[18..20].copy_from_slice(&0x801F_u16.to_le_bytes());
+        data[20..22].copy_from_slice(&0x7C00_u16.to_le_bytes());
+
+        let mut pixels = [0_u16; 2];
+        Image::parse(&data)
+            .unwrap()
+            .decode_into(&mut pixels)
+            .unwrap();
+        assert_eq!(pixels, [0x003F, 0xF800]);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |data: &[u8]| Image::parse(data).err();
+
+        assert_eq!(error(&bmp(i32::MAX, 1, 32)), Some(ImageError::Truncated));
+        assert_eq!(error(&bmp(2, 2, 16)), Some(ImageError::Unsupported));
+        assert_eq!(error(b"BM"), Some(ImageError::Truncated));
+
+        let data = bmp(2, 2, 32);
+        let image = Image::parse(&data).unwrap();
+        let mut pixels = [0_u16; 3];
+        assert_eq!(
+            image.decode_into(&mut pixels).err(),
+            Some(ImageError::BufferTooSmall)
+        );
+    }
+}
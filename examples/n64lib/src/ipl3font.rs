@@ -3,7 +3,11 @@
 //! Draws strings using the font embedded in the official Nintendo IPL3 bootcode.
 //! See: <https://github.com/PeterLemon/N64/blob/master/BOOTCODE/IPL3Font.asm>
 
+use crate::font::{self, Font};
 use crate::vi;
+use rrt0::mem::map;
+
+pub use crate::font::{Align, Extent, Rect, Span};
 
 /// Glyph width (pixels or bits)
 pub const WIDTH: usize = 13;
@@ -23,55 +27,129 @@ const GLYPH_SIZE: usize = 23;
 
 /// Location of font in ROM
 /// This is only guaranteed to be accurate for the CIC-NUS-6102 IPL3.
-const GLYPH_ADDR: usize = 0xB000_0B70;
+const GLYPH_ADDR: usize = map::to_kseg1(map::ROM + 0x0B70);
 
 /// Font kerning (in pixels)
 const KERNING: usize = 1;
 
+/// Capacity of the buffer used by [`draw_text!`](crate::draw_text) (in bytes)
+pub const TEXT_BUFFER_SIZE: usize = 256;
+
+/// Fixed-capacity buffer for formatting text without an allocator.
+/// Text that does not fit is truncated.
+pub type TextBuffer = rrt0::fmt::FormatBuffer<TEXT_BUFFER_SIZE>;
+
+/// Format and draw text using the embedded font with its top-left corner at the given position.
+///
+/// Text is formatted into a [`TextBuffer`] on the stack and drawn with [`draw_str_at`], returning
+/// the extent of the drawn text. Output longer than [`TEXT_BUFFER_SIZE`] bytes is truncated.
+///
+/// e.g. `draw_text!(16, 16, WHITE, "score: {}", score);`
+#[macro_export]
+macro_rules! draw_text {
+    ($x:expr, $y:expr, $color:expr, $($arg:tt)*) => {{
+        let mut buffer = $crate::ipl3font::TextBuffer::new();
+        let _ = ::core::fmt::Write::write_fmt(&mut buffer, ::core::format_args!($($arg)*));
+        $crate::ipl3font::draw_str_at($x, $y, $color, buffer.as_str())
+    }};
+}
+
+/// The font embedded in the IPL3 bootcode
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ipl3Font;
+
+impl Font for Ipl3Font {
+    fn height(&self) -> usize {
+        HEIGHT
+    }
+
+    fn spacing(&self) -> usize {
+        KERNING
+    }
+
+    fn glyph_width(&self, _ch: char) -> usize {
+        WIDTH
+    }
+
+    fn draw_glyph(&self, x: usize, y: usize, color: u16, ch: char) {
+        draw_char(x, y, color, ch);
+    }
+}
+
 /// Draw a string using the embedded font, centered on the frame buffer.
 /// Only supports a small subset of the ASCII character set.
 pub fn draw_str_centered(color: u16, string: &str) {
-    let x = (vi::WIDTH - string.len() * WIDTH) / 2;
-    let y = (vi::HEIGHT - HEIGHT) / 2;
-
-    draw_str(x, y, color, string);
+    font::draw_str_centered(&Ipl3Font, color, string);
 }
 
-/// Draw a string using the embedded font.
+/// Draw a string using the embedded font, wrapping at the edge of the frame buffer.
 /// Only supports a small subset of the ASCII character set.
-pub fn draw_str(mut x: usize, mut y: usize, color: u16, string: &str) {
-    for mut ch in string.bytes() {
-        // Bail if we're trying to draw outside of the frame buffer
-        if y >= vi::HEIGHT {
-            return;
-        }
+pub fn draw_str(x: usize, y: usize, color: u16, string: &str) {
+    let rect = Rect {
+        x,
+        y,
+        width: vi::WIDTH.saturating_sub(x),
+        height: vi::HEIGHT.saturating_sub(y),
+    };
+
+    draw_str_wrapped(rect, color, string);
+}
 
-        // Special handling for space characters
-        if ch == b' ' {
-            x += WIDTH;
-            if x >= vi::WIDTH {
-                x = 0;
-                y += HEIGHT;
-            }
-            continue;
-        }
+/// Draw a string using the embedded font with its top-left corner at the given position.
+/// See [`font::draw_str_at`].
+pub fn draw_str_at(x: usize, y: usize, color: u16, string: &str) -> Extent {
+    font::draw_str_at(&Ipl3Font, x, y, color, string)
+}
 
-        // Special handling for lowercase letters
-        if (b'a'..=b'z').contains(&ch) {
-            ch -= b'a' - b'A';
-        }
+/// Draw a string using the embedded font, word wrapped to fit within a rectangle.
+/// See [`font::draw_str_wrapped`].
+pub fn draw_str_wrapped(rect: Rect, color: u16, string: &str) -> Extent {
+    font::draw_str_wrapped(&Ipl3Font, rect, color, string)
+}
 
-        draw_char(x, y, color, ch);
-        x += WIDTH + KERNING;
-    }
+/// Draw a string using the embedded font, word wrapped to fit within a rectangle, with each line
+/// aligned within its width. See [`font::draw_str_aligned`].
+pub fn draw_str_aligned(rect: Rect, align: Align, color: u16, string: &str) -> Extent {
+    font::draw_str_aligned(&Ipl3Font, rect, align, color, string)
+}
+
+/// Draw a sequence of colored spans using the embedded font.
+/// See [`font::draw_spans_at`].
+pub fn draw_spans_at(x: usize, y: usize, spans: &[Span<'_>]) -> Extent {
+    font::draw_spans_at(&Ipl3Font, x, y, spans)
+}
+
+/// Draw a sequence of colored spans using the embedded font, word wrapped to fit within a
+/// rectangle. See [`font::draw_spans_wrapped`].
+pub fn draw_spans_wrapped(rect: Rect, spans: &[Span<'_>]) -> Extent {
+    font::draw_spans_wrapped(&Ipl3Font, rect, spans)
+}
+
+/// Measure the extent of a string without drawing it.
+pub fn measure_str(string: &str) -> Extent {
+    font::measure_str(&Ipl3Font, string)
+}
+
+/// Measure the extent of a string word wrapped to the given width, without drawing it.
+pub fn measure_str_wrapped(width: usize, string: &str) -> Extent {
+    font::measure_str_wrapped(&Ipl3Font, width, string)
+}
+
+/// Measure the extent of a sequence of spans without drawing them.
+pub fn measure_spans(spans: &[Span<'_>]) -> Extent {
+    font::measure_spans(&Ipl3Font, spans)
 }
 
 /// Draw a character.
 /// Only supports a small subset of the ASCII character set.
-fn draw_char(x: usize, y: usize, color: u16, ch: u8) {
+fn draw_char(x: usize, y: usize, color: u16, ch: char) {
     let frame_buffer = vi::next_buffer() as usize;
 
-    let index = GLYPHS.iter().position(|c| *c == ch).unwrap_or(UNKNOWN);
+    // Special handling for lowercase letters; anything outside of ASCII is unknown
+    let index = u8::try_from(ch.to_ascii_uppercase())
+        .ok()
+        .and_then(|ch| GLYPHS.iter().position(|c| *c == ch))
+        .unwrap_or(UNKNOWN);
 
     let mut address = GLYPH_ADDR + index * GLYPH_SIZE;
     let mut shift = (4 - (address & 3)) * 8 - 1;
@@ -0,0 +1,188 @@
+//! On-screen keyboard.
+//!
+//! A grid of characters navigated with [`Input`] events, for entering text without a keyboard.
+//! The last row of the grid holds the space, delete and done keys. Pressing `Back` also deletes
+//! the last character.
+
+use crate::font::{self, Font};
+use crate::menu::Input;
+
+/// Default key layout, limited to the characters covered by the IPL3 font.
+pub const DEFAULT_LAYOUT: &[&str] = &["ABCDEFGHIJ", "KLMNOPQRST", "UVWXYZ0123", "456789.-!?"];
+
+/// Space between keys (in pixels)
+const KEY_SPACING: usize = 6;
+
+/// A key on the keyboard
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Key {
+    Char(char),
+    Space,
+    Delete,
+    Done,
+}
+
+/// Keys on the last row
+const SPECIAL_KEYS: [Key; 3] = [Key::Space, Key::Delete, Key::Done];
+
+impl Key {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Char(_) => "",
+            Self::Space => "SPACE",
+            Self::Delete => "DEL",
+            Self::Done => "OK",
+        }
+    }
+}
+
+/// On-screen keyboard state, holding up to `N` bytes of UTF-8 text.
+pub struct Keyboard<const N: usize> {
+    layout: &'static [&'static str],
+    text: [u8; N],
+    len: usize,
+    row: usize,
+    col: usize,
+    color: u16,
+    highlight: u16,
+}
+
+impl<const N: usize> Keyboard<N> {
+    /// Create an empty keyboard with the default layout. Keys are drawn with `color`, and the
+    /// selected key with `highlight`.
+    pub const fn new(color: u16, highlight: u16) -> Self {
+        Self {
+            layout: DEFAULT_LAYOUT,
+            text: [0; N],
+            len: 0,
+            row: 0,
+            col: 0,
+            color,
+            highlight,
+        }
+    }
+
+    /// Use a different key layout, with one string per row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any row is empty.
+    pub fn with_layout(mut self, layout: &'static [&'static str]) -> Self {
+        assert!(
+            layout.iter().all(|row| !row.is_empty()),
+            "Keyboard rows must not be empty"
+        );
+
+        self.layout = layout;
+        self.row = 0;
+        self.col = 0;
+        self
+    }
+
+    /// The text entered so far.
+    pub fn text(&self) -> &str {
+        // Only whole characters are ever pushed
+        core::str::from_utf8(&self.text[..self.len]).unwrap_or_default()
+    }
+
+    /// Clear the text and select the first key.
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.row = 0;
+        self.col = 0;
+    }
+
+    /// Apply an input event. Returns the entered text when the done key is pressed.
+    pub fn update(&mut self, input: Input) -> Option<&str> {
+        let rows = self.layout.len() + 1;
+
+        match input {
+            Input::Up => {
+                self.row = self.row.checked_sub(1).unwrap_or(rows - 1);
+                self.col = self.col.min(self.row_len(self.row) - 1);
+            }
+            Input::Down => {
+                self.row = (self.row + 1) % rows;
+                self.col = self.col.min(self.row_len(self.row) - 1);
+            }
+            Input::Left => {
+                self.col = self
+                    .col
+                    .checked_sub(1)
+                    .unwrap_or(self.row_len(self.row) - 1);
+            }
+            Input::Right => self.col = (self.col + 1) % self.row_len(self.row),
+            Input::Back => self.pop(),
+            Input::Accept => match self.key(self.row, self.col) {
+                Key::Char(ch) => self.push(ch),
+                Key::Space => self.push(' '),
+                Key::Delete => self.pop(),
+                Key::Done => return Some(self.text()),
+            },
+        }
+
+        None
+    }
+
+    /// Draw the text field and keys onto the back buffer with the top-left corner at the given
+    /// position.
+    pub fn draw<F: Font + ?Sized>(&self, font: &F, x: usize, y: usize) {
+        let line_height = font.height() + font.leading();
+        let key_width = font.glyph_width('M') + KEY_SPACING;
+
+        let extent = font::draw_str_at(font, x, y, self.color, self.text());
+        font::draw_str_at(font, x + extent.width, y, self.highlight, "_");
+
+        for row in 0..self.layout.len() + 1 {
+            let mut xx = x;
+            let yy = y + (row + 2) * line_height;
+
+            for col in 0..self.row_len(row) {
+                let color = if (row, col) == (self.row, self.col) {
+                    self.highlight
+                } else {
+                    self.color
+                };
+
+                match self.key(row, col) {
+                    Key::Char(ch) => {
+                        font.draw_glyph(xx, yy, color, ch);
+                        xx += key_width;
+                    }
+                    key => {
+                        let extent = font::draw_str_at(font, xx, yy, color, key.label());
+                        xx += extent.width + KEY_SPACING * 2;
+                    }
+                }
+            }
+        }
+    }
+
+    fn row_len(&self, row: usize) -> usize {
+        match self.layout.get(row) {
+            Some(keys) => keys.chars().count(),
+            None => SPECIAL_KEYS.len(),
+        }
+    }
+
+    fn key(&self, row: usize, col: usize) -> Key {
+        match self.layout.get(row) {
+            Some(keys) => keys.chars().nth(col).map_or(Key::Space, Key::Char),
+            None => SPECIAL_KEYS[col],
+        }
+    }
+
+    fn push(&mut self, ch: char) {
+        let len = self.len + ch.len_utf8();
+        if len <= N {
+            ch.encode_utf8(&mut self.text[self.len..len]);
+            self.len = len;
+        }
+    }
+
+    fn pop(&mut self) {
+        if let Some(ch) = self.text().chars().next_back() {
+            self.len -= ch.len_utf8();
+        }
+    }
+}
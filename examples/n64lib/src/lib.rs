@@ -1,6 +1,19 @@
 #![no_std]
 
+pub mod assert;
+pub mod backend;
+pub mod color;
+pub mod controller;
+pub mod font;
+pub mod gfx;
+pub mod image;
 pub mod ipl3font;
+pub mod keyboard;
+pub mod menu;
+pub mod overlay;
+pub mod raster;
+pub mod terminal;
+pub mod tilemap;
 pub mod vi;
 
 pub use rrt0::prelude::*;
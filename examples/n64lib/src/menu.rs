@@ -0,0 +1,222 @@
+//! Debug menu.
+//!
+//! An immediate-mode menu for exposing runtime switches. The menu is described every frame inside
+//! [`Menu::run`] by calling item methods on a [`Ui`]; items on the open page are drawn as they are
+//! declared, and input is applied to the selected item. Pages can be nested up to `DEPTH` levels.
+//!
+//! Input is passed in as [`Input`] events, so the menu can be driven by a controller or any other
+//! source the application maps to them.
+
+use crate::font::{self, Font};
+use crate::ipl3font::TextBuffer;
+use core::fmt::{self, Write};
+use core::ops::RangeInclusive;
+
+/// Menu navigation events
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Input {
+    /// Select the previous item
+    Up,
+    /// Select the next item
+    Down,
+    /// Decrease the selected slider, or switch the selected toggle
+    Left,
+    /// Increase the selected slider, or switch the selected toggle
+    Right,
+    /// Activate the selected item
+    Accept,
+    /// Return to the parent page
+    Back,
+}
+
+/// Debug menu state, with pages nested up to `DEPTH` levels (including the root page).
+pub struct Menu<const DEPTH: usize> {
+    /// Selected item index on each open page
+    path: [usize; DEPTH],
+    /// Index of the open page in `path`
+    depth: usize,
+    color: u16,
+    highlight: u16,
+}
+
+impl<const DEPTH: usize> Menu<DEPTH> {
+    /// Create a menu with the first item on the root page selected. Items are drawn with `color`,
+    /// and the selected item with `highlight`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `DEPTH` is zero.
+    pub const fn new(color: u16, highlight: u16) -> Self {
+        assert!(DEPTH > 0, "Menu depth must not be zero");
+
+        Self {
+            path: [0; DEPTH],
+            depth: 0,
+            color,
+            highlight,
+        }
+    }
+
+    /// Return to the first item on the root page.
+    pub fn reset(&mut self) {
+        self.path = [0; DEPTH];
+        self.depth = 0;
+    }
+
+    /// Build, update and draw the menu for one frame. The open page is drawn onto the back buffer
+    /// with its top-left corner at the given position.
+    ///
+    /// Selection changes from `Up`, `Down` and `Back` take effect on the next frame.
+    pub fn run<F, B>(&mut self, font: &F, x: usize, y: usize, input: Option<Input>, build: B)
+    where
+        F: Font + ?Sized,
+        B: FnOnce(&mut Ui<'_, F, DEPTH>),
+    {
+        let mut ui = Ui {
+            menu: self,
+            font,
+            x,
+            y,
+            input,
+            level: 0,
+            index: 0,
+            count: 0,
+        };
+        build(&mut ui);
+
+        let count = ui.count;
+        let input = ui.input;
+        let selected = &mut self.path[self.depth];
+
+        if count == 0 {
+            // The open page no longer exists
+            self.depth = self.depth.saturating_sub(1);
+            return;
+        }
+
+        match input {
+            Some(Input::Up) => *selected = selected.checked_sub(1).unwrap_or(count - 1),
+            Some(Input::Down) => *selected = (*selected + 1) % count,
+            Some(Input::Back) => self.depth = self.depth.saturating_sub(1),
+            _ => *selected = (*selected).min(count - 1),
+        }
+    }
+}
+
+/// Item builder for one frame of a [`Menu`].
+pub struct Ui<'a, F: Font + ?Sized, const DEPTH: usize> {
+    menu: &'a mut Menu<DEPTH>,
+    font: &'a F,
+    x: usize,
+    y: usize,
+    /// Input not yet consumed by an item
+    input: Option<Input>,
+    /// Nesting level of the items being declared
+    level: usize,
+    /// Index of the next item on the current page
+    index: usize,
+    /// Number of items on the open page
+    count: usize,
+}
+
+impl<F: Font + ?Sized, const DEPTH: usize> Ui<'_, F, DEPTH> {
+    /// A nested page. Selecting the item opens the page, whose items are declared by `build`.
+    pub fn page(&mut self, label: &str, build: impl FnOnce(&mut Self)) {
+        let index = self.index;
+        let open = self.level < self.menu.depth && self.menu.path[self.level] == index;
+
+        if let Some(input) = self.item(label, format_args!("...")) {
+            if input == Input::Accept && self.level + 1 < DEPTH {
+                self.menu.depth += 1;
+                self.menu.path[self.menu.depth] = 0;
+            }
+        }
+
+        if open {
+            if self.level + 1 == self.menu.depth {
+                self.line(self.menu.color, format_args!("= {} =", label));
+            }
+
+            self.level += 1;
+            self.index = 0;
+            build(self);
+            self.level -= 1;
+            self.index = index + 1;
+        }
+    }
+
+    /// A boolean switch. Returns `true` if the value was changed.
+    pub fn toggle(&mut self, label: &str, value: &mut bool) -> bool {
+        let state = if *value { "ON" } else { "OFF" };
+
+        match self.item(label, format_args!(": {}", state)) {
+            Some(Input::Left | Input::Right | Input::Accept) => {
+                *value = !*value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// An integer adjusted by `step` within `range`. Returns `true` if the value was changed.
+    pub fn slider(
+        &mut self,
+        label: &str,
+        value: &mut i32,
+        range: RangeInclusive<i32>,
+        step: i32,
+    ) -> bool {
+        let old = *value;
+
+        match self.item(label, format_args!(": {}", old)) {
+            Some(Input::Left) => *value = old.saturating_sub(step),
+            Some(Input::Right) => *value = old.saturating_add(step),
+            _ => (),
+        }
+        *value = (*value).clamp(*range.start(), *range.end());
+
+        *value != old
+    }
+
+    /// A button. Returns `true` if it was activated.
+    pub fn action(&mut self, label: &str) -> bool {
+        self.item(label, format_args!("")) == Some(Input::Accept)
+    }
+
+    /// A line of text that cannot be selected, drawn only while its page is open.
+    pub fn label(&mut self, args: fmt::Arguments<'_>) {
+        if self.level == self.menu.depth {
+            self.line(self.menu.color, args);
+        }
+    }
+
+    /// Declare an item, drawing it if its page is open. Returns the input for the item if it is
+    /// selected.
+    fn item(&mut self, label: &str, value: fmt::Arguments<'_>) -> Option<Input> {
+        let index = self.index;
+        self.index += 1;
+        if self.level != self.menu.depth {
+            return None;
+        }
+
+        self.count += 1;
+        if index != self.menu.path[self.level] {
+            self.line(self.menu.color, format_args!("  {}{}", label, value));
+            return None;
+        }
+
+        self.line(self.menu.highlight, format_args!("* {}{}", label, value));
+        match self.input {
+            Some(Input::Left | Input::Right | Input::Accept) => self.input.take(),
+            _ => None,
+        }
+    }
+
+    fn line(&mut self, color: u16, args: fmt::Arguments<'_>) {
+        let mut text = TextBuffer::new();
+        let _ = text.write_fmt(args);
+
+        font::draw_str_at(self.font, self.x, self.y, color, text.as_str());
+        self.y += self.font.height() + self.font.leading();
+    }
+}
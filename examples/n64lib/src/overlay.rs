@@ -0,0 +1,216 @@
+//! On-screen debug overlay.
+//!
+//! Draws frame timing, CPU load, heap usage and custom counters in a corner of the screen. Call
+//! [`Overlay::start_frame`] at the top of the main loop, [`Overlay::end_frame`] when the frame's
+//! work is done (before waiting for vblank), and [`Overlay::draw`] last thing before swapping
+//! buffers.
+
+use crate::font::{self, Font, Rect};
+use crate::gfx;
+use crate::ipl3font::TextBuffer;
+use crate::vi;
+use core::fmt::Write;
+use core::time::Duration;
+use rrt0::time::{self, Instant};
+
+/// Margin between the overlay and the edge of the screen (in pixels)
+const MARGIN: usize = 8;
+
+/// Padding between the overlay background and its text (in pixels)
+const PADDING: usize = 2;
+
+/// Screen corner for the overlay
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Debug overlay state, with room for `N` custom counters.
+pub struct Overlay<const N: usize> {
+    enabled: bool,
+    corner: Corner,
+    color: u16,
+    background: Option<u16>,
+    frame_start: Option<Instant>,
+    frame_ticks: u32,
+    work_ticks: u32,
+    vblank_misses: u32,
+    heap: Option<(usize, usize)>,
+    counters: [Option<(&'static str, i32)>; N],
+}
+
+impl<const N: usize> Overlay<N> {
+    /// Create a disabled overlay in the top-left corner.
+    pub const fn new(color: u16) -> Self {
+        Self {
+            enabled: false,
+            corner: Corner::TopLeft,
+            color,
+            background: None,
+            frame_start: None,
+            frame_ticks: 0,
+            work_ticks: 0,
+            vblank_misses: 0,
+            heap: None,
+            counters: [None; N],
+        }
+    }
+
+    /// Whether the overlay is drawn.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Show or hide the overlay. Statistics are collected either way.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Toggle the overlay, e.g. in response to a button press.
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    /// Move the overlay to a corner of the screen.
+    pub fn set_corner(&mut self, corner: Corner) {
+        self.corner = corner;
+    }
+
+    /// Fill the area behind the text with a solid color, or leave it transparent with `None`.
+    pub fn set_background(&mut self, background: Option<u16>) {
+        self.background = background;
+    }
+
+    /// Mark the start of a frame, updating frame time and counting missed vblanks since the
+    /// previous frame.
+    pub fn start_frame(&mut self) {
+        let now = Instant::now();
+
+        if let Some(start) = self.frame_start {
+            self.frame_ticks = now.ticks_since(start);
+
+            // Anything past the first refresh period means at least one vblank was missed
+            let period = time::COUNTER_FREQUENCY / vi::get_refresh_rate();
+            let periods = (self.frame_ticks + period / 2) / period;
+            self.vblank_misses += periods.saturating_sub(1);
+        }
+
+        self.frame_start = Some(now);
+    }
+
+    /// Mark the end of the frame's work, for measuring CPU load.
+    pub fn end_frame(&mut self) {
+        if let Some(start) = self.frame_start {
+            self.work_ticks = Instant::now().ticks_since(start);
+        }
+    }
+
+    /// Duration of the last full frame.
+    pub fn frame_time(&self) -> Duration {
+        time::ticks_to_duration(self.frame_ticks)
+    }
+
+    /// Total number of missed vblanks.
+    pub fn vblank_misses(&self) -> u32 {
+        self.vblank_misses
+    }
+
+    /// Reset the missed vblank count.
+    pub fn reset_vblank_misses(&mut self) {
+        self.vblank_misses = 0;
+    }
+
+    /// Report heap usage (in bytes) for display.
+    pub fn set_heap_usage(&mut self, used: usize, total: usize) {
+        self.heap = Some((used, total));
+    }
+
+    /// Set a custom counter, adding it if it doesn't exist. Counters beyond the capacity of the
+    /// overlay are ignored.
+    pub fn set_counter(&mut self, name: &'static str, value: i32) {
+        let index = self
+            .counters
+            .iter()
+            .position(|slot| matches!(slot, Some((n, _)) if *n == name))
+            .or_else(|| self.counters.iter().position(|slot| slot.is_none()));
+
+        if let Some(index) = index {
+            self.counters[index] = Some((name, value));
+        }
+    }
+
+    /// Remove a custom counter.
+    pub fn remove_counter(&mut self, name: &'static str) {
+        for slot in &mut self.counters {
+            if matches!(slot, Some((n, _)) if *n == name) {
+                *slot = None;
+            }
+        }
+    }
+
+    /// Draw the overlay onto the back buffer with the given font, if it is enabled.
+    pub fn draw<F: Font + ?Sized>(&self, font: &F) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut text = TextBuffer::new();
+        let _ = self.format(&mut text);
+        let text = text.as_str();
+
+        let extent = font::measure_str(font, text);
+        let width = extent.width + PADDING * 2;
+        let height = extent.height + PADDING * 2;
+        let x = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => MARGIN,
+            Corner::TopRight | Corner::BottomRight => vi::WIDTH.saturating_sub(width + MARGIN),
+        };
+        let y = match self.corner {
+            Corner::TopLeft | Corner::TopRight => MARGIN,
+            Corner::BottomLeft | Corner::BottomRight => vi::HEIGHT.saturating_sub(height + MARGIN),
+        };
+
+        if let Some(background) = self.background {
+            let mut surface = gfx::back_buffer();
+            surface.fill_rect(x as i32, y as i32, width as u32, height as u32, background);
+        }
+
+        let rect = Rect {
+            x: x + PADDING,
+            y: y + PADDING,
+            width: extent.width,
+            height: extent.height,
+        };
+        font::draw_str_wrapped(font, rect, self.color, text);
+    }
+
+    fn format(&self, out: &mut TextBuffer) -> core::fmt::Result {
+        let frame_us = self.frame_time().as_micros() as u32;
+        let fps_x10 = if frame_us > 0 {
+            10_000_000 / frame_us
+        } else {
+            0
+        };
+        let load = if self.frame_ticks > 0 {
+            self.work_ticks as u64 * 100 / self.frame_ticks as u64
+        } else {
+            0
+        };
+
+        write!(out, "FPS {}.{}", fps_x10 / 10, fps_x10 % 10)?;
+        write!(out, "\nMS {}.{}", frame_us / 1000, frame_us % 1000 / 100)?;
+        write!(out, "\nCPU {}%", load)?;
+        write!(out, "\nMISS {}", self.vblank_misses)?;
+        if let Some((used, total)) = self.heap {
+            write!(out, "\nHEAP {}K/{}K", used / 1024, total / 1024)?;
+        }
+        for (name, value) in self.counters.iter().flatten() {
+            write!(out, "\n{} {}", name, value)?;
+        }
+
+        Ok(())
+    }
+}
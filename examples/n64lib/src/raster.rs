@@ -0,0 +1,178 @@
+//! Software triangle rasterizer.
+//!
+//! Fills triangles on a [`Surface`] one scanline at a time, with flat or Gouraud shading. Pixels
+//! are sampled at their centers with a top-left fill convention, so triangles sharing an edge
+//! never overlap or leave gaps.
+
+use crate::color::Color;
+use crate::gfx::{Pixel, Surface};
+
+/// A triangle vertex in screen space, with a color for Gouraud shading.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vertex {
+    pub x: f32,
+    pub y: f32,
+    pub color: Color,
+}
+
+impl Vertex {
+    /// Create a vertex.
+    pub const fn new(x: f32, y: f32, color: Color) -> Self {
+        Self { x, y, color }
+    }
+}
+
+/// A simple perspective projection from camera space to screen space.
+///
+/// Camera space is right-handed with +Y up, looking down +Z.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Projection {
+    /// Distance from the eye to the projection plane (in pixels)
+    pub focal_length: f32,
+    /// Screen position of the optical axis (in pixels)
+    pub center: (f32, f32),
+    /// Points closer than this are not projected
+    pub near: f32,
+}
+
+impl Projection {
+    /// Create a projection for a screen of the given size with a vertical field of view (in
+    /// radians).
+    pub fn new(fov_y: f32, width: usize, height: usize, near: f32) -> Self {
+        let focal_length = height as f32 / 2.0 / rrt0::tanf(fov_y / 2.0);
+
+        Self {
+            focal_length,
+            center: (width as f32 / 2.0, height as f32 / 2.0),
+            near,
+        }
+    }
+
+    /// Project a point to screen space. Returns `None` if the point is closer than the near
+    /// plane.
+    pub fn project(&self, point: [f32; 3]) -> Option<(f32, f32)> {
+        let [x, y, z] = point;
+        if z < self.near {
+            return None;
+        }
+
+        let scale = self.focal_length / z;
+        Some((self.center.0 + x * scale, self.center.1 - y * scale))
+    }
+}
+
+/// Color as floating point components, for interpolation.
+type Components = [f32; 4];
+
+impl<P: Pixel> Surface<'_, P> {
+    /// Fill a triangle with a single color.
+    pub fn fill_triangle(&mut self, a: (f32, f32), b: (f32, f32), c: (f32, f32), color: P) {
+        let vertex = |(x, y)| Vertex::new(x, y, Color::TRANSPARENT);
+
+        self.rasterize(
+            [vertex(a), vertex(b), vertex(c)],
+            |surface, x0, x1, y, _, _| {
+                surface.hspan(x0, x1, y, color);
+            },
+        );
+    }
+
+    /// Fill a triangle, interpolating the vertex colors across its surface.
+    pub fn fill_triangle_gouraud(&mut self, vertices: [Vertex; 3]) {
+        self.rasterize(vertices, |surface, x0, x1, y, left, right| {
+            let (xl, cl) = left;
+            let (xr, cr) = right;
+            let width = xr - xl;
+
+            for x in x0.max(0)..=x1.min(surface.width() as i32 - 1) {
+                let t = if width > 0.0 {
+                    (x as f32 + 0.5 - xl) / width
+                } else {
+                    0.0
+                };
+                let [r, g, b, a] = lerp(cl, cr, t).map(|c| c as u8);
+
+                surface.set_pixel(x, y, P::from_color(Color::rgba(r, g, b, a)));
+            }
+        });
+    }
+
+    /// Walk the scanlines covered by a triangle, calling `span` with the inclusive range of
+    /// covered pixels on each row, along with the exact position and color of the left and right
+    /// edges.
+    fn rasterize(
+        &mut self,
+        mut v: [Vertex; 3],
+        mut span: impl FnMut(&mut Self, i32, i32, i32, (f32, Components), (f32, Components)),
+    ) {
+        v.sort_unstable_by(|a, b| a.y.partial_cmp(&b.y).unwrap_or(core::cmp::Ordering::Equal));
+        let [v0, v1, v2] = v;
+
+        // First and last scanlines whose centers are covered, clipped to the surface
+        let y_start = pixel_start(v0.y).max(0);
+        let y_end = pixel_start(v2.y).min(self.height() as i32);
+
+        for y in y_start..y_end {
+            let yc = y as f32 + 0.5;
+
+            let long = edge(&v0, &v2, yc);
+            let short = if yc < v1.y {
+                edge(&v0, &v1, yc)
+            } else {
+                edge(&v1, &v2, yc)
+            };
+            let (left, right) = if long.0 <= short.0 {
+                (long, short)
+            } else {
+                (short, long)
+            };
+
+            let x0 = pixel_start(left.0);
+            let x1 = pixel_start(right.0) - 1;
+            if x0 <= x1 {
+                span(self, x0, x1, y, left, right);
+            }
+        }
+    }
+}
+
+/// First pixel whose center is at or after the given coordinate.
+fn pixel_start(coord: f32) -> i32 {
+    let coord = coord - 0.5;
+    let truncated = coord as i32;
+
+    if (truncated as f32) < coord {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
+
+/// Position and color where the edge from `a` to `b` crosses the given row.
+fn edge(a: &Vertex, b: &Vertex, y: f32) -> (f32, Components) {
+    let height = b.y - a.y;
+    let t = if height > 0.0 {
+        (y - a.y) / height
+    } else {
+        0.0
+    };
+
+    let x = a.x + (b.x - a.x) * t;
+    let color = lerp(components(a.color), components(b.color), t);
+
+    (x, color)
+}
+
+fn components(color: Color) -> Components {
+    [color.r, color.g, color.b, color.a].map(|c| c as f32)
+}
+
+/// Linear interpolation between two colors.
+fn lerp(a: Components, b: Components, t: f32) -> Components {
+    let mut color = a;
+    for (c, b) in color.iter_mut().zip(b) {
+        *c += (b - *c) * t;
+    }
+
+    color
+}
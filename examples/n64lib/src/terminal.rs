@@ -0,0 +1,393 @@
+//! On-screen terminal.
+//!
+//! A scrolling character grid that understands the most common VT100/ANSI escape sequences, so
+//! text formatted for a serial terminal renders correctly on the frame buffer. Write to it with
+//! [`core::fmt::Write`] and draw it once per frame with [`Terminal::draw`].
+//!
+//! Supported control characters: `\n` (new line), `\r` (carriage return), `\t` (tab), and
+//! backspace.
+//!
+//! Supported escape sequences (`ESC [ ...`):
+//!
+//! - `A`, `B`, `C`, `D`: cursor up, down, forward, back
+//! - `H`, `f`: cursor position (1-based row and column)
+//! - `J`: erase in display (0: to end, 1: to start, 2 and 3: everything)
+//! - `K`: erase in line (0: to end, 1: to start, 2: everything)
+//! - `m`: colors (0: reset, 1: bright, 30-37 and 90-97: foreground, 40-47 and 100-107: background,
+//!   39 and 49: default)
+//!
+//! Other sequences are parsed and ignored.
+
+use crate::color::Color;
+use crate::font::Font;
+use crate::gfx;
+use core::fmt;
+
+/// Tab stop interval (in columns)
+const TAB_WIDTH: usize = 8;
+
+/// Maximum number of numeric parameters in an escape sequence
+const MAX_PARAMS: usize = 4;
+
+/// ANSI color palette: black, red, green, yellow, blue, magenta, cyan, white
+const PALETTE: [Color; 8] = [
+    Color::rgb(0x00, 0x00, 0x00),
+    Color::rgb(0xAA, 0x00, 0x00),
+    Color::rgb(0x00, 0xAA, 0x00),
+    Color::rgb(0xAA, 0x55, 0x00),
+    Color::rgb(0x00, 0x00, 0xAA),
+    Color::rgb(0xAA, 0x00, 0xAA),
+    Color::rgb(0x00, 0xAA, 0xAA),
+    Color::rgb(0xAA, 0xAA, 0xAA),
+];
+
+/// Bright ANSI color palette
+const BRIGHT_PALETTE: [Color; 8] = [
+    Color::rgb(0x55, 0x55, 0x55),
+    Color::rgb(0xFF, 0x55, 0x55),
+    Color::rgb(0x55, 0xFF, 0x55),
+    Color::rgb(0xFF, 0xFF, 0x55),
+    Color::rgb(0x55, 0x55, 0xFF),
+    Color::rgb(0xFF, 0x55, 0xFF),
+    Color::rgb(0x55, 0xFF, 0xFF),
+    Color::rgb(0xFF, 0xFF, 0xFF),
+];
+
+const DEFAULT_FG: u16 = PALETTE[7].to_rgba5551();
+const DEFAULT_BG: u16 = PALETTE[0].to_rgba5551();
+
+/// A single character cell
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Cell {
+    ch: char,
+    fg: u16,
+    bg: u16,
+}
+
+impl Cell {
+    const BLANK: Self = Self {
+        ch: ' ',
+        fg: DEFAULT_FG,
+        bg: DEFAULT_BG,
+    };
+}
+
+/// Escape sequence parser state
+#[derive(Clone, Copy, Debug)]
+enum State {
+    Ground,
+    Escape,
+    Csi {
+        params: [u16; MAX_PARAMS],
+        count: usize,
+    },
+}
+
+/// A scrolling terminal with `COLS` columns and `LINES` lines of storage, shared between the
+/// visible screen and the scrollback history.
+pub struct Terminal<const COLS: usize, const LINES: usize> {
+    lines: [[Cell; COLS]; LINES],
+    /// Index of the oldest stored line in the ring buffer
+    first: usize,
+    /// Number of stored lines, including the visible screen
+    len: usize,
+    /// Number of visible rows
+    rows: usize,
+    /// Number of lines the view is scrolled back into the history
+    view: usize,
+    row: usize,
+    col: usize,
+    fg: u16,
+    bg: u16,
+    bright: bool,
+    state: State,
+}
+
+impl<const COLS: usize, const LINES: usize> Terminal<COLS, LINES> {
+    /// Create a blank terminal with the given number of visible rows. The remaining lines are
+    /// used for scrollback.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rows` is zero or greater than `LINES`, or if `COLS` is zero.
+    pub fn new(rows: usize) -> Self {
+        assert!(rows > 0 && rows <= LINES, "Invalid terminal size");
+        assert!(COLS > 0, "Invalid terminal size");
+
+        Self {
+            lines: [[Cell::BLANK; COLS]; LINES],
+            first: 0,
+            len: rows,
+            rows,
+            view: 0,
+            row: 0,
+            col: 0,
+            fg: DEFAULT_FG,
+            bg: DEFAULT_BG,
+            bright: false,
+            state: State::Ground,
+        }
+    }
+
+    /// Cursor position as `(row, column)`, 0-based.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// Number of lines of history above the visible screen.
+    pub fn history(&self) -> usize {
+        self.len - self.rows
+    }
+
+    /// Scroll the view back into the history. Scrolling stops at the oldest line.
+    pub fn scroll_back(&mut self, lines: usize) {
+        self.view = (self.view + lines).min(self.history());
+    }
+
+    /// Scroll the view forward towards the live screen.
+    pub fn scroll_forward(&mut self, lines: usize) {
+        self.view = self.view.saturating_sub(lines);
+    }
+
+    /// Clear the screen and history, and reset the cursor and colors.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.rows);
+    }
+
+    /// Process a single character of output.
+    pub fn put_char(&mut self, ch: char) {
+        match self.state {
+            State::Ground => self.ground(ch),
+            State::Escape => {
+                self.state = if ch == '[' {
+                    State::Csi {
+                        params: [0; MAX_PARAMS],
+                        count: 0,
+                    }
+                } else {
+                    State::Ground
+                };
+            }
+            State::Csi {
+                mut params,
+                mut count,
+            } => match ch {
+                '0'..='9' => {
+                    let digit = ch as u16 - '0' as u16;
+                    count = count.max(1);
+                    let param = &mut params[count - 1];
+                    *param = param.saturating_mul(10).saturating_add(digit);
+                    self.state = State::Csi { params, count };
+                }
+                ';' => {
+                    count = (count.max(1) + 1).min(MAX_PARAMS);
+                    self.state = State::Csi { params, count };
+                }
+                '\x40'..='\x7E' => {
+                    self.state = State::Ground;
+                    self.csi(ch, &params[..count]);
+                }
+                _ => self.state = State::Csi { params, count },
+            },
+        }
+    }
+
+    /// Draw the visible rows with their top-left corner at the given position on the back
+    /// buffer. Cells are sized to fit the glyph for `M` in the font.
+    pub fn draw<F: Font + ?Sized>(&self, font: &F, x: usize, y: usize) {
+        let cell_width = font.glyph_width('M') + font.spacing();
+        let cell_height = font.height() + font.leading();
+        let visible = || {
+            (0..self.rows).map(move |row| {
+                let line = &self.lines[self.index(self.len - self.rows - self.view + row)];
+                (y + row * cell_height, line)
+            })
+        };
+
+        // Fill the backgrounds first; the surface must be dropped before drawing glyphs
+        {
+            let mut surface = gfx::back_buffer();
+            for (yy, line) in visible() {
+                for (col, cell) in line.iter().enumerate() {
+                    let xx = x + col * cell_width;
+                    surface.fill_rect(
+                        xx as i32,
+                        yy as i32,
+                        cell_width as u32,
+                        cell_height as u32,
+                        cell.bg,
+                    );
+                }
+            }
+        }
+
+        for (yy, line) in visible() {
+            for (col, cell) in line.iter().enumerate() {
+                if cell.ch != ' ' {
+                    font.draw_glyph(x + col * cell_width, yy, cell.fg, cell.ch);
+                }
+            }
+        }
+    }
+
+    fn ground(&mut self, ch: char) {
+        match ch {
+            '\x1B' => self.state = State::Escape,
+            '\n' => {
+                self.col = 0;
+                self.line_feed();
+            }
+            '\r' => self.col = 0,
+            '\t' => self.col = ((self.col / TAB_WIDTH + 1) * TAB_WIDTH).min(COLS - 1),
+            '\x08' => self.col = self.col.saturating_sub(1),
+            ch if ch.is_control() => (),
+            ch => {
+                if self.col >= COLS {
+                    self.col = 0;
+                    self.line_feed();
+                }
+
+                let cell = Cell {
+                    ch,
+                    fg: self.fg,
+                    bg: self.bg,
+                };
+                *self.cell_mut(self.row, self.col) = cell;
+                self.col += 1;
+            }
+        }
+    }
+
+    fn csi(&mut self, command: char, params: &[u16]) {
+        let param = |index: usize, default: u16| match params.get(index) {
+            Some(&0) | None => default as usize,
+            Some(&value) => value as usize,
+        };
+
+        match command {
+            'A' => self.row = self.row.saturating_sub(param(0, 1)),
+            'B' => self.row = (self.row + param(0, 1)).min(self.rows - 1),
+            'C' => self.col = (self.col + param(0, 1)).min(COLS - 1),
+            'D' => self.col = self.col.min(COLS - 1).saturating_sub(param(0, 1)),
+            'H' | 'f' => {
+                self.row = (param(0, 1) - 1).min(self.rows - 1);
+                self.col = (param(1, 1) - 1).min(COLS - 1);
+            }
+            'J' => match param(0, 0) {
+                0 => {
+                    self.erase_line(self.row, self.col, COLS);
+                    for row in self.row + 1..self.rows {
+                        self.erase_line(row, 0, COLS);
+                    }
+                }
+                1 => {
+                    for row in 0..self.row {
+                        self.erase_line(row, 0, COLS);
+                    }
+                    self.erase_line(self.row, 0, self.col + 1);
+                }
+                _ => {
+                    for row in 0..self.rows {
+                        self.erase_line(row, 0, COLS);
+                    }
+                }
+            },
+            'K' => match param(0, 0) {
+                0 => self.erase_line(self.row, self.col, COLS),
+                1 => self.erase_line(self.row, 0, self.col + 1),
+                _ => self.erase_line(self.row, 0, COLS),
+            },
+            'm' => {
+                if params.is_empty() {
+                    self.sgr(0);
+                }
+                for &param in params {
+                    self.sgr(param);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Select graphic rendition
+    fn sgr(&mut self, param: u16) {
+        let palette = |bright: bool, index: u16| {
+            let palette = if bright { &BRIGHT_PALETTE } else { &PALETTE };
+            palette[index as usize].to_rgba5551()
+        };
+
+        match param {
+            0 => {
+                self.fg = DEFAULT_FG;
+                self.bg = DEFAULT_BG;
+                self.bright = false;
+            }
+            1 => self.bright = true,
+            22 => self.bright = false,
+            30..=37 => self.fg = palette(self.bright, param - 30),
+            39 => self.fg = DEFAULT_FG,
+            40..=47 => self.bg = palette(false, param - 40),
+            49 => self.bg = DEFAULT_BG,
+            90..=97 => self.fg = palette(true, param - 90),
+            100..=107 => self.bg = palette(true, param - 100),
+            _ => (),
+        }
+    }
+
+    /// Move the cursor down one row, scrolling the screen if it is on the last row.
+    fn line_feed(&mut self) {
+        if self.row + 1 < self.rows {
+            self.row += 1;
+            return;
+        }
+
+        // Push a new line onto the screen, moving the top row into the history
+        if self.len < LINES {
+            self.len += 1;
+        } else {
+            self.first = (self.first + 1) % LINES;
+        }
+
+        let index = self.index(self.len - 1);
+        self.lines[index] = [Cell::BLANK; COLS];
+
+        // Keep the view steady while scrolled back
+        if self.view > 0 {
+            self.scroll_back(1);
+        }
+    }
+
+    /// Erase columns `start..end` of a visible row with the current background color.
+    fn erase_line(&mut self, row: usize, start: usize, end: usize) {
+        let blank = Cell {
+            bg: self.bg,
+            ..Cell::BLANK
+        };
+
+        let index = self.index(self.len - self.rows + row);
+        let end = end.min(COLS);
+        if start < end {
+            self.lines[index][start..end].fill(blank);
+        }
+    }
+
+    fn cell_mut(&mut self, row: usize, col: usize) -> &mut Cell {
+        let index = self.index(self.len - self.rows + row);
+        &mut self.lines[index][col]
+    }
+
+    /// Ring buffer index of a stored line, where 0 is the oldest.
+    fn index(&self, line: usize) -> usize {
+        (self.first + line) % LINES
+    }
+}
+
+impl<const COLS: usize, const LINES: usize> fmt::Write for Terminal<COLS, LINES> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for ch in s.chars() {
+            self.put_char(ch);
+        }
+
+        Ok(())
+    }
+}
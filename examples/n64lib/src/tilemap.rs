@@ -0,0 +1,201 @@
+//! Tile-based background renderer.
+//!
+//! Draws a scrolling 2D map of tiles from a tileset onto a [`Surface`]. Each row of each visible
+//! tile is copied as a single span, which is much faster than blitting tiles pixel by pixel.
+
+use crate::gfx::{Pixel, Rect, Sprite, Surface};
+
+/// A sprite sheet of equally sized tiles, indexed left-to-right, top-to-bottom.
+#[derive(Clone, Copy, Debug)]
+pub struct Tileset<'a, P: Pixel> {
+    sprite: Sprite<'a, P>,
+    tile_width: usize,
+    tile_height: usize,
+    columns: usize,
+    count: usize,
+}
+
+impl<'a, P: Pixel> Tileset<'a, P> {
+    /// Create a tileset from a sprite sheet. Partial tiles at the right and bottom edges of the
+    /// sprite are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile size is zero.
+    pub fn new(sprite: Sprite<'a, P>, tile_width: usize, tile_height: usize) -> Self {
+        assert!(
+            tile_width > 0 && tile_height > 0,
+            "Tile size must not be zero"
+        );
+
+        let columns = sprite.width() / tile_width;
+        let rows = sprite.height() / tile_height;
+
+        Self {
+            sprite,
+            tile_width,
+            tile_height,
+            columns,
+            count: columns * rows,
+        }
+    }
+
+    /// Tile width (in pixels)
+    pub fn tile_width(&self) -> usize {
+        self.tile_width
+    }
+
+    /// Tile height (in pixels)
+    pub fn tile_height(&self) -> usize {
+        self.tile_height
+    }
+
+    /// Number of tiles in the tileset
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Whether the tileset contains no tiles
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Pixels of one row of a tile.
+    fn row(&self, tile: usize, y: usize) -> &'a [P] {
+        let x = (tile % self.columns) * self.tile_width;
+        let y = (tile / self.columns) * self.tile_height + y;
+        let offset = y * self.sprite.width() + x;
+
+        &self.sprite.pixels()[offset..offset + self.tile_width]
+    }
+}
+
+/// A 2D map of tile indices drawn with a [`Tileset`].
+#[derive(Clone, Copy, Debug)]
+pub struct Tilemap<'a, P: Pixel> {
+    tileset: Tileset<'a, P>,
+    tiles: &'a [u16],
+    width: usize,
+    height: usize,
+    wrap: bool,
+    empty: Option<u16>,
+}
+
+impl<'a, P: Pixel> Tilemap<'a, P> {
+    /// Create a map of `width * height` tiles in row-major order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map is too small.
+    pub fn new(tileset: Tileset<'a, P>, tiles: &'a [u16], width: usize, height: usize) -> Self {
+        assert!(tiles.len() >= width * height, "Tilemap is too small");
+
+        Self {
+            tileset,
+            tiles,
+            width,
+            height,
+            wrap: false,
+            empty: None,
+        }
+    }
+
+    /// Repeat the map infinitely in both directions when scrolling past its edges.
+    /// Otherwise, pixels outside of the map are left untouched.
+    pub fn with_wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Skip drawing tiles with the given index, leaving the pixels behind them untouched.
+    /// Tiles with indices outside of the tileset are always skipped.
+    pub fn with_empty_tile(mut self, tile: Option<u16>) -> Self {
+        self.empty = tile;
+        self
+    }
+
+    /// Map width (in tiles)
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Map height (in tiles)
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the tile at the given map position, or `None` if it is outside of the map.
+    pub fn tile(&self, x: usize, y: usize) -> Option<u16> {
+        if x < self.width && y < self.height {
+            Some(self.tiles[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    /// Draw the map into a region of a surface. The pixel at `(scroll_x, scroll_y)` in the map is
+    /// drawn at the top-left corner of the viewport.
+    pub fn draw(&self, surface: &mut Surface<'_, P>, viewport: Rect, scroll_x: i32, scroll_y: i32) {
+        let tile_width = self.tileset.tile_width;
+        let tile_height = self.tileset.tile_height;
+        let map_width = (self.width * tile_width) as i32;
+        let map_height = (self.height * tile_height) as i32;
+        if map_width == 0 || map_height == 0 {
+            return;
+        }
+
+        // Clip the viewport to the surface
+        let surface_width = surface.width();
+        let x0 = viewport.x.min(surface_width);
+        let x1 = (viewport.x + viewport.width).min(surface_width);
+        let y0 = viewport.y.min(surface.height());
+        let y1 = (viewport.y + viewport.height).min(surface.height());
+        let pixels = surface.pixels_mut();
+
+        for y in y0..y1 {
+            let world_y = match self.world(scroll_y + (y - viewport.y) as i32, map_height) {
+                Some(world_y) => world_y,
+                None => continue,
+            };
+            let row = world_y / tile_height;
+            let tile_y = world_y % tile_height;
+            let dst_row = &mut pixels[y * surface_width..][..x1];
+
+            let mut x = x0;
+            while x < x1 {
+                let world_x = scroll_x + (x - viewport.x) as i32;
+                let world_x = match self.world(world_x, map_width) {
+                    Some(world_x) => world_x,
+                    None if world_x < 0 => {
+                        // Skip ahead to the left edge of the map
+                        x += (-world_x) as usize;
+                        continue;
+                    }
+                    None => break,
+                };
+                let col = world_x / tile_width;
+                let tile_x = world_x % tile_width;
+                let len = (tile_width - tile_x).min(x1 - x);
+
+                let tile = self.tiles[row * self.width + col];
+                if Some(tile) != self.empty && (tile as usize) < self.tileset.count {
+                    let src = &self.tileset.row(tile as usize, tile_y)[tile_x..tile_x + len];
+                    dst_row[x..x + len].copy_from_slice(src);
+                }
+
+                x += len;
+            }
+        }
+    }
+
+    /// Map a world coordinate into the map, wrapping if enabled.
+    fn world(&self, coord: i32, size: i32) -> Option<usize> {
+        if self.wrap {
+            Some(coord.rem_euclid(size) as usize)
+        } else if (0..size).contains(&coord) {
+            Some(coord as usize)
+        } else {
+            None
+        }
+    }
+}
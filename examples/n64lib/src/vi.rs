@@ -2,33 +2,19 @@
 //!
 //! Provides low level access to the N64 vi hardware.
 
+use crate::color::{self, Color};
 use core::ptr::read_volatile;
+use rrt0::mem::map;
+use rrt0::mmio::{ViCtrl, VI};
 
 // TODO: Heap allocate (needs std and global_allocator)
-const FRAME_BUFFER: *mut u16 = 0xA010_0000 as *mut u16;
+const FRAME_BUFFER: *mut u16 = map::to_kseg1(map::RDRAM + 0x10_0000) as *mut u16;
 
 pub const WIDTH: usize = 320;
 pub const HEIGHT: usize = 240;
 pub const FRAME_BUFFER_SIZE: usize = WIDTH * HEIGHT * 2;
 
-const VI_BASE: usize = 0xA440_0000;
-
-const VI_STATUS: *mut u32 = VI_BASE as *mut u32;
-const VI_DRAM_ADDR: *mut usize = (VI_BASE + 0x04) as *mut usize;
-const VI_H_WIDTH: *mut u32 = (VI_BASE + 0x08) as *mut u32;
-const VI_V_INTR: *mut u32 = (VI_BASE + 0x0C) as *mut u32;
-const VI_CURRENT: *const u32 = (VI_BASE + 0x10) as *const u32;
-const VI_TIMING: *mut u32 = (VI_BASE + 0x14) as *mut u32;
-const VI_V_SYNC: *mut u32 = (VI_BASE + 0x18) as *mut u32;
-const VI_H_SYNC: *mut u32 = (VI_BASE + 0x1C) as *mut u32;
-const VI_H_SYNC_LEAP: *mut u32 = (VI_BASE + 0x20) as *mut u32;
-const VI_H_VIDEO: *mut u32 = (VI_BASE + 0x24) as *mut u32;
-const VI_V_VIDEO: *mut u32 = (VI_BASE + 0x28) as *mut u32;
-const VI_V_BURST: *mut u32 = (VI_BASE + 0x2C) as *mut u32;
-const VI_X_SCALE: *mut u32 = (VI_BASE + 0x30) as *mut u32;
-const VI_Y_SCALE: *mut u32 = (VI_BASE + 0x34) as *mut u32;
-
-const VIDEO_MODE: *const u32 = 0x8000_0300 as *const u32;
+const VIDEO_MODE: *const u32 = map::to_kseg0(map::OS_TV_TYPE) as *const u32;
 
 pub enum VideoMode {
     PAL,
@@ -45,6 +31,14 @@ pub fn get_video_frequency() -> u32 {
     }
 }
 
+/// Display refresh rate in Hertz
+pub fn get_refresh_rate() -> u32 {
+    match get_video_mode() {
+        VideoMode::PAL => 50,
+        VideoMode::NTSC | VideoMode::MPAL => 60,
+    }
+}
+
 /// Returns the current video mode
 pub fn get_video_mode() -> VideoMode {
     match unsafe { read_volatile(VIDEO_MODE) } {
@@ -54,19 +48,20 @@ pub fn get_video_mode() -> VideoMode {
     }
 }
 
+/// Whether the VI is in VBlank
+pub fn in_vblank() -> bool {
+    let current_halfline = VI.v_current.read();
+    current_halfline <= 10
+}
+
 /// Busy-wait for VBlank
 pub fn wait_for_ready() {
-    loop {
-        let current_halfline = unsafe { read_volatile(VI_CURRENT) };
-        if current_halfline <= 10 {
-            break;
-        }
-    }
+    while !in_vblank() {}
 }
 
 /// Return a raw pointer to the back buffer
 pub fn next_buffer() -> *mut u16 {
-    let current_fb = unsafe { read_volatile(VI_DRAM_ADDR) };
+    let current_fb = VI.origin.read();
 
     if current_fb & 0xFFFFF != 0 {
         FRAME_BUFFER
@@ -77,36 +72,39 @@ pub fn next_buffer() -> *mut u16 {
 
 /// Swap frame buffers (display the back buffer)
 pub fn swap_buffer() {
-    unsafe {
-        *VI_DRAM_ADDR = next_buffer() as usize;
-    }
+    VI.origin.write(next_buffer() as u32);
 }
 
 /// Initialize Video Interface with 320x240x16 resolution and double buffering
 pub fn init() {
     // Clear both frame buffers to black, writing two pixels at a time
+    let black = Color::BLACK.to_rgba5551();
     let frame_buffer = FRAME_BUFFER as usize;
     for i in 0..WIDTH * HEIGHT {
         let p = (frame_buffer + i * 4) as *mut u32;
         unsafe {
-            *p = 0x0001_0001;
+            *p = color::pack_rgba5551_pair(black, black);
         }
     }
 
     // Initialize VI
-    unsafe {
-        *VI_STATUS = 0x0000_320E;
-        *VI_DRAM_ADDR = frame_buffer;
-        *VI_H_WIDTH = WIDTH as u32;
-        *VI_V_INTR = 2;
-        *VI_TIMING = 0x03E5_2239;
-        *VI_V_SYNC = 0x0000_020D;
-        *VI_H_SYNC = 0x0000_0C15;
-        *VI_H_SYNC_LEAP = 0x0C15_0C15;
-        *VI_H_VIDEO = 0x006C_02EC;
-        *VI_V_VIDEO = 0x0025_01FF;
-        *VI_V_BURST = 0x000E_0204;
-        *VI_X_SCALE = 0x0000_0200;
-        *VI_Y_SCALE = 0x0000_0400;
-    }
+    let ctrl = ViCtrl::default()
+        .with_pixel_type(2)
+        .with_gamma_dither(true)
+        .with_gamma(true)
+        .with_aa_mode(2)
+        .with_pixel_advance(3);
+    VI.ctrl.write(ctrl);
+    VI.origin.write(frame_buffer as u32);
+    VI.width.write(WIDTH as u32);
+    VI.v_intr.write(2);
+    VI.burst.write(0x03E5_2239);
+    VI.v_sync.write(0x0000_020D);
+    VI.h_sync.write(0x0000_0C15);
+    VI.h_sync_leap.write(0x0C15_0C15);
+    VI.h_video.write(0x006C_02EC);
+    VI.v_video.write(0x0025_01FF);
+    VI.v_burst.write(0x000E_0204);
+    VI.x_scale.write(0x0000_0200);
+    VI.y_scale.write(0x0000_0400);
 }
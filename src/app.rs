@@ -0,0 +1,194 @@
+//! Game loop framework.
+//!
+//! A program implements [`App`], splitting each frame into updating its state and drawing it, and
+//! hands it to [`run`] with a [`Backend`] for the display and controller. The loop polls the
+//! controller, updates, draws to the back buffer, swaps it in at the next vertical blank, and
+//! passes the time that frame took to the next update:
+//!
+//! ```text
+//! struct Game {
+//!     x: i32,
+//! }
+//!
+//! impl App for Game {
+//!     fn update(&mut self, _dt: Duration, input: &Input) {
+//!         if input.is_held(BUTTON_RIGHT) {
+//!             self.x += 1;
+//!         }
+//!     }
+//!
+//!     fn draw(&mut self, frame: &mut Frame<'_, u16>) {
+//!         frame.clear(0);
+//!         frame.set(self.x as usize, 120, 0xFFFF);
+//!     }
+//! }
+//!
+//! rrt0::app::run(Game { x: 0 }, &mut backend);
+//! ```
+//!
+//! The backend is an extension point, like the [`Screen`](crate::host::Screen) of the host
+//! simulation: display and controller drivers implement it, like the VI and controller backend
+//! of the `n64lib` example library on N64, and so does the [`Host`](crate::host::Host)
+//! simulation.
+//!
+//! Time is counted in vertical blanks rather than read from a clock, so an update after a dropped
+//! frame gets twice the refresh period.
+
+use core::time::Duration;
+
+/// Controller button bits, in the order the controller reports them
+pub const BUTTON_A: u16 = 0x8000;
+pub const BUTTON_B: u16 = 0x4000;
+pub const BUTTON_Z: u16 = 0x2000;
+pub const BUTTON_START: u16 = 0x1000;
+pub const BUTTON_UP: u16 = 0x0800;
+pub const BUTTON_DOWN: u16 = 0x0400;
+pub const BUTTON_LEFT: u16 = 0x0200;
+pub const BUTTON_RIGHT: u16 = 0x0100;
+pub const BUTTON_L: u16 = 0x0020;
+pub const BUTTON_R: u16 = 0x0010;
+pub const BUTTON_C_UP: u16 = 0x0008;
+pub const BUTTON_C_DOWN: u16 = 0x0004;
+pub const BUTTON_C_LEFT: u16 = 0x0002;
+pub const BUTTON_C_RIGHT: u16 = 0x0001;
+
+/// A program driven by [`run`]. `P` is the pixel type of the backend's frame buffers.
+pub trait App<P = u16> {
+    /// Advance the state by `dt`, the time since the previous update.
+    fn update(&mut self, dt: Duration, input: &Input);
+
+    /// Draw the state to the back buffer.
+    fn draw(&mut self, frame: &mut Frame<'_, P>);
+
+    /// Whether to leave the loop.
+    fn is_done(&self) -> bool {
+        false
+    }
+}
+
+/// The display and controller the loop runs on.
+pub trait Backend {
+    /// Pixel type of the frame buffers, e.g. `u16` for RGBA5551
+    type Pixel: Copy;
+
+    /// Time between vertical blanks
+    fn refresh_period(&self) -> Duration;
+
+    /// Block until the next vertical blank. Returns the number of vertical blanks since the
+    /// previous call, at least 1.
+    fn wait_vsync(&mut self) -> u32;
+
+    /// The controller buttons held down, as `BUTTON_*` bits in the order the controller reports
+    /// them.
+    fn buttons(&mut self) -> u16;
+
+    /// The back buffer, which is not shown until [`swap`](Self::swap).
+    fn frame(&mut self) -> Frame<'_, Self::Pixel>;
+
+    /// Show the back buffer from the next vertical blank, and draw to the other buffer.
+    fn swap(&mut self);
+
+    /// Whether the display is still open. The loop stops when it is closed.
+    fn is_open(&self) -> bool {
+        true
+    }
+}
+
+/// Controller state for one update.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Input {
+    /// Buttons held down
+    pub held: u16,
+    /// Buttons pressed since the previous update
+    pub pressed: u16,
+    /// Buttons released since the previous update
+    pub released: u16,
+}
+
+impl Input {
+    /// Compare the buttons held down with those of the previous update.
+    pub fn new(held: u16, previous: u16) -> Self {
+        Self {
+            held,
+            pressed: held & !previous,
+            released: !held & previous,
+        }
+    }
+
+    /// Whether all the buttons are held down.
+    pub fn is_held(&self, buttons: u16) -> bool {
+        self.held & buttons == buttons
+    }
+
+    /// Whether any of the buttons was pressed since the previous update.
+    pub fn is_pressed(&self, buttons: u16) -> bool {
+        self.pressed & buttons != 0
+    }
+
+    /// Whether any of the buttons was released since the previous update.
+    pub fn is_released(&self, buttons: u16) -> bool {
+        self.released & buttons != 0
+    }
+}
+
+/// A frame buffer to draw to, in rows of `width` pixels.
+#[derive(Debug)]
+pub struct Frame<'a, P> {
+    pub pixels: &'a mut [P],
+    pub width: usize,
+    pub height: usize,
+}
+
+impl<'a, P: Copy> Frame<'a, P> {
+    /// Panics if the buffer is smaller than `width` by `height` pixels.
+    pub fn new(pixels: &'a mut [P], width: usize, height: usize) -> Self {
+        assert!(pixels.len() >= width * height, "frame buffer too small");
+
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Fill the frame with one color.
+    pub fn clear(&mut self, color: P) {
+        self.pixels.fill(color);
+    }
+
+    /// Set a pixel. Pixels outside the frame are ignored.
+    pub fn set(&mut self, x: usize, y: usize, color: P) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<P> {
+        match x < self.width && y < self.height {
+            true => Some(self.pixels[y * self.width + x]),
+            false => None,
+        }
+    }
+}
+
+/// Run the game loop until the app is done or the display is closed.
+pub fn run<A, B>(mut app: A, backend: &mut B)
+where
+    A: App<B::Pixel>,
+    B: Backend,
+{
+    let mut dt = backend.refresh_period();
+    let mut previous = 0;
+
+    while backend.is_open() && !app.is_done() {
+        let buttons = backend.buttons();
+        let input = Input::new(buttons, previous);
+        previous = buttons;
+
+        app.update(dt, &input);
+        app.draw(&mut backend.frame());
+        backend.swap();
+
+        dt = backend.refresh_period() * backend.wait_vsync().max(1);
+    }
+}
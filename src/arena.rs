@@ -0,0 +1,135 @@
+//! Arena allocation.
+//!
+//! An [`Arena`] hands out memory from a fixed buffer by bumping an offset, and frees everything at
+//! once when it is reset. This suits temporary data with a common lifetime, like per-frame
+//! scratch data, without fragmenting a long-lived heap. No global allocator is needed.
+//!
+//! Values allocated in an arena are never dropped.
+
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+
+/// A bump allocator over a borrowed buffer.
+pub struct Arena<'a> {
+    start: *mut u8,
+    capacity: usize,
+    offset: Cell<usize>,
+    buffer: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> Arena<'a> {
+    /// Create an arena that allocates from `buffer`.
+    pub fn new(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        Self {
+            start: buffer.as_mut_ptr().cast(),
+            capacity: buffer.len(),
+            offset: Cell::new(0),
+            buffer: PhantomData,
+        }
+    }
+
+    /// Size of the buffer (in bytes)
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of bytes allocated, including alignment padding
+    pub fn used(&self) -> usize {
+        self.offset.get()
+    }
+
+    /// Number of bytes left for allocation, ignoring alignment padding
+    pub fn remaining(&self) -> usize {
+        self.capacity - self.offset.get()
+    }
+
+    /// Free all allocations.
+    pub fn reset(&mut self) {
+        self.offset.set(0);
+    }
+
+    /// Run a function with a scope inside the arena. Allocations made in the scope are freed when
+    /// it returns, while earlier allocations are kept.
+    pub fn scope<R>(&mut self, f: impl FnOnce(&Arena<'_>) -> R) -> R {
+        let offset = self.offset.get();
+        let result = f(self);
+        self.offset.set(offset);
+
+        result
+    }
+
+    /// Allocate uninitialized memory with the given size and alignment. Returns `None` if the
+    /// arena is full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn alloc_raw(&self, size: usize, align: usize) -> Option<ptr::NonNull<u8>> {
+        assert!(align.is_power_of_two(), "Alignment must be a power of two");
+
+        let base = self.start as usize;
+        let current = base + self.offset.get();
+        let aligned = current.checked_add(align - 1)? & !(align - 1);
+        let end = aligned.checked_add(size)?;
+        if end > base + self.capacity {
+            return None;
+        }
+
+        self.offset.set(end - base);
+        ptr::NonNull::new(aligned as *mut u8)
+    }
+
+    /// Move a value into the arena. Returns the value back if the arena is full.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, value: T) -> Result<&mut T, T> {
+        match self.alloc_raw(mem::size_of::<T>(), mem::align_of::<T>()) {
+            Some(ptr) => {
+                let ptr = ptr.as_ptr().cast::<T>();
+                unsafe {
+                    ptr.write(value);
+                    Ok(&mut *ptr)
+                }
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Copy a slice into the arena. Returns `None` if the arena is full.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice<T: Copy>(&self, values: &[T]) -> Option<&mut [T]> {
+        let size = mem::size_of_val(values);
+        let ptr = self.alloc_raw(size, mem::align_of::<T>())?;
+        let ptr = ptr.as_ptr().cast::<T>();
+
+        unsafe {
+            ptr::copy_nonoverlapping(values.as_ptr(), ptr, values.len());
+            Some(core::slice::from_raw_parts_mut(ptr, values.len()))
+        }
+    }
+
+    /// Allocate a slice of `len` copies of a value. Returns `None` if the arena is full.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_fill<T: Copy>(&self, len: usize, value: T) -> Option<&mut [T]> {
+        let size = mem::size_of::<T>().checked_mul(len)?;
+        let ptr = self.alloc_raw(size, mem::align_of::<T>())?;
+        let ptr = ptr.as_ptr().cast::<T>();
+
+        unsafe {
+            for i in 0..len {
+                ptr.add(i).write(value);
+            }
+            Some(core::slice::from_raw_parts_mut(ptr, len))
+        }
+    }
+
+    /// Copy a string into the arena. Returns `None` if the arena is full.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_str(&self, s: &str) -> Option<&mut str> {
+        let bytes = self.alloc_slice(s.as_bytes())?;
+
+        // The bytes were copied from a valid string
+        Some(unsafe { core::str::from_utf8_unchecked_mut(bytes) })
+    }
+}
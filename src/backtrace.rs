@@ -0,0 +1,108 @@
+//! Stack backtraces.
+//!
+//! [`capture`] lists the return addresses on the stack of the calling code, innermost first. It
+//! can be used at any time, not only when panicking, e.g. to log which code triggered a slow path.
+//!
+//! Code is not built with frame pointers, so the stack is scanned for words that point just after
+//! a `jal` or `jalr` instruction in the program. Stale return addresses left behind by functions
+//! that already returned can show up as extra frames. On other platforms, backtraces are empty.
+
+/// An iterator over the return addresses on the stack, innermost first.
+#[derive(Clone, Debug)]
+pub struct Backtrace {
+    /// Address of the next stack word to scan
+    pos: usize,
+    /// Address just past the top of the stack
+    end: usize,
+}
+
+/// Capture a backtrace of the calling code.
+///
+/// The stack is scanned lazily, while the backtrace is iterated. It must be iterated before the
+/// calling function returns.
+#[inline(always)]
+pub fn capture() -> Backtrace {
+    Backtrace {
+        pos: platform::stack_pointer(),
+        end: platform::stack_top(),
+    }
+}
+
+impl Iterator for Backtrace {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.pos < self.end {
+            let word = unsafe { (self.pos as *const usize).read_volatile() };
+            self.pos += core::mem::size_of::<usize>();
+            if platform::is_return_address(word) {
+                return Some(word);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use crate::mem::map;
+    use core::arch::asm;
+
+    /// Boot value holding the installed RDRAM size
+    const OS_MEM_SIZE: *const usize = map::to_kseg0(map::OS_MEM_SIZE) as *const usize;
+
+    /// Start of the program code, after the boot segment's low memory
+    const TEXT_START: usize = map::to_kseg0(map::TEXT_START);
+
+    /// Gap the entrypoint leaves between the initial stack pointer and the end of RDRAM
+    const STACK_TOP_GAP: usize = 0x10;
+
+    extern "C" {
+        /// Start of `.bss`, which the linker script places after the code and data
+        static __bss_start: u8;
+    }
+
+    #[inline(always)]
+    pub fn stack_pointer() -> usize {
+        let sp: usize;
+        unsafe { asm!("move $2, $sp", out("$2") sp, options(nomem, nostack)) };
+
+        sp
+    }
+
+    pub fn stack_top() -> usize {
+        let top_reserved = crate::layout::get().top_reserved;
+
+        map::KSEG0 + unsafe { OS_MEM_SIZE.read_volatile() } - top_reserved - STACK_TOP_GAP
+    }
+
+    /// Check if a word points just after a `jal` or `jalr` and its delay slot.
+    pub fn is_return_address(word: usize) -> bool {
+        let text_end = unsafe { &__bss_start as *const u8 as usize };
+        if word % 4 != 0 || word < TEXT_START + 8 || word > text_end {
+            return false;
+        }
+
+        let call = unsafe { ((word - 8) as *const u32).read_volatile() };
+        let opcode = call >> 26;
+        let funct = call & 0x3F;
+
+        opcode == 0x03 || (opcode == 0x00 && funct == 0x09)
+    }
+}
+
+#[cfg(not(rrt0_platform = "n64"))]
+mod platform {
+    pub fn stack_pointer() -> usize {
+        0
+    }
+
+    pub fn stack_top() -> usize {
+        0
+    }
+
+    pub fn is_return_address(_word: usize) -> bool {
+        false
+    }
+}
@@ -0,0 +1,138 @@
+//! Benchmarking on target.
+//!
+//! [`Bench`] runs a function a number of times after some warmup runs, timing each run with the
+//! cycle counter. Runs far outside the typical range (by Tukey's fences), like those hit by an
+//! interrupt, are rejected as outliers. The resulting [`BenchResult`] is printed with `Display`,
+//! e.g. `write!(out, "{}", Bench::new("sort").run(|| sort(&mut data)))`.
+//!
+//! Times are in CPU cycles, at twice the counter rate, with the cost of reading the counter
+//! removed. On other platforms, the counter does not advance and every time is 0.
+
+use crate::time;
+use core::fmt;
+
+/// Most timed runs per benchmark
+pub const MAX_ITERATIONS: usize = 256;
+
+/// A benchmark configuration.
+#[derive(Clone, Copy, Debug)]
+pub struct Bench {
+    name: &'static str,
+    warmup: usize,
+    iterations: usize,
+}
+
+/// Benchmark statistics (in CPU cycles), excluding outliers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BenchResult {
+    pub name: &'static str,
+    /// Number of timed runs, including outliers
+    pub iterations: usize,
+    /// Number of runs rejected as outliers
+    pub outliers: usize,
+    pub min: u32,
+    pub median: u32,
+    pub mean: u32,
+    pub max: u32,
+}
+
+impl Bench {
+    /// Create a benchmark with 10 warmup runs and 100 timed runs.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            warmup: 10,
+            iterations: 100,
+        }
+    }
+
+    /// Set the number of untimed runs, which fill the caches before timing.
+    pub const fn warmup(mut self, warmup: usize) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Set the number of timed runs, up to [`MAX_ITERATIONS`].
+    pub const fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = if iterations < MAX_ITERATIONS {
+            iterations
+        } else {
+            MAX_ITERATIONS
+        };
+        self
+    }
+
+    /// Run the benchmark.
+    pub fn run<R>(&self, mut f: impl FnMut() -> R) -> BenchResult {
+        for _ in 0..self.warmup {
+            black_box(f());
+        }
+
+        let overhead = (0..8)
+            .map(|_| {
+                let start = time::counter();
+                time::counter().wrapping_sub(start)
+            })
+            .min()
+            .unwrap_or(0);
+
+        let mut samples = [0; MAX_ITERATIONS];
+        let samples = &mut samples[..self.iterations.max(1)];
+        for sample in samples.iter_mut() {
+            let start = time::counter();
+            black_box(f());
+            let ticks = time::counter().wrapping_sub(start);
+            *sample = ticks.saturating_sub(overhead).saturating_mul(2);
+        }
+        samples.sort_unstable();
+
+        // Tukey's fences, 1.5 interquartile ranges outside the quartiles
+        let q1 = samples[samples.len() / 4];
+        let q3 = samples[samples.len() * 3 / 4];
+        let fence = (q3 - q1).saturating_add((q3 - q1) / 2);
+        let (low, high) = (q1.saturating_sub(fence), q3.saturating_add(fence));
+        let start = samples
+            .iter()
+            .position(|&sample| sample >= low)
+            .unwrap_or(0);
+        let end = samples
+            .iter()
+            .rposition(|&sample| sample <= high)
+            .map_or(0, |end| end + 1);
+        let kept = &samples[start..end];
+
+        let sum: u64 = kept.iter().map(|&sample| sample as u64).sum();
+        BenchResult {
+            name: self.name,
+            iterations: samples.len(),
+            outliers: samples.len() - kept.len(),
+            min: kept[0],
+            median: kept[kept.len() / 2],
+            mean: (sum / kept.len() as u64) as u32,
+            max: kept[kept.len() - 1],
+        }
+    }
+}
+
+/// Run a function with the default [`Bench`] configuration.
+pub fn bench<R>(name: &'static str, f: impl FnMut() -> R) -> BenchResult {
+    Bench::new(name).run(f)
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} cycles (min {}, mean {}, max {}, {} outliers in {} runs)",
+            self.name, self.median, self.min, self.mean, self.max, self.outliers, self.iterations
+        )
+    }
+}
+
+/// Keep the optimizer from removing the computation of a value.
+fn black_box<T>(value: T) -> T {
+    let result = unsafe { core::ptr::read_volatile(&value) };
+    core::mem::forget(value);
+
+    result
+}
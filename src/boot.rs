@@ -0,0 +1,108 @@
+//! Boot timings, and boot chaining.
+//!
+//! The entry point records the `Count` register as it boots, and [`timings`] reports how long each
+//! step took, to track what initialization code costs on hardware. The time before the entry
+//! point, in the PIF and IPL3, is not reported: the VR4300 does not reset `Count`, so its value at
+//! the entry point says nothing about when the console was reset.
+//!
+//! A ROM can bundle several program images, each a complete ROM image with its own header. [`chain`]
+//! boots one of them the way IPL3 boots the cartridge: it copies the first 1 MiB after the image's
+//! boot code to the entry point in its header, and jumps there. The running program is replaced.
+//!
+//! The chained program sees the same cartridge as the program that started it, so it must be built
+//! to find its data relative to its own image offset rather than the start of the ROM.
+//!
+//! Only available on N64.
+
+use crate::header;
+use crate::interrupt;
+use crate::mem::map;
+use crate::mmio::{SpStatusWrite, SP};
+use crate::time::ticks_to_duration;
+use core::fmt;
+use core::time::Duration;
+
+/// Offset of the boot code in a ROM image, after the header and IPL3
+const BOOT_CODE_OFFSET: u32 = 0x1000;
+
+/// Size of the boot code that IPL3 copies (in bytes)
+const BOOT_CODE_SIZE: u32 = 0x10_0000;
+
+/// Physical address of the start of the cartridge ROM
+const ROM_PHYS: u32 = map::ROM as u32;
+
+/// Uncached address of the RSP instruction memory, where the trampoline runs
+const IMEM: usize = map::to_kseg1(map::SP_IMEM);
+
+extern "C" {
+    /// `Count` at the entry point, after clearing `.bss`, and after initializing I/O
+    static rrt0_boot_timings: [u32; 3];
+    static rrt0_chain_start: u32;
+    static rrt0_chain_end: u32;
+}
+
+/// How long each step of booting took.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Timings {
+    /// Clearing `.bss`
+    pub bss_clear: Duration,
+    /// Initializing the FPU, the PIF, and the OS pointers in low memory
+    pub io_init: Duration,
+}
+
+/// Get the boot timings of the running program.
+pub fn timings() -> Timings {
+    let [entry, bss_cleared, io_initialized] =
+        unsafe { (&rrt0_boot_timings as *const [u32; 3]).read_volatile() };
+
+    Timings {
+        bss_clear: ticks_to_duration(bss_cleared.wrapping_sub(entry)),
+        io_init: ticks_to_duration(io_initialized.wrapping_sub(bss_cleared)),
+    }
+}
+
+/// Formats each step in microseconds, e.g. `bss 120us, io 3us`.
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bss {}us, io {}us",
+            self.bss_clear.as_micros(),
+            self.io_init.as_micros()
+        )
+    }
+}
+
+/// Boot the program image at a ROM offset. Panics if it has no header.
+///
+/// # Safety
+///
+/// The offset must hold a program image. The RSP is halted, and the contents of its instruction
+/// memory are lost.
+pub unsafe fn chain(rom_offset: u32) -> ! {
+    let entry = header::read(rom_offset)
+        .map(|header| header.entry_point)
+        .expect("no program image at ROM offset");
+
+    interrupt::without_interrupts(|| {
+        SP.status
+            .write(SpStatusWrite::default().with_set_halt(true));
+
+        // Copy the trampoline to IMEM, which only accepts 32-bit writes
+        let start = &rrt0_chain_start as *const u32;
+        let len = (&rrt0_chain_end as *const u32).offset_from(start) as usize;
+        for index in 0..len {
+            (IMEM as *mut u32)
+                .add(index)
+                .write_volatile(start.add(index).read());
+        }
+
+        let trampoline: extern "C" fn(u32, u32, u32, u32) -> ! = core::mem::transmute(IMEM);
+        trampoline(
+            map::physical(entry as usize) as u32,
+            ROM_PHYS + rom_offset + BOOT_CODE_OFFSET,
+            BOOT_CODE_SIZE - 1,
+            entry,
+        )
+    })
+}
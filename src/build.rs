@@ -0,0 +1,80 @@
+//! Build metadata, for identifying which build a crash report or log came from.
+//!
+//! [`build_info!`](crate::build_info!) embeds a [`BuildInfo`] in the program, and
+//! [`build_info`](crate::build_info()) reads it at runtime. On N64, it is placed in the
+//! `.rodata.rrt0_build_info` input section, which a linker script can put at a fixed address. Tools
+//! can also find it by the `RRT0_BUILD_INFO` symbol in the program ELF.
+//!
+//! The commit hash and build time come from the `RRT0_GIT_HASH` and `RRT0_BUILD_TIME` environment
+//! variables when the program is compiled, which its build script can set:
+//!
+//! ```text
+//! let hash = Command::new("git").args(["rev-parse", "--short", "HEAD"]).output()?;
+//! println!("cargo:rustc-env=RRT0_GIT_HASH={}", String::from_utf8(hash.stdout)?.trim());
+//! println!("cargo:rerun-if-changed=.git/HEAD");
+//! ```
+//!
+//! Fields without a value are `"unknown"`.
+
+use core::fmt;
+
+/// Embed the build metadata of the calling crate. Use it once, at the top level of the program.
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        #[no_mangle]
+        #[used]
+        #[cfg_attr(target_vendor = "nintendo64", link_section = ".rodata.rrt0_build_info")]
+        pub static RRT0_BUILD_INFO: $crate::build::BuildInfo = $crate::build::BuildInfo {
+            package: ::core::env!("CARGO_PKG_NAME"),
+            version: ::core::env!("CARGO_PKG_VERSION"),
+            git_hash: match ::core::option_env!("RRT0_GIT_HASH") {
+                ::core::option::Option::Some(hash) => hash,
+                ::core::option::Option::None => "unknown",
+            },
+            build_time: match ::core::option_env!("RRT0_BUILD_TIME") {
+                ::core::option::Option::Some(time) => time,
+                ::core::option::Option::None => "unknown",
+            },
+            profile: if ::core::cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            },
+        };
+    };
+}
+
+/// Metadata about the build of the program.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct BuildInfo {
+    pub package: &'static str,
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    pub build_time: &'static str,
+    /// `debug` or `release`, by whether debug assertions are enabled
+    pub profile: &'static str,
+}
+
+/// Formats as e.g. `game 0.1.0 (1a2b3c4 2024-05-01T12:00:00Z release)`.
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({} {} {})",
+            self.package, self.version, self.git_hash, self.build_time, self.profile
+        )
+    }
+}
+
+/// Get the build metadata embedded with [`build_info!`](crate::build_info!), if it was.
+pub fn build_info() -> Option<&'static BuildInfo> {
+    extern "Rust" {
+        // Null when no build metadata is embedded
+        #[linkage = "extern_weak"]
+        static RRT0_BUILD_INFO: *const BuildInfo;
+    }
+
+    unsafe { RRT0_BUILD_INFO.as_ref() }
+}
@@ -0,0 +1,214 @@
+//! Checksums.
+//!
+//! CRC-32 (the IEEE 802.3 polynomial used by zip and PNG), Adler-32 and Fletcher checksums, for
+//! validating save data and assets. Each checksum can be computed in one call, or incrementally
+//! over several buffers with its streaming type.
+
+/// Reversed CRC-32 polynomial
+const CRC32_POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// CRC-32 lookup table, one entry per byte value
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC32_POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+/// Compute the CRC-32 of a buffer.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+/// Streaming CRC-32
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            let index = (self.state as u8 ^ byte) as usize;
+            self.state = (self.state >> 8) ^ CRC32_TABLE[index];
+        }
+    }
+
+    /// The CRC-32 of all data so far.
+    pub const fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Largest prime smaller than 2^16
+const ADLER32_MODULUS: u32 = 65521;
+
+/// Number of bytes that can be summed before the Adler-32 sums must be reduced to avoid overflow
+const ADLER32_BLOCK: usize = 5552;
+
+/// Compute the Adler-32 checksum of a buffer.
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut adler = Adler32::new();
+    adler.update(data);
+    adler.finish()
+}
+
+/// Streaming Adler-32
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    pub const fn new() -> Self {
+        Self { a: 1, b: 0 }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for block in data.chunks(ADLER32_BLOCK) {
+            for &byte in block {
+                self.a += byte as u32;
+                self.b += self.a;
+            }
+            self.a %= ADLER32_MODULUS;
+            self.b %= ADLER32_MODULUS;
+        }
+    }
+
+    /// The Adler-32 checksum of all data so far.
+    pub const fn finish(&self) -> u32 {
+        self.b << 16 | self.a
+    }
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compute the Fletcher-16 checksum of a buffer.
+pub fn fletcher16(data: &[u8]) -> u16 {
+    let (mut a, mut b) = (0_u32, 0_u32);
+
+    // 380 bytes is the most that can be summed without overflowing `b`
+    for block in data.chunks(380) {
+        for &byte in block {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 255;
+        b %= 255;
+    }
+
+    (b << 8 | a) as u16
+}
+
+/// Compute the Fletcher-32 checksum of a buffer, read as big-endian 16-bit words. An odd final
+/// byte is padded with zero.
+pub fn fletcher32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (0_u32, 0_u32);
+
+    // 359 words is the most that can be summed without overflowing `b`
+    for block in data.chunks(359 * 2) {
+        for word in block.chunks(2) {
+            let high = word[0] as u32;
+            let low = word.get(1).copied().unwrap_or(0) as u32;
+            a += high << 8 | low;
+            b += a;
+        }
+        a %= 65535;
+        b %= 65535;
+    }
+
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 6001 bytes, enough to cross the block sizes of all the checksums
+    fn long_data() -> [u8; 6001] {
+        let mut data = [0; 6001];
+        for (index, byte) in data.iter_mut().enumerate() {
+            *byte = (index % 251) as u8;
+        }
+        data
+    }
+
+    #[test]
+    fn crc32_vectors() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+        assert_eq!(crc32(&long_data()), 0x0D71_9779);
+    }
+
+    #[test]
+    fn crc32_streaming() {
+        let data = long_data();
+        let mut crc = Crc32::new();
+        crc.update(&data[..100]);
+        crc.update(&data[100..]);
+        assert_eq!(crc.finish(), crc32(&data));
+    }
+
+    #[test]
+    fn adler32_vectors() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(adler32(&long_data()), 0x2B61_6895);
+
+        let data = long_data();
+        let mut adler = Adler32::new();
+        for chunk in data.chunks(1000) {
+            adler.update(chunk);
+        }
+        assert_eq!(adler.finish(), 0x2B61_6895);
+    }
+
+    #[test]
+    fn fletcher_vectors() {
+        assert_eq!(fletcher16(b"abcde"), 0xC8F0);
+        assert_eq!(fletcher16(b"abcdef"), 0x2057);
+        assert_eq!(fletcher16(&long_data()), 0x3662);
+
+        // Big-endian words, so these differ from the usual little-endian vectors
+        assert_eq!(fletcher32(b"abcde"), 0x4FF0_29C7);
+        assert_eq!(fletcher32(b"abcdef"), 0x5056_2A2D);
+        assert_eq!(fletcher32(&long_data()), 0x4A9E_283A);
+    }
+}
@@ -0,0 +1,138 @@
+//! CIC lockout chip variants.
+//!
+//! Each cartridge has a CIC chip paired with the IPL3 boot code in its ROM, and the PIF only boots
+//! the ROM if the checksums in its header match the boot code and the first 1 MiB of the program,
+//! computed with the seed of the chip. ROM tools use [`Cic::checksum`] to fix the header up after
+//! linking.
+//!
+//! The variants differ in what the program sees at boot:
+//!
+//! * CIC-6103 and CIC-6106 IPL3 load the program below the entry point in the header, by
+//!   [`Cic::entry_point_offset`], so the header must hold the linked entry point plus the offset.
+//! * CIC-6105 IPL3 stores the memory size at a different address, which the entry point reads when
+//!   the `cic-6105` feature is enabled.
+//!
+//! The PAL CIC-71xx chips behave like the matching NTSC CIC-61xx chips.
+
+use crate::checksum::crc32;
+
+/// Offset of the IPL3 boot code in the ROM
+const IPL3_OFFSET: usize = 0x40;
+
+/// Size of the IPL3 boot code (in bytes)
+const IPL3_SIZE: usize = 0x1000 - IPL3_OFFSET;
+
+/// Offset of the program in the ROM
+const PROGRAM_OFFSET: usize = 0x1000;
+
+/// Size of the program covered by the checksums (in bytes)
+const CHECKSUM_SIZE: usize = 0x10_0000;
+
+/// A CIC variant.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Cic {
+    /// CIC-6101, used by Star Fox 64
+    Cic6101,
+    /// CIC-6102 or CIC-7101, used by most cartridges
+    Cic6102,
+    /// CIC-6103 or CIC-7103
+    Cic6103,
+    /// CIC-6105 or CIC-7105
+    Cic6105,
+    /// CIC-6106 or CIC-7106
+    Cic6106,
+    /// CIC-7102, the PAL variant of CIC-6101
+    Cic7102,
+}
+
+impl Cic {
+    /// Identify the variant from the IPL3 boot code in a ROM image, which must hold at least the
+    /// first 4 KiB.
+    pub fn identify(rom: &[u8]) -> Option<Self> {
+        let ipl3 = rom.get(IPL3_OFFSET..IPL3_OFFSET + IPL3_SIZE)?;
+
+        match crc32(ipl3) {
+            0x6170_A4A1 => Some(Self::Cic6101),
+            0x90BB_6CB5 => Some(Self::Cic6102),
+            0x0B05_0EE0 => Some(Self::Cic6103),
+            0x98BC_2C86 => Some(Self::Cic6105),
+            0xACC8_580A => Some(Self::Cic6106),
+            0x009E_9EA3 => Some(Self::Cic7102),
+            _ => None,
+        }
+    }
+
+    /// Seed that the PIF passes to IPL3.
+    pub fn seed(self) -> u8 {
+        match self {
+            Self::Cic6101 | Self::Cic6102 | Self::Cic7102 => 0x3F,
+            Self::Cic6103 => 0x78,
+            Self::Cic6105 => 0x91,
+            Self::Cic6106 => 0x85,
+        }
+    }
+
+    /// How far below the header entry point IPL3 loads the program (in bytes).
+    pub fn entry_point_offset(self) -> u32 {
+        match self {
+            Self::Cic6103 => 0x10_0000,
+            Self::Cic6106 => 0x20_0000,
+            _ => 0,
+        }
+    }
+
+    /// Compute the header checksums `(crc1, crc2)` of a ROM image. Images shorter than the 1 MiB
+    /// the checksums cover are treated as padded with zeros.
+    pub fn checksum(self, rom: &[u8]) -> (u32, u32) {
+        let seed: u32 = match self {
+            Self::Cic6101 | Self::Cic6102 | Self::Cic7102 => 0xF8CA_4DDC,
+            Self::Cic6103 => 0xA388_6759,
+            Self::Cic6105 => 0xDF26_F436,
+            Self::Cic6106 => 0x1FEA_617A,
+        };
+        let word = |offset: usize| {
+            let mut bytes = [0; 4];
+            for (index, byte) in bytes.iter_mut().enumerate() {
+                *byte = rom.get(offset + index).copied().unwrap_or(0);
+            }
+            u32::from_be_bytes(bytes)
+        };
+
+        let [mut t1, mut t2, mut t3, mut t4, mut t5, mut t6] = [seed; 6];
+        for offset in (PROGRAM_OFFSET..PROGRAM_OFFSET + CHECKSUM_SIZE).step_by(4) {
+            let data = word(offset);
+            let (sum, carry) = t6.overflowing_add(data);
+            if carry {
+                t4 = t4.wrapping_add(1);
+            }
+            t6 = sum;
+            t3 ^= data;
+            let rotated = data.rotate_left(data & 0x1F);
+            t5 = t5.wrapping_add(rotated);
+            t2 ^= if t2 > data { rotated } else { t6 ^ data };
+            t1 = t1.wrapping_add(match self {
+                // Mixes in a word of the IPL3 boot code
+                Self::Cic6105 => word(IPL3_OFFSET + 0x0710 + (offset & 0xFF)) ^ data,
+                _ => t5 ^ data,
+            });
+        }
+
+        match self {
+            Self::Cic6103 => ((t6 ^ t4).wrapping_add(t3), (t5 ^ t2).wrapping_add(t1)),
+            Self::Cic6106 => (
+                t6.wrapping_mul(t4).wrapping_add(t3),
+                t5.wrapping_mul(t2).wrapping_add(t1),
+            ),
+            _ => (t6 ^ t4 ^ t3, t5 ^ t2 ^ t1),
+        }
+    }
+}
+
+/// Identify the variant the running program was booted with, from its IPL3 boot code.
+#[cfg(rrt0_platform = "n64")]
+pub fn current() -> Option<Cic> {
+    let mut rom = [0; PROGRAM_OFFSET];
+    crate::mem::read_rom(&mut rom, 0);
+
+    Cic::identify(&rom)
+}
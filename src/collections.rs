@@ -0,0 +1,647 @@
+//! Fixed-capacity collections.
+//!
+//! [`Vec`], [`String`] and [`LinearMap`] store their contents in place, with a capacity set by a
+//! const parameter, so they need no allocator. Operations that would exceed the capacity fail
+//! instead of growing. They are also exported from the [`prelude`](crate::prelude).
+
+use core::borrow::{Borrow, BorrowMut};
+use core::convert::TryFrom;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+/// The error returned when a collection has no room for more elements.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CapacityError;
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("collection is full")
+    }
+}
+
+/// A vector with room for up to `N` elements.
+pub struct Vec<T, const N: usize> {
+    buffer: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> Vec<T, N> {
+    /// Create an empty vector.
+    pub const fn new() -> Self {
+        Self {
+            // An array of `MaybeUninit` needs no initialization
+            buffer: unsafe { MaybeUninit::uninit().assume_init() },
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { core::slice::from_raw_parts(self.buffer.as_ptr().cast(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { core::slice::from_raw_parts_mut(self.buffer.as_mut_ptr().cast(), self.len) }
+    }
+
+    /// Append an element. Returns the element back if the vector is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        match self.buffer.get_mut(self.len) {
+            Some(slot) => {
+                slot.write(value);
+                self.len += 1;
+                Ok(())
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Remove the last element.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        self.len -= 1;
+        Some(unsafe { self.buffer[self.len].assume_init_read() })
+    }
+
+    /// Insert an element at `index`, shifting later elements up. Returns the element back if the
+    /// vector is full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length.
+    pub fn insert(&mut self, index: usize, value: T) -> Result<(), T> {
+        assert!(index <= self.len, "Index out of bounds");
+        if self.is_full() {
+            return Err(value);
+        }
+
+        unsafe {
+            let ptr = self.buffer.as_mut_ptr().add(index).cast::<T>();
+            ptr::copy(ptr, ptr.add(1), self.len - index);
+            ptr.write(value);
+        }
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /// Remove the element at `index`, shifting later elements down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "Index out of bounds");
+
+        self.len -= 1;
+        unsafe {
+            let ptr = self.buffer.as_mut_ptr().add(index).cast::<T>();
+            let value = ptr.read();
+            ptr::copy(ptr.add(1), ptr, self.len - index);
+            value
+        }
+    }
+
+    /// Remove the element at `index`, replacing it with the last element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "Index out of bounds");
+
+        let last = self.len - 1;
+        self.as_mut_slice().swap(index, last);
+        self.len = last;
+        unsafe { self.buffer[last].assume_init_read() }
+    }
+
+    /// Shorten the vector to `len` elements, dropping the rest.
+    pub fn truncate(&mut self, len: usize) {
+        while self.len > len {
+            self.pop();
+        }
+    }
+
+    /// Remove all elements.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Keep only the elements for which `f` returns `true`.
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        let mut index = 0;
+        while index < self.len {
+            if f(&self[index]) {
+                index += 1;
+            } else {
+                self.remove(index);
+            }
+        }
+    }
+}
+
+impl<T: Clone, const N: usize> Vec<T, N> {
+    /// Append clones of all elements in a slice. Nothing is appended if they do not all fit.
+    pub fn extend_from_slice(&mut self, values: &[T]) -> Result<(), CapacityError> {
+        if values.len() > N - self.len {
+            return Err(CapacityError);
+        }
+
+        for value in values {
+            // Cannot fail, since the capacity was checked
+            let _ = self.push(value.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Create a vector from a slice. Returns an error if it is too long.
+    pub fn from_slice(values: &[T]) -> Result<Self, CapacityError> {
+        let mut vec = Self::new();
+        vec.extend_from_slice(values)?;
+
+        Ok(vec)
+    }
+}
+
+impl<T, const N: usize> Drop for Vec<T, N> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) };
+    }
+}
+
+impl<T, const N: usize> Default for Vec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for Vec<T, N> {
+    fn clone(&self) -> Self {
+        let mut vec = Self::new();
+        for value in self.iter() {
+            let _ = vec.push(value.clone());
+        }
+
+        vec
+    }
+}
+
+impl<T, const N: usize> Deref for Vec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for Vec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, const N: usize> AsRef<[T]> for Vec<T, N> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, const N: usize> AsMut<[T]> for Vec<T, N> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, const N: usize> Borrow<[T]> for Vec<T, N> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, const N: usize> BorrowMut<[T]> for Vec<T, N> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for Vec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl<T: PartialEq, const N: usize, const M: usize> PartialEq<Vec<T, M>> for Vec<T, N> {
+    fn eq(&self, other: &Vec<T, M>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for Vec<T, N> {}
+
+impl<'a, T, const N: usize> IntoIterator for &'a Vec<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut Vec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = core::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// A UTF-8 string with room for up to `N` bytes.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct String<const N: usize> {
+    bytes: Vec<u8, N>,
+}
+
+impl<const N: usize> String<N> {
+    /// Create an empty string.
+    pub const fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    /// Length (in bytes)
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Capacity (in bytes)
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole strings and characters are appended, so the bytes are always valid UTF-8
+        unsafe { core::str::from_utf8_unchecked(&self.bytes) }
+    }
+
+    pub fn as_mut_str(&mut self) -> &mut str {
+        unsafe { core::str::from_utf8_unchecked_mut(&mut self.bytes) }
+    }
+
+    /// Append a string. Nothing is appended if it does not fit.
+    pub fn push_str(&mut self, s: &str) -> Result<(), CapacityError> {
+        self.bytes.extend_from_slice(s.as_bytes())
+    }
+
+    /// Append a character. Nothing is appended if it does not fit.
+    pub fn push(&mut self, c: char) -> Result<(), CapacityError> {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Remove the last character.
+    pub fn pop(&mut self) -> Option<char> {
+        let c = self.as_str().chars().next_back()?;
+        self.bytes.truncate(self.len() - c.len_utf8());
+
+        Some(c)
+    }
+
+    /// Shorten the string to `len` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is not on a character boundary.
+    pub fn truncate(&mut self, len: usize) {
+        assert!(
+            self.as_str().is_char_boundary(len),
+            "Not a character boundary"
+        );
+        self.bytes.truncate(len);
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for String<N> {
+    type Error = CapacityError;
+
+    fn try_from(s: &str) -> Result<Self, CapacityError> {
+        let mut string = Self::new();
+        string.push_str(s)?;
+
+        Ok(string)
+    }
+}
+
+impl<const N: usize> Deref for String<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> DerefMut for String<N> {
+    fn deref_mut(&mut self) -> &mut str {
+        self.as_mut_str()
+    }
+}
+
+impl<const N: usize> AsRef<str> for String<N> {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<const N: usize> Borrow<str> for String<N> {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl<const N: usize> PartialEq<str> for String<N> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for String<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<const N: usize> fmt::Write for String<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s).map_err(|_| fmt::Error)
+    }
+}
+
+impl<const N: usize> fmt::Display for String<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<const N: usize> fmt::Debug for String<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+/// A map with room for up to `N` entries, searched linearly. Suits small maps, where a linear
+/// search is as fast as hashing.
+#[derive(Clone)]
+pub struct LinearMap<K, V, const N: usize> {
+    entries: Vec<(K, V), N>,
+}
+
+impl<K: Eq, V, const N: usize> LinearMap<K, V, N> {
+    /// Create an empty map.
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Insert a value, returning the previous value for the key. Returns the entry back if the key
+    /// is new and the map is full.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        match self.get_mut(&key) {
+            Some(old) => Ok(Some(core::mem::replace(old, value))),
+            None => self.entries.push((key, value)).map(|_| None),
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.borrow() == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.entries
+            .iter_mut()
+            .find(|(k, _)| k.borrow() == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Remove a key, returning its value. Does not preserve the order of the other entries.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let index = self.entries.iter().position(|(k, _)| k.borrow() == key)?;
+
+        Some(self.entries.swap_remove(index).1)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Iterate over the entries in insertion order, unless entries have been removed.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.entries.iter_mut().map(|(k, v)| (&*k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+impl<K: Eq, V, const N: usize> Default for LinearMap<K, V, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: fmt::Debug + Eq, V: fmt::Debug, const N: usize> fmt::Debug for LinearMap<K, V, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    /// Counts its drops
+    #[derive(Clone)]
+    struct Counted<'a>(&'a Cell<u32>);
+
+    impl Drop for Counted<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn vec_push_pop() {
+        let mut vec = Vec::<u8, 2>::new();
+        assert_eq!(vec.push(1), Ok(()));
+        assert_eq!(vec.push(2), Ok(()));
+        assert_eq!(vec.push(3), Err(3));
+        assert!(vec.is_full());
+        assert_eq!(vec.as_slice(), [1, 2]);
+        assert_eq!(vec.pop(), Some(2));
+        assert_eq!(vec.pop(), Some(1));
+        assert_eq!(vec.pop(), None);
+    }
+
+    #[test]
+    fn vec_insert_remove() {
+        let mut vec = Vec::<u8, 5>::from_slice(&[1, 2, 4]).unwrap();
+        assert_eq!(vec.insert(2, 3), Ok(()));
+        assert_eq!(vec.insert(0, 0), Ok(()));
+        assert_eq!(vec.insert(0, 9), Err(9));
+        assert_eq!(vec.as_slice(), [0, 1, 2, 3, 4]);
+
+        assert_eq!(vec.remove(1), 1);
+        assert_eq!(vec.swap_remove(0), 0);
+        assert_eq!(vec.as_slice(), [4, 2, 3]);
+
+        vec.retain(|&value| value != 2);
+        assert_eq!(vec.as_slice(), [4, 3]);
+        assert_eq!(vec.extend_from_slice(&[5, 6, 7, 8]), Err(CapacityError));
+        assert_eq!(vec.as_slice(), [4, 3]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn vec_insert_out_of_bounds() {
+        let mut vec = Vec::<u8, 4>::new();
+        let _ = vec.insert(1, 0);
+    }
+
+    #[test]
+    fn vec_drops_elements() {
+        let drops = Cell::new(0);
+        let mut vec = Vec::<_, 4>::new();
+        for _ in 0..4 {
+            assert!(vec.push(Counted(&drops)).is_ok());
+        }
+
+        vec.truncate(3);
+        assert_eq!(drops.get(), 1);
+        vec.retain(|_| false);
+        assert_eq!(drops.get(), 4);
+
+        assert!(vec.push(Counted(&drops)).is_ok());
+        let clone = vec.clone();
+        drop(vec);
+        drop(clone);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn string() {
+        let mut string = String::<6>::try_from("ab").unwrap();
+        assert_eq!(string.push('é'), Ok(()));
+        assert_eq!(string, "abé");
+        // Nothing is appended when it does not fit
+        assert_eq!(string.push_str("cde"), Err(CapacityError));
+        assert_eq!(string.push_str("c"), Ok(()));
+        assert_eq!(string.push('é'), Err(CapacityError));
+        assert_eq!(string.len(), 5);
+
+        assert_eq!(string.pop(), Some('c'));
+        assert_eq!(string.pop(), Some('é'));
+        assert_eq!(string, "ab");
+        assert!(String::<1>::try_from("ab").is_err());
+
+        let mut string = String::<8>::new();
+        fmt::Write::write_fmt(&mut string, format_args!("{}-{}", 12, 34)).unwrap();
+        assert_eq!(string, "12-34");
+    }
+
+    #[test]
+    #[should_panic]
+    fn string_truncate_inside_character() {
+        String::<4>::try_from("é").unwrap().truncate(1);
+    }
+
+    #[test]
+    fn linear_map() {
+        let mut map = LinearMap::<&str, u32, 2>::new();
+        assert_eq!(map.insert("a", 1), Ok(None));
+        assert_eq!(map.insert("b", 2), Ok(None));
+        assert_eq!(map.insert("a", 3), Ok(Some(1)));
+        assert_eq!(map.insert("c", 4), Err(("c", 4)));
+
+        assert_eq!(map.get("a"), Some(&3));
+        assert!(map.contains_key("b"));
+        *map.get_mut("b").unwrap() += 1;
+        assert_eq!(map.remove("b"), Some(3));
+        assert_eq!(map.remove("b"), None);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.insert("c", 4), Ok(None));
+        assert!(map.keys().copied().eq(["a", "c"]));
+    }
+}
@@ -0,0 +1,193 @@
+//! Coprocessors that run their own programs.
+//!
+//! Several consoles pair the main CPU with a second processor that runs a separate program out of
+//! its own memory, like the RSP of the N64, the vector units of the PS2, or the slave SH-2 of the
+//! Saturn. [`Coprocessor`] is the common shape of driving one: load a program, exchange data
+//! through a memory both sides can reach, start it, and synchronize on signals or its halting.
+//!
+//! ```text
+//! let mut rsp = unsafe { Rsp::new() };
+//! rsp.load(UCODE);
+//! rsp.write_shared(0, &input);
+//! rsp.start();
+//! rsp.wait();
+//! rsp.read_shared(OUTPUT_OFFSET, &mut output);
+//! ```
+//!
+//! The RSP is available as [`Rsp`] on N64. Code written against the trait carries over to the
+//! coprocessors of other platforms as their support is added.
+
+/// A processor that runs its own program, beside the main CPU.
+pub trait Coprocessor {
+    /// Size of the program memory (in bytes)
+    fn program_size(&self) -> usize;
+
+    /// Size of the memory shared with the main CPU (in bytes)
+    fn shared_size(&self) -> usize;
+
+    /// Halt the coprocessor and copy a program to the start of its program memory. Panics if it
+    /// does not fit.
+    fn load(&mut self, program: &[u8]);
+
+    /// Copy data to the shared memory at an offset. Panics if it does not fit.
+    fn write_shared(&mut self, offset: usize, data: &[u8]);
+
+    /// Copy data from the shared memory at an offset. Panics if it does not fit.
+    fn read_shared(&self, offset: usize, data: &mut [u8]);
+
+    /// Run the loaded program from its start.
+    fn start(&mut self);
+
+    /// Stop the coprocessor.
+    fn halt(&mut self);
+
+    /// Check whether the coprocessor is running, i.e. neither halted nor stopped by its program.
+    fn is_running(&self) -> bool;
+
+    /// Raise one of the signals both sides can set, clear and poll.
+    fn signal(&mut self, signal: u8);
+
+    fn clear_signal(&mut self, signal: u8);
+
+    fn is_signaled(&self, signal: u8) -> bool;
+
+    /// Block until the coprocessor stops running.
+    fn wait(&self) {
+        while self.is_running() {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Block until a signal is raised.
+    fn wait_signal(&self, signal: u8) {
+        while !self.is_signaled(signal) {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(rrt0_platform = "n64")]
+pub use self::rsp::Rsp;
+
+#[cfg(rrt0_platform = "n64")]
+mod rsp {
+    use super::Coprocessor;
+    use crate::mem::map;
+    use crate::mmio::{SpStatusWrite, SP};
+
+    /// Uncached address of the RSP data memory
+    const DMEM: usize = map::to_kseg1(map::SP_DMEM);
+    /// Uncached address of the RSP instruction memory
+    const IMEM: usize = map::to_kseg1(map::SP_IMEM);
+    /// Size of DMEM and IMEM (in bytes)
+    const MEM_SIZE: usize = map::SP_MEM_SIZE;
+
+    /// RSP program counter
+    const SP_PC: *mut u32 = map::to_kseg1(map::SP_PC) as *mut u32;
+
+    /// Number of signals in `SP_STATUS`
+    const SIGNALS: u8 = 8;
+
+    /// The RSP, with IMEM as its program memory and DMEM as the shared memory.
+    ///
+    /// DMEM and IMEM only accept 32-bit accesses, so offsets and lengths must be multiples of 4
+    /// bytes. While the RSP is halted, [`dma_copy`](crate::mem::dma_copy) may stage transfers
+    /// through DMEM, losing its contents.
+    #[derive(Debug)]
+    pub struct Rsp(());
+
+    impl Rsp {
+        /// Get a handle to the RSP.
+        ///
+        /// # Safety
+        ///
+        /// Nothing else may drive the RSP while the handle is in use.
+        pub const unsafe fn new() -> Self {
+            Self(())
+        }
+
+        fn check(offset: usize, len: usize) {
+            assert!(
+                offset % 4 == 0 && len % 4 == 0 && offset + len <= MEM_SIZE,
+                "RSP memory access out of bounds or unaligned"
+            );
+        }
+
+        fn status(command: SpStatusWrite) {
+            SP.status.write(command);
+        }
+
+        fn write_words(base: usize, offset: usize, data: &[u8]) {
+            Self::check(offset, data.len());
+            for (index, word) in data.chunks_exact(4).enumerate() {
+                let word = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+                unsafe {
+                    ((base + offset) as *mut u32)
+                        .add(index)
+                        .write_volatile(word)
+                };
+            }
+        }
+    }
+
+    impl Coprocessor for Rsp {
+        fn program_size(&self) -> usize {
+            MEM_SIZE
+        }
+
+        fn shared_size(&self) -> usize {
+            MEM_SIZE
+        }
+
+        fn load(&mut self, program: &[u8]) {
+            self.halt();
+            Self::write_words(IMEM, 0, program);
+        }
+
+        fn write_shared(&mut self, offset: usize, data: &[u8]) {
+            Self::write_words(DMEM, offset, data);
+        }
+
+        fn read_shared(&self, offset: usize, data: &mut [u8]) {
+            Self::check(offset, data.len());
+            for (index, word) in data.chunks_exact_mut(4).enumerate() {
+                let value = unsafe { ((DMEM + offset) as *const u32).add(index).read_volatile() };
+                word.copy_from_slice(&value.to_be_bytes());
+            }
+        }
+
+        fn start(&mut self) {
+            unsafe { SP_PC.write_volatile(0) };
+            Self::status(
+                SpStatusWrite::default()
+                    .with_clear_broke(true)
+                    .with_clear_halt(true),
+            );
+        }
+
+        fn halt(&mut self) {
+            Self::status(SpStatusWrite::default().with_set_halt(true));
+        }
+
+        fn is_running(&self) -> bool {
+            let status = SP.status.read();
+            !status.halt() && !status.broke()
+        }
+
+        /// Panics if the signal is not 0 to 7.
+        fn signal(&mut self, signal: u8) {
+            assert!(signal < SIGNALS, "no RSP signal {}", signal);
+            Self::status(SpStatusWrite::from_bits(1 << (10 + 2 * signal)));
+        }
+
+        /// Panics if the signal is not 0 to 7.
+        fn clear_signal(&mut self, signal: u8) {
+            assert!(signal < SIGNALS, "no RSP signal {}", signal);
+            Self::status(SpStatusWrite::from_bits(1 << (9 + 2 * signal)));
+        }
+
+        fn is_signaled(&self, signal: u8) -> bool {
+            SP.status.read().signals() & (1 << signal) != 0
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Code coverage.
+//!
+//! [`coverage_point!`](crate::coverage_point) marks a point in the code, counting how many times it
+//! is reached. After a test run, the hit counts can be listed with [`for_each`] or written as text
+//! with [`report`] and sent to the host, which can compare them with the points in the source to
+//! find the code paths that were never reached. Points only appear once they have been hit.
+//!
+//! Without the `coverage` feature, `coverage_point!` expands to nothing.
+
+use crate::interrupt::{self, without_interrupts};
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+
+/// Count the times this point in the code is reached.
+#[cfg(feature = "coverage")]
+#[macro_export]
+macro_rules! coverage_point {
+    () => {{
+        static POINT: $crate::coverage::Point =
+            $crate::coverage::Point::new(::core::file!(), ::core::line!(), ::core::column!());
+        POINT.hit();
+    }};
+}
+
+/// Count the times this point in the code is reached (disabled without the `coverage` feature).
+#[cfg(not(feature = "coverage"))]
+#[macro_export]
+macro_rules! coverage_point {
+    () => {{}};
+}
+
+/// A point in the code counted by [`coverage_point!`](crate::coverage_point).
+pub struct Point {
+    pub file: &'static str,
+    pub line: u32,
+    pub column: u32,
+    hits: AtomicU32,
+    registered: AtomicBool,
+    /// Next registered point
+    next: AtomicPtr<Point>,
+}
+
+/// The most recently registered point
+static HEAD: AtomicPtr<Point> = AtomicPtr::new(ptr::null_mut());
+
+impl Point {
+    pub const fn new(file: &'static str, line: u32, column: u32) -> Self {
+        Self {
+            file,
+            line,
+            column,
+            hits: AtomicU32::new(0),
+            registered: AtomicBool::new(false),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Count a hit, registering the point the first time.
+    pub fn hit(&'static self) {
+        interrupt::fetch_add(&self.hits, 1);
+        if !interrupt::swap(&self.registered, true) {
+            without_interrupts(|| {
+                self.next
+                    .store(HEAD.load(Ordering::Relaxed), Ordering::Relaxed);
+                HEAD.store(self as *const Point as *mut Point, Ordering::Release);
+            });
+        }
+    }
+
+    /// Number of times the point was reached
+    pub fn hits(&self) -> u32 {
+        self.hits.load(Ordering::Relaxed)
+    }
+}
+
+/// Call a function for each point that has been hit, most recently registered first.
+pub fn for_each(mut f: impl FnMut(&Point)) {
+    let mut point = HEAD.load(Ordering::Acquire);
+    while let Some(current) = unsafe { point.as_ref() } {
+        f(current);
+        point = current.next.load(Ordering::Relaxed);
+    }
+}
+
+/// Reset the hit counts of all points to zero. Points stay registered.
+pub fn reset() {
+    for_each(|point| point.hits.store(0, Ordering::Relaxed));
+}
+
+/// Write a `file:line:column hits` line for each point that has been hit.
+pub fn report(out: &mut impl fmt::Write) -> fmt::Result {
+    let mut result = Ok(());
+    for_each(|point| {
+        result = result.and_then(|_| {
+            writeln!(
+                out,
+                "{}:{}:{} {}",
+                point.file,
+                point.line,
+                point.column,
+                point.hits()
+            )
+        });
+    });
+
+    result
+}
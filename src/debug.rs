@@ -0,0 +1,410 @@
+//! Debugging aids built on CPU exceptions.
+//!
+//! Breakpoints are `break` instructions, either compiled in with [`breakpoint`] or written over
+//! existing code with [`set_breakpoint`]. When one is hit, the function registered with
+//! [`set_breakpoint_handler`] is called with the interrupted CPU state, which it can inspect and
+//! modify. Without one, a hit panics. A breakpoint set at runtime is removed when it is hit, so the
+//! original instruction runs when the handler returns.
+//!
+//! [`trace`] single-steps through a function, calling a sink function before each instruction
+//! with the CPU state, e.g. to stream the PCs to the host. Steps are taken by placing a breakpoint
+//! on the next instruction to run, so branches are stepped together with their delay slot. Code
+//! that the exception handler itself runs, like `memcpy`, must not be traced.
+//!
+//! A watchpoint raises an exception when the CPU reads or writes a watched address, catching
+//! memory corruption at the instruction that causes it. The VR4300 has a single watchpoint,
+//! covering an aligned 8 byte block. When it is hit, it is cleared and the function registered
+//! with [`set_watch_handler`] is called with the offending PC. Without one, a hit panics.
+//!
+//! On other platforms, breakpoints and watchpoints are never hit.
+
+use crate::collections::CapacityError;
+use crate::exception::{self, ExceptionCode, Frame};
+use crate::interrupt::Mutex;
+use crate::mem::{invalidate_icache, map, writeback_dcache};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Most breakpoints that can be set at runtime at once
+pub const MAX_BREAKPOINTS: usize = 16;
+
+/// `break 1`, distinguishing breakpoints from other `break` instructions, like traps
+const BREAK_INSTRUCTION: u32 = 0x0001_000D;
+
+/// Original instructions at the addresses of runtime breakpoints
+static BREAKPOINTS: Mutex<[Option<(usize, u32)>; MAX_BREAKPOINTS]> =
+    Mutex::new([None; MAX_BREAKPOINTS]);
+
+/// Breakpoint handler as a function pointer, or 0
+static BREAKPOINT_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+/// `break 2`, starting a trace
+const TRACE_START_INSTRUCTION: u32 = 0x0002_000D;
+/// `break 3`, placed on the next instruction to trace
+const STEP_INSTRUCTION: u32 = 0x0003_000D;
+/// `break 4`, stopping a trace
+const TRACE_STOP_INSTRUCTION: u32 = 0x0004_000D;
+
+/// Single-step state
+struct TraceState {
+    sink: Option<fn(&Step<'_>)>,
+    /// Address and original instruction of the pending step breakpoint
+    step: Option<(usize, u32)>,
+    /// General purpose registers at the previous step
+    previous: [u64; 32],
+}
+
+static TRACE: Mutex<TraceState> = Mutex::new(TraceState {
+    sink: None,
+    step: None,
+    previous: [0; 32],
+});
+
+/// An instruction about to run while tracing.
+pub struct Step<'a> {
+    pub pc: usize,
+    pub instruction: u32,
+    /// CPU state before the instruction runs
+    pub frame: &'a Frame,
+    previous: &'a [u64; 32],
+}
+
+impl Step<'_> {
+    /// General purpose registers changed by the previous step, as register numbers and new values.
+    pub fn changed_registers(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        let registers = self.frame.gpr.iter().zip(self.previous).enumerate();
+        registers
+            .filter(|(_, (value, previous))| value != previous)
+            .map(|(index, (&value, _))| (index, value))
+    }
+}
+
+/// Accesses that hit a watchpoint.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// A watchpoint hit.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct WatchHit {
+    /// Address of the load or store instruction
+    pub pc: usize,
+    /// Start of the watched 8 byte block
+    pub address: usize,
+}
+
+/// Watch hit handler as a function pointer, or 0
+static WATCH_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+/// Stop at a breakpoint.
+#[inline(always)]
+pub fn breakpoint() {
+    install_breakpoints();
+    platform::breakpoint();
+}
+
+/// Set a breakpoint on the instruction at an address, replacing it with a `break` instruction
+/// until it is hit or cleared. Also installs the exception handler.
+///
+/// # Safety
+///
+/// The address must be the address of an instruction, and it must not be executing while the
+/// breakpoint is set, e.g. in an interrupt handler.
+pub unsafe fn set_breakpoint(address: usize) -> Result<(), CapacityError> {
+    install_breakpoints();
+
+    with_breakpoints(|breakpoints| {
+        if breakpoints.iter().flatten().any(|&(set, _)| set == address) {
+            return Ok(());
+        }
+
+        let slot = breakpoints
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(CapacityError)?;
+        *slot = Some((address, write_instruction(address, BREAK_INSTRUCTION)));
+
+        Ok(())
+    })
+}
+
+/// Remove the breakpoint at an address, restoring the original instruction. Returns `false` if
+/// there was none.
+pub fn clear_breakpoint(address: usize) -> bool {
+    with_breakpoints(|breakpoints| {
+        let slot = breakpoints
+            .iter_mut()
+            .find(|slot| matches!(slot, Some((set, _)) if *set == address));
+        match slot.and_then(Option::take) {
+            Some((address, instruction)) => {
+                unsafe { write_instruction(address, instruction) };
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// Register the function called when a breakpoint is hit, replacing any previous one.
+pub fn set_breakpoint_handler(handler: fn(&mut Frame)) {
+    BREAKPOINT_HANDLER.store(handler as usize, Ordering::Release);
+}
+
+fn install_breakpoints() {
+    exception::install();
+    exception::set_handler(ExceptionCode::Breakpoint, Some(handle_breakpoint));
+}
+
+fn with_breakpoints<R>(f: impl FnOnce(&mut [Option<(usize, u32)>; MAX_BREAKPOINTS]) -> R) -> R {
+    BREAKPOINTS.lock(f)
+}
+
+fn with_trace<R>(f: impl FnOnce(&mut TraceState) -> R) -> R {
+    TRACE.lock(f)
+}
+
+/// Replace the instruction at an address, returning the previous one.
+unsafe fn write_instruction(address: usize, instruction: u32) -> u32 {
+    let ptr = address as *mut u32;
+    let previous = ptr.read_volatile();
+    ptr.write_volatile(instruction);
+    writeback_dcache(ptr as *const u8, 4);
+    invalidate_icache(ptr as *const u8, 4);
+
+    previous
+}
+
+/// Run a function, calling `sink` before each instruction it runs. Also installs the exception
+/// handler.
+pub fn trace<R>(sink: fn(&Step<'_>), f: impl FnOnce() -> R) -> R {
+    install_breakpoints();
+    with_trace(|trace| trace.sink = Some(sink));
+
+    platform::trace_start();
+    let result = f();
+    platform::trace_stop();
+
+    result
+}
+
+fn handle_breakpoint(frame: &mut Frame) {
+    let pc = frame.pc();
+    let instruction = unsafe { (pc as *const u32).read_volatile() };
+    match instruction {
+        BREAK_INSTRUCTION => {}
+        TRACE_START_INSTRUCTION => {
+            frame.skip_instruction();
+            with_trace(|trace| {
+                trace.previous = frame.gpr;
+                trace.step = Some((frame.epc as usize, unsafe {
+                    write_instruction(frame.epc as usize, STEP_INSTRUCTION)
+                }));
+            });
+            return;
+        }
+        STEP_INSTRUCTION => {
+            let (step, sink, previous) =
+                with_trace(|trace| (trace.step.take(), trace.sink, trace.previous));
+            let (address, original) = step.expect("Stray step breakpoint");
+            unsafe { write_instruction(address, original) };
+            // Called outside the lock, so the sink can use the debugger
+            if let Some(sink) = sink {
+                sink(&Step {
+                    pc,
+                    instruction: original,
+                    frame,
+                    previous: &previous,
+                });
+            }
+
+            let next = next_pc(frame, original);
+            with_trace(|trace| {
+                trace.previous = frame.gpr;
+                trace.step = Some((next, unsafe { write_instruction(next, STEP_INSTRUCTION) }));
+            });
+            return;
+        }
+        TRACE_STOP_INSTRUCTION => {
+            frame.skip_instruction();
+            if let Some((address, original)) = with_trace(|trace| {
+                trace.sink = None;
+                trace.step.take()
+            }) {
+                unsafe { write_instruction(address, original) };
+            }
+            return;
+        }
+        _ => panic!("Trap"),
+    }
+
+    // Runtime breakpoints rerun the original instruction, others are skipped
+    if !clear_breakpoint(pc) {
+        frame.skip_instruction();
+    }
+
+    match BREAKPOINT_HANDLER.load(Ordering::Acquire) {
+        0 => panic!("Breakpoint hit"),
+        handler => {
+            // Only function pointers are stored
+            let handler = unsafe { core::mem::transmute::<usize, fn(&mut Frame)>(handler) };
+            handler(frame);
+        }
+    }
+}
+
+/// Find the address of the instruction to run after the one at `frame.pc()`, and its delay slot
+/// if it is a branch.
+fn next_pc(frame: &Frame, instruction: u32) -> usize {
+    let pc = frame.pc() as u32;
+    let rs = frame.gpr[(instruction >> 21) as usize & 0x1F] as i64;
+    let rt = frame.gpr[(instruction >> 16) as usize & 0x1F] as i64;
+    let branch = pc
+        .wrapping_add(4)
+        .wrapping_add(((instruction as i16 as i32) << 2) as u32);
+    let jump = (pc.wrapping_add(4) & 0xF000_0000) | ((instruction & 0x03FF_FFFF) << 2);
+
+    let taken = match instruction >> 26 {
+        // SPECIAL: jr, jalr
+        0x00 if matches!(instruction & 0x3F, 0x08 | 0x09) => return rs as usize,
+        // REGIMM: bltz, bgez, bltzl, bgezl and their linking variants
+        0x01 => match (instruction >> 16) & 0x1F {
+            0x00 | 0x02 | 0x10 | 0x12 => Some(rs < 0),
+            0x01 | 0x03 | 0x11 | 0x13 => Some(rs >= 0),
+            _ => None,
+        },
+        // j, jal
+        0x02 | 0x03 => return jump as usize,
+        // beq, bne, blez, bgtz and their likely variants
+        0x04 | 0x14 => Some(rs == rt),
+        0x05 | 0x15 => Some(rs != rt),
+        0x06 | 0x16 => Some(rs <= 0),
+        0x07 | 0x17 => Some(rs > 0),
+        // COP1 bc1f, bc1t, bc1fl, bc1tl, on the FPU condition bit
+        0x11 if (instruction >> 21) & 0x1F == 0x08 => {
+            let condition = frame.fcsr & 0x0080_0000 != 0;
+            Some(condition == ((instruction >> 16) & 1 != 0))
+        }
+        _ => None,
+    };
+
+    match taken {
+        Some(true) => branch as usize,
+        Some(false) => pc as usize + 8,
+        None => pc as usize + 4,
+    }
+}
+
+/// Raise an exception when an address is written.
+pub fn watch_write(address: usize) {
+    watch(address, WatchKind::Write);
+}
+
+/// Raise an exception when an address is read.
+pub fn watch_read(address: usize) {
+    watch(address, WatchKind::Read);
+}
+
+/// Raise an exception when an address is accessed, replacing the current watchpoint. Also installs
+/// the exception handler.
+pub fn watch(address: usize, kind: WatchKind) {
+    exception::install();
+    exception::set_handler(ExceptionCode::Watch, Some(handle_watch));
+
+    let access = match kind {
+        WatchKind::Read => platform::WATCH_R,
+        WatchKind::Write => platform::WATCH_W,
+        WatchKind::ReadWrite => platform::WATCH_R | platform::WATCH_W,
+    };
+    platform::set_watch(map::physical(address) as u32 & !7 | access);
+}
+
+/// Remove the watchpoint.
+pub fn clear_watch() {
+    platform::set_watch(0);
+}
+
+/// Register the function called when the watchpoint is hit, replacing any previous one. Execution
+/// continues when it returns.
+pub fn set_watch_handler(handler: fn(WatchHit)) {
+    WATCH_HANDLER.store(handler as usize, Ordering::Release);
+}
+
+fn handle_watch(frame: &mut Frame) {
+    let hit = WatchHit {
+        pc: frame.pc(),
+        address: map::to_kseg0(platform::watch() as usize & !7),
+    };
+
+    // The access is retried when the handler returns, so it must not hit again
+    clear_watch();
+
+    match WATCH_HANDLER.load(Ordering::Acquire) {
+        0 => panic!("Watchpoint hit"),
+        handler => {
+            // Only function pointers are stored
+            let handler = unsafe { core::mem::transmute::<usize, fn(WatchHit)>(handler) };
+            handler(hit);
+        }
+    }
+}
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use core::arch::asm;
+
+    /// `WatchLo` bit to watch loads
+    pub const WATCH_R: u32 = 0x2;
+    /// `WatchLo` bit to watch stores
+    pub const WATCH_W: u32 = 0x1;
+
+    /// Write the COP0 `WatchLo` register, clearing `WatchHi` (physical address bits above 32).
+    pub fn set_watch(watch_lo: u32) {
+        unsafe {
+            asm!("mtc0 $0, $19", options(nostack));
+            asm!("mtc0 $2, $18", in("$2") watch_lo, options(nostack));
+        }
+    }
+
+    #[inline(always)]
+    pub fn breakpoint() {
+        unsafe { asm!("break 1", options(nomem, nostack)) };
+    }
+
+    #[inline(always)]
+    pub fn trace_start() {
+        unsafe { asm!("break 2", options(nostack)) };
+    }
+
+    #[inline(always)]
+    pub fn trace_stop() {
+        unsafe { asm!("break 4", options(nostack)) };
+    }
+
+    /// Read the COP0 `WatchLo` register.
+    pub fn watch() -> u32 {
+        let watch_lo: u32;
+        unsafe { asm!("mfc0 $2, $18", out("$2") watch_lo, options(nomem, nostack)) };
+
+        watch_lo
+    }
+}
+
+#[cfg(not(rrt0_platform = "n64"))]
+mod platform {
+    pub const WATCH_R: u32 = 0x2;
+    pub const WATCH_W: u32 = 0x1;
+
+    pub fn breakpoint() {}
+
+    pub fn trace_start() {}
+
+    pub fn trace_stop() {}
+
+    pub fn set_watch(_watch_lo: u32) {}
+
+    pub fn watch() -> u32 {
+        0
+    }
+}
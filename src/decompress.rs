@@ -0,0 +1,578 @@
+//! Decompression.
+//!
+//! Decompressors for LZ4 blocks and (with the `deflate` feature) raw DEFLATE and zlib streams.
+//! Both decompress into a caller-provided buffer without an allocator. LZ4 is several times
+//! faster to decompress, while DEFLATE compresses better.
+//!
+//! On N64, the `*_rom` functions read compressed data from the cartridge ROM with
+//! [`read_rom`](crate::mem::read_rom) before decompressing it.
+
+use core::fmt;
+
+/// Errors that can occur while decompressing.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DecompressError {
+    /// The data ends before the compressed stream does.
+    Truncated,
+    /// The data is not a valid compressed stream.
+    Invalid,
+    /// The output buffer is too small for the decompressed data.
+    BufferTooSmall,
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Truncated => "compressed data is truncated",
+            Self::Invalid => "invalid compressed data",
+            Self::BufferTooSmall => "output buffer is too small",
+        })
+    }
+}
+
+/// Decompress an LZ4 block (without the LZ4 frame header). Returns the decompressed size.
+pub fn lz4(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let mut read = 0;
+    let mut pos = 0;
+
+    loop {
+        let token = lz4_byte(src, &mut read)?;
+
+        // Literals
+        let len = lz4_length(src, &mut read, token >> 4)?;
+        let literals = src
+            .get(read..read + len)
+            .ok_or(DecompressError::Truncated)?;
+        dst.get_mut(pos..pos + len)
+            .ok_or(DecompressError::BufferTooSmall)?
+            .copy_from_slice(literals);
+        read += len;
+        pos += len;
+
+        // The last sequence has no match
+        if read == src.len() {
+            return Ok(pos);
+        }
+
+        // Match
+        let offset = u16::from_le_bytes([lz4_byte(src, &mut read)?, lz4_byte(src, &mut read)?]);
+        let offset = offset as usize;
+        if offset == 0 || offset > pos {
+            return Err(DecompressError::Invalid);
+        }
+
+        let len = lz4_length(src, &mut read, token & 0x0F)? + 4;
+        if pos + len > dst.len() {
+            return Err(DecompressError::BufferTooSmall);
+        }
+
+        // Byte by byte, since the match may overlap the output
+        for i in pos..pos + len {
+            dst[i] = dst[i - offset];
+        }
+        pos += len;
+    }
+}
+
+fn lz4_byte(src: &[u8], read: &mut usize) -> Result<u8, DecompressError> {
+    let byte = *src.get(*read).ok_or(DecompressError::Truncated)?;
+    *read += 1;
+
+    Ok(byte)
+}
+
+/// Read a length from a token nibble, followed by extra bytes if the nibble is 15.
+fn lz4_length(src: &[u8], read: &mut usize, nibble: u8) -> Result<usize, DecompressError> {
+    let mut len = nibble as usize;
+    if nibble == 15 {
+        loop {
+            let byte = lz4_byte(src, read)?;
+            len += byte as usize;
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+
+    Ok(len)
+}
+
+/// Read an LZ4 block of `len` bytes from ROM at `offset` into `scratch`, and decompress it into
+/// `dst`. Returns the decompressed size.
+#[cfg(rrt0_platform = "n64")]
+pub fn lz4_rom(
+    offset: u32,
+    len: usize,
+    scratch: &mut [u8],
+    dst: &mut [u8],
+) -> Result<usize, DecompressError> {
+    let src = scratch
+        .get_mut(..len)
+        .ok_or(DecompressError::BufferTooSmall)?;
+    crate::mem::read_rom(src, offset);
+
+    lz4(src, dst)
+}
+
+#[cfg(feature = "deflate")]
+pub use self::deflate::{inflate, zlib};
+
+#[cfg(all(feature = "deflate", rrt0_platform = "n64"))]
+pub use self::deflate::zlib_rom;
+
+#[cfg(feature = "deflate")]
+mod deflate {
+    use super::DecompressError;
+
+    /// Base lengths for length symbols 257..=285
+    const LENGTH_BASE: [u16; 29] = [
+        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+        131, 163, 195, 227, 258,
+    ];
+
+    /// Extra bits for length symbols 257..=285
+    const LENGTH_EXTRA: [u8; 29] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+    ];
+
+    /// Base distances for distance symbols 0..=29
+    const DISTANCE_BASE: [u16; 30] = [
+        1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+        2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+    ];
+
+    /// Extra bits for distance symbols 0..=29
+    const DISTANCE_EXTRA: [u8; 30] = [
+        0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12,
+        13, 13,
+    ];
+
+    /// Order of the code length code lengths in a dynamic block header
+    const CODE_LENGTH_ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+
+    const MAX_BITS: usize = 15;
+    const MAX_LITERALS: usize = 288;
+    const MAX_DISTANCES: usize = 30;
+
+    /// Canonical Huffman code
+    struct Huffman {
+        /// Number of codes of each length
+        counts: [u16; MAX_BITS + 1],
+        /// Symbols ordered by code
+        symbols: [u16; MAX_LITERALS],
+    }
+
+    impl Huffman {
+        fn new(lengths: &[u8]) -> Result<Self, DecompressError> {
+            let mut counts = [0; MAX_BITS + 1];
+            for &len in lengths {
+                counts[len as usize] += 1;
+            }
+
+            // Reject over-subscribed codes
+            let mut left = 1_i32;
+            for &count in &counts[1..] {
+                left = (left << 1) - count as i32;
+                if left < 0 {
+                    return Err(DecompressError::Invalid);
+                }
+            }
+
+            let mut offsets = [0; MAX_BITS + 1];
+            for len in 1..MAX_BITS {
+                offsets[len + 1] = offsets[len] + counts[len];
+            }
+
+            let mut symbols = [0; MAX_LITERALS];
+            for (symbol, &len) in lengths.iter().enumerate() {
+                if len != 0 {
+                    symbols[offsets[len as usize] as usize] = symbol as u16;
+                    offsets[len as usize] += 1;
+                }
+            }
+
+            counts[0] = 0;
+            Ok(Self { counts, symbols })
+        }
+    }
+
+    struct Inflater<'a, 'b> {
+        src: &'a [u8],
+        read: usize,
+        bits: u32,
+        bit_count: u32,
+        dst: &'b mut [u8],
+        pos: usize,
+    }
+
+    impl Inflater<'_, '_> {
+        fn bits(&mut self, count: u32) -> Result<u32, DecompressError> {
+            while self.bit_count < count {
+                let byte = *self.src.get(self.read).ok_or(DecompressError::Truncated)?;
+                self.read += 1;
+                self.bits |= (byte as u32) << self.bit_count;
+                self.bit_count += 8;
+            }
+
+            let value = self.bits & ((1 << count) - 1);
+            self.bits >>= count;
+            self.bit_count -= count;
+
+            Ok(value)
+        }
+
+        fn decode(&mut self, huffman: &Huffman) -> Result<u16, DecompressError> {
+            let (mut code, mut first, mut index) = (0, 0, 0);
+            for &count in &huffman.counts[1..] {
+                code |= self.bits(1)? as i32;
+                let count = count as i32;
+                if code - count < first {
+                    return Ok(huffman.symbols[(index + code - first) as usize]);
+                }
+                index += count;
+                first = (first + count) << 1;
+                code <<= 1;
+            }
+
+            Err(DecompressError::Invalid)
+        }
+
+        fn stored(&mut self) -> Result<(), DecompressError> {
+            // Skip to the next byte boundary
+            self.bits = 0;
+            self.bit_count = 0;
+
+            let header = self
+                .src
+                .get(self.read..self.read + 4)
+                .ok_or(DecompressError::Truncated)?;
+            let len = u16::from_le_bytes([header[0], header[1]]);
+            let inverse = u16::from_le_bytes([header[2], header[3]]);
+            if len != !inverse {
+                return Err(DecompressError::Invalid);
+            }
+            self.read += 4;
+
+            let len = len as usize;
+            let data = self
+                .src
+                .get(self.read..self.read + len)
+                .ok_or(DecompressError::Truncated)?;
+            self.dst
+                .get_mut(self.pos..self.pos + len)
+                .ok_or(DecompressError::BufferTooSmall)?
+                .copy_from_slice(data);
+            self.read += len;
+            self.pos += len;
+
+            Ok(())
+        }
+
+        fn codes(
+            &mut self,
+            literals: &Huffman,
+            distances: &Huffman,
+        ) -> Result<(), DecompressError> {
+            loop {
+                let symbol = match self.decode(literals)? as usize {
+                    literal @ 0..=255 => {
+                        let byte = self
+                            .dst
+                            .get_mut(self.pos)
+                            .ok_or(DecompressError::BufferTooSmall)?;
+                        *byte = literal as u8;
+                        self.pos += 1;
+                        continue;
+                    }
+                    256 => return Ok(()),
+                    symbol => symbol - 257,
+                };
+
+                if symbol >= LENGTH_BASE.len() {
+                    return Err(DecompressError::Invalid);
+                }
+                let len =
+                    LENGTH_BASE[symbol] as usize + self.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+                let symbol = self.decode(distances)? as usize;
+                if symbol >= DISTANCE_BASE.len() {
+                    return Err(DecompressError::Invalid);
+                }
+                let distance = DISTANCE_BASE[symbol] as usize
+                    + self.bits(DISTANCE_EXTRA[symbol] as u32)? as usize;
+
+                if distance > self.pos {
+                    return Err(DecompressError::Invalid);
+                }
+                if self.pos + len > self.dst.len() {
+                    return Err(DecompressError::BufferTooSmall);
+                }
+
+                for i in self.pos..self.pos + len {
+                    self.dst[i] = self.dst[i - distance];
+                }
+                self.pos += len;
+            }
+        }
+
+        fn fixed(&mut self) -> Result<(), DecompressError> {
+            let mut lengths = [0; MAX_LITERALS];
+            lengths[..144].fill(8);
+            lengths[144..256].fill(9);
+            lengths[256..280].fill(7);
+            lengths[280..].fill(8);
+            let literals = Huffman::new(&lengths)?;
+            let distances = Huffman::new(&[5; MAX_DISTANCES])?;
+
+            self.codes(&literals, &distances)
+        }
+
+        fn dynamic(&mut self) -> Result<(), DecompressError> {
+            let literal_count = self.bits(5)? as usize + 257;
+            let distance_count = self.bits(5)? as usize + 1;
+            let code_length_count = self.bits(4)? as usize + 4;
+            if literal_count > MAX_LITERALS || distance_count > MAX_DISTANCES {
+                return Err(DecompressError::Invalid);
+            }
+
+            let mut lengths = [0; MAX_LITERALS + MAX_DISTANCES];
+            for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+                lengths[index] = self.bits(3)? as u8;
+            }
+            let code_lengths = Huffman::new(&lengths[..19])?;
+
+            let total = literal_count + distance_count;
+            let mut index = 0;
+            while index < total {
+                let symbol = self.decode(&code_lengths)?;
+                let (len, repeat) = match symbol {
+                    0..=15 => (symbol as u8, 1),
+                    16 => {
+                        let previous = index
+                            .checked_sub(1)
+                            .map(|i| lengths[i])
+                            .ok_or(DecompressError::Invalid)?;
+                        (previous, 3 + self.bits(2)? as usize)
+                    }
+                    17 => (0, 3 + self.bits(3)? as usize),
+                    _ => (0, 11 + self.bits(7)? as usize),
+                };
+
+                if index + repeat > total {
+                    return Err(DecompressError::Invalid);
+                }
+                lengths[index..index + repeat].fill(len);
+                index += repeat;
+            }
+
+            // The end-of-block code is required
+            if lengths[256] == 0 {
+                return Err(DecompressError::Invalid);
+            }
+
+            let literals = Huffman::new(&lengths[..literal_count])?;
+            let distances = Huffman::new(&lengths[literal_count..total])?;
+
+            self.codes(&literals, &distances)
+        }
+    }
+
+    /// Decompress a raw DEFLATE stream. Returns the decompressed size and the number of
+    /// compressed bytes consumed.
+    pub fn inflate(src: &[u8], dst: &mut [u8]) -> Result<(usize, usize), DecompressError> {
+        let mut inflater = Inflater {
+            src,
+            read: 0,
+            bits: 0,
+            bit_count: 0,
+            dst,
+            pos: 0,
+        };
+
+        loop {
+            let last = inflater.bits(1)? == 1;
+            match inflater.bits(2)? {
+                0 => inflater.stored()?,
+                1 => inflater.fixed()?,
+                2 => inflater.dynamic()?,
+                _ => return Err(DecompressError::Invalid),
+            }
+
+            if last {
+                return Ok((inflater.pos, inflater.read));
+            }
+        }
+    }
+
+    /// Decompress a zlib stream, verifying its header and Adler-32 checksum. Returns the
+    /// decompressed size.
+    pub fn zlib(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+        let header = src.get(..2).ok_or(DecompressError::Truncated)?;
+        let (cmf, flags) = (header[0], header[1]);
+
+        // Deflate with a window of at most 32K, no preset dictionary
+        let valid = cmf & 0x0F == 8
+            && cmf >> 4 <= 7
+            && flags & 0x20 == 0
+            && u16::from_be_bytes([cmf, flags]) % 31 == 0;
+        if !valid {
+            return Err(DecompressError::Invalid);
+        }
+
+        let (len, read) = inflate(&src[2..], dst)?;
+        let trailer = src
+            .get(2 + read..2 + read + 4)
+            .ok_or(DecompressError::Truncated)?;
+        let checksum = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        if crate::checksum::adler32(&dst[..len]) != checksum {
+            return Err(DecompressError::Invalid);
+        }
+
+        Ok(len)
+    }
+
+    /// Read a zlib stream of `len` bytes from ROM at `offset` into `scratch`, and decompress it
+    /// into `dst`. Returns the decompressed size.
+    #[cfg(rrt0_platform = "n64")]
+    pub fn zlib_rom(
+        offset: u32,
+        len: usize,
+        scratch: &mut [u8],
+        dst: &mut [u8],
+    ) -> Result<usize, DecompressError> {
+        let src = scratch
+            .get_mut(..len)
+            .ok_or(DecompressError::BufferTooSmall)?;
+        crate::mem::read_rom(src, offset);
+
+        zlib(src, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lz4_literals_and_overlapping_matches() {
+        // "abc", then a 9 byte match 3 bytes back, then the final literals
+        let src = [0x35, b'a', b'b', b'c', 3, 0, 0x10, b'!'];
+        let mut dst = [0; 13];
+        assert_eq!(lz4(&src, &mut dst), Ok(13));
+        assert_eq!(&dst, b"abcabcabcabc!");
+    }
+
+    #[test]
+    fn lz4_long_lengths() {
+        // 15 + 5 literals, then a 4 + 15 + 255 + 1 byte match of the last byte
+        let mut src = [0; 30];
+        src[..2].copy_from_slice(&[0xFF, 5]);
+        src[2..22].copy_from_slice(b"0123456789abcdefghij");
+        src[22..27].copy_from_slice(&[1, 0, 255, 1, 0x00]);
+        let mut dst = [0; 295];
+        assert_eq!(lz4(&src[..27], &mut dst), Ok(295));
+        assert_eq!(&dst[..20], b"0123456789abcdefghij");
+        assert!(dst[20..].iter().all(|&byte| byte == b'j'));
+    }
+
+    #[test]
+    fn lz4_errors() {
+        let mut dst = [0; 16];
+        assert_eq!(lz4(&[], &mut dst), Err(DecompressError::Truncated));
+        assert_eq!(
+            lz4(&[0x30, b'a'], &mut dst),
+            Err(DecompressError::Truncated)
+        );
+        // Offsets of zero, and before the start of the output
+        assert_eq!(
+            lz4(&[0x10, b'a', 0, 0, 0x00], &mut dst),
+            Err(DecompressError::Invalid)
+        );
+        assert_eq!(
+            lz4(&[0x10, b'a', 2, 0, 0x00], &mut dst),
+            Err(DecompressError::Invalid)
+        );
+        assert_eq!(
+            lz4(&[0x35, b'a', b'b', b'c', 3, 0, 0x10, b'!'], &mut dst[..12]),
+            Err(DecompressError::BufferTooSmall)
+        );
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn zlib_stored_and_fixed_blocks() {
+        let stored = [
+            0x78, 0x01, 0x01, 0x03, 0x00, 0xFC, 0xFF, 0x61, 0x62, 0x63, 0x02, 0x4D, 0x01, 0x27,
+        ];
+        let mut dst = [0; 32];
+        assert_eq!(zlib(&stored, &mut dst), Ok(3));
+        assert_eq!(&dst[..3], b"abc");
+
+        let fixed = [
+            0x78, 0xDA, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57, 0xC8, 0x40, 0x27, 0x01, 0x68, 0x03,
+            0x08, 0xB1,
+        ];
+        assert_eq!(zlib(&fixed, &mut dst), Ok(23));
+        assert_eq!(&dst[..23], b"hello hello hello hello");
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn zlib_dynamic_block() {
+        let src = [
+            0x78, 0xDA, 0x85, 0xD3, 0xCB, 0x09, 0x80, 0x30, 0x14, 0x44, 0xD1, 0x56, 0x5E, 0x01,
+            0x2E, 0x9C, 0x89, 0xDF, 0x72, 0x14, 0x22, 0x2E, 0x82, 0x01, 0x0D, 0xD8, 0xBE, 0x1D,
+            0x78, 0xD7, 0x77, 0x77, 0x98, 0x19, 0xFA, 0xD8, 0x6B, 0x6B, 0x25, 0x3F, 0x51, 0x8F,
+            0xD8, 0x73, 0xBE, 0xA3, 0x5E, 0xD1, 0xCE, 0x1C, 0xEF, 0x56, 0x4A, 0x17, 0x69, 0x85,
+            0xBE, 0x40, 0x9F, 0xA1, 0x4F, 0xD0, 0x47, 0xE8, 0x03, 0xF4, 0x04, 0xDD, 0xD0, 0x05,
+            0x1D, 0xFC, 0x0C, 0x7E, 0x06, 0x3F, 0x83, 0x9F, 0xC1, 0xCF, 0xE0, 0x67, 0xF0, 0x33,
+            0xF8, 0x19, 0xFC, 0x0C, 0x7E, 0x06, 0x3F, 0x81, 0x9F, 0xC0, 0x4F, 0xE0, 0x27, 0xF0,
+            0x13, 0xF8, 0x09, 0xFC, 0x04, 0x7E, 0x02, 0x3F, 0x81, 0x9F, 0xC0, 0x0F, 0xF8, 0x40,
+            0x0F, 0xF0, 0xC0, 0x0E, 0xE8, 0xE8, 0xB9, 0x30, 0x1C, 0x70, 0xF9, 0xCF, 0x1F, 0x1F,
+            0x7E, 0xA9, 0xE4,
+        ];
+
+        let mut expected = [0; 1271];
+        let mut writer = crate::fmt::BufWriter::new(&mut expected);
+        for bottles in (1..=40).rev() {
+            fmt::Write::write_fmt(
+                &mut writer,
+                format_args!("{} bottles of beer on the wall, ", bottles),
+            )
+            .unwrap();
+        }
+
+        let mut dst = [0; 1271];
+        assert_eq!(zlib(&src, &mut dst), Ok(1271));
+        assert_eq!(dst, expected);
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn zlib_errors() {
+        let mut dst = [0; 32];
+        let mut fixed = [
+            0x78, 0xDA, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57, 0xC8, 0x40, 0x27, 0x01, 0x68, 0x03,
+            0x08, 0xB1,
+        ];
+        assert_eq!(zlib(&fixed[..2], &mut dst), Err(DecompressError::Truncated));
+        assert_eq!(
+            zlib(&fixed[..12], &mut dst),
+            Err(DecompressError::Truncated)
+        );
+        assert_eq!(
+            zlib(&fixed, &mut dst[..8]),
+            Err(DecompressError::BufferTooSmall)
+        );
+
+        // A bad checksum, and a bad header check
+        fixed[15] ^= 1;
+        assert_eq!(zlib(&fixed, &mut dst), Err(DecompressError::Invalid));
+        assert_eq!(
+            zlib(&[0x78, 0xDB, 0x03, 0x00], &mut dst),
+            Err(DecompressError::Invalid)
+        );
+    }
+}
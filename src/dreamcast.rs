@@ -0,0 +1,77 @@
+//! Dreamcast support.
+//!
+//! [`Console`] writes text to the host through dc-tool, for printing diagnostics, e.g. as the
+//! output of the [test runner](crate::test::set_output):
+//!
+//! ```text
+//! rrt0::test::set_output(rrt0::dreamcast::console_output);
+//! ```
+//!
+//! When the program was started by dcload (the loader dc-tool talks to), the text goes through its
+//! `write` syscall to the standard output of dc-tool, over serial or Ethernet. Otherwise it is sent
+//! to the SCIF serial port as is.
+//!
+//! rustc has no inline assembly for SuperH, so the startup code in
+//! `src/platforms/dreamcast/crt0.s` is assembled and linked by the program's build.
+//!
+//! Only available on Dreamcast.
+
+use core::fmt;
+
+/// Where dcload stores its magic value, and the address of its syscall handler after it
+const DCLOAD_MAGIC: *const u32 = 0x8C00_4004 as *const u32;
+const DCLOAD_SYSCALL: *const DcloadSyscall = 0x8C00_4008 as *const DcloadSyscall;
+const DCLOAD_MAGIC_VALUE: u32 = 0xDEAD_BEEF;
+
+/// dcload `write` syscall, and the file descriptor of standard output on the host
+const DCLOAD_WRITE: u32 = 1;
+const STDOUT: u32 = 1;
+
+type DcloadSyscall = extern "C" fn(syscall: u32, fd: u32, buf: *const u8, len: usize) -> i32;
+
+/// SCIF FIFO status register, and its transmit FIFO data register
+const SCFSR2: *mut u16 = 0xFFE8_0010 as *mut u16;
+const SCFTDR2: *mut u8 = 0xFFE8_000C as *mut u8;
+
+/// Transmit FIFO data empty and transmission end flags in `SCFSR2`
+const SCFSR2_TDFE: u16 = 0x0020;
+const SCFSR2_TEND: u16 = 0x0040;
+
+/// Text output to the host.
+#[derive(Debug, Default)]
+pub struct Console;
+
+impl Console {
+    /// Write bytes through dcload, or to the SCIF when dcload is not present.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        if unsafe { DCLOAD_MAGIC.read_volatile() } == DCLOAD_MAGIC_VALUE {
+            let syscall = unsafe { DCLOAD_SYSCALL.read_volatile() };
+            syscall(DCLOAD_WRITE, STDOUT, bytes.as_ptr(), bytes.len());
+            return;
+        }
+
+        for byte in bytes {
+            while unsafe { SCFSR2.read_volatile() } & SCFSR2_TDFE == 0 {}
+
+            unsafe {
+                SCFTDR2.write_volatile(*byte);
+                let status = SCFSR2.read_volatile();
+                SCFSR2.write_volatile(status & !(SCFSR2_TDFE | SCFSR2_TEND));
+            }
+        }
+    }
+}
+
+impl fmt::Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_bytes(s.as_bytes());
+
+        Ok(())
+    }
+}
+
+/// Write text to the host, with the signature of an output function for the
+/// [test runner](crate::test::set_output).
+pub fn console_output(s: &str) {
+    Console.write_bytes(s.as_bytes());
+}
@@ -0,0 +1,150 @@
+//! Interpolation and easing.
+//!
+//! Functions for animating values over time. They work with any [`Scalar`] (`f32` or
+//! [`Fx16_16`](crate::fixed::Fx16_16)) as the parameter, and [`lerp`] and the Bézier curves also
+//! work with vectors.
+//!
+//! Easing functions map a normalized time `t` in `[0, 1]` to a progress value, which is `0` at
+//! `t = 0` and `1` at `t = 1`. Combine them with [`lerp`] to animate between two values, e.g.
+//! `lerp(start, end, Ease::InOutCubic.apply(t))`.
+
+use crate::vector::Scalar;
+use core::ops::{Add, Mul, Sub};
+
+/// Values that can be interpolated with a scalar parameter, like scalars and vectors.
+pub trait Interpolate<T>:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<T, Output = Self>
+{
+}
+
+impl<T, V> Interpolate<T> for V where
+    V: Copy + Add<Output = V> + Sub<Output = V> + Mul<T, Output = V>
+{
+}
+
+/// Linear interpolation from `a` (at `t = 0`) to `b` (at `t = 1`). `t` is not clamped.
+pub fn lerp<T: Scalar, V: Interpolate<T>>(a: V, b: V, t: T) -> V {
+    a + (b - a) * t
+}
+
+/// The parameter `t` for which `lerp(a, b, t) == value`. Returns zero if `a == b`.
+pub fn inverse_lerp<T: Scalar>(a: T, b: T, value: T) -> T {
+    if a == b {
+        T::ZERO
+    } else {
+        (value - a) / (b - a)
+    }
+}
+
+/// Map a value from the range `[from_a, from_b]` to the range `[to_a, to_b]`.
+pub fn remap<T: Scalar>(value: T, from_a: T, from_b: T, to_a: T, to_b: T) -> T {
+    lerp(to_a, to_b, inverse_lerp(from_a, from_b, value))
+}
+
+/// Clamp a value to `[0, 1]`.
+pub fn saturate<T: Scalar>(t: T) -> T {
+    if t < T::ZERO {
+        T::ZERO
+    } else if t > T::ONE {
+        T::ONE
+    } else {
+        t
+    }
+}
+
+/// Smooth Hermite step from 0 (at `edge0`) to 1 (at `edge1`), clamped outside of the edges.
+pub fn smoothstep<T: Scalar>(edge0: T, edge1: T, x: T) -> T {
+    let t = saturate(inverse_lerp(edge0, edge1, x));
+    let two = T::ONE + T::ONE;
+
+    t * t * (two + T::ONE - two * t)
+}
+
+/// Like [`smoothstep`], but with zero first and second derivatives at the edges.
+pub fn smootherstep<T: Scalar>(edge0: T, edge1: T, x: T) -> T {
+    let t = saturate(inverse_lerp(edge0, edge1, x));
+    let two = T::ONE + T::ONE;
+    let five = two + two + T::ONE;
+    let six = five + T::ONE;
+    let ten = five + five;
+
+    t * t * t * (t * (t * six - five - ten) + ten)
+}
+
+/// Point on a quadratic Bézier curve with control point `p1`.
+pub fn quadratic_bezier<T: Scalar, V: Interpolate<T>>(p0: V, p1: V, p2: V, t: T) -> V {
+    lerp(lerp(p0, p1, t), lerp(p1, p2, t), t)
+}
+
+/// Point on a cubic Bézier curve with control points `p1` and `p2`.
+pub fn cubic_bezier<T: Scalar, V: Interpolate<T>>(p0: V, p1: V, p2: V, p3: V, t: T) -> V {
+    let a = quadratic_bezier(p0, p1, p2, t);
+    let b = quadratic_bezier(p1, p2, p3, t);
+
+    lerp(a, b, t)
+}
+
+/// Standard easing curves. `In` curves start slowly, `Out` curves end slowly, and `InOut` curves
+/// do both.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Ease {
+    #[default]
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InSine,
+    OutSine,
+    InOutSine,
+    /// [`smoothstep`] over `[0, 1]`
+    Smooth,
+}
+
+impl Ease {
+    /// Apply the curve to a normalized time, which is clamped to `[0, 1]`.
+    pub fn apply<T: Scalar>(self, t: T) -> T {
+        let t = saturate(t);
+        let one = T::ONE;
+        let two = one + one;
+        let half = one / two;
+
+        // Mirror an `In` curve to get the `Out` curve
+        let out = |ease_in: fn(T) -> T| one - ease_in(one - t);
+        let in_out = |ease_in: fn(T) -> T| {
+            if t < half {
+                ease_in(t * two) / two
+            } else {
+                one - ease_in((one - t) * two) / two
+            }
+        };
+
+        match self {
+            Self::Linear => t,
+            Self::InQuad => quad(t),
+            Self::OutQuad => out(quad),
+            Self::InOutQuad => in_out(quad),
+            Self::InCubic => cubic(t),
+            Self::OutCubic => out(cubic),
+            Self::InOutCubic => in_out(cubic),
+            Self::InSine => sine(t),
+            Self::OutSine => out(sine),
+            Self::InOutSine => in_out(sine),
+            Self::Smooth => smoothstep(T::ZERO, one, t),
+        }
+    }
+}
+
+fn quad<T: Scalar>(t: T) -> T {
+    t * t
+}
+
+fn cubic<T: Scalar>(t: T) -> T {
+    t * t * t
+}
+
+fn sine<T: Scalar>(t: T) -> T {
+    T::ONE - (t * T::FRAC_PI_2).sin_cos().1
+}
@@ -0,0 +1,111 @@
+//! Entropy pool.
+//!
+//! Collects unpredictable timing and input samples into a small pool, used to seed the global
+//! generator in [`rand`](crate::rand). On N64, the pool is filled on first use by sampling the CPU
+//! cycle counter each time the VI moves to a new line, which jitters with bus contention and the
+//! PIF boot timing.
+//!
+//! Once [`enable_rcp_events`](crate::event::enable_rcp_events) is called, every RCP interrupt it
+//! handles mixes in the cycle counter and the remaining length of the audio DMA, so the timing of
+//! the VI, the audio, the RSP, and the controller reads keeps feeding the pool. The controller
+//! reader of n64lib mixes in the buttons and analog stick readings.
+//!
+//! Other sources, like the cartridge RTC, can be mixed in with [`add_sample`].
+
+use crate::interrupt;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// Pool state
+static POOL: [AtomicU32; 4] = [
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+];
+
+/// Index of the next pool word to mix into
+static CURSOR: AtomicU32 = AtomicU32::new(0);
+
+/// Whether the pool has been filled from the hardware
+static HARVESTED: AtomicBool = AtomicBool::new(false);
+
+/// Number of samples taken by [`harvest`]
+const HARVEST_SAMPLES: usize = 32;
+
+/// Mix a sample into the pool, along with the current cycle counter.
+pub fn add_sample(sample: u32) {
+    let index = interrupt::fetch_add(&CURSOR, 1) as usize % POOL.len();
+    let word = &POOL[index];
+    let value = word.load(Ordering::Relaxed) ^ sample ^ crate::time::counter().rotate_left(16);
+
+    word.store(
+        value.wrapping_mul(0x9E37_79B1).rotate_left(13) ^ index as u32,
+        Ordering::Relaxed,
+    );
+}
+
+/// Mix a slice of bytes into the pool.
+pub fn add_bytes(bytes: &[u8]) {
+    for chunk in bytes.chunks(4) {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        add_sample(u32::from_le_bytes(word));
+    }
+}
+
+/// Fill the pool from the hardware sources, unless it has already been done.
+pub fn harvest() {
+    if interrupt::swap(&HARVESTED, true) {
+        return;
+    }
+
+    for _ in 0..HARVEST_SAMPLES {
+        add_sample(wait_for_line());
+    }
+}
+
+/// Derive a 64-bit seed from the pool, harvesting first if needed. Each call returns a different
+/// seed.
+pub fn seed() -> u64 {
+    harvest();
+
+    let mut hash = 0_u64;
+    for word in &POOL {
+        hash = mix64(hash ^ word.load(Ordering::Relaxed) as u64);
+    }
+
+    // Stir the pool so the next seed differs
+    add_sample(hash as u32);
+
+    hash
+}
+
+/// The splitmix64 finalizer
+fn mix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Wait for the VI to start a new line and return the line number. Gives up after a while in case
+/// the VI is not running.
+#[cfg(rrt0_platform = "n64")]
+fn wait_for_line() -> u32 {
+    use crate::mmio::VI;
+
+    let line = VI.v_current.read();
+    for _ in 0..10_000 {
+        let current = VI.v_current.read();
+        if current != line {
+            return current;
+        }
+    }
+
+    line
+}
+
+#[cfg(not(rrt0_platform = "n64"))]
+fn wait_for_line() -> u32 {
+    0
+}
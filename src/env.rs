@@ -0,0 +1,117 @@
+//! Runtime environment detection.
+//!
+//! [`detect`] identifies the flashcart the program runs from by its registers, and whether an
+//! Expansion Pak is installed, so programs can pick backends, e.g. for USB logging or SD card
+//! saves, and skip tests that need specific hardware.
+//!
+//! Emulators (cen64, ares, simple64) are not identified. They have no documented signature of
+//! their own, ares emulates the flashcart registers too, and timing probes differ between their
+//! versions and settings, so any guess would be wrong often enough to skip the wrong tests. Code
+//! that needs to know should be told by its build, e.g. with a feature.
+//!
+//! With the `ique` feature, no flashcart is probed for, and the 8 MiB of an iQue Player count as
+//! an Expansion Pak.
+//!
+//! On other platforms, no flashcart or Expansion Pak is detected.
+
+/// A flashcart with registers on the cartridge bus.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Flashcart {
+    SixtyFourDrive,
+    /// EverDrive-64 X series
+    EverDrive64,
+    SummerCart64,
+}
+
+/// The detected environment.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Environment {
+    pub flashcart: Option<Flashcart>,
+    pub expansion_pak: bool,
+}
+
+/// Detect the environment. Unlocks the SummerCart64 registers as a side effect.
+pub fn detect() -> Environment {
+    Environment {
+        flashcart: platform::detect_flashcart(),
+        expansion_pak: platform::expansion_pak(),
+    }
+}
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use super::Flashcart;
+    use crate::mem::{detect_memory_size, map, EXPANDED_RDRAM_SIZE};
+    use crate::mmio::PI;
+
+    /// 64drive `CI_MAGIC` register, reading "UDEV"
+    const SIXTY_FOUR_DRIVE_MAGIC: *const u32 =
+        map::to_kseg1(map::SIXTY_FOUR_DRIVE_REGS + 0x2EC) as *const u32;
+    const SIXTY_FOUR_DRIVE_ID: u32 = 0x5544_4556;
+
+    /// EverDrive-64 X series `REG_EDID` register, with the cart ID in the upper half
+    const EVERDRIVE_EDID: *const u32 = map::to_kseg1(map::EVERDRIVE_REGS + 0x14) as *const u32;
+    const EVERDRIVE_ID: u32 = 0xED64_0000;
+
+    /// SummerCart64 `KEY` register, unlocking the others when written "_UNLOCK_"
+    const SUMMERCART_KEY: *mut u32 = map::to_kseg1(map::SUMMERCART_REGS + 0x10) as *mut u32;
+    const SUMMERCART_UNLOCK: [u32; 3] = [0, 0x5F55_4E4C, 0x4F43_4B5F];
+    /// SummerCart64 `IDENTIFIER` register, reading "SCv2"
+    const SUMMERCART_IDENTIFIER: *const u32 =
+        map::to_kseg1(map::SUMMERCART_REGS + 0x0C) as *const u32;
+    const SUMMERCART_ID: u32 = 0x5343_7632;
+
+    pub fn detect_flashcart() -> Option<Flashcart> {
+        // The iQue Player has no cartridge bus, and probing it faults
+        if cfg!(feature = "ique") {
+            return None;
+        }
+
+        if read(SIXTY_FOUR_DRIVE_MAGIC) == SIXTY_FOUR_DRIVE_ID {
+            return Some(Flashcart::SixtyFourDrive);
+        }
+        if read(EVERDRIVE_EDID) & 0xFFFF_0000 == EVERDRIVE_ID {
+            return Some(Flashcart::EverDrive64);
+        }
+
+        for key in SUMMERCART_UNLOCK {
+            wait_for_pi();
+            unsafe { SUMMERCART_KEY.write_volatile(key) };
+        }
+        if read(SUMMERCART_IDENTIFIER) == SUMMERCART_ID {
+            return Some(Flashcart::SummerCart64);
+        }
+
+        None
+    }
+
+    pub fn expansion_pak() -> bool {
+        detect_memory_size() == EXPANDED_RDRAM_SIZE
+    }
+
+    /// Read a cartridge bus register.
+    fn read(register: *const u32) -> u32 {
+        wait_for_pi();
+        unsafe { register.read_volatile() }
+    }
+
+    fn wait_for_pi() {
+        while {
+            let status = PI.status.read();
+            status.dma_busy() || status.io_busy()
+        } {}
+    }
+}
+
+#[cfg(not(rrt0_platform = "n64"))]
+mod platform {
+    use super::Flashcart;
+
+    pub fn detect_flashcart() -> Option<Flashcart> {
+        None
+    }
+
+    pub fn expansion_pak() -> bool {
+        false
+    }
+}
@@ -0,0 +1,171 @@
+//! A general purpose error type for application code.
+//!
+//! [`Error`] holds a short formatted message and the source location it was created at, without
+//! an allocator. [`bail!`](crate::bail) returns one early, and [`ensure!`](crate::ensure) returns
+//! one when a condition does not hold. Both convert it with `Into`, so they can also be used in
+//! functions returning a custom error type that implements `From<Error>`. The errors returned by
+//! the rest of this crate convert into it with `?`, keeping the location of the `?`.
+//!
+//! `core::error::Error` is not available on the toolchain this crate is built with, so only
+//! `Display` and `Debug` are implemented.
+
+use crate::collections::CapacityError;
+use crate::decompress::DecompressError;
+use crate::fat::FatError;
+use crate::fmt::FormatBuffer;
+#[cfg(feature = "heap")]
+use crate::heap::AllocError;
+#[cfg(rrt0_platform = "n64")]
+use crate::loader::LoadError;
+#[cfg(rrt0_platform = "n64")]
+use crate::overlay::OverlayError;
+#[cfg(rrt0_platform = "n64")]
+use crate::romfs::RomfsError;
+use crate::sd::BlockError;
+use core::fmt::{self, Write};
+use core::panic::Location;
+
+/// Longest message kept by an [`Error`] (in bytes). Longer messages are truncated.
+pub const MESSAGE_SIZE: usize = 64;
+
+/// A `Result` defaulting to [`Error`].
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+/// Return early with an [`Error`] formatted from the arguments.
+///
+/// e.g. `bail!("NO SAVE IN SLOT {}", slot);`
+#[macro_export]
+macro_rules! bail {
+    ($($arg:tt)*) => {
+        return ::core::result::Result::Err(::core::convert::Into::into(
+            $crate::error::Error::from_args(::core::format_args!($($arg)*)),
+        ))
+    };
+}
+
+/// Return early with an [`Error`] if a condition does not hold. Without a message, the condition
+/// itself is used.
+///
+/// e.g. `ensure!(header.len() == 64, "BAD HEADER LENGTH {}", header.len());`
+#[macro_export]
+macro_rules! ensure {
+    ($condition:expr $(,)?) => {
+        if !$condition {
+            $crate::bail!(::core::concat!("condition failed: ", ::core::stringify!($condition)));
+        }
+    };
+    ($condition:expr, $($arg:tt)+) => {
+        if !$condition {
+            $crate::bail!($($arg)+);
+        }
+    };
+}
+
+/// An error message with the location it was created at.
+#[derive(Clone)]
+pub struct Error {
+    message: FormatBuffer<MESSAGE_SIZE>,
+    location: &'static Location<'static>,
+}
+
+impl Error {
+    /// Create an error with a message, at the caller's location.
+    #[track_caller]
+    pub fn new(message: impl fmt::Display) -> Self {
+        Self::from_args(format_args!("{}", message))
+    }
+
+    /// Create an error from formatting arguments, at the caller's location.
+    #[track_caller]
+    pub fn from_args(args: fmt::Arguments<'_>) -> Self {
+        let mut message = FormatBuffer::new();
+        let _ = message.write_fmt(args);
+
+        Self {
+            message,
+            location: Location::caller(),
+        }
+    }
+
+    /// Get the message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the source location the error was created at.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+}
+
+impl From<CapacityError> for Error {
+    #[track_caller]
+    fn from(error: CapacityError) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<DecompressError> for Error {
+    #[track_caller]
+    fn from(error: DecompressError) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<FatError> for Error {
+    #[track_caller]
+    fn from(error: FatError) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<BlockError> for Error {
+    #[track_caller]
+    fn from(error: BlockError) -> Self {
+        Self::new(error)
+    }
+}
+
+#[cfg(feature = "heap")]
+impl From<AllocError> for Error {
+    #[track_caller]
+    fn from(error: AllocError) -> Self {
+        Self::new(error)
+    }
+}
+
+#[cfg(rrt0_platform = "n64")]
+impl From<LoadError> for Error {
+    #[track_caller]
+    fn from(error: LoadError) -> Self {
+        Self::new(error)
+    }
+}
+
+#[cfg(rrt0_platform = "n64")]
+impl From<OverlayError> for Error {
+    #[track_caller]
+    fn from(error: OverlayError) -> Self {
+        Self::new(error)
+    }
+}
+
+#[cfg(rrt0_platform = "n64")]
+impl From<RomfsError> for Error {
+    #[track_caller]
+    fn from(error: RomfsError) -> Self {
+        Self::new(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} at {}", self.message.as_str(), self.location)
+    }
+}
@@ -0,0 +1,319 @@
+//! Event queues, for getting events out of interrupt handlers.
+//!
+//! An [`EventQueue`] holds up to `N` events of one type in place, published from anywhere,
+//! including interrupt handlers, and drained by the program, usually once per frame:
+//!
+//! ```text
+//! rrt0::event::enable_rcp_events(MiInterrupts::default().with_vi(true).with_si(true));
+//!
+//! loop {
+//!     rrt0::event::EVENTS.drain(|event| match event {
+//!         Event::VBlank => game.vblank(),
+//!         Event::SiDma => game.read_controllers(),
+//!         Event::Reset => game.save_and_halt(),
+//!         _ => {}
+//!     });
+//!     ...
+//! }
+//! ```
+//!
+//! The hardware subsystems publish to the global [`EVENTS`] queue. [`enable_rcp_events`] installs
+//! an interrupt handler that acknowledges the RCP interrupts and publishes them as [`Event`]s,
+//! along with the reset button. Programs publish their own with [`Event::User`], or keep separate
+//! queues of their own event types.
+//!
+//! When a queue is full, new events are dropped and counted.
+//!
+//! On other platforms, there are no hardware events.
+
+use crate::exception::{self, ExceptionCode, Frame};
+use crate::interrupt::Mutex;
+use crate::mmio::MiInterrupts;
+use core::mem::MaybeUninit;
+
+/// Capacity of the global event queue
+pub const MAX_EVENTS: usize = 64;
+
+/// The global event queue
+pub static EVENTS: EventQueue<Event, MAX_EVENTS> = EventQueue::new();
+
+/// An event published by the hardware subsystems.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Event {
+    /// The VI reached its interrupt line, usually the start of the vertical blank
+    VBlank,
+    /// A PI DMA finished
+    PiDma,
+    /// An SI DMA finished, e.g. with new controller state
+    SiDma,
+    /// The RSP raised its interrupt, or stopped on a break
+    Rsp,
+    /// The RDP finished a full sync
+    Rdp,
+    /// The AI started playing the next sample buffer, so another can be queued
+    Audio,
+    /// The reset button was pressed. The console resets about half a second later.
+    Reset,
+    /// An event published by the program
+    User(u32),
+}
+
+/// A queue of up to `N` events.
+pub struct EventQueue<T, const N: usize>(Mutex<Queue<T, N>>);
+
+struct Queue<T, const N: usize> {
+    events: [MaybeUninit<T>; N],
+    /// Index of the oldest event
+    start: usize,
+    len: usize,
+    dropped: u32,
+}
+
+impl<T: Copy, const N: usize> EventQueue<T, N> {
+    pub const fn new() -> Self {
+        Self(Mutex::new(Queue {
+            // An array of `MaybeUninit` needs no initialization
+            events: unsafe { MaybeUninit::uninit().assume_init() },
+            start: 0,
+            len: 0,
+            dropped: 0,
+        }))
+    }
+
+    /// Add an event at the end of the queue. Returns `false` if the queue is full and the event
+    /// was dropped.
+    pub fn publish(&self, event: T) -> bool {
+        self.with_queue(|queue| {
+            if queue.len == N {
+                queue.dropped = queue.dropped.saturating_add(1);
+                return false;
+            }
+
+            queue.events[(queue.start + queue.len) % N] = MaybeUninit::new(event);
+            queue.len += 1;
+            true
+        })
+    }
+
+    /// Remove the oldest event.
+    pub fn pop(&self) -> Option<T> {
+        self.with_queue(|queue| {
+            if queue.len == 0 {
+                return None;
+            }
+
+            // Events up to `len` from `start` are initialized
+            let event = unsafe { queue.events[queue.start].assume_init() };
+            queue.start = (queue.start + 1) % N;
+            queue.len -= 1;
+            Some(event)
+        })
+    }
+
+    /// Remove the events published so far, passing them to a function, oldest first. Events
+    /// published while it runs are passed too.
+    pub fn drain(&self, mut f: impl FnMut(T)) {
+        // Popped one at a time, so `f` runs with interrupts enabled
+        while let Some(event) = self.pop() {
+            f(event);
+        }
+    }
+
+    /// Number of events in the queue
+    pub fn len(&self) -> usize {
+        self.with_queue(|queue| queue.len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all events.
+    pub fn clear(&self) {
+        self.with_queue(|queue| queue.len = 0);
+    }
+
+    /// Number of events dropped because the queue was full, since the last call.
+    pub fn dropped(&self) -> u32 {
+        self.with_queue(|queue| core::mem::take(&mut queue.dropped))
+    }
+
+    fn with_queue<R>(&self, f: impl FnOnce(&mut Queue<T, N>) -> R) -> R {
+        self.0.lock(f)
+    }
+}
+
+impl<T: Copy, const N: usize> Default for EventQueue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `Cause` register bits for pending interrupts
+const CAUSE_IP: u32 = 0xFF00;
+/// `Cause` register bits for a pending RCP interrupt, and for the reset button
+const CAUSE_IP2: u32 = 0x0400;
+const CAUSE_IP4: u32 = 0x1000;
+
+struct ListenerState {
+    enabled: bool,
+    /// Interrupt handler to pass other interrupts to
+    next: Option<exception::Handler>,
+}
+
+static LISTENER: Mutex<ListenerState> = Mutex::new(ListenerState {
+    enabled: false,
+    next: None,
+});
+
+/// Publish the RCP interrupts from some sources, and the reset button, to [`EVENTS`]. Also
+/// installs the exception handler. Interrupts from the other sources are disabled.
+pub fn enable_rcp_events(sources: MiInterrupts) {
+    exception::install();
+    with_listener(|listener| {
+        if !listener.enabled {
+            listener.next = exception::handler(ExceptionCode::Interrupt);
+            listener.enabled = true;
+        }
+        exception::set_handler(ExceptionCode::Interrupt, Some(handle_interrupt));
+    });
+
+    // Changes the Status register, so it can't be done with interrupts disabled
+    platform::enable(sources);
+}
+
+/// Stop publishing RCP interrupts and the reset button.
+pub fn disable_rcp_events() {
+    platform::disable();
+    with_listener(|listener| {
+        if listener.enabled {
+            exception::set_handler(ExceptionCode::Interrupt, listener.next.take());
+            listener.enabled = false;
+        }
+    });
+}
+
+fn with_listener<R>(f: impl FnOnce(&mut ListenerState) -> R) -> R {
+    LISTENER.lock(f)
+}
+
+fn handle_interrupt(frame: &mut Frame) {
+    if frame.cause & CAUSE_IP2 != 0 {
+        platform::acknowledge(|event| {
+            EVENTS.publish(event);
+        });
+    }
+    if frame.cause & CAUSE_IP4 != 0 {
+        // Stays pending until the reset, so it is only reported once
+        platform::disable_reset();
+        EVENTS.publish(Event::Reset);
+    }
+
+    if frame.cause & CAUSE_IP & !(CAUSE_IP2 | CAUSE_IP4) != 0 {
+        match with_listener(|listener| listener.next) {
+            Some(next) => next(frame),
+            None => panic!("Unhandled interrupt"),
+        }
+    }
+}
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use super::Event;
+    use crate::mmio::{
+        MiInterrupts, MiMaskWrite, PiStatusWrite, SpStatusWrite, AI, MI, PI, SI, SP, VI,
+    };
+    use core::arch::asm;
+
+    /// `Status` register bits enabling RCP and reset button interrupts, and interrupts in general
+    const STATUS_IM2: u32 = 0x0400;
+    const STATUS_IM4: u32 = 0x1000;
+    const STATUS_IE: u32 = 0x0001;
+
+    /// `MI_MODE` command clearing the DP interrupt
+    const MI_MODE_CLEAR_DP: u32 = 0x0800;
+
+    fn set_status(set: u32, clear: u32) {
+        unsafe {
+            let status: u32;
+            asm!("mfc0 $2, $12", out("$2") status, options(nomem, nostack));
+            asm!("mtc0 $2, $12", in("$2") (status & !clear) | set, options(nostack));
+        }
+    }
+
+    pub fn enable(sources: MiInterrupts) {
+        let mask = MiMaskWrite::default()
+            .with_clear_sp(!sources.sp())
+            .with_set_sp(sources.sp())
+            .with_clear_si(!sources.si())
+            .with_set_si(sources.si())
+            .with_clear_ai(!sources.ai())
+            .with_set_ai(sources.ai())
+            .with_clear_vi(!sources.vi())
+            .with_set_vi(sources.vi())
+            .with_clear_pi(!sources.pi())
+            .with_set_pi(sources.pi())
+            .with_clear_dp(!sources.dp())
+            .with_set_dp(sources.dp());
+        MI.mask.write(mask);
+        set_status(STATUS_IM2 | STATUS_IM4 | STATUS_IE, 0);
+    }
+
+    pub fn disable() {
+        set_status(0, STATUS_IM2 | STATUS_IM4);
+    }
+
+    pub fn disable_reset() {
+        set_status(0, STATUS_IM4);
+    }
+
+    /// Acknowledge the pending RCP interrupts that are enabled, passing each as an event.
+    pub fn acknowledge(mut publish: impl FnMut(Event)) {
+        let pending = MI.interrupt.read().bits() & MI.mask.read().bits();
+        let pending = MiInterrupts::from_bits(pending);
+
+        // The time of each interrupt, and how far the audio DMA got, jitter with bus contention
+        crate::entropy::add_sample(pending.bits() ^ AI.len.read());
+
+        if pending.sp() {
+            SP.status
+                .write(SpStatusWrite::default().with_clear_intr(true));
+            publish(Event::Rsp);
+        }
+        if pending.si() {
+            SI.status.write(0);
+            publish(Event::SiDma);
+        }
+        if pending.ai() {
+            AI.status.write(0);
+            publish(Event::Audio);
+        }
+        if pending.vi() {
+            VI.v_current.write(0);
+            publish(Event::VBlank);
+        }
+        if pending.pi() {
+            PI.status
+                .write(PiStatusWrite::default().with_clear_intr(true));
+            publish(Event::PiDma);
+        }
+        if pending.dp() {
+            MI.mode.write(MI_MODE_CLEAR_DP);
+            publish(Event::Rdp);
+        }
+    }
+}
+
+#[cfg(not(rrt0_platform = "n64"))]
+mod platform {
+    use super::Event;
+    use crate::mmio::MiInterrupts;
+
+    pub fn enable(_sources: MiInterrupts) {}
+
+    pub fn disable() {}
+
+    pub fn disable_reset() {}
+
+    pub fn acknowledge(_publish: impl FnMut(Event)) {}
+}
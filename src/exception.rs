@@ -0,0 +1,212 @@
+//! CPU exception handling.
+//!
+//! [`install`] points the CPU exception vectors at a handler that saves the interrupted context in
+//! a [`Frame`] and calls the function registered for the exception's [`ExceptionCode`] with
+//! [`set_handler`]. Handlers can inspect and modify the frame, which is restored when they return.
+//! Execution resumes at [`Frame::epc`], so a handler for an exception caused by an instruction
+//! must either fix its cause or skip the instruction. Exceptions without a handler panic.
+//!
+//! Handlers run with interrupts disabled, on the stack of the interrupted code.
+//!
+//! On other platforms, exceptions are never raised.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A function handling an exception.
+pub type Handler = fn(&mut Frame);
+
+/// The cause of an exception, from the `ExcCode` field of the COP0 `Cause` register.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ExceptionCode {
+    Interrupt,
+    TlbModification,
+    TlbLoad,
+    TlbStore,
+    AddressErrorLoad,
+    AddressErrorStore,
+    BusErrorFetch,
+    BusErrorData,
+    Syscall,
+    Breakpoint,
+    ReservedInstruction,
+    CoprocessorUnusable,
+    Overflow,
+    Trap,
+    FloatingPoint,
+    Watch,
+    /// A code not raised by the VR4300
+    Other(u8),
+}
+
+impl ExceptionCode {
+    pub fn from_cause(cause: u32) -> Self {
+        match (cause >> 2) & 0x1F {
+            0 => Self::Interrupt,
+            1 => Self::TlbModification,
+            2 => Self::TlbLoad,
+            3 => Self::TlbStore,
+            4 => Self::AddressErrorLoad,
+            5 => Self::AddressErrorStore,
+            6 => Self::BusErrorFetch,
+            7 => Self::BusErrorData,
+            8 => Self::Syscall,
+            9 => Self::Breakpoint,
+            10 => Self::ReservedInstruction,
+            11 => Self::CoprocessorUnusable,
+            12 => Self::Overflow,
+            13 => Self::Trap,
+            15 => Self::FloatingPoint,
+            23 => Self::Watch,
+            code => Self::Other(code as u8),
+        }
+    }
+
+    /// The `ExcCode` field value.
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Interrupt => 0,
+            Self::TlbModification => 1,
+            Self::TlbLoad => 2,
+            Self::TlbStore => 3,
+            Self::AddressErrorLoad => 4,
+            Self::AddressErrorStore => 5,
+            Self::BusErrorFetch => 6,
+            Self::BusErrorData => 7,
+            Self::Syscall => 8,
+            Self::Breakpoint => 9,
+            Self::ReservedInstruction => 10,
+            Self::CoprocessorUnusable => 11,
+            Self::Overflow => 12,
+            Self::Trap => 13,
+            Self::FloatingPoint => 15,
+            Self::Watch => 23,
+            Self::Other(code) => code & 0x1F,
+        }
+    }
+}
+
+/// The CPU state saved when an exception was raised.
+#[derive(Clone, Debug)]
+#[repr(C)]
+pub struct Frame {
+    /// General purpose registers, by number. `$k0` and `$k1` are not saved, and changes to `$sp`
+    /// are not restored.
+    pub gpr: [u64; 32],
+    pub hi: u64,
+    pub lo: u64,
+    /// Floating point registers, by number. With the `Status` FR bit clear, the odd registers are
+    /// the upper halves of the even ones and are not saved separately.
+    pub fpr: [u64; 32],
+    /// Floating point control and status register
+    pub fcsr: u32,
+    /// COP0 `Status` register. Changes are not restored.
+    pub status: u32,
+    /// COP0 `Cause` register
+    pub cause: u32,
+    /// Exception program counter, where execution resumes
+    pub epc: u32,
+    /// COP0 `BadVAddr` register, the address that caused an address or TLB exception
+    pub badvaddr: u32,
+    _padding: u32,
+}
+
+// The layout is hardcoded in `exception.s`
+const _: () = assert!(core::mem::size_of::<Frame>() == 552);
+
+/// `Cause` register bit set when the exception was raised by an instruction in a branch delay
+/// slot, in which case `epc` points to the branch.
+const CAUSE_BD: u32 = 0x8000_0000;
+
+impl Frame {
+    /// The cause of the exception.
+    pub fn code(&self) -> ExceptionCode {
+        ExceptionCode::from_cause(self.cause)
+    }
+
+    /// Address of the instruction that raised the exception.
+    pub fn pc(&self) -> usize {
+        self.epc as usize + if self.in_delay_slot() { 4 } else { 0 }
+    }
+
+    /// Check if the instruction that raised the exception is in a branch delay slot.
+    pub fn in_delay_slot(&self) -> bool {
+        self.cause & CAUSE_BD != 0
+    }
+
+    /// Resume execution after the instruction that raised the exception. Only valid for
+    /// instructions outside of branch delay slots.
+    pub fn skip_instruction(&mut self) {
+        debug_assert!(!self.in_delay_slot());
+        self.epc += 4;
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+/// Handlers as function pointers, indexed by exception code
+static HANDLERS: [AtomicUsize; 32] = [NO_HANDLER; 32];
+
+/// Register the handler for an exception code, replacing any previous one. `None` removes it.
+pub fn set_handler(code: ExceptionCode, handler: Option<Handler>) {
+    let handler = handler.map_or(0, |handler| handler as usize);
+    HANDLERS[code.code() as usize].store(handler, Ordering::Release);
+}
+
+/// Get the handler registered for an exception code.
+pub fn handler(code: ExceptionCode) -> Option<Handler> {
+    match HANDLERS[code.code() as usize].load(Ordering::Acquire) {
+        0 => None,
+        // Only function pointers are stored
+        handler => Some(unsafe { core::mem::transmute::<usize, Handler>(handler) }),
+    }
+}
+
+/// Point the exception vectors at the exception handler. Safe to call more than once.
+pub fn install() {
+    platform::install();
+}
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use super::{handler, Frame};
+    use crate::mem::{invalidate_icache, map, writeback_dcache};
+
+    /// TLB refill, 64-bit TLB refill and general exception vectors
+    const VECTORS: [usize; 3] = [
+        map::to_kseg0(map::TLB_REFILL_VECTOR),
+        map::to_kseg0(map::XTLB_REFILL_VECTOR),
+        map::to_kseg0(map::GENERAL_VECTOR),
+    ];
+
+    extern "C" {
+        fn rrt0_exception_entry();
+    }
+
+    pub fn install() {
+        // j rrt0_exception_entry; nop
+        let jump = 0x0800_0000 | ((rrt0_exception_entry as usize >> 2) & 0x03FF_FFFF) as u32;
+        for vector in VECTORS {
+            let ptr = vector as *mut u32;
+            unsafe {
+                ptr.write_volatile(jump);
+                ptr.add(1).write_volatile(0);
+            }
+            writeback_dcache(ptr as *const u8, 8);
+            invalidate_icache(ptr as *const u8, 8);
+        }
+    }
+
+    #[no_mangle]
+    extern "C" fn rrt0_exception(frame: &mut Frame) {
+        match handler(frame.code()) {
+            Some(handler) => handler(frame),
+            None => panic!("Unhandled exception"),
+        }
+    }
+}
+
+#[cfg(not(rrt0_platform = "n64"))]
+mod platform {
+    pub fn install() {}
+}
@@ -0,0 +1,76 @@
+//! Fast approximate math.
+//!
+//! Approximations for hot loops, where speed matters more than the last few bits of precision.
+//! These avoid the VR4300's slow `sqrt.s` and `div.s` instructions and the argument reduction in
+//! `libm`. Error bounds are measured over the documented input ranges.
+//!
+//! Only available with the `fast-math` feature.
+
+use core::f32::consts::{FRAC_PI_2, PI, TAU};
+
+/// Approximate reciprocal square root, `1 / sqrt(x)`.
+///
+/// Maximum relative error is `1.8e-3` for positive normal `x`. Returns garbage for zero, negative
+/// and subnormal inputs.
+pub fn rsqrt(x: f32) -> f32 {
+    let y = f32::from_bits(0x5F37_5A86 - (x.to_bits() >> 1));
+
+    // One Newton-Raphson step
+    y * (1.5 - 0.5 * x * y * y)
+}
+
+/// Approximate square root.
+///
+/// Maximum relative error is `1.8e-3` for positive normal `x`, and returns zero for zero.
+pub fn sqrt(x: f32) -> f32 {
+    if x == 0.0 {
+        0.0
+    } else {
+        x * rsqrt(x)
+    }
+}
+
+/// Approximate reciprocal, `1 / x`.
+///
+/// Maximum relative error is `7e-6` for normal `x` with a magnitude below `2^126`. Returns
+/// garbage for zero and subnormal inputs.
+pub fn recip(x: f32) -> f32 {
+    let y = f32::from_bits(0x7EF3_11C7_u32.wrapping_sub(x.to_bits()));
+
+    // Two Newton-Raphson steps
+    let y = y * (2.0 - x * y);
+    y * (2.0 - x * y)
+}
+
+/// Approximate sine of an angle in radians.
+///
+/// Maximum absolute error is `2e-6` for `x` in `[-PI, PI]`, growing with the magnitude of `x`
+/// outside of that range due to argument reduction (about `1e-5` at `100`).
+pub fn sin(x: f32) -> f32 {
+    // Reduce to [-PI, PI]
+    let turns = x * (1.0 / TAU);
+    let turns = if turns < 0.0 {
+        turns - 0.5
+    } else {
+        turns + 0.5
+    } as i32;
+    let x = x - turns as f32 * TAU;
+
+    // Reflect to [-PI / 2, PI / 2]
+    let x = if x > FRAC_PI_2 {
+        PI - x
+    } else if x < -FRAC_PI_2 {
+        -PI - x
+    } else {
+        x
+    };
+
+    // Minimax polynomial
+    let x2 = x * x;
+    x * (0.999_996_6 + x2 * (-0.166_648_24 + x2 * (0.008_306_226 + x2 * -0.000_183_636_54)))
+}
+
+/// Approximate cosine of an angle in radians, with the same error bounds as [`sin`].
+pub fn cos(x: f32) -> f32 {
+    sin(x + FRAC_PI_2)
+}
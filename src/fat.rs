@@ -0,0 +1,937 @@
+//! A FAT32 filesystem on a [`BlockDevice`], like an SD card.
+//!
+//! ```text
+//! let mut fs = FileSystem::mount(SummerCart64::init()?)?;
+//! let mut save = fs.create("saves/slot1.sav")?;
+//! save.write(&data)?;
+//! fs.flush()?;
+//! ```
+//!
+//! The volume is either the whole device or its first FAT32 partition. Files can be opened,
+//! created, written, and removed, and directories listed, but not created. Only short (8.3) names
+//! are supported: long names are skipped when listing directories, and names are matched without
+//! case.
+//!
+//! Sectors are cached one at a time, and written back when another sector is needed. [`flush`]
+//! writes the cached sector back, and should be called before the device is removed or the console
+//! is turned off.
+//!
+//! [`flush`]: FileSystem::flush
+
+use crate::io::{self, SeekFrom};
+use crate::sd::{BlockDevice, BlockError, SECTOR_SIZE};
+use core::fmt;
+
+/// Errors that can occur while using a filesystem.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FatError {
+    /// The device failed to read or write.
+    Io,
+    /// The device has no FAT32 volume.
+    NotFat32,
+    /// The filesystem data is inconsistent.
+    Corrupt,
+    /// No file or directory has the name.
+    NotFound,
+    /// A path component is a file.
+    NotADirectory,
+    /// The path names a directory.
+    IsADirectory,
+    /// The name is not a valid short (8.3) name.
+    InvalidName,
+    /// The volume has no free clusters.
+    Full,
+}
+
+impl fmt::Display for FatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Io => "device error",
+            Self::NotFat32 => "no FAT32 volume",
+            Self::Corrupt => "filesystem is corrupt",
+            Self::NotFound => "file not found",
+            Self::NotADirectory => "not a directory",
+            Self::IsADirectory => "is a directory",
+            Self::InvalidName => "invalid file name",
+            Self::Full => "volume is full",
+        })
+    }
+}
+
+impl From<BlockError> for FatError {
+    fn from(_: BlockError) -> Self {
+        Self::Io
+    }
+}
+
+const DIR_ENTRY_SIZE: usize = 32;
+
+const ATTR_VOLUME_ID: u8 = 0x08;
+/// Attributes of the entries holding a long name, before its short name entry
+const ATTR_LONG_NAME: u8 = 0x0F;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_ARCHIVE: u8 = 0x20;
+
+/// First byte of a deleted directory entry
+const DELETED: u8 = 0xE5;
+
+/// FAT entries at or above this end a cluster chain.
+const END_OF_CHAIN: u32 = 0x0FFF_FFF8;
+/// The FAT entry written to end a chain
+const END_OF_CHAIN_MARKER: u32 = 0x0FFF_FFFF;
+/// Clusters are numbered from 2 up to the bad cluster marker, `0x0FFF_FFF7`.
+const MAX_CLUSTERS: u32 = 0x0FFF_FFF5;
+
+/// Long names have up to 255 characters, 13 per entry.
+const MAX_LONG_NAME_ENTRIES: usize = 20;
+
+/// A mounted FAT32 volume.
+pub struct FileSystem<D: BlockDevice> {
+    device: D,
+    /// First sector of the first FAT
+    fat_start: u32,
+    /// Size of each FAT (in sectors)
+    fat_size: u32,
+    fat_count: u32,
+    /// First sector of cluster 2
+    data_start: u32,
+    sectors_per_cluster: u32,
+    cluster_count: u32,
+    root_cluster: u32,
+    /// Sector holding the free cluster count, until it is invalidated by an allocation
+    fs_info: Option<u32>,
+    /// Where to start looking for a free cluster
+    next_free: u32,
+    buffer: [u8; SECTOR_SIZE],
+    buffered: Option<u32>,
+    dirty: bool,
+}
+
+/// The location of a directory entry.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Location {
+    sector: u32,
+    offset: usize,
+}
+
+/// A directory entry found by name.
+#[derive(Clone, Copy, Debug)]
+struct Found {
+    location: Location,
+    cluster: u32,
+    size: u32,
+    attributes: u8,
+}
+
+impl Found {
+    fn new(entry: &[u8; DIR_ENTRY_SIZE], location: Location) -> Self {
+        Self {
+            location,
+            cluster: (read_u16(entry, 20) as u32) << 16 | read_u16(entry, 26) as u32,
+            size: read_u32(entry, 28),
+            attributes: entry[11],
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        self.attributes & ATTR_DIRECTORY != 0
+    }
+}
+
+impl<D: BlockDevice> FileSystem<D> {
+    /// Mount the FAT32 volume on a device.
+    pub fn mount(device: D) -> Result<Self, FatError> {
+        let mut fs = Self {
+            device,
+            fat_start: 0,
+            fat_size: 0,
+            fat_count: 0,
+            data_start: 0,
+            sectors_per_cluster: 0,
+            cluster_count: 0,
+            root_cluster: 0,
+            fs_info: None,
+            next_free: 2,
+            buffer: [0; SECTOR_SIZE],
+            buffered: None,
+            dirty: false,
+        };
+
+        // Use the whole device, or else the first FAT32 partition in the MBR
+        let mut start = 0;
+        if !is_fat32(fs.sector(0)?) {
+            let mbr = fs.sector(0)?;
+            if mbr[510..] != [0x55, 0xAA] {
+                return Err(FatError::NotFat32);
+            }
+            start = mbr[446..510]
+                .chunks_exact(16)
+                .find(|partition| matches!(partition[4], 0x0B | 0x0C))
+                .map(|partition| read_u32(partition, 8))
+                .ok_or(FatError::NotFat32)?;
+            if !is_fat32(fs.sector(start)?) {
+                return Err(FatError::NotFat32);
+            }
+        }
+
+        let boot = *fs.sector(start)?;
+        // Checked, as the boot sector may hold anything that passes `is_fat32`
+        fs.sectors_per_cluster = boot[13] as u32;
+        fs.fat_start = start
+            .checked_add(read_u16(&boot, 14) as u32)
+            .ok_or(FatError::NotFat32)?;
+        fs.fat_count = boot[16] as u32;
+        fs.fat_size = read_u32(&boot, 36);
+        fs.data_start = fs
+            .fat_count
+            .checked_mul(fs.fat_size)
+            .and_then(|fats| fats.checked_add(fs.fat_start))
+            .ok_or(FatError::NotFat32)?;
+        fs.root_cluster = read_u32(&boot, 44);
+        let data_size = start
+            .checked_add(read_u32(&boot, 32))
+            .and_then(|end| end.checked_sub(fs.data_start))
+            .ok_or(FatError::NotFat32)?;
+        // Only as many clusters as each FAT has entries for
+        let fat_entries = fs.fat_size.saturating_mul((SECTOR_SIZE / 4) as u32);
+        fs.cluster_count = (data_size / fs.sectors_per_cluster)
+            .min(fat_entries.saturating_sub(2))
+            .min(MAX_CLUSTERS);
+        fs.fs_info = match read_u16(&boot, 48) {
+            0 | 0xFFFF => None,
+            sector => Some(start + sector as u32),
+        };
+
+        Ok(fs)
+    }
+
+    /// Open a file to read or write, at the start.
+    pub fn open(&mut self, path: &str) -> Result<File<'_, D>, FatError> {
+        let (_, _, found) = self.lookup(path)?;
+        let found = found.ok_or(FatError::NotFound)?;
+        if found.is_dir() {
+            return Err(FatError::IsADirectory);
+        }
+
+        Ok(File::new(self, found))
+    }
+
+    /// Create a file in an existing directory, or truncate it if it exists.
+    pub fn create(&mut self, path: &str) -> Result<File<'_, D>, FatError> {
+        let (dir, name, found) = self.lookup(path)?;
+        let found = match found {
+            Some(found) if found.is_dir() => return Err(FatError::IsADirectory),
+            Some(mut found) => {
+                self.free_chain(found.cluster)?;
+                found.cluster = 0;
+                found.size = 0;
+                self.update_entry(found.location, 0, 0)?;
+                found
+            }
+            None => {
+                let location = self.free_slot(dir)?;
+                let entry =
+                    &mut self.sector_mut(location.sector)?[location.offset..][..DIR_ENTRY_SIZE];
+                entry.fill(0);
+                entry[..11].copy_from_slice(&name);
+                entry[11] = ATTR_ARCHIVE;
+                Found {
+                    location,
+                    cluster: 0,
+                    size: 0,
+                    attributes: ATTR_ARCHIVE,
+                }
+            }
+        };
+
+        Ok(File::new(self, found))
+    }
+
+    /// Remove a file, along with its long name if it has one.
+    pub fn remove(&mut self, path: &str) -> Result<(), FatError> {
+        let (dir, name, found) = self.lookup(path)?;
+        let found = found.ok_or(FatError::NotFound)?;
+        if found.is_dir() {
+            return Err(FatError::IsADirectory);
+        }
+
+        // The long name entries right before the entry, if they belong to its short name
+        let checksum = long_name_checksum(&name);
+        let mut long_name = [found.location; MAX_LONG_NAME_ENTRIES];
+        let mut long_name_len = 0;
+        self.walk(dir, |entry, location| {
+            if location == found.location {
+                return Some(());
+            }
+            let belongs = entry[0] != DELETED
+                && entry[11] == ATTR_LONG_NAME
+                && entry[13] == checksum
+                && long_name_len < MAX_LONG_NAME_ENTRIES;
+            long_name_len = match belongs {
+                true => {
+                    long_name[long_name_len] = location;
+                    long_name_len + 1
+                }
+                false => 0,
+            };
+            None
+        })?;
+
+        self.free_chain(found.cluster)?;
+        for location in long_name[..long_name_len]
+            .iter()
+            .chain([found.location].iter())
+        {
+            self.sector_mut(location.sector)?[location.offset] = DELETED;
+        }
+
+        Ok(())
+    }
+
+    /// Call a function with each entry of a directory, except `.` and `..`. The root directory is
+    /// `/` or the empty path.
+    pub fn read_dir(&mut self, path: &str, mut f: impl FnMut(&DirEntry)) -> Result<(), FatError> {
+        let dir = match path.trim_matches('/') {
+            "" => self.root_cluster,
+            _ => match self.lookup(path)? {
+                (_, _, Some(found)) if found.is_dir() => self.dir_cluster(found.cluster),
+                (_, _, Some(_)) => return Err(FatError::NotADirectory),
+                (_, _, None) => return Err(FatError::NotFound),
+            },
+        };
+
+        self.walk(dir, |entry, _| {
+            if is_used(entry) && entry[0] != b'.' {
+                f(&DirEntry::new(entry));
+            }
+            None::<()>
+        })?;
+
+        Ok(())
+    }
+
+    /// Write the cached sector back to the device, if it changed.
+    pub fn flush(&mut self) -> Result<(), FatError> {
+        if let (Some(sector), true) = (self.buffered, self.dirty) {
+            self.device.write(sector, &self.buffer)?;
+            self.dirty = false;
+        }
+
+        Ok(())
+    }
+
+    /// Flush the cache and return the device.
+    pub fn unmount(mut self) -> Result<D, FatError> {
+        self.flush()?;
+
+        Ok(self.device)
+    }
+
+    /// Get a sector through the cache.
+    fn sector(&mut self, sector: u32) -> Result<&mut [u8; SECTOR_SIZE], FatError> {
+        if self.buffered != Some(sector) {
+            self.flush()?;
+            self.buffered = None;
+            self.device.read(sector, &mut self.buffer)?;
+            self.buffered = Some(sector);
+        }
+
+        Ok(&mut self.buffer)
+    }
+
+    /// Get a sector through the cache, to change it.
+    fn sector_mut(&mut self, sector: u32) -> Result<&mut [u8; SECTOR_SIZE], FatError> {
+        self.sector(sector)?;
+        self.dirty = true;
+
+        Ok(&mut self.buffer)
+    }
+
+    /// Find the parent directory, short name, and entry (if it exists) of a path.
+    fn lookup(&mut self, path: &str) -> Result<(u32, [u8; 11], Option<Found>), FatError> {
+        let mut components = path.split('/').filter(|component| !component.is_empty());
+        let mut component = components.next().ok_or(FatError::InvalidName)?;
+        let mut dir = self.root_cluster;
+        loop {
+            let name = short_name(component)?;
+            let found = self.walk(dir, |entry, location| {
+                (is_used(entry) && entry[..11] == name).then(|| Found::new(entry, location))
+            })?;
+
+            component = match components.next() {
+                Some(next) => next,
+                None => return Ok((dir, name, found)),
+            };
+            dir = match found {
+                Some(found) if found.is_dir() => self.dir_cluster(found.cluster),
+                Some(_) => return Err(FatError::NotADirectory),
+                None => return Err(FatError::NotFound),
+            };
+        }
+    }
+
+    /// Call a function with each directory entry up to the end marker, until it returns a value.
+    fn walk<R>(
+        &mut self,
+        dir: u32,
+        mut f: impl FnMut(&[u8; DIR_ENTRY_SIZE], Location) -> Option<R>,
+    ) -> Result<Option<R>, FatError> {
+        let mut cluster = dir;
+        while is_cluster(cluster) {
+            let first = self.cluster_sector(cluster)?;
+            for sector in first..first + self.sectors_per_cluster {
+                for offset in (0..SECTOR_SIZE).step_by(DIR_ENTRY_SIZE) {
+                    let mut entry = [0; DIR_ENTRY_SIZE];
+                    entry.copy_from_slice(&self.sector(sector)?[offset..offset + DIR_ENTRY_SIZE]);
+                    if let Some(result) = f(&entry, Location { sector, offset }) {
+                        return Ok(Some(result));
+                    }
+                    if entry[0] == 0 {
+                        return Ok(None);
+                    }
+                }
+            }
+            cluster = self.next_cluster(cluster)?;
+        }
+
+        Ok(None)
+    }
+
+    /// Find a free entry in a directory, extending it if it is full.
+    fn free_slot(&mut self, dir: u32) -> Result<Location, FatError> {
+        let free = self.walk(dir, |entry, location| {
+            matches!(entry[0], 0 | DELETED).then(|| location)
+        })?;
+        if let Some(location) = free {
+            return Ok(location);
+        }
+
+        let mut last = dir;
+        while is_cluster(self.next_cluster(last)?) {
+            last = self.next_cluster(last)?;
+        }
+        let cluster = self.allocate(Some(last))?;
+        let first = self.cluster_sector(cluster)?;
+        for sector in first..first + self.sectors_per_cluster {
+            self.sector_mut(sector)?.fill(0);
+        }
+
+        Ok(Location {
+            sector: first,
+            offset: 0,
+        })
+    }
+
+    fn update_entry(
+        &mut self,
+        location: Location,
+        cluster: u32,
+        size: u32,
+    ) -> Result<(), FatError> {
+        let entry = &mut self.sector_mut(location.sector)?[location.offset..][..DIR_ENTRY_SIZE];
+        entry[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+        entry[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+        entry[28..32].copy_from_slice(&size.to_le_bytes());
+
+        Ok(())
+    }
+
+    /// The cluster of a directory, where 0 refers to the root directory.
+    fn dir_cluster(&self, cluster: u32) -> u32 {
+        match cluster {
+            0 => self.root_cluster,
+            cluster => cluster,
+        }
+    }
+
+    fn cluster_size(&self) -> u32 {
+        self.sectors_per_cluster * SECTOR_SIZE as u32
+    }
+
+    fn cluster_sector(&self, cluster: u32) -> Result<u32, FatError> {
+        if !(2..self.cluster_count + 2).contains(&cluster) {
+            return Err(FatError::Corrupt);
+        }
+
+        Ok(self.data_start + (cluster - 2) * self.sectors_per_cluster)
+    }
+
+    /// Read the FAT entry of a cluster: the next cluster in its chain.
+    fn next_cluster(&mut self, cluster: u32) -> Result<u32, FatError> {
+        if cluster >= self.cluster_count + 2 {
+            return Err(FatError::Corrupt);
+        }
+        let offset = cluster as usize * 4 % SECTOR_SIZE;
+        let sector = self.sector(self.fat_start + cluster * 4 / SECTOR_SIZE as u32)?;
+
+        Ok(read_u32(sector, offset) & 0x0FFF_FFFF)
+    }
+
+    /// Write the FAT entry of a cluster in each FAT.
+    fn set_next_cluster(&mut self, cluster: u32, next: u32) -> Result<(), FatError> {
+        let offset = cluster as usize * 4 % SECTOR_SIZE;
+        for fat in 0..self.fat_count {
+            let sector = self.fat_start + fat * self.fat_size + cluster * 4 / SECTOR_SIZE as u32;
+            let entry = &mut self.sector_mut(sector)?[offset..offset + 4];
+            let next = read_u32(entry, 0) & 0xF000_0000 | next;
+            entry.copy_from_slice(&next.to_le_bytes());
+        }
+
+        Ok(())
+    }
+
+    /// Allocate a free cluster at the end of a chain, or as a new chain.
+    fn allocate(&mut self, previous: Option<u32>) -> Result<u32, FatError> {
+        // The free cluster count is not kept up to date, so mark it unknown
+        if let Some(sector) = self.fs_info.take() {
+            self.sector_mut(sector)?[488..496].fill(0xFF);
+        }
+
+        for index in 0..self.cluster_count {
+            let cluster = 2 + (self.next_free - 2 + index) % self.cluster_count;
+            if self.next_cluster(cluster)? == 0 {
+                self.set_next_cluster(cluster, END_OF_CHAIN_MARKER)?;
+                if let Some(previous) = previous {
+                    self.set_next_cluster(previous, cluster)?;
+                }
+                self.next_free = cluster;
+
+                return Ok(cluster);
+            }
+        }
+
+        Err(FatError::Full)
+    }
+
+    fn free_chain(&mut self, mut cluster: u32) -> Result<(), FatError> {
+        while is_cluster(cluster) {
+            let next = self.next_cluster(cluster)?;
+            self.set_next_cluster(cluster, 0)?;
+            cluster = next;
+        }
+
+        Ok(())
+    }
+}
+
+impl<D: BlockDevice> fmt::Debug for FileSystem<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileSystem")
+            .field("cluster_size", &self.cluster_size())
+            .field("cluster_count", &self.cluster_count)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A directory entry listed by [`FileSystem::read_dir`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DirEntry {
+    name: [u8; 12],
+    name_len: usize,
+    /// Size of the file (in bytes)
+    pub size: u32,
+    pub is_dir: bool,
+}
+
+impl DirEntry {
+    fn new(entry: &[u8; DIR_ENTRY_SIZE]) -> Self {
+        let mut name = [0; 12];
+        let mut name_len = 0;
+        let base = entry[..8].iter().take_while(|&&byte| byte != b' ');
+        let extension = entry[8..11].iter().take_while(|&&byte| byte != b' ');
+        for &byte in base {
+            name[name_len] = byte;
+            name_len += 1;
+        }
+        if entry[8] != b' ' {
+            name[name_len] = b'.';
+            name_len += 1;
+        }
+        for &byte in extension {
+            name[name_len] = byte;
+            name_len += 1;
+        }
+
+        Self {
+            name,
+            name_len,
+            size: read_u32(entry, 28),
+            is_dir: entry[11] & ATTR_DIRECTORY != 0,
+        }
+    }
+
+    /// Short name, e.g. `SLOT1.SAV`
+    pub fn name(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.name_len]).unwrap_or("")
+    }
+}
+
+/// An open file. Only one file can be open at a time, as it borrows the filesystem.
+#[derive(Debug)]
+pub struct File<'a, D: BlockDevice> {
+    fs: &'a mut FileSystem<D>,
+    entry: Location,
+    first_cluster: u32,
+    len: u32,
+    position: u32,
+    /// The last cluster used, and its index in the chain
+    cursor: (u32, u32),
+}
+
+impl<'a, D: BlockDevice> File<'a, D> {
+    fn new(fs: &'a mut FileSystem<D>, found: Found) -> Self {
+        Self {
+            fs,
+            entry: found.location,
+            first_cluster: found.cluster,
+            len: found.size,
+            position: 0,
+            cursor: (0, found.cluster),
+        }
+    }
+
+    /// Size of the file (in bytes)
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Current position (in bytes)
+    pub fn position(&self) -> usize {
+        self.position as usize
+    }
+
+    /// Move to a position, up to the end of the file.
+    pub fn seek(&mut self, position: usize) {
+        self.position = position.min(self.len()) as u32;
+    }
+
+    /// Read bytes from the current position, advancing it. Returns the number of bytes read,
+    /// which is less than the buffer length at the end of the file.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, FatError> {
+        let len = buffer.len().min((self.len - self.position) as usize);
+        let mut done = 0;
+        while done < len {
+            let (sector, offset) = self.sector(false)?;
+            let chunk = (len - done).min(SECTOR_SIZE - offset);
+            let data = &self.fs.sector(sector)?[offset..offset + chunk];
+            buffer[done..done + chunk].copy_from_slice(data);
+            done += chunk;
+            self.position += chunk as u32;
+        }
+
+        Ok(len)
+    }
+
+    /// Write bytes at the current position, advancing it and extending the file. Returns the
+    /// number of bytes written, which is less than the buffer length when the volume is full.
+    pub fn write(&mut self, buffer: &[u8]) -> Result<usize, FatError> {
+        let start = self.position;
+        let result = self.write_sectors(buffer);
+        self.fs
+            .update_entry(self.entry, self.first_cluster, self.len)?;
+
+        match result {
+            Err(error) if self.position == start => Err(error),
+            _ => Ok((self.position - start) as usize),
+        }
+    }
+
+    fn write_sectors(&mut self, buffer: &[u8]) -> Result<(), FatError> {
+        let mut done = 0;
+        while done < buffer.len() {
+            let (sector, offset) = self.sector(true)?;
+            let chunk = (buffer.len() - done).min(SECTOR_SIZE - offset);
+            self.fs.sector_mut(sector)?[offset..offset + chunk]
+                .copy_from_slice(&buffer[done..done + chunk]);
+            done += chunk;
+            self.position += chunk as u32;
+            self.len = self.len.max(self.position);
+        }
+
+        Ok(())
+    }
+
+    /// The sector and offset of the current position, optionally allocating its cluster.
+    fn sector(&mut self, allocate: bool) -> Result<(u32, usize), FatError> {
+        let cluster_size = self.fs.cluster_size();
+        let index = self.position / cluster_size;
+
+        if self.first_cluster == 0 {
+            if !allocate {
+                return Err(FatError::Corrupt);
+            }
+            self.first_cluster = self.fs.allocate(None)?;
+            self.cursor = (0, self.first_cluster);
+        }
+
+        let (mut current, mut cluster) = match self.cursor {
+            cursor if cursor.0 <= index => cursor,
+            _ => (0, self.first_cluster),
+        };
+        while current < index {
+            let next = self.fs.next_cluster(cluster)?;
+            cluster = match next {
+                next if is_cluster(next) => next,
+                _ if allocate => self.fs.allocate(Some(cluster))?,
+                _ => return Err(FatError::Corrupt),
+            };
+            current += 1;
+        }
+        self.cursor = (current, cluster);
+
+        let offset = self.position % cluster_size;
+        let sector = self.fs.cluster_sector(cluster)? + offset / SECTOR_SIZE as u32;
+
+        Ok((sector, offset as usize % SECTOR_SIZE))
+    }
+}
+
+impl<D: BlockDevice> io::ErrorType for File<'_, D> {
+    type Error = FatError;
+}
+
+impl<D: BlockDevice> io::Read for File<'_, D> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        File::read(self, buffer)
+    }
+}
+
+impl<D: BlockDevice> io::Write for File<'_, D> {
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, Self::Error> {
+        File::write(self, buffer)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.fs.flush()
+    }
+}
+
+impl<D: BlockDevice> io::Seek for File<'_, D> {
+    fn seek(&mut self, position: SeekFrom) -> Result<u64, Self::Error> {
+        let position = io::seek_position(position, self.position as u64, self.len as u64);
+        self.position = position as u32;
+
+        Ok(position)
+    }
+}
+
+/// Check if a sector is a FAT32 boot sector.
+fn is_fat32(sector: &[u8; SECTOR_SIZE]) -> bool {
+    sector[510..] == [0x55, 0xAA]
+        && read_u16(sector, 11) == SECTOR_SIZE as u16
+        // Non-zero, as the data area is divided into clusters of this many sectors
+        && sector[13].is_power_of_two()
+        && sector[16] != 0
+        // FAT32 has no fixed root directory, and a 32-bit FAT size
+        && read_u16(sector, 17) == 0
+        && read_u16(sector, 22) == 0
+        && read_u32(sector, 36) != 0
+}
+
+/// Check if a directory entry is a file or directory.
+fn is_used(entry: &[u8; DIR_ENTRY_SIZE]) -> bool {
+    !matches!(entry[0], 0 | DELETED) && entry[11] & ATTR_VOLUME_ID == 0
+}
+
+fn is_cluster(cluster: u32) -> bool {
+    (2..END_OF_CHAIN).contains(&cluster)
+}
+
+/// Convert a name to its short (8.3) form, e.g. `slot1.sav` to `SLOT1   SAV`.
+fn short_name(name: &str) -> Result<[u8; 11], FatError> {
+    let mut short = [b' '; 11];
+    if name == "." || name == ".." {
+        short[..name.len()].copy_from_slice(name.as_bytes());
+        return Ok(short);
+    }
+
+    let (base, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    if base.is_empty() || base.len() > 8 || extension.len() > 3 {
+        return Err(FatError::InvalidName);
+    }
+    let (short_base, short_extension) = short.split_at_mut(8);
+    let bytes = base.bytes().zip(short_base);
+    for (byte, short) in bytes.chain(extension.bytes().zip(short_extension)) {
+        if !byte.is_ascii_alphanumeric() && !b"!#$%&'()-@^_`{}~".contains(&byte) {
+            return Err(FatError::InvalidName);
+        }
+        *short = byte.to_ascii_uppercase();
+    }
+
+    Ok(short)
+}
+
+/// Checksum of a short name, stored in the entries of its long name.
+fn long_name_checksum(name: &[u8; 11]) -> u8 {
+    name.iter()
+        .fold(0u8, |sum, &byte| sum.rotate_right(1).wrapping_add(byte))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let bytes = &bytes[offset..offset + 4];
+
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTORS: usize = 64;
+
+    /// A volume in memory, with 1-sector clusters and FATs, cluster 2 as the root directory, and
+    /// sector 6 as the start of cluster 2
+    struct Memory([[u8; SECTOR_SIZE]; SECTORS]);
+
+    impl Memory {
+        fn new() -> Self {
+            let mut sectors = [[0; SECTOR_SIZE]; SECTORS];
+            let boot = &mut sectors[0];
+            boot[11..13].copy_from_slice(&(SECTOR_SIZE as u16).to_le_bytes());
+            boot[13] = 1;
+            boot[14] = 4;
+            boot[16] = 2;
+            boot[32..36].copy_from_slice(&(SECTORS as u32).to_le_bytes());
+            boot[36] = 1;
+            boot[44] = 2;
+            boot[48] = 1;
+            boot[510..].copy_from_slice(&[0x55, 0xAA]);
+            for fat in &mut sectors[4..6] {
+                fat[..12].copy_from_slice(&[
+                    0xF8, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF, 0xFF, 0x0F,
+                ]);
+            }
+
+            Self(sectors)
+        }
+    }
+
+    impl BlockDevice for Memory {
+        fn read(&mut self, sector: u32, buffer: &mut [u8; SECTOR_SIZE]) -> Result<(), BlockError> {
+            *buffer = *self.0.get(sector as usize).ok_or(BlockError)?;
+            Ok(())
+        }
+
+        fn write(&mut self, sector: u32, buffer: &[u8; SECTOR_SIZE]) -> Result<(), BlockError> {
+            *self.0.get_mut(sector as usize).ok_or(BlockError)? = *buffer;
+            Ok(())
+        }
+    }
+
+    fn data(len: usize) -> [u8; 1200] {
+        let mut data = [0; 1200];
+        for (index, byte) in data[..len].iter_mut().enumerate() {
+            *byte = (index % 251) as u8;
+        }
+        data
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let mut fs = FileSystem::mount(Memory::new()).unwrap();
+        let data = data(1200);
+        assert_eq!(fs.create("slot1.sav").unwrap().write(&data), Ok(1200));
+        let memory = fs.unmount().unwrap();
+
+        let mut fs = FileSystem::mount(memory).unwrap();
+        let mut file = fs.open("SLOT1.SAV").unwrap();
+        assert_eq!(file.len(), 1200);
+        let mut read = [0; 1300];
+        assert_eq!(file.read(&mut read), Ok(1200));
+        assert_eq!(read[..1200], data[..]);
+
+        // Spans three clusters, after the root directory
+        assert_eq!(fs.next_cluster(3), Ok(4));
+        assert_eq!(fs.next_cluster(4), Ok(5));
+        assert_eq!(fs.next_cluster(5), Ok(END_OF_CHAIN_MARKER));
+
+        let mut names = 0;
+        fs.read_dir("/", |entry| {
+            assert_eq!((entry.name(), entry.size), ("SLOT1.SAV", 1200));
+            names += 1;
+        })
+        .unwrap();
+        assert_eq!(names, 1);
+        assert_eq!(fs.open("slot2.sav").err(), Some(FatError::NotFound));
+    }
+
+    #[test]
+    fn remove_frees_clusters_and_long_name() {
+        let mut fs = FileSystem::mount(Memory::new()).unwrap();
+        fs.create("save.dat").unwrap().write(&data(600)).unwrap();
+        let mut memory = fs.unmount().unwrap();
+
+        // Move the entry after a long name entry for it, and one for another name
+        let root = &mut memory.0[6];
+        root.copy_within(..32, 64);
+        root[..64].fill(0);
+        for (entry, checksum) in root[..64]
+            .chunks_exact_mut(32)
+            .zip([0, long_name_checksum(b"SAVE    DAT")])
+        {
+            entry[0] = 0x41;
+            entry[11] = ATTR_LONG_NAME;
+            entry[13] = checksum;
+        }
+
+        let mut fs = FileSystem::mount(memory).unwrap();
+        fs.remove("save.dat").unwrap();
+        assert_eq!(fs.open("save.dat").err(), Some(FatError::NotFound));
+        assert_eq!(fs.next_cluster(3), Ok(0));
+        assert_eq!(fs.next_cluster(4), Ok(0));
+
+        let memory = fs.unmount().unwrap();
+        assert_eq!(memory.0[6][0], 0x41);
+        assert_eq!(memory.0[6][32], DELETED);
+        assert_eq!(memory.0[6][64], DELETED);
+    }
+
+    #[test]
+    fn full_volume() {
+        let mut fs = FileSystem::mount(Memory::new()).unwrap();
+        let mut file = fs.create("big.bin").unwrap();
+        let data = data(1200);
+        let mut written = 0;
+        while let Ok(len) = file.write(&data[..1024]) {
+            written += len;
+        }
+        // All but the root directory's cluster
+        assert_eq!(written, (SECTORS - 7) * SECTOR_SIZE);
+        assert_eq!(file.len(), written);
+    }
+
+    #[test]
+    fn mount_rejects_bad_boot_sectors() {
+        let mut memory = Memory::new();
+        memory.0[0][13] = 0;
+        assert_eq!(FileSystem::mount(memory).err(), Some(FatError::NotFat32));
+
+        // FATs past the end of the sector numbers
+        let mut memory = Memory::new();
+        memory.0[0][36..40].copy_from_slice(&0x8000_0000u32.to_le_bytes());
+        assert_eq!(FileSystem::mount(memory).err(), Some(FatError::NotFat32));
+
+        // Data area past the end of the volume
+        let mut memory = Memory::new();
+        memory.0[0][32..36].copy_from_slice(&4u32.to_le_bytes());
+        assert_eq!(FileSystem::mount(memory).err(), Some(FatError::NotFat32));
+    }
+
+    #[test]
+    fn short_names() {
+        assert_eq!(short_name("slot1.sav"), Ok(*b"SLOT1   SAV"));
+        assert_eq!(short_name("readme"), Ok(*b"README     "));
+        assert_eq!(short_name("..").map(|name| name[..2] == *b".."), Ok(true));
+        assert_eq!(short_name("toolongname.txt"), Err(FatError::InvalidName));
+        assert_eq!(short_name("a.text"), Err(FatError::InvalidName));
+        assert_eq!(short_name("a b.txt"), Err(FatError::InvalidName));
+    }
+}
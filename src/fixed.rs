@@ -0,0 +1,543 @@
+//! Fixed-point arithmetic.
+//!
+//! [`Fx16_16`] and [`Fx8_8`] are signed fixed-point numbers with 16 and 8 fractional bits. On the
+//! VR4300 integer arithmetic avoids FPU stalls and float-to-int conversions, and formatting them
+//! with [`Display`](fmt::Display) does not pull in the float formatting code.
+//!
+//! Arithmetic behaves like the underlying integer type: addition and subtraction panic on
+//! overflow in debug builds, multiplication wraps, and division by zero panics. Trigonometric
+//! functions are table-driven with linear interpolation, accurate to about `1e-4`.
+
+use core::fmt;
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+macro_rules! fixed {
+    (
+        $(#[$attr:meta])*
+        $name:ident($inner:ty, $wide:ty, $frac:expr, $digits:expr)
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        pub struct $name($inner);
+
+        impl $name {
+            /// Number of fractional bits
+            pub const FRAC_BITS: u32 = $frac;
+            pub const ZERO: Self = Self(0);
+            pub const ONE: Self = Self(1 << $frac);
+            pub const HALF: Self = Self(1 << ($frac - 1));
+            pub const MIN: Self = Self(<$inner>::MIN);
+            pub const MAX: Self = Self(<$inner>::MAX);
+            /// Smallest positive value
+            pub const EPSILON: Self = Self(1);
+
+            /// Create a value from its raw bits.
+            pub const fn from_raw(raw: $inner) -> Self {
+                Self(raw)
+            }
+
+            /// The raw bits of the value.
+            pub const fn to_raw(self) -> $inner {
+                self.0
+            }
+
+            /// Convert an integer. Wraps if it is out of range.
+            pub const fn from_int(value: $inner) -> Self {
+                Self(value << $frac)
+            }
+
+            /// Convert to an integer, rounding towards negative infinity.
+            pub const fn to_int(self) -> $inner {
+                self.0 >> $frac
+            }
+
+            /// Convert a float. Saturates if it is out of range.
+            pub fn from_f32(value: f32) -> Self {
+                Self((value * (1 << $frac) as f32) as $inner)
+            }
+
+            /// Convert to a float.
+            pub fn to_f32(self) -> f32 {
+                self.0 as f32 / (1 << $frac) as f32
+            }
+
+            /// Absolute value. Wraps for `MIN`.
+            pub const fn abs(self) -> Self {
+                Self(self.0.wrapping_abs())
+            }
+
+            /// Round towards negative infinity.
+            pub const fn floor(self) -> Self {
+                Self(self.0 & !((1 << $frac) - 1))
+            }
+
+            /// Fractional part, `self - self.floor()`.
+            pub const fn fract(self) -> Self {
+                Self(self.0 & ((1 << $frac) - 1))
+            }
+
+            /// Round to the nearest integer, with halves rounded up.
+            pub const fn round(self) -> Self {
+                Self(self.0.wrapping_add(Self::HALF.0)).floor()
+            }
+
+            pub const fn saturating_add(self, rhs: Self) -> Self {
+                Self(self.0.saturating_add(rhs.0))
+            }
+
+            pub const fn saturating_sub(self, rhs: Self) -> Self {
+                Self(self.0.saturating_sub(rhs.0))
+            }
+
+            /// Multiply, saturating on overflow.
+            pub const fn saturating_mul(self, rhs: Self) -> Self {
+                let product = (self.0 as $wide * rhs.0 as $wide) >> $frac;
+                if product > <$inner>::MAX as $wide {
+                    Self::MAX
+                } else if product < <$inner>::MIN as $wide {
+                    Self::MIN
+                } else {
+                    Self(product as $inner)
+                }
+            }
+
+            /// Divide, returning `None` if `rhs` is zero.
+            pub const fn checked_div(self, rhs: Self) -> Option<Self> {
+                if rhs.0 == 0 {
+                    None
+                } else {
+                    Some(Self((((self.0 as $wide) << $frac) / rhs.0 as $wide) as $inner))
+                }
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self {
+                Self(self.0 + rhs.0)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                Self(self.0 - rhs.0)
+            }
+        }
+
+        impl Mul for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: Self) -> Self {
+                Self(((self.0 as $wide * rhs.0 as $wide) >> $frac) as $inner)
+            }
+        }
+
+        impl Div for $name {
+            type Output = Self;
+
+            fn div(self, rhs: Self) -> Self {
+                Self((((self.0 as $wide) << $frac) / rhs.0 as $wide) as $inner)
+            }
+        }
+
+        impl Mul<$inner> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: $inner) -> Self {
+                Self(self.0 * rhs)
+            }
+        }
+
+        impl Div<$inner> for $name {
+            type Output = Self;
+
+            fn div(self, rhs: $inner) -> Self {
+                Self(self.0 / rhs)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self(-self.0)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, rhs: Self) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, rhs: Self) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl MulAssign for $name {
+            fn mul_assign(&mut self, rhs: Self) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl DivAssign for $name {
+            fn div_assign(&mut self, rhs: Self) {
+                *self = *self / rhs;
+            }
+        }
+
+        impl From<$inner> for $name {
+            fn from(value: $inner) -> Self {
+                Self::from_int(value)
+            }
+        }
+
+        /// Formats with integer arithmetic only. The precision defaults to the number of decimal
+        /// digits needed to distinguish adjacent values.
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let digits = f.precision().unwrap_or($digits).min(9) as u32;
+                let raw = (self.0 as i64).unsigned_abs();
+                let scale = 10u64.pow(digits);
+
+                let mut int = raw >> $frac;
+                let mut frac = ((raw & ((1 << $frac) - 1)) * scale + (1 << ($frac - 1))) >> $frac;
+                if frac >= scale {
+                    int += 1;
+                    frac -= scale;
+                }
+
+                let sign = if self.0 < 0 { "-" } else { "" };
+                if digits == 0 {
+                    write!(f, "{}{}", sign, int)
+                } else {
+                    write!(f, "{}{}.{:0width$}", sign, int, frac, width = digits as usize)
+                }
+            }
+        }
+    };
+}
+
+fixed! {
+    /// Signed fixed-point number with 16 integer bits and 16 fractional bits.
+    Fx16_16(i32, i64, 16, 4)
+}
+
+fixed! {
+    /// Signed fixed-point number with 8 integer bits and 8 fractional bits.
+    Fx8_8(i16, i32, 8, 2)
+}
+
+/// Number of table entries per quarter turn
+const TABLE_SIZE: i64 = 256;
+
+impl Fx16_16 {
+    pub const PI: Self = Self(205_887);
+    pub const FRAC_PI_2: Self = Self(102_944);
+    pub const TAU: Self = Self(411_775);
+
+    /// Sine of an angle in radians.
+    pub fn sin(self) -> Self {
+        Self(sine(self.phase()))
+    }
+
+    /// Cosine of an angle in radians.
+    pub fn cos(self) -> Self {
+        Self(sine(self.phase() + (TABLE_SIZE << 16)))
+    }
+
+    /// Sine and cosine of an angle in radians.
+    pub fn sin_cos(self) -> (Self, Self) {
+        let phase = self.phase();
+
+        (Self(sine(phase)), Self(sine(phase + (TABLE_SIZE << 16))))
+    }
+
+    /// Four-quadrant arctangent of `self / x` (in radians), in the range `[-PI, PI]`.
+    pub fn atan2(self, x: Self) -> Self {
+        let (y, x) = (self.0 as i64, x.0 as i64);
+        let (ay, ax) = (y.abs(), x.abs());
+        if ax == 0 && ay == 0 {
+            return Self::ZERO;
+        }
+
+        let mut angle = if ay <= ax {
+            arctangent((ay << 24) / ax)
+        } else {
+            Self::FRAC_PI_2.0 as i64 - arctangent((ax << 24) / ay)
+        };
+        if x < 0 {
+            angle = Self::PI.0 as i64 - angle;
+        }
+        if y < 0 {
+            angle = -angle;
+        }
+
+        Self(angle as i32)
+    }
+
+    /// Square root. Returns zero for negative values.
+    pub fn sqrt(self) -> Self {
+        if self.0 <= 0 {
+            return Self::ZERO;
+        }
+
+        Self(isqrt((self.0 as u64) << 16) as i32)
+    }
+
+    /// Angle in table steps, in 16.16 fixed point.
+    fn phase(self) -> i64 {
+        self.0 as i64 * ((TABLE_SIZE * 4) << 16) / Self::TAU.0 as i64
+    }
+}
+
+impl Fx8_8 {
+    pub const PI: Self = Self(804);
+    pub const FRAC_PI_2: Self = Self(402);
+    pub const TAU: Self = Self(1608);
+
+    /// Convert from [`Fx16_16`], saturating if it is out of range.
+    pub const fn from_fx16_16(value: Fx16_16) -> Self {
+        let raw = value.0 >> 8;
+        if raw > i16::MAX as i32 {
+            Self::MAX
+        } else if raw < i16::MIN as i32 {
+            Self::MIN
+        } else {
+            Self(raw as i16)
+        }
+    }
+
+    /// Sine of an angle in radians.
+    pub fn sin(self) -> Self {
+        Self::from_fx16_16(Fx16_16::from(self).sin())
+    }
+
+    /// Cosine of an angle in radians.
+    pub fn cos(self) -> Self {
+        Self::from_fx16_16(Fx16_16::from(self).cos())
+    }
+
+    /// Four-quadrant arctangent of `self / x` (in radians), in the range `[-PI, PI]`.
+    pub fn atan2(self, x: Self) -> Self {
+        Self::from_fx16_16(Fx16_16::from(self).atan2(Fx16_16::from(x)))
+    }
+
+    /// Square root. Returns zero for negative values.
+    pub fn sqrt(self) -> Self {
+        Self::from_fx16_16(Fx16_16::from(self).sqrt())
+    }
+}
+
+impl From<Fx8_8> for Fx16_16 {
+    fn from(value: Fx8_8) -> Self {
+        Self((value.0 as i32) << 8)
+    }
+}
+
+/// Sine of a phase in 16.16 fixed point table steps, where `TABLE_SIZE * 4` steps is a full turn.
+fn sine(phase: i64) -> i32 {
+    let quarter = TABLE_SIZE << 16;
+    let phase = phase.rem_euclid(quarter * 4);
+    let pos = phase % quarter;
+
+    match phase / quarter {
+        0 => lookup(&SIN_TABLE, pos),
+        1 => lookup(&SIN_TABLE, quarter - pos),
+        2 => -lookup(&SIN_TABLE, pos),
+        _ => -lookup(&SIN_TABLE, quarter - pos),
+    }
+}
+
+/// Arctangent (in 16.16 fixed point radians) of a ratio in the range `[0, 1]`, given as a 16.16
+/// fixed point table index.
+fn arctangent(ratio: i64) -> i64 {
+    lookup(&ATAN_TABLE, ratio.min(TABLE_SIZE << 16)) as i64
+}
+
+/// Linearly interpolate a table at a 16.16 fixed point index.
+fn lookup(table: &[i32; TABLE_SIZE as usize + 1], pos: i64) -> i32 {
+    let index = (pos >> 16) as usize;
+    let frac = pos & 0xFFFF;
+    if index >= TABLE_SIZE as usize {
+        return table[TABLE_SIZE as usize];
+    }
+
+    let (a, b) = (table[index] as i64, table[index + 1] as i64);
+    (a + (((b - a) * frac) >> 16)) as i32
+}
+
+/// Integer square root, rounded down.
+fn isqrt(value: u64) -> u64 {
+    let mut value = value;
+    let mut result = 0;
+    let mut bit = 1 << 62;
+    while bit > value {
+        bit >>= 2;
+    }
+
+    while bit != 0 {
+        if value >= result + bit {
+            value -= result + bit;
+            result = (result >> 1) + bit;
+        } else {
+            result >>= 1;
+        }
+        bit >>= 2;
+    }
+
+    result
+}
+
+/// First quadrant of the sine wave in 16.16 fixed point, with `TABLE_SIZE + 1` entries
+const SIN_TABLE: [i32; 257] = [
+    0, 402, 804, 1206, 1608, 2010, 2412, 2814, 3216, 3617, 4019, 4420, 4821, 5222, 5623, 6023,
+    6424, 6824, 7224, 7623, 8022, 8421, 8820, 9218, 9616, 10014, 10411, 10808, 11204, 11600, 11996,
+    12391, 12785, 13180, 13573, 13966, 14359, 14751, 15143, 15534, 15924, 16314, 16703, 17091,
+    17479, 17867, 18253, 18639, 19024, 19409, 19792, 20175, 20557, 20939, 21320, 21699, 22078,
+    22457, 22834, 23210, 23586, 23961, 24335, 24708, 25080, 25451, 25821, 26190, 26558, 26925,
+    27291, 27656, 28020, 28383, 28745, 29106, 29466, 29824, 30182, 30538, 30893, 31248, 31600,
+    31952, 32303, 32652, 33000, 33347, 33692, 34037, 34380, 34721, 35062, 35401, 35738, 36075,
+    36410, 36744, 37076, 37407, 37736, 38064, 38391, 38716, 39040, 39362, 39683, 40002, 40320,
+    40636, 40951, 41264, 41576, 41886, 42194, 42501, 42806, 43110, 43412, 43713, 44011, 44308,
+    44604, 44898, 45190, 45480, 45769, 46056, 46341, 46624, 46906, 47186, 47464, 47741, 48015,
+    48288, 48559, 48828, 49095, 49361, 49624, 49886, 50146, 50404, 50660, 50914, 51166, 51417,
+    51665, 51911, 52156, 52398, 52639, 52878, 53114, 53349, 53581, 53812, 54040, 54267, 54491,
+    54714, 54934, 55152, 55368, 55582, 55794, 56004, 56212, 56418, 56621, 56823, 57022, 57219,
+    57414, 57607, 57798, 57986, 58172, 58356, 58538, 58718, 58896, 59071, 59244, 59415, 59583,
+    59750, 59914, 60075, 60235, 60392, 60547, 60700, 60851, 60999, 61145, 61288, 61429, 61568,
+    61705, 61839, 61971, 62101, 62228, 62353, 62476, 62596, 62714, 62830, 62943, 63054, 63162,
+    63268, 63372, 63473, 63572, 63668, 63763, 63854, 63944, 64031, 64115, 64197, 64277, 64354,
+    64429, 64501, 64571, 64639, 64704, 64766, 64827, 64884, 64940, 64993, 65043, 65091, 65137,
+    65180, 65220, 65259, 65294, 65328, 65358, 65387, 65413, 65436, 65457, 65476, 65492, 65505,
+    65516, 65525, 65531, 65535, 65536,
+];
+
+/// Arctangent of `i / TABLE_SIZE` (in 16.16 fixed point radians) for each table index `i`
+const ATAN_TABLE: [i32; 257] = [
+    0, 256, 512, 768, 1024, 1280, 1536, 1792, 2047, 2303, 2559, 2814, 3070, 3325, 3580, 3836, 4091,
+    4346, 4600, 4855, 5110, 5364, 5618, 5872, 6126, 6380, 6633, 6887, 7140, 7392, 7645, 7898, 8150,
+    8402, 8653, 8905, 9156, 9407, 9657, 9908, 10158, 10408, 10657, 10906, 11155, 11403, 11652,
+    11899, 12147, 12394, 12641, 12887, 13133, 13379, 13624, 13869, 14114, 14358, 14601, 14845,
+    15088, 15330, 15572, 15814, 16055, 16296, 16536, 16776, 17015, 17254, 17492, 17730, 17968,
+    18205, 18441, 18677, 18913, 19148, 19382, 19616, 19850, 20083, 20315, 20547, 20779, 21009,
+    21240, 21469, 21699, 21927, 22156, 22383, 22610, 22836, 23062, 23288, 23512, 23737, 23960,
+    24183, 24406, 24627, 24849, 25069, 25289, 25509, 25727, 25946, 26163, 26380, 26597, 26813,
+    27028, 27242, 27456, 27670, 27882, 28094, 28306, 28517, 28727, 28936, 29145, 29354, 29561,
+    29768, 29975, 30180, 30386, 30590, 30794, 30997, 31200, 31402, 31603, 31803, 32003, 32203,
+    32401, 32600, 32797, 32994, 33190, 33385, 33580, 33774, 33968, 34160, 34353, 34544, 34735,
+    34925, 35115, 35304, 35492, 35680, 35867, 36053, 36239, 36424, 36608, 36792, 36975, 37158,
+    37340, 37521, 37701, 37881, 38060, 38239, 38417, 38594, 38771, 38947, 39123, 39297, 39472,
+    39645, 39818, 39990, 40162, 40333, 40503, 40673, 40842, 41010, 41178, 41346, 41512, 41678,
+    41844, 42008, 42172, 42336, 42499, 42661, 42823, 42984, 43145, 43304, 43464, 43622, 43780,
+    43938, 44095, 44251, 44407, 44562, 44716, 44870, 45024, 45176, 45328, 45480, 45631, 45781,
+    45931, 46080, 46229, 46377, 46525, 46672, 46818, 46964, 47109, 47254, 47398, 47542, 47685,
+    47827, 47969, 48111, 48251, 48392, 48531, 48671, 48809, 48947, 49085, 49222, 49359, 49495,
+    49630, 49765, 49899, 50033, 50167, 50299, 50432, 50563, 50695, 50826, 50956, 51086, 51215,
+    51344, 51472,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check a result is within a number of raw steps of a value.
+    fn assert_near(actual: Fx16_16, expected: f32, steps: i32) {
+        let expected = Fx16_16::from_f32(expected);
+        assert!(
+            (actual.to_raw() - expected.to_raw()).abs() <= steps,
+            "{} is not near {}",
+            actual,
+            expected,
+        );
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Fx16_16::from_int(3);
+        let b = Fx16_16::HALF;
+
+        assert_eq!((a + b).to_raw(), 0x0003_8000);
+        assert_eq!((a * b).to_raw(), 0x0001_8000);
+        assert_eq!((a / b).to_int(), 6);
+        assert_eq!((-a * 2).to_int(), -6);
+        assert_eq!(a.checked_div(Fx16_16::ZERO), None);
+        assert_eq!(Fx16_16::MAX.saturating_mul(a), Fx16_16::MAX);
+        assert_eq!(Fx16_16::MIN.saturating_mul(a), Fx16_16::MIN);
+    }
+
+    #[test]
+    fn rounding() {
+        let x = Fx16_16::from_f32(-1.25);
+
+        assert_eq!(x.floor().to_int(), -2);
+        assert_eq!(x.fract(), Fx16_16::from_f32(0.75));
+        assert_eq!(x.round().to_int(), -1);
+        assert_eq!(Fx16_16::from_f32(2.5).round().to_int(), 3);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            crate::format_buf!([16], "{}", Fx16_16::from_f32(1.5)).as_str(),
+            "1.5000"
+        );
+        assert_eq!(
+            crate::format_buf!([16], "{}", Fx16_16::from_f32(-0.25)).as_str(),
+            "-0.2500"
+        );
+        assert_eq!(
+            crate::format_buf!([16], "{:.1}", Fx16_16::from_f32(9.96)).as_str(),
+            "10.0"
+        );
+        assert_eq!(
+            crate::format_buf!([16], "{:.0}", Fx8_8::from_int(-7)).as_str(),
+            "-7"
+        );
+        assert_eq!(
+            crate::format_buf!([16], "{}", Fx8_8::from_raw(1)).as_str(),
+            "0.00"
+        );
+    }
+
+    #[test]
+    fn trigonometry() {
+        assert_near(Fx16_16::ZERO.sin(), 0.0, 0);
+        assert_near(Fx16_16::ZERO.cos(), 1.0, 0);
+        assert_near(Fx16_16::FRAC_PI_2.sin(), 1.0, 7);
+        assert_near(Fx16_16::PI.cos(), -1.0, 7);
+        assert_near(Fx16_16::from_f32(-0.5).sin(), -0.479_425_5, 7);
+        assert_near(Fx16_16::from_f32(2.0).cos(), -0.416_146_8, 7);
+        assert_near(
+            Fx16_16::ONE.atan2(Fx16_16::ONE),
+            core::f32::consts::FRAC_PI_4,
+            7,
+        );
+        assert_near(
+            Fx16_16::ONE.atan2(-Fx16_16::ONE),
+            3.0 * core::f32::consts::FRAC_PI_4,
+            7,
+        );
+        assert_near(Fx16_16::ZERO.atan2(Fx16_16::ZERO), 0.0, 0);
+    }
+
+    #[test]
+    fn square_root() {
+        assert_eq!(Fx16_16::from_int(4).sqrt(), Fx16_16::from_int(2));
+        assert_near(Fx16_16::from_int(2).sqrt(), core::f32::consts::SQRT_2, 1);
+        assert_eq!(Fx16_16::from_int(-4).sqrt(), Fx16_16::ZERO);
+        assert_eq!(Fx8_8::from_int(9).sqrt(), Fx8_8::from_int(3));
+    }
+
+    #[test]
+    fn conversion() {
+        assert_eq!(Fx16_16::from(Fx8_8::HALF), Fx16_16::HALF);
+        assert_eq!(Fx8_8::from_fx16_16(Fx16_16::from_int(1000)), Fx8_8::MAX);
+        assert_eq!(Fx8_8::from_fx16_16(Fx16_16::from_int(-1000)), Fx8_8::MIN);
+    }
+}
@@ -0,0 +1,364 @@
+//! Formatting without an allocator.
+//!
+//! [`format_buf!`](crate::format_buf) formats into a byte buffer and returns the text as `&str`,
+//! for the places where `format!` is unavailable or its allocation is unwanted, like drawing text
+//! every frame. Output that does not fit is truncated on a character boundary.
+//!
+//! Integers and floats can also be converted to text directly, with [`u32_to_str`],
+//! [`f32_to_str`] and friends. These are faster than `core::fmt`, and the float formatter is much
+//! smaller. [`Decimal`] formats a float with them when used with `write!`.
+
+use core::fmt;
+use core::ops::Deref;
+
+/// Format text into a buffer, returning it as a `&str`. Output that does not fit is truncated.
+///
+/// The buffer is either a caller-provided `&mut [u8]`, or a capacity in brackets for a
+/// [`FormatBuffer`] on the stack, which is returned instead.
+///
+/// e.g. `let name = format_buf!(&mut buf, "SLOT {}", slot);` or
+/// `let text = format_buf!([32], "SCORE {}", score);`
+#[macro_export]
+macro_rules! format_buf {
+    ([$capacity:expr], $($arg:tt)*) => {{
+        let mut buffer = $crate::fmt::FormatBuffer::<{ $capacity }>::new();
+        let _ = ::core::fmt::Write::write_fmt(&mut buffer, ::core::format_args!($($arg)*));
+        buffer
+    }};
+    ($buffer:expr, $($arg:tt)*) => {{
+        let mut writer = $crate::fmt::BufWriter::new($buffer);
+        let _ = ::core::fmt::Write::write_fmt(&mut writer, ::core::format_args!($($arg)*));
+        writer.into_str()
+    }};
+}
+
+/// Append as much of `s` as fits in `buffer` after `len` bytes, on a character boundary.
+fn append(buffer: &mut [u8], len: &mut usize, s: &str) {
+    let mut count = s.len().min(buffer.len() - *len);
+    while !s.is_char_boundary(count) {
+        count -= 1;
+    }
+
+    buffer[*len..*len + count].copy_from_slice(&s.as_bytes()[..count]);
+    *len += count;
+}
+
+/// A writer into a borrowed byte buffer. Text that does not fit is truncated.
+pub struct BufWriter<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> BufWriter<'a> {
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    /// Get the written text.
+    pub fn as_str(&self) -> &str {
+        // Truncation only happens on character boundaries, so the contents are always valid UTF-8
+        unsafe { core::str::from_utf8_unchecked(&self.buffer[..self.len]) }
+    }
+
+    /// Get the written text, borrowing the buffer for as long as the writer did.
+    pub fn into_str(self) -> &'a str {
+        unsafe { core::str::from_utf8_unchecked(&self.buffer[..self.len]) }
+    }
+}
+
+impl fmt::Write for BufWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        append(self.buffer, &mut self.len, s);
+        Ok(())
+    }
+}
+
+/// Fixed-capacity buffer for formatting text without an allocator. Text that does not fit is
+/// truncated.
+#[derive(Clone)]
+pub struct FormatBuffer<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> FormatBuffer<N> {
+    /// Create an empty buffer.
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Get the formatted text.
+    pub fn as_str(&self) -> &str {
+        // Truncation only happens on character boundaries, so the contents are always valid UTF-8
+        unsafe { core::str::from_utf8_unchecked(&self.bytes[..self.len]) }
+    }
+
+    /// Remove all text.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for FormatBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Deref for FormatBuffer<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Write for FormatBuffer<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        append(&mut self.bytes, &mut self.len, s);
+        Ok(())
+    }
+}
+
+impl<const N: usize> fmt::Display for FormatBuffer<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<const N: usize> fmt::Debug for FormatBuffer<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+/// Size of the buffer needed by [`u64_to_str`] and [`i64_to_str`] (in bytes)
+pub const INT_BUFFER_SIZE: usize = 20;
+
+/// Size of the buffer needed by [`f32_to_str`] and [`f64_to_str`] (in bytes)
+pub const FLOAT_BUFFER_SIZE: usize = 32;
+
+/// Most digits after the decimal point written by the float formatters
+pub const MAX_PRECISION: usize = 9;
+
+/// Pairs of decimal digits from "00" to "99"
+static DIGIT_PAIRS: [u8; 200] = digit_pairs();
+
+const fn digit_pairs() -> [u8; 200] {
+    let mut pairs = [0; 200];
+    let mut i = 0;
+    while i < 100 {
+        pairs[i * 2] = b'0' + (i / 10) as u8;
+        pairs[i * 2 + 1] = b'0' + (i % 10) as u8;
+        i += 1;
+    }
+
+    pairs
+}
+
+/// Write the digits of `value` at the end of `buffer`, returning the index of the first digit.
+fn write_u32(mut value: u32, buffer: &mut [u8]) -> usize {
+    let mut pos = buffer.len();
+    while value >= 100 {
+        let pair = (value % 100) as usize * 2;
+        value /= 100;
+        pos -= 2;
+        buffer[pos..pos + 2].copy_from_slice(&DIGIT_PAIRS[pair..pair + 2]);
+    }
+
+    if value >= 10 {
+        let pair = value as usize * 2;
+        pos -= 2;
+        buffer[pos..pos + 2].copy_from_slice(&DIGIT_PAIRS[pair..pair + 2]);
+    } else {
+        pos -= 1;
+        buffer[pos] = b'0' + value as u8;
+    }
+
+    pos
+}
+
+/// Like [`write_u32`], with 64-bit division only for the digits above `u32::MAX`.
+fn write_u64(mut value: u64, buffer: &mut [u8]) -> usize {
+    let mut end = buffer.len();
+    while value > u32::MAX as u64 {
+        // Write the low 9 digits with leading zeros
+        let low = (value % 1_000_000_000) as u32;
+        value /= 1_000_000_000;
+        let pos = write_u32(low, &mut buffer[..end]);
+        buffer[end - 9..pos].fill(b'0');
+        end -= 9;
+    }
+
+    write_u32(value as u32, &mut buffer[..end])
+}
+
+/// Write `value` into the end of `buffer` with a leading minus sign if negative, returning the text.
+fn signed(negative: bool, buffer: &mut [u8], write: impl FnOnce(&mut [u8]) -> usize) -> &str {
+    let mut pos = write(buffer);
+    if negative {
+        pos -= 1;
+        buffer[pos] = b'-';
+    }
+
+    // Only ASCII digits and signs are written
+    unsafe { core::str::from_utf8_unchecked(&buffer[pos..]) }
+}
+
+/// Convert an integer to decimal text in a buffer.
+pub fn u32_to_str(value: u32, buffer: &mut [u8; 10]) -> &str {
+    signed(false, buffer, |buffer| write_u32(value, buffer))
+}
+
+/// Convert an integer to decimal text in a buffer.
+pub fn i32_to_str(value: i32, buffer: &mut [u8; 11]) -> &str {
+    signed(value < 0, buffer, |buffer| {
+        write_u32(value.unsigned_abs(), buffer)
+    })
+}
+
+/// Convert an integer to decimal text in a buffer.
+pub fn u64_to_str(value: u64, buffer: &mut [u8; INT_BUFFER_SIZE]) -> &str {
+    signed(false, buffer, |buffer| write_u64(value, buffer))
+}
+
+/// Convert an integer to decimal text in a buffer.
+pub fn i64_to_str(value: i64, buffer: &mut [u8; INT_BUFFER_SIZE]) -> &str {
+    signed(value < 0, buffer, |buffer| {
+        write_u64(value.unsigned_abs(), buffer)
+    })
+}
+
+/// Convert a float to decimal text in a buffer, with `precision` digits after the decimal point
+/// (up to [`MAX_PRECISION`]). Values of 10^15 and above are written in scientific notation.
+pub fn f32_to_str(value: f32, precision: usize, buffer: &mut [u8; FLOAT_BUFFER_SIZE]) -> &str {
+    f64_to_str(value as f64, precision, buffer)
+}
+
+/// Convert a float to decimal text in a buffer, with `precision` digits after the decimal point
+/// (up to [`MAX_PRECISION`]). Values of 10^15 and above are written in scientific notation.
+pub fn f64_to_str(value: f64, precision: usize, buffer: &mut [u8; FLOAT_BUFFER_SIZE]) -> &str {
+    if value.is_nan() {
+        return "NaN";
+    }
+    if value.is_infinite() {
+        return if value < 0.0 { "-inf" } else { "inf" };
+    }
+
+    let precision = precision.min(MAX_PRECISION);
+    let abs = libm::fabs(value);
+    let (mantissa, exponent) = if abs < 1e15 {
+        (abs, None)
+    } else {
+        // The logarithm can be off by one near powers of ten
+        let mut exponent = libm::floor(libm::log10(abs)) as i32;
+        let mut mantissa = abs / libm::pow(10.0, exponent as f64);
+        if mantissa >= 10.0 {
+            mantissa /= 10.0;
+            exponent += 1;
+        } else if mantissa < 1.0 {
+            mantissa *= 10.0;
+            exponent -= 1;
+        }
+        (mantissa, Some(exponent))
+    };
+
+    // Split into integer and fraction digits, carrying a rounded up fraction
+    let scale = 10_u64.pow(precision as u32);
+    let mut int = mantissa as u64;
+    let mut frac = libm::round((mantissa - int as f64) * scale as f64) as u64;
+    if frac >= scale {
+        int += 1;
+        frac -= scale;
+    }
+    // Rounding up can carry into another digit
+    let exponent = match exponent {
+        Some(exponent) if int >= 10 => {
+            int /= 10;
+            Some(exponent + 1)
+        }
+        exponent => exponent,
+    };
+
+    signed(value.is_sign_negative(), buffer, |buffer| {
+        let mut end = buffer.len();
+        if let Some(exponent) = exponent {
+            let pos = write_u32(exponent.unsigned_abs(), &mut buffer[..end]);
+            let sign = if exponent < 0 { b'-' } else { b'+' };
+            buffer[pos - 2..pos].copy_from_slice(&[b'e', sign]);
+            end = pos - 2;
+        }
+        if precision > 0 {
+            let pos = write_u64(frac, &mut buffer[..end]);
+            buffer[end - precision..pos].fill(b'0');
+            end -= precision + 1;
+            buffer[end] = b'.';
+        }
+
+        write_u64(int, &mut buffer[..end])
+    })
+}
+
+/// A float formatted with [`f32_to_str`] by `Display`, with a number of digits after the decimal
+/// point.
+///
+/// e.g. `write!(out, "X {}", Decimal(x, 2))`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decimal(pub f32, pub usize);
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(f32_to_str(self.0, self.1, &mut [0; FLOAT_BUFFER_SIZE]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        assert_eq!(u32_to_str(0, &mut [0; 10]), "0");
+        assert_eq!(u32_to_str(7, &mut [0; 10]), "7");
+        assert_eq!(u32_to_str(1_000_005, &mut [0; 10]), "1000005");
+        assert_eq!(u32_to_str(u32::MAX, &mut [0; 10]), "4294967295");
+        assert_eq!(i32_to_str(-42, &mut [0; 11]), "-42");
+        assert_eq!(i32_to_str(i32::MIN, &mut [0; 11]), "-2147483648");
+        assert_eq!(u64_to_str(u64::MAX, &mut [0; 20]), "18446744073709551615");
+        assert_eq!(u64_to_str(5_000_000_001, &mut [0; 20]), "5000000001");
+        assert_eq!(i64_to_str(i64::MIN, &mut [0; 20]), "-9223372036854775808");
+    }
+
+    #[test]
+    fn floats() {
+        let mut buffer = [0; FLOAT_BUFFER_SIZE];
+        assert_eq!(f64_to_str(0.0, 2, &mut buffer), "0.00");
+        assert_eq!(f64_to_str(-0.5, 0, &mut buffer), "-1");
+        assert_eq!(f64_to_str(-0.4, 0, &mut buffer), "-0");
+        assert_eq!(f64_to_str(1.005, 1, &mut buffer), "1.0");
+        assert_eq!(f64_to_str(9.996, 2, &mut buffer), "10.00");
+        assert_eq!(f64_to_str(-3.25, 3, &mut buffer), "-3.250");
+        assert_eq!(f64_to_str(0.001, 2, &mut buffer), "0.00");
+        assert_eq!(f64_to_str(0.1234, 20, &mut buffer), "0.123400000");
+        assert_eq!(f64_to_str(123456.5, 1, &mut buffer), "123456.5");
+        assert_eq!(f64_to_str(2.5e20, 2, &mut buffer), "2.50e+20");
+        assert_eq!(f64_to_str(-9.999e15, 1, &mut buffer), "-1.0e+16");
+        assert_eq!(f64_to_str(f64::NAN, 2, &mut buffer), "NaN");
+        assert_eq!(f64_to_str(f64::NEG_INFINITY, 2, &mut buffer), "-inf");
+        assert_eq!(f32_to_str(1.5, 2, &mut buffer), "1.50");
+    }
+
+    #[test]
+    fn buffers_truncate_on_character_boundaries() {
+        let mut buffer = [0; 3];
+        assert_eq!(format_buf!(&mut buffer, "{}é", "ab"), "ab");
+        assert_eq!(format_buf!([5], "{}", 123456).as_str(), "12345");
+        assert_eq!(
+            format_buf!([16], "X {}", Decimal(2.345, 2)).as_str(),
+            "X 2.35"
+        );
+    }
+}
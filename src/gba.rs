@@ -0,0 +1,73 @@
+//! Game Boy Advance support.
+//!
+//! [`Mode3Text`] draws text to the bitmap of video mode 3 with a [`TextConsole`], for printing
+//! diagnostics, e.g. as the output of the [test runner](crate::test::set_output):
+//!
+//! ```text
+//! let mut text = Mode3Text::new(Mode3, &FONT);
+//! text.init();
+//! writeln!(text, "hello from rrt0")?;
+//! ```
+//!
+//! Only available on GBA.
+
+pub use crate::text::FONT_SIZE;
+use crate::text::{Surface, TextConsole};
+
+/// Display control register
+const DISPCNT: *mut u16 = 0x0400_0000 as *mut u16;
+
+/// Video mode 3 with background 2 enabled: a 240x160 bitmap of 15-bit colors
+const DISPCNT_MODE3: u16 = 0x0403;
+
+/// The mode 3 bitmap in VRAM
+const VRAM: *mut u16 = 0x0600_0000 as *mut u16;
+
+/// Size of the screen (in pixels)
+pub const SCREEN_WIDTH: usize = 240;
+pub const SCREEN_HEIGHT: usize = 160;
+
+/// Convert an 8-bit per channel color to the 15-bit BGR format of the GBA.
+pub const fn rgb(r: u8, g: u8, b: u8) -> u16 {
+    (r as u16 >> 3) | (g as u16 >> 3) << 5 | (b as u16 >> 3) << 10
+}
+
+/// The mode 3 bitmap, as a surface for a [`TextConsole`].
+#[derive(Debug)]
+pub struct Mode3;
+
+impl Mode3 {
+    /// Switch the display to mode 3.
+    pub fn enable(&mut self) {
+        unsafe { DISPCNT.write_volatile(DISPCNT_MODE3) };
+    }
+}
+
+impl Surface for Mode3 {
+    type Color = u16;
+
+    const WIDTH: usize = SCREEN_WIDTH;
+    const HEIGHT: usize = SCREEN_HEIGHT;
+
+    const FOREGROUND: u16 = rgb(0xFF, 0xFF, 0xFF);
+    const BACKGROUND: u16 = 0;
+
+    fn read(&self, x: usize, y: usize) -> u16 {
+        unsafe { VRAM.add(y * SCREEN_WIDTH + x).read_volatile() }
+    }
+
+    fn write(&mut self, x: usize, y: usize, color: u16) {
+        unsafe { VRAM.add(y * SCREEN_WIDTH + x).write_volatile(color) };
+    }
+}
+
+/// A text console drawn to the mode 3 bitmap. Scrolls up when the screen is full.
+pub type Mode3Text = TextConsole<Mode3>;
+
+impl Mode3Text {
+    /// Switch the display to mode 3 and clear the screen.
+    pub fn init(&mut self) {
+        self.surface_mut().enable();
+        self.clear();
+    }
+}
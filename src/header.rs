@@ -0,0 +1,152 @@
+//! The cartridge ROM header.
+//!
+//! The first 64 bytes of a ROM image describe it: the internal name, game code, region, and the
+//! checksums that IPL3 verifies. [`read`] reads the header of the running program, or of another
+//! program image embedded at an offset in the ROM, e.g. to list them in a launcher.
+
+use core::fmt;
+
+/// Size of the header (in bytes)
+pub const HEADER_SIZE: usize = 64;
+
+/// Length of the internal name (in bytes)
+pub const NAME_LEN: usize = 20;
+
+/// First byte of a header in the native (big-endian) byte order, from the PI configuration
+const PI_CONFIG_FIRST_BYTE: u8 = 0x80;
+
+/// A ROM header.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Header {
+    /// PI bus timings for the cartridge domain
+    pub pi_config: u32,
+    pub clock_rate: u32,
+    /// Address that IPL3 copies the program to and jumps to
+    pub entry_point: u32,
+    /// libultra version the program was built with
+    pub release: u32,
+    pub crc1: u32,
+    pub crc2: u32,
+    name: [u8; NAME_LEN],
+    game_code: [u8; 4],
+    /// Revision of the program, starting at 0
+    pub version: u8,
+}
+
+/// Region, from the last character of the game code.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Region {
+    Japan,
+    NorthAmerica,
+    Europe,
+    Germany,
+    France,
+    Italy,
+    Spain,
+    Australia,
+    /// Any region, for homebrew
+    All,
+    Other(u8),
+}
+
+impl From<u8> for Region {
+    fn from(code: u8) -> Self {
+        match code {
+            b'J' => Self::Japan,
+            b'E' => Self::NorthAmerica,
+            b'P' | b'X' | b'Y' => Self::Europe,
+            b'D' => Self::Germany,
+            b'F' => Self::France,
+            b'I' => Self::Italy,
+            b'S' => Self::Spain,
+            b'U' => Self::Australia,
+            b'A' => Self::All,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl Header {
+    /// Parse a header. Returns `None` if it is not in the native byte order, like the byte-swapped
+    /// images some tools create.
+    pub fn parse(bytes: &[u8; HEADER_SIZE]) -> Option<Self> {
+        if bytes[0] != PI_CONFIG_FIRST_BYTE {
+            return None;
+        }
+
+        let word = |offset: usize| {
+            u32::from_be_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+        let mut name = [0; NAME_LEN];
+        name.copy_from_slice(&bytes[0x20..0x20 + NAME_LEN]);
+        let mut game_code = [0; 4];
+        game_code.copy_from_slice(&bytes[0x3B..0x3F]);
+
+        Some(Self {
+            pi_config: word(0x00),
+            clock_rate: word(0x04),
+            entry_point: word(0x08),
+            release: word(0x0C),
+            crc1: word(0x10),
+            crc2: word(0x14),
+            name,
+            game_code,
+            version: bytes[0x3F],
+        })
+    }
+
+    /// Internal name, without the padding. Names that are not ASCII, like the JIS X 0201 names of
+    /// some Japanese releases, are cut at the first byte that is not.
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|byte| !byte.is_ascii() || *byte == 0)
+            .unwrap_or(NAME_LEN);
+
+        core::str::from_utf8(&self.name[..len])
+            .unwrap_or("")
+            .trim_end()
+    }
+
+    /// Game code, e.g. `NSME`: the media format, a two character ID, and the region. Empty if it
+    /// is not ASCII.
+    pub fn game_code(&self) -> &str {
+        match core::str::from_utf8(&self.game_code) {
+            Ok(code) if self.game_code.iter().all(u8::is_ascii_graphic) => code,
+            _ => "",
+        }
+    }
+
+    pub fn region(&self) -> Region {
+        Region::from(self.game_code[3])
+    }
+}
+
+/// Formats as e.g. `SUPER MARIO 64 (NSME) v0`.
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) v{}",
+            self.name(),
+            self.game_code(),
+            self.version
+        )
+    }
+}
+
+/// Read the header of the program image at a ROM offset, 0 for the running program. Returns
+/// `None` if there is no header in the native byte order.
+#[cfg(rrt0_platform = "n64")]
+pub fn read(rom_offset: u32) -> Option<Header> {
+    let mut bytes = [0; HEADER_SIZE];
+    crate::mem::read_rom(&mut bytes, rom_offset);
+
+    Header::parse(&bytes)
+}
@@ -0,0 +1,455 @@
+//! Heap allocation.
+//!
+//! [`Heap`] is a first-fit free list allocator over two RDRAM regions: the base 4 MiB, and the
+//! 4 MiB added by the Expansion Pak. General allocations come from the base region first, and
+//! [`Heap::alloc_in`] allocates from a specific region, e.g. to keep framebuffers and DMA buffers
+//! apart from ordinary data.
+//!
+//! On N64 [`HEAP`] covers the memory between the end of the program and the stack that the
+//! [memory layout](crate::layout) leaves to it, and is the global allocator, so the `alloc` crate
+//! can be used. Its usage is reported by [`stats`].
+//!
+//! Running out of memory in `Box::new` or `Vec::with_capacity` panics. [`try_box`] and
+//! [`try_vec_with_capacity`] return an error instead, so a game can do without, e.g. skip a sound
+//! effect, when the heap is full.
+
+use crate::interrupt::Mutex;
+use crate::layout::Reserved;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt;
+use core::mem;
+use core::ptr::{self, NonNull};
+
+/// Memory reserved for the stack by default (in bytes). Programs can change it with
+/// [`memory_layout!`](crate::memory_layout).
+pub const STACK_SIZE: usize = crate::layout::DEFAULT_STACK_SIZE;
+
+/// The global heap.
+#[cfg(rrt0_platform = "n64")]
+#[cfg_attr(not(feature = "leak-tracking"), global_allocator)]
+pub static HEAP: Heap = Heap::boot();
+
+/// Number of live allocations recorded by [`LEAKS`]
+#[cfg(feature = "leak-tracking")]
+pub const LEAK_RECORDS: usize = 256;
+
+/// The global allocator when leak tracking is enabled, wrapping [`HEAP`].
+#[cfg(all(feature = "leak-tracking", rrt0_platform = "n64"))]
+#[global_allocator]
+pub static LEAKS: crate::leak::LeakTracker<Heap, LEAK_RECORDS> =
+    crate::leak::LeakTracker::new(&HEAP);
+
+/// Usage statistics of the global heap.
+#[cfg(rrt0_platform = "n64")]
+pub fn stats() -> Stats {
+    HEAP.stats()
+}
+
+/// An RDRAM region managed by the heap.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Region {
+    /// The first 4 MiB of RDRAM
+    Base,
+    /// The 4 MiB added by the Expansion Pak
+    Expansion,
+}
+
+/// Heap usage statistics, over all regions. Sizes are in bytes, including padding.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Stats {
+    /// Total size of all regions
+    pub size: usize,
+    /// Bytes currently allocated
+    pub used: usize,
+    /// Most bytes allocated at once
+    pub peak: usize,
+    /// Number of live allocations
+    pub allocations: usize,
+    /// Size of the largest free block, which bounds the largest possible allocation
+    pub largest_free: usize,
+}
+
+/// Header of a free block, stored in the block itself.
+struct Node {
+    /// Size of the block (in bytes), including the header
+    size: usize,
+    next: Option<NonNull<Node>>,
+}
+
+/// Allocation granularity. Every block is aligned to and a multiple of this size, so there is
+/// always room for a header when a block is freed.
+const GRANULE: usize = mem::size_of::<Node>();
+
+/// Address-ordered list of free blocks in one region.
+struct FreeList {
+    head: Option<NonNull<Node>>,
+    start: usize,
+    end: usize,
+    /// Bytes between start and end that are not managed
+    excluded: usize,
+    used: usize,
+    allocations: usize,
+}
+
+impl FreeList {
+    const fn new() -> Self {
+        Self {
+            head: None,
+            start: 0,
+            end: 0,
+            excluded: 0,
+            used: 0,
+            allocations: 0,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.end - self.start - self.excluded
+    }
+
+    fn largest_free(&self) -> usize {
+        let mut largest = 0;
+        let mut node = self.head;
+        while let Some(free) = node {
+            let free = unsafe { free.as_ref() };
+            largest = largest.max(free.size);
+            node = free.next;
+        }
+
+        largest
+    }
+
+    fn contains(&self, addr: usize) -> bool {
+        (self.start..self.end).contains(&addr)
+    }
+
+    /// Hand a range of memory to the list, except for the reserved regions in it. The list must be
+    /// empty.
+    unsafe fn init(&mut self, start: usize, end: usize, reserved: &[Reserved]) {
+        let start = align_up(start, GRANULE);
+        let end = end & !(GRANULE - 1);
+        if start >= end {
+            return;
+        }
+
+        self.start = start;
+        self.end = end;
+        self.excluded = end - start;
+
+        // Insert the gaps between reserved regions, in address order
+        let mut gap = start;
+        while gap < end {
+            let next = reserved
+                .iter()
+                .filter(|region| region.end() > gap && region.start < end)
+                .min_by_key(|region| region.start);
+            let gap_end = next.map_or(end, |region| region.start.max(gap));
+
+            let free_start = align_up(gap, GRANULE);
+            let free_end = gap_end & !(GRANULE - 1);
+            if free_start < free_end {
+                self.insert(free_start, free_end - free_start);
+                self.excluded -= free_end - free_start;
+            }
+            gap = next.map_or(end, |region| region.end().max(gap));
+        }
+    }
+
+    fn alloc(&mut self, size: usize, align: usize) -> Option<NonNull<u8>> {
+        let mut link: *mut Option<NonNull<Node>> = &mut self.head;
+
+        unsafe {
+            while let Some(node) = *link {
+                let start = node.as_ptr() as usize;
+                let end = start + node.as_ref().size;
+                let aligned = align_up(start, align);
+
+                if let Some(alloc_end) = aligned.checked_add(size).filter(|&e| e <= end) {
+                    // Unlink the block, and return any padding before and after the allocation
+                    *link = node.as_ref().next;
+                    if aligned > start {
+                        self.insert(start, aligned - start);
+                    }
+                    if end > alloc_end {
+                        self.insert(alloc_end, end - alloc_end);
+                    }
+
+                    self.used += size;
+                    self.allocations += 1;
+                    return NonNull::new(aligned as *mut u8);
+                }
+
+                link = &mut (*node.as_ptr()).next;
+            }
+        }
+
+        None
+    }
+
+    unsafe fn dealloc(&mut self, ptr: NonNull<u8>, size: usize) {
+        self.used -= size;
+        self.allocations -= 1;
+        self.insert(ptr.as_ptr() as usize, size);
+    }
+
+    /// Add a free block, merging it with adjacent free blocks.
+    unsafe fn insert(&mut self, addr: usize, size: usize) {
+        let mut prev: Option<NonNull<Node>> = None;
+        let mut link: *mut Option<NonNull<Node>> = &mut self.head;
+        while let Some(node) = *link {
+            if node.as_ptr() as usize > addr {
+                break;
+            }
+            prev = Some(node);
+            link = &mut (*node.as_ptr()).next;
+        }
+
+        let node = addr as *mut Node;
+        node.write(Node { size, next: *link });
+
+        if let Some(next) = (*node).next {
+            if addr + size == next.as_ptr() as usize {
+                (*node).size += next.as_ref().size;
+                (*node).next = next.as_ref().next;
+            }
+        }
+
+        if let Some(prev) = prev {
+            let prev = prev.as_ptr();
+            if prev as usize + (*prev).size == addr {
+                (*prev).size += (*node).size;
+                (*prev).next = (*node).next;
+                return;
+            }
+        }
+
+        *link = NonNull::new(node);
+    }
+}
+
+struct State {
+    regions: [FreeList; 2],
+    /// Most bytes allocated at once, over all regions
+    peak: usize,
+    /// Whether the regions have been set up
+    ready: bool,
+}
+
+// The free lists only point into the memory they own
+unsafe impl Send for State {}
+
+/// A heap allocator over the base and expansion RDRAM regions.
+pub struct Heap {
+    state: Mutex<State>,
+}
+
+impl Heap {
+    /// Create a heap with no memory. Memory is added with [`Heap::init`].
+    pub const fn new() -> Self {
+        Self::with_ready(true)
+    }
+
+    /// Create a heap that claims the free memory described by the boot environment on first use.
+    #[cfg(rrt0_platform = "n64")]
+    const fn boot() -> Self {
+        Self::with_ready(false)
+    }
+
+    const fn with_ready(ready: bool) -> Self {
+        Self {
+            state: Mutex::new(State {
+                regions: [FreeList::new(), FreeList::new()],
+                peak: 0,
+                ready,
+            }),
+        }
+    }
+
+    /// Give the heap a range of memory to allocate from in a region.
+    ///
+    /// # Safety
+    ///
+    /// The memory must be valid for reads and writes, unused by anything else for as long as the
+    /// heap exists, and the region must not have been initialized already.
+    pub unsafe fn init(&self, region: Region, start: *mut u8, len: usize) {
+        self.with(|state| {
+            state.regions[region as usize].init(start as usize, start as usize + len, &[]);
+        });
+    }
+
+    /// Allocate memory in a specific region. Returns `None` if the region is out of memory.
+    pub fn alloc_in(&self, region: Region, layout: Layout) -> Option<NonNull<u8>> {
+        let (size, align) = block_layout(layout);
+        self.with(|state| {
+            let ptr = state.regions[region as usize].alloc(size, align)?;
+            let used = state.regions.iter().map(|list| list.used).sum();
+            state.peak = state.peak.max(used);
+
+            Some(ptr)
+        })
+    }
+
+    /// Free memory allocated with [`Heap::alloc_in`] or the global allocator.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by this heap with the same layout, and not already freed.
+    pub unsafe fn free(&self, ptr: NonNull<u8>, layout: Layout) {
+        let (size, _) = block_layout(layout);
+        self.with(|state| {
+            let addr = ptr.as_ptr() as usize;
+            if let Some(list) = state.regions.iter_mut().find(|list| list.contains(addr)) {
+                list.dealloc(ptr, size);
+            }
+        });
+    }
+
+    /// Size of a region (in bytes), or zero if it has no memory
+    pub fn size(&self, region: Region) -> usize {
+        self.with(|state| state.regions[region as usize].size())
+    }
+
+    /// Number of bytes allocated in a region, including padding
+    pub fn used(&self, region: Region) -> usize {
+        self.with(|state| state.regions[region as usize].used)
+    }
+
+    /// Usage statistics over all regions
+    pub fn stats(&self) -> Stats {
+        self.with(|state| {
+            let mut stats = Stats {
+                peak: state.peak,
+                ..Stats::default()
+            };
+            for list in &state.regions {
+                stats.size += list.size();
+                stats.used += list.used;
+                stats.allocations += list.allocations;
+                stats.largest_free = stats.largest_free.max(list.largest_free());
+            }
+
+            stats
+        })
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        self.state.lock(|state| {
+            if !state.ready {
+                state.ready = true;
+                unsafe { platform::init(&mut state.regions) };
+            }
+
+            f(state)
+        })
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.alloc_in(Region::Base, layout)
+            .or_else(|| self.alloc_in(Region::Expansion, layout))
+            .map_or(ptr::null_mut(), NonNull::as_ptr)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if let Some(ptr) = NonNull::new(ptr) {
+            self.free(ptr, layout);
+        }
+    }
+}
+
+/// The error returned when an allocation fails.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("out of memory")
+    }
+}
+
+/// Move a value to the heap. Returns the value back if there is not enough memory.
+pub fn try_box<T>(value: T) -> Result<Box<T>, T> {
+    let layout = Layout::new::<T>();
+    if layout.size() == 0 {
+        return Ok(Box::new(value));
+    }
+
+    let ptr = unsafe { alloc::alloc::alloc(layout) }.cast::<T>();
+    if ptr.is_null() {
+        return Err(value);
+    }
+
+    unsafe {
+        ptr.write(value);
+        Ok(Box::from_raw(ptr))
+    }
+}
+
+/// Create an empty vector with room for at least `capacity` elements.
+pub fn try_vec_with_capacity<T>(capacity: usize) -> Result<Vec<T>, AllocError> {
+    let mut vec = Vec::new();
+    vec.try_reserve_exact(capacity).map_err(|_| AllocError)?;
+
+    Ok(vec)
+}
+
+/// Size and alignment of the block used for an allocation.
+fn block_layout(layout: Layout) -> (usize, usize) {
+    let size = align_up(layout.size().max(1), GRANULE);
+    let align = layout.align().max(GRANULE);
+
+    (size, align)
+}
+
+fn align_up(addr: usize, align: usize) -> usize {
+    (addr + align - 1) & !(align - 1)
+}
+
+#[cfg(rrt0_platform = "n64")]
+#[alloc_error_handler]
+fn alloc_error(layout: Layout) -> ! {
+    panic!("Out of memory allocating {} bytes", layout.size());
+}
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use super::FreeList;
+    use crate::layout;
+    use crate::mem::{detect_memory_size, map, RDRAM_SIZE};
+
+    /// Set by the entrypoint to the end of the program
+    const HEAP_START: *const usize = map::to_kseg0(map::HEAP_START) as *const usize;
+
+    const RDRAM_BASE: usize = map::to_kseg0(map::RDRAM);
+    const EXPANSION_START: usize = RDRAM_BASE + RDRAM_SIZE;
+
+    /// Split the memory between the program and the stack into the two regions, following the
+    /// memory layout.
+    pub(super) unsafe fn init(regions: &mut [FreeList; 2]) {
+        let layout = layout::get();
+        let start = HEAP_START.read_volatile();
+        let top = RDRAM_BASE + detect_memory_size() - layout.top_reserved - layout.stack_size;
+        let top = top.min(start.saturating_add(layout.heap_size));
+
+        regions[0].init(start, top.min(EXPANSION_START), layout.reserved);
+        if top > EXPANSION_START {
+            regions[1].init(EXPANSION_START, top, layout.reserved);
+        }
+    }
+}
+
+#[cfg(not(rrt0_platform = "n64"))]
+mod platform {
+    use super::FreeList;
+
+    pub(super) unsafe fn init(_regions: &mut [FreeList; 2]) {}
+}
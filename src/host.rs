@@ -0,0 +1,327 @@
+//! Host simulation, for iterating on game logic at desktop speed before testing on hardware.
+//!
+//! [`Host`] shows the frame buffers a program would hand to the VI on a [`Screen`], and maps keys
+//! to controller buttons. With the `window` feature, [`Window`] shows them in a desktop window
+//! drawn with `minifb`. Without it, the [`Terminal`] screen draws them in the terminal instead:
+//!
+//! ```text
+//! let mut host = Host::new(Window::new("game", 320, 240)?);
+//! while host.is_open() {
+//!     game.update(host.buttons());
+//!     host.present_rgba5551(&frame_buffer, 320);
+//! }
+//! ```
+//!
+//! The screen is an extension point, so programs can also implement it over another windowing
+//! crate, like `softbuffer`, by presenting the 0RGB pixels and reporting which [`Key`]s are down.
+//!
+//! `Host` is also a [`Backend`](crate::app::Backend) for the [game loop](crate::app), with a
+//! 60 Hz refresh simulated by sleeping and an RGBA5551 frame buffer of the size set with
+//! [`Host::with_resolution`], 320x240 by default.
+//!
+//! Text output goes to the real standard output with [`stdout`], e.g. as the output of the
+//! [test runner](crate::test::set_output).
+//!
+//! Only available on Unix and Windows, with the `host` feature.
+
+use crate::app::{
+    Backend, Frame, BUTTON_A, BUTTON_B, BUTTON_C_DOWN, BUTTON_C_LEFT, BUTTON_C_RIGHT, BUTTON_C_UP,
+    BUTTON_DOWN, BUTTON_L, BUTTON_LEFT, BUTTON_R, BUTTON_RIGHT, BUTTON_START, BUTTON_UP, BUTTON_Z,
+};
+use core::time::Duration;
+use std::io::Write;
+use std::time::Instant;
+use std::vec::Vec;
+
+/// Time between simulated vertical blanks
+const REFRESH_PERIOD: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Keys mapped to controller buttons.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    X,
+    C,
+    Z,
+    Enter,
+    A,
+    S,
+    I,
+    J,
+    K,
+    L,
+}
+
+/// Which button each key presses: arrows for the D-pad, X and C for A and B, Z, Enter for Start,
+/// A and S for the shoulder buttons, and IJKL for the C buttons
+const KEY_MAP: [(Key, u16); 14] = [
+    (Key::Up, BUTTON_UP),
+    (Key::Down, BUTTON_DOWN),
+    (Key::Left, BUTTON_LEFT),
+    (Key::Right, BUTTON_RIGHT),
+    (Key::X, BUTTON_A),
+    (Key::C, BUTTON_B),
+    (Key::Z, BUTTON_Z),
+    (Key::Enter, BUTTON_START),
+    (Key::A, BUTTON_L),
+    (Key::S, BUTTON_R),
+    (Key::I, BUTTON_C_UP),
+    (Key::K, BUTTON_C_DOWN),
+    (Key::J, BUTTON_C_LEFT),
+    (Key::L, BUTTON_C_RIGHT),
+];
+
+/// A desktop window, or anything else that can show frames.
+pub trait Screen {
+    /// Show a frame of 0RGB pixels.
+    fn present(&mut self, width: usize, height: usize, pixels: &[u32]);
+
+    /// Whether the screen is still open. The program usually stops when it is closed.
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn is_key_down(&self, _key: Key) -> bool {
+        false
+    }
+}
+
+/// Simulated display and controller, over a screen.
+#[derive(Debug)]
+pub struct Host<S> {
+    screen: S,
+    pixels: Vec<u32>,
+    /// Back buffer of the game loop backend
+    frame_buffer: Vec<u16>,
+    width: usize,
+    /// Time of the last simulated vertical blank
+    vsync: Option<Instant>,
+}
+
+impl<S: Screen> Host<S> {
+    pub fn new(screen: S) -> Self {
+        Self::with_resolution(screen, 320, 240)
+    }
+
+    /// Create a host whose game loop backend draws frames of `width` by `height` pixels.
+    pub fn with_resolution(screen: S, width: usize, height: usize) -> Self {
+        Self {
+            screen,
+            pixels: Vec::new(),
+            frame_buffer: std::vec![0; width * height],
+            width,
+            vsync: None,
+        }
+    }
+
+    pub fn screen(&self) -> &S {
+        &self.screen
+    }
+
+    pub fn screen_mut(&mut self) -> &mut S {
+        &mut self.screen
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.screen.is_open()
+    }
+
+    /// Show a 16-bit RGBA5551 frame buffer, the format of the VI in 16-bit mode.
+    pub fn present_rgba5551(&mut self, frame_buffer: &[u16], width: usize) {
+        let expand = |channel: u16| u32::from((channel << 3) | (channel >> 2));
+
+        self.pixels.clear();
+        self.pixels.extend(frame_buffer.iter().map(|pixel| {
+            let r = expand((pixel >> 11) & 0x1F);
+            let g = expand((pixel >> 6) & 0x1F);
+            let b = expand((pixel >> 1) & 0x1F);
+
+            r << 16 | g << 8 | b
+        }));
+        self.screen
+            .present(width, frame_buffer.len() / width, &self.pixels);
+    }
+
+    /// Show a 32-bit RGBA8888 frame buffer, the format of the VI in 32-bit mode.
+    pub fn present_rgba8888(&mut self, frame_buffer: &[u32], width: usize) {
+        self.pixels.clear();
+        self.pixels
+            .extend(frame_buffer.iter().map(|pixel| pixel >> 8));
+        self.screen
+            .present(width, frame_buffer.len() / width, &self.pixels);
+    }
+
+    /// The controller buttons held down, as `BUTTON_*` bits.
+    pub fn buttons(&self) -> u16 {
+        KEY_MAP
+            .iter()
+            .filter(|(key, _)| self.screen.is_key_down(*key))
+            .fold(0, |buttons, (_, button)| buttons | button)
+    }
+}
+
+impl<S: Screen> Backend for Host<S> {
+    type Pixel = u16;
+
+    fn refresh_period(&self) -> Duration {
+        REFRESH_PERIOD
+    }
+
+    fn wait_vsync(&mut self) -> u32 {
+        let now = Instant::now();
+        let last = self.vsync.unwrap_or(now);
+
+        // Sleep until the first vertical blank after now
+        let vsyncs = (now.duration_since(last).as_nanos() / REFRESH_PERIOD.as_nanos()) as u32 + 1;
+        let next = last + REFRESH_PERIOD * vsyncs;
+        std::thread::sleep(next.saturating_duration_since(now));
+        self.vsync = Some(next);
+
+        vsyncs
+    }
+
+    fn buttons(&mut self) -> u16 {
+        Host::buttons(self)
+    }
+
+    fn frame(&mut self) -> Frame<'_, u16> {
+        let height = self.frame_buffer.len() / self.width.max(1);
+        Frame::new(&mut self.frame_buffer, self.width, height)
+    }
+
+    fn swap(&mut self) {
+        let frame_buffer = core::mem::take(&mut self.frame_buffer);
+        self.present_rgba5551(&frame_buffer, self.width);
+        self.frame_buffer = frame_buffer;
+    }
+
+    fn is_open(&self) -> bool {
+        Host::is_open(self)
+    }
+}
+
+/// A screen drawn in the terminal with 24-bit color escape codes, two pixels per character. It
+/// has no keyboard input.
+#[derive(Debug)]
+pub struct Terminal {
+    /// Pixels per character column, and per half character row
+    scale: usize,
+}
+
+impl Terminal {
+    /// Create a screen showing every `scale` pixels, e.g. 4 to fit a 320 pixel wide frame in 80
+    /// columns.
+    pub fn new(scale: usize) -> Self {
+        Self {
+            scale: scale.max(1),
+        }
+    }
+}
+
+impl Screen for Terminal {
+    fn present(&mut self, width: usize, height: usize, pixels: &[u32]) {
+        let rgb = |pixel: u32| (pixel >> 16 & 0xFF, pixel >> 8 & 0xFF, pixel & 0xFF);
+        let mut out = Vec::new();
+
+        // Move to the top left, and draw the upper pixel as the foreground of a half block
+        out.extend_from_slice(b"\x1B[H");
+        for y in (0..height).step_by(self.scale * 2) {
+            for x in (0..width).step_by(self.scale) {
+                let (r1, g1, b1) = rgb(pixels[y * width + x]);
+                let lower = (y + self.scale).min(height - 1);
+                let (r2, g2, b2) = rgb(pixels[lower * width + x]);
+                std::write!(
+                    out,
+                    "\x1B[38;2;{};{};{}m\x1B[48;2;{};{};{}m\u{2580}",
+                    r1,
+                    g1,
+                    b1,
+                    r2,
+                    g2,
+                    b2
+                )
+                .ok();
+            }
+            out.extend_from_slice(b"\x1B[0m\n");
+        }
+
+        let mut stdout = std::io::stdout();
+        stdout.write_all(&out).ok();
+        stdout.flush().ok();
+    }
+}
+
+/// A desktop window scaled up to twice the frame size, with the `window` feature. Keys are read
+/// while it has focus.
+#[cfg(feature = "window")]
+pub struct Window(minifb::Window);
+
+#[cfg(feature = "window")]
+impl Window {
+    /// Open a window for frames of `width` by `height` pixels. It can be resized, and keeps the
+    /// aspect ratio of the frames.
+    pub fn new(title: &str, width: usize, height: usize) -> minifb::Result<Self> {
+        let options = minifb::WindowOptions {
+            resize: true,
+            scale: minifb::Scale::X2,
+            scale_mode: minifb::ScaleMode::AspectRatioStretch,
+            ..minifb::WindowOptions::default()
+        };
+        let mut window = minifb::Window::new(title, width, height, options)?;
+
+        // The host already waits for each simulated vertical blank
+        window.limit_update_rate(None);
+
+        Ok(Self(window))
+    }
+
+    pub fn window(&self) -> &minifb::Window {
+        &self.0
+    }
+
+    pub fn window_mut(&mut self) -> &mut minifb::Window {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "window")]
+impl Screen for Window {
+    fn present(&mut self, width: usize, height: usize, pixels: &[u32]) {
+        // Errors are only reported for buffers of the wrong size, which the host never passes
+        self.0.update_with_buffer(pixels, width, height).ok();
+    }
+
+    fn is_open(&self) -> bool {
+        self.0.is_open()
+    }
+
+    fn is_key_down(&self, key: Key) -> bool {
+        self.0.is_key_down(match key {
+            Key::Up => minifb::Key::Up,
+            Key::Down => minifb::Key::Down,
+            Key::Left => minifb::Key::Left,
+            Key::Right => minifb::Key::Right,
+            Key::X => minifb::Key::X,
+            Key::C => minifb::Key::C,
+            Key::Z => minifb::Key::Z,
+            Key::Enter => minifb::Key::Enter,
+            Key::A => minifb::Key::A,
+            Key::S => minifb::Key::S,
+            Key::I => minifb::Key::I,
+            Key::J => minifb::Key::J,
+            Key::K => minifb::Key::K,
+            Key::L => minifb::Key::L,
+        })
+    }
+}
+
+/// Write text to standard output, with the signature of an output function for the
+/// [test runner](crate::test::set_output).
+pub fn stdout(s: &str) {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(s.as_bytes()).ok();
+    stdout.flush().ok();
+}
@@ -0,0 +1,301 @@
+//! CPU interrupt control.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// Run a function with CPU interrupts disabled, restoring the previous state afterward. Keeps
+/// state shared with interrupt handlers consistent.
+pub fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+    let status = platform::disable();
+    let result = f();
+    platform::restore(status);
+
+    result
+}
+
+/// Data shared with interrupt handlers, only accessed with interrupts disabled.
+///
+/// On the consoles, which run the program on a single core, that alone keeps accesses from
+/// overlapping. On the host, where the program may have several threads, a spin lock is held as
+/// well. Locking it again from inside [`Mutex::lock`] panics on the consoles, and deadlocks on
+/// the host.
+pub struct Mutex<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+// Only accessed by one thread at a time, with interrupts disabled
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Run a function on the data, with interrupts disabled.
+    pub fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        without_interrupts(|| {
+            let guard = Guard::acquire(&self.locked);
+            let result = f(unsafe { &mut *self.value.get() });
+            drop(guard);
+
+            result
+        })
+    }
+
+    /// Get the data from a unique reference, which needs no lock.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+/// Holds the lock of a [`Mutex`], releasing it when dropped, including when unwinding.
+struct Guard<'a>(&'a AtomicBool);
+
+impl<'a> Guard<'a> {
+    #[cfg(any(unix, windows))]
+    fn acquire(locked: &'a AtomicBool) -> Self {
+        while locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+
+        Self(locked)
+    }
+
+    /// With interrupts disabled, the lock can only be held by the caller itself.
+    #[cfg(not(any(unix, windows)))]
+    fn acquire(locked: &'a AtomicBool) -> Self {
+        assert!(!locked.load(Ordering::Relaxed), "Mutex locked recursively");
+        locked.store(true, Ordering::Relaxed);
+
+        Self(locked)
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Add to a counter shared with interrupt handlers, returning its previous value.
+///
+/// Targets without atomic read-modify-write instructions, like the ARMv4T of the GBA, do it with
+/// interrupts disabled instead.
+pub(crate) fn fetch_add(counter: &AtomicU32, value: u32) -> u32 {
+    #[cfg(target_has_atomic = "32")]
+    return counter.fetch_add(value, Ordering::Relaxed);
+
+    #[cfg(not(target_has_atomic = "32"))]
+    return without_interrupts(|| {
+        let previous = counter.load(Ordering::Relaxed);
+        counter.store(previous.wrapping_add(value), Ordering::Relaxed);
+        previous
+    });
+}
+
+/// Set a flag shared with interrupt handlers, returning its previous value. Like [`fetch_add`],
+/// with interrupts disabled on targets without atomic read-modify-write instructions.
+pub(crate) fn swap(flag: &AtomicBool, value: bool) -> bool {
+    #[cfg(target_has_atomic = "8")]
+    return flag.swap(value, Ordering::Relaxed);
+
+    #[cfg(not(target_has_atomic = "8"))]
+    return without_interrupts(|| {
+        let previous = flag.load(Ordering::Relaxed);
+        flag.store(value, Ordering::Relaxed);
+        previous
+    });
+}
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use core::arch::asm;
+
+    /// Interrupt enable bit in the COP0 Status register
+    const STATUS_IE: u32 = 0x0001;
+
+    /// Clear the interrupt enable bit, returning the previous Status register.
+    pub fn disable() -> u32 {
+        let status: u32;
+        unsafe {
+            asm!("mfc0 $2, $12", out("$2") status, options(nomem, nostack));
+            asm!("mtc0 $2, $12", in("$2") status & !STATUS_IE, options(nostack));
+        }
+
+        status
+    }
+
+    pub fn restore(status: u32) {
+        unsafe { asm!("mtc0 $2, $12", in("$2") status, options(nostack)) };
+    }
+}
+
+#[cfg(rrt0_platform = "gba")]
+mod platform {
+    use core::sync::atomic::{compiler_fence, Ordering};
+
+    /// Interrupt master enable register
+    const IME: *mut u16 = 0x0400_0208 as *mut u16;
+
+    /// Clear the master enable, returning its previous value.
+    pub fn disable() -> u16 {
+        let ime = unsafe {
+            let ime = IME.read_volatile();
+            IME.write_volatile(0);
+
+            ime
+        };
+        // Volatile accesses alone don't keep other memory accesses from moving past them
+        compiler_fence(Ordering::SeqCst);
+
+        ime
+    }
+
+    pub fn restore(ime: u16) {
+        compiler_fence(Ordering::SeqCst);
+        unsafe { IME.write_volatile(ime) };
+    }
+}
+
+#[cfg(rrt0_platform = "n3ds")]
+mod platform {
+    use core::arch::asm;
+
+    /// Disable IRQs in the CPSR, returning its previous value.
+    pub fn disable() -> u32 {
+        let cpsr: u32;
+        unsafe { asm!("mrs {}, cpsr", "cpsid i", out(reg) cpsr, options(nostack)) };
+
+        cpsr
+    }
+
+    pub fn restore(cpsr: u32) {
+        unsafe { asm!("msr cpsr_c, {}", in(reg) cpsr, options(nostack)) };
+    }
+}
+
+#[cfg(rrt0_platform = "ps2")]
+mod platform {
+    use core::arch::asm;
+
+    /// Enable bit for all interrupts in the COP0 Status register of the Emotion Engine
+    const STATUS_EIE: u32 = 0x0001_0000;
+
+    /// Clear the interrupt enable bit, returning the previous Status register.
+    pub fn disable() -> u32 {
+        let status: u32;
+        unsafe {
+            asm!("mfc0 $2, $12", out("$2") status, options(nomem, nostack));
+            asm!("mtc0 $2, $12", "sync", in("$2") status & !STATUS_EIE, options(nostack));
+        }
+
+        status
+    }
+
+    pub fn restore(status: u32) {
+        unsafe { asm!("mtc0 $2, $12", "sync", in("$2") status, options(nostack)) };
+    }
+}
+
+#[cfg(target_os = "psp")]
+mod platform {
+    extern "C" {
+        fn sceKernelCpuSuspendIntr() -> u32;
+        fn sceKernelCpuResumeIntr(flags: u32);
+    }
+
+    /// Suspend interrupts through the kernel, returning the previous state.
+    pub fn disable() -> u32 {
+        unsafe { sceKernelCpuSuspendIntr() }
+    }
+
+    pub fn restore(flags: u32) {
+        unsafe { sceKernelCpuResumeIntr(flags) };
+    }
+}
+
+#[cfg(any(rrt0_platform = "dreamcast", rrt0_platform = "saturn"))]
+mod platform {
+    // Defined in the startup code
+    extern "C" {
+        fn rrt0_interrupts_disable() -> u32;
+        fn rrt0_interrupts_restore(status: u32);
+    }
+
+    /// Mask all interrupts in the SR register, returning its previous value.
+    pub fn disable() -> u32 {
+        unsafe { rrt0_interrupts_disable() }
+    }
+
+    pub fn restore(status: u32) {
+        unsafe { rrt0_interrupts_restore(status) };
+    }
+}
+
+#[cfg(all(
+    any(target_arch = "riscv32", target_arch = "riscv64"),
+    target_os = "none"
+))]
+mod platform {
+    use core::arch::asm;
+
+    /// Clear the interrupt enable bit in the status register, returning its previous value.
+    #[cfg(not(feature = "sbi"))]
+    pub fn disable() -> usize {
+        let status: usize;
+        unsafe { asm!("csrrci {}, mstatus, 0x8", out(reg) status, options(nostack)) };
+
+        status & 0x8
+    }
+
+    #[cfg(not(feature = "sbi"))]
+    pub fn restore(status: usize) {
+        if status != 0 {
+            unsafe { asm!("csrsi mstatus, 0x8", options(nostack)) };
+        }
+    }
+
+    /// Clear the interrupt enable bit in the status register, returning its previous value.
+    #[cfg(feature = "sbi")]
+    pub fn disable() -> usize {
+        let status: usize;
+        unsafe { asm!("csrrci {}, sstatus, 0x2", out(reg) status, options(nostack)) };
+
+        status & 0x2
+    }
+
+    #[cfg(feature = "sbi")]
+    pub fn restore(status: usize) {
+        if status != 0 {
+            unsafe { asm!("csrsi sstatus, 0x2", options(nostack)) };
+        }
+    }
+}
+
+#[cfg(not(any(
+    all(
+        any(target_arch = "riscv32", target_arch = "riscv64"),
+        target_os = "none"
+    ),
+    rrt0_platform = "n64",
+    rrt0_platform = "gba",
+    rrt0_platform = "n3ds",
+    rrt0_platform = "ps2",
+    target_os = "psp",
+    rrt0_platform = "dreamcast",
+    rrt0_platform = "saturn"
+)))]
+mod platform {
+    pub fn disable() -> u32 {
+        0
+    }
+
+    pub fn restore(_status: u32) {}
+}
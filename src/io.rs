@@ -0,0 +1,170 @@
+//! Traits for reading, writing, and seeking in streams of bytes.
+//!
+//! Code streaming assets or saves can be written once against these traits, and used with any
+//! backend: files in the [`romfs`](crate::romfs) or a [`fat`](crate::fat) volume, or byte slices in
+//! memory. They follow the `std::io` traits, except that each stream has its own error type.
+
+use core::convert::{Infallible, TryFrom};
+use core::fmt;
+
+/// The error type of a stream.
+pub trait ErrorType {
+    type Error: fmt::Debug;
+}
+
+/// Errors that can occur in [`Read::read_exact`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReadExactError<E> {
+    /// The stream ended before the buffer was filled.
+    UnexpectedEof,
+    Other(E),
+}
+
+impl<E: fmt::Display> fmt::Display for ReadExactError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof => f.write_str("unexpected end of stream"),
+            Self::Other(error) => error.fmt(f),
+        }
+    }
+}
+
+/// A stream that bytes can be read from.
+pub trait Read: ErrorType {
+    /// Read bytes into a buffer, returning how many were read. Returns 0 at the end of the stream.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+
+    /// Read exactly enough bytes to fill a buffer.
+    fn read_exact(&mut self, mut buffer: &mut [u8]) -> Result<(), ReadExactError<Self::Error>> {
+        while !buffer.is_empty() {
+            match self.read(buffer).map_err(ReadExactError::Other)? {
+                0 => return Err(ReadExactError::UnexpectedEof),
+                len => buffer = &mut buffer[len..],
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Errors that can occur in [`Write::write_all`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WriteAllError<E> {
+    /// The stream stopped accepting bytes before the whole buffer was written.
+    WriteZero,
+    Other(E),
+}
+
+impl<E: fmt::Display> fmt::Display for WriteAllError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WriteZero => f.write_str("stream is full"),
+            Self::Other(error) => error.fmt(f),
+        }
+    }
+}
+
+/// A stream that bytes can be written to.
+pub trait Write: ErrorType {
+    /// Write bytes from a buffer, returning how many were written.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, Self::Error>;
+
+    /// Write any buffered bytes to the backend.
+    fn flush(&mut self) -> Result<(), Self::Error>;
+
+    /// Write a whole buffer.
+    fn write_all(&mut self, mut buffer: &[u8]) -> Result<(), WriteAllError<Self::Error>> {
+        while !buffer.is_empty() {
+            match self.write(buffer).map_err(WriteAllError::Other)? {
+                0 => return Err(WriteAllError::WriteZero),
+                len => buffer = &buffer[len..],
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A position to seek to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SeekFrom {
+    /// Offset from the start of the stream
+    Start(u64),
+    /// Offset from the end of the stream
+    End(i64),
+    /// Offset from the current position
+    Current(i64),
+}
+
+/// A stream with a position that can be moved.
+pub trait Seek: ErrorType {
+    /// Move to a position, returning the new position from the start of the stream. Streams that
+    /// cannot move past their end stop at it.
+    fn seek(&mut self, position: SeekFrom) -> Result<u64, Self::Error>;
+
+    /// Move to the start of the stream.
+    fn rewind(&mut self) -> Result<(), Self::Error> {
+        self.seek(SeekFrom::Start(0))?;
+
+        Ok(())
+    }
+
+    /// Get the current position from the start of the stream.
+    fn stream_position(&mut self) -> Result<u64, Self::Error> {
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+impl<T: ErrorType + ?Sized> ErrorType for &mut T {
+    type Error = T::Error;
+}
+
+impl<T: Read + ?Sized> Read for &mut T {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        (**self).read(buffer)
+    }
+}
+
+impl<T: Write + ?Sized> Write for &mut T {
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, Self::Error> {
+        (**self).write(buffer)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        (**self).flush()
+    }
+}
+
+impl<T: Seek + ?Sized> Seek for &mut T {
+    fn seek(&mut self, position: SeekFrom) -> Result<u64, Self::Error> {
+        (**self).seek(position)
+    }
+}
+
+impl ErrorType for &[u8] {
+    type Error = Infallible;
+}
+
+/// Reading from a slice advances it past the bytes read.
+impl Read for &[u8] {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        let len = buffer.len().min(self.len());
+        let (data, rest) = self.split_at(len);
+        buffer[..len].copy_from_slice(data);
+        *self = rest;
+
+        Ok(len)
+    }
+}
+
+/// Resolve a seek in a stream of a length, stopping at the start and end.
+pub(crate) fn seek_position(position: SeekFrom, current: u64, len: u64) -> u64 {
+    let signed = |value: u64| i64::try_from(value).unwrap_or(i64::MAX);
+    let position = match position {
+        SeekFrom::Start(offset) => signed(offset),
+        SeekFrom::End(offset) => signed(len).saturating_add(offset),
+        SeekFrom::Current(offset) => signed(current).saturating_add(offset),
+    };
+
+    position.clamp(0, signed(len)) as u64
+}
@@ -0,0 +1,89 @@
+//! Memory layout configuration.
+//!
+//! A program declares how it uses RDRAM in one place with [`memory_layout!`](crate::memory_layout),
+//! and the runtime follows it: the entry point puts the stack below the memory reserved at the top
+//! of RDRAM, and the [`heap`](crate::heap) stays out of the stack, the reserved regions, and
+//! anything past its size limit.
+//!
+//! ```text
+//! rrt0::memory_layout!(MemoryLayout {
+//!     top_reserved: 2 * 320 * 240 * 2,
+//!     stack_size: 128 * 1024,
+//!     ..MemoryLayout::DEFAULT
+//! });
+//! ```
+//!
+//! Programs that do not declare a layout get [`MemoryLayout::DEFAULT`].
+
+/// Default size of the stack (in bytes)
+pub const DEFAULT_STACK_SIZE: usize = 64 * 1024;
+
+/// Declare the memory layout of the program. Use it once, at the top level of the program.
+#[macro_export]
+macro_rules! memory_layout {
+    ($layout:expr) => {
+        #[no_mangle]
+        #[used]
+        pub static RRT0_MEMORY_LAYOUT: $crate::layout::MemoryLayout = $layout;
+    };
+}
+
+/// How a program uses RDRAM.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+// The entry point reads `top_reserved` at offset 0
+#[repr(C)]
+pub struct MemoryLayout {
+    /// Memory reserved at the top of RDRAM, above the stack (in bytes), e.g. for frame buffers
+    pub top_reserved: usize,
+    /// Memory reserved for the stack, below `top_reserved` (in bytes)
+    pub stack_size: usize,
+    /// Most memory the heap uses (in bytes)
+    pub heap_size: usize,
+    /// Regions the heap does not use, e.g. for buffers at fixed addresses
+    pub reserved: &'static [Reserved],
+}
+
+/// A region of RDRAM reserved by the program.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Reserved {
+    pub start: usize,
+    /// Size (in bytes)
+    pub len: usize,
+}
+
+impl Reserved {
+    pub const fn new(start: usize, len: usize) -> Self {
+        Self { start, len }
+    }
+
+    pub fn end(&self) -> usize {
+        self.start + self.len
+    }
+}
+
+impl MemoryLayout {
+    /// A stack of [`DEFAULT_STACK_SIZE`] at the top of RDRAM, and the heap over all the rest.
+    pub const DEFAULT: Self = Self {
+        top_reserved: 0,
+        stack_size: DEFAULT_STACK_SIZE,
+        heap_size: usize::MAX,
+        reserved: &[],
+    };
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Get the memory layout declared with [`memory_layout!`](crate::memory_layout), or the default.
+pub fn get() -> &'static MemoryLayout {
+    extern "Rust" {
+        // Null when no layout is declared
+        #[linkage = "extern_weak"]
+        static RRT0_MEMORY_LAYOUT: *const MemoryLayout;
+    }
+
+    unsafe { RRT0_MEMORY_LAYOUT.as_ref() }.unwrap_or(&MemoryLayout::DEFAULT)
+}
@@ -0,0 +1,182 @@
+//! Allocation leak tracking.
+//!
+//! [`LeakTracker`] wraps an allocator and records every live allocation, so allocations that are
+//! never freed can be listed with [`LeakTracker::report`]. Allocations made inside
+//! [`LeakTracker::track`] are tagged with the source location it was called from.
+//!
+//! With the `leak-tracking` feature, the global heap is wrapped by a tracker, `heap::LEAKS`, on
+//! N64. [`LeakTracker::report_at_exit`] prints its report when `main` returns, or when the
+//! [test runner](crate::test) exits.
+
+use crate::interrupt::Mutex;
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt::{self, Write};
+use core::panic::Location;
+
+/// A live allocation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Allocation {
+    pub ptr: *mut u8,
+    /// Size (in bytes)
+    pub size: usize,
+    /// Where [`LeakTracker::track`] was called, if the allocation was made inside it
+    pub location: Option<&'static Location<'static>>,
+}
+
+struct State<const N: usize> {
+    records: [Option<Allocation>; N],
+    /// Location of the innermost [`LeakTracker::track`] call
+    location: Option<&'static Location<'static>>,
+    /// Number of live allocations that did not fit in `records`
+    untracked: usize,
+    /// Where to write the report at exit
+    exit_output: Option<fn(&str)>,
+}
+
+// The records are only compared with, never dereferenced
+unsafe impl<const N: usize> Send for State<N> {}
+
+/// An allocator wrapper that records up to `N` live allocations.
+pub struct LeakTracker<A: 'static, const N: usize> {
+    inner: &'static A,
+    state: Mutex<State<N>>,
+}
+
+impl<A: GlobalAlloc, const N: usize> LeakTracker<A, N> {
+    pub const fn new(inner: &'static A) -> Self {
+        Self {
+            inner,
+            state: Mutex::new(State {
+                records: [None; N],
+                location: None,
+                untracked: 0,
+                exit_output: None,
+            }),
+        }
+    }
+
+    /// Run a function, tagging the allocations it makes with the caller's location.
+    #[track_caller]
+    pub fn track<R>(&self, f: impl FnOnce() -> R) -> R {
+        let location = Location::caller();
+        let outer = self.with(|state| state.location.replace(location));
+        let result = f();
+        self.with(|state| state.location = outer);
+
+        result
+    }
+
+    /// Number of live allocations, including untracked ones
+    pub fn live(&self) -> usize {
+        self.with(|state| state.records.iter().flatten().count() + state.untracked)
+    }
+
+    /// Number of live allocations that were not recorded because the tracker was full
+    pub fn untracked(&self) -> usize {
+        self.with(|state| state.untracked)
+    }
+
+    /// Call a function for each recorded live allocation, in no particular order.
+    ///
+    /// The allocations are those live when it is called. `f` may allocate, but its own
+    /// allocations are not passed to it.
+    pub fn for_each(&self, f: impl FnMut(&Allocation)) {
+        // Copied out, so the state is not locked while `f` runs
+        let records = self.with(|state| state.records);
+        records.iter().flatten().for_each(f);
+    }
+
+    /// Write a line for each live allocation, followed by a summary.
+    pub fn report(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        let mut result = Ok(());
+        let mut count = 0;
+        let mut bytes = 0;
+        self.for_each(|allocation| {
+            count += 1;
+            bytes += allocation.size;
+            result = result.and_then(|_| {
+                write!(out, "{} bytes at {:p}", allocation.size, allocation.ptr)?;
+                match allocation.location {
+                    Some(location) => writeln!(out, " ({})", location),
+                    None => writeln!(out),
+                }
+            });
+        });
+        result?;
+
+        write!(out, "{} live allocations, {} bytes", count, bytes)?;
+        match self.untracked() {
+            0 => writeln!(out),
+            untracked => writeln!(out, ", {} untracked", untracked),
+        }
+    }
+
+    /// Write the report to an output when the program exits. `None` cancels it.
+    pub fn report_at_exit(&self, output: Option<fn(&str)>) {
+        self.with(|state| state.exit_output = output);
+    }
+
+    /// Write the report, if [`report_at_exit`](Self::report_at_exit) set an output. Called at
+    /// exit for `heap::LEAKS`; other trackers are reported by calling it.
+    pub fn report_exit(&self) {
+        struct Output(fn(&str));
+
+        impl Write for Output {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                (self.0)(s);
+                Ok(())
+            }
+        }
+
+        if let Some(output) = self.with(|state| state.exit_output.take()) {
+            let _ = self.report(&mut Output(output));
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut State<N>) -> R) -> R {
+        self.state.lock(f)
+    }
+}
+
+/// Write the report of the global tracker, if it has an output set. Called when the program
+/// exits.
+pub(crate) fn exit() {
+    #[cfg(all(feature = "leak-tracking", rrt0_platform = "n64"))]
+    crate::heap::LEAKS.report_exit();
+}
+
+unsafe impl<A: GlobalAlloc, const N: usize> GlobalAlloc for LeakTracker<A, N> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            self.with(|state| {
+                let allocation = Allocation {
+                    ptr,
+                    size: layout.size(),
+                    location: state.location,
+                };
+                match state.records.iter_mut().find(|record| record.is_none()) {
+                    Some(record) => *record = Some(allocation),
+                    None => state.untracked += 1,
+                }
+            });
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.with(|state| {
+            let record = state
+                .records
+                .iter_mut()
+                .find(|record| matches!(record, Some(allocation) if allocation.ptr == ptr));
+            match record {
+                Some(record) => *record = None,
+                None => state.untracked = state.untracked.saturating_sub(1),
+            }
+        });
+
+        self.inner.dealloc(ptr, layout);
+    }
+}
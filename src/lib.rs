@@ -1,13 +1,101 @@
-#![cfg_attr(target_vendor = "nintendo64", feature(asm_experimental_arch))]
+#![cfg_attr(
+    any(rrt0_platform = "n64", rrt0_platform = "ps2", target_os = "psp"),
+    feature(asm_experimental_arch)
+)]
+#![cfg_attr(
+    all(feature = "heap", rrt0_platform = "n64"),
+    feature(alloc_error_handler)
+)]
+#![feature(linkage)]
 #![no_std]
 
+#[cfg(feature = "heap")]
+extern crate alloc;
+#[cfg(all(feature = "host", any(unix, windows)))]
+extern crate std;
+
+pub mod app;
+pub mod arena;
+pub mod backtrace;
+pub mod bench;
+#[cfg(rrt0_platform = "n64")]
+pub mod boot;
+pub mod build;
+pub mod checksum;
+pub mod cic;
+pub mod collections;
+pub mod coprocessor;
+pub mod coverage;
+pub mod debug;
+pub mod decompress;
+#[cfg(rrt0_platform = "dreamcast")]
+pub mod dreamcast;
+pub mod easing;
+pub mod entropy;
+pub mod env;
+pub mod error;
+pub mod event;
+pub mod exception;
+#[cfg(feature = "fast-math")]
+pub mod fast;
+pub mod fat;
+pub mod fixed;
+pub mod fmt;
+#[cfg(rrt0_platform = "gba")]
+pub mod gba;
+pub mod header;
+#[cfg(feature = "heap")]
+pub mod heap;
+#[cfg(all(feature = "host", any(unix, windows)))]
+pub mod host;
+pub mod interrupt;
+pub mod io;
+pub mod layout;
+pub mod leak;
+#[cfg(rrt0_platform = "n64")]
+pub mod loader;
+pub mod locale;
+// compiler_builtins already provides the math functions on bare-metal ARM
+#[cfg(not(all(target_arch = "arm", target_os = "none")))]
 mod math;
+pub mod mem;
+pub mod mmio;
+#[cfg(rrt0_platform = "n64")]
+pub mod overlay;
+pub mod platform;
 mod platforms;
+pub mod pool;
 pub mod prelude;
+pub mod profile;
+#[cfg(rrt0_platform = "ps2")]
+pub mod ps2;
+#[cfg(target_os = "psp")]
+pub mod psp;
+pub mod rand;
+pub mod reload;
+#[cfg(all(
+    any(target_arch = "riscv32", target_arch = "riscv64"),
+    target_os = "none"
+))]
+pub mod riscv;
+pub mod romfs;
+#[cfg(rrt0_platform = "saturn")]
+pub mod saturn;
+pub mod scene;
+pub mod scheduler;
+pub mod sd;
+pub mod symbols;
+pub mod test;
+pub mod text;
+pub mod throttle;
+pub mod time;
+pub mod vector;
 
+pub use crate::build::build_info;
 pub use crate::platforms::*;
 
 #[no_mangle]
 fn panic_main() -> ! {
+    leak::exit();
     panic!("Main cannot return");
 }
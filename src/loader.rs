@@ -0,0 +1,159 @@
+//! Loading sub-programs from the cartridge ROM.
+//!
+//! A sub-program is linked for a fixed address range that the main program leaves unused, and
+//! stored in the ROM either as an ELF file ([`load_elf`]) or as a raw image ([`load_raw`]). Loading
+//! copies it into RDRAM, clears its `.bss`, and keeps the caches coherent. [`Program::run`] then
+//! calls its entry point as a function, so control returns to the caller when it returns.
+//!
+//! Position-independent sub-programs are not relocated, so they must also be loaded at the address
+//! they were linked for.
+//!
+//! Only available on N64.
+
+use crate::mem::{invalidate_icache, read_rom, writeback_dcache};
+use core::fmt;
+
+/// Errors that can occur while loading a sub-program.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LoadError {
+    /// The data is not an ELF file.
+    Invalid,
+    /// The ELF file is not a 32-bit big-endian MIPS executable.
+    Unsupported,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Invalid => "not an ELF file",
+            Self::Unsupported => "not a 32-bit big-endian MIPS executable",
+        })
+    }
+}
+
+/// A loaded sub-program.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Program {
+    entry: usize,
+}
+
+/// ELF header size (in bytes)
+const ELF_HEADER_SIZE: usize = 52;
+/// ELF program header size (in bytes)
+const PROGRAM_HEADER_SIZE: usize = 32;
+/// Program header type of a loadable segment
+const PT_LOAD: u32 = 1;
+/// ELF file type of an executable
+const ET_EXEC: u16 = 2;
+/// ELF machine type for MIPS
+const EM_MIPS: u16 = 8;
+
+/// Load an ELF executable stored at a ROM offset, copying each loadable segment to its address.
+///
+/// # Safety
+///
+/// The segments must not overlap any memory in use, including the running program and its stack.
+pub unsafe fn load_elf(rom_offset: u32) -> Result<Program, LoadError> {
+    let mut header = [0; ELF_HEADER_SIZE];
+    read_rom(&mut header, rom_offset);
+    if header[..4] != *b"\x7fELF" {
+        return Err(LoadError::Invalid);
+    }
+    if header[4] != 1
+        || header[5] != 2
+        || read_u16(&header, 16) != ET_EXEC
+        || read_u16(&header, 18) != EM_MIPS
+    {
+        return Err(LoadError::Unsupported);
+    }
+
+    let entry = read_u32(&header, 24) as usize;
+    let table = read_u32(&header, 28);
+    let size = read_u16(&header, 42) as u32;
+    let count = read_u16(&header, 44) as u32;
+    if count > 0 && size < PROGRAM_HEADER_SIZE as u32 {
+        return Err(LoadError::Invalid);
+    }
+
+    for index in 0..count {
+        let position = index
+            .checked_mul(size)
+            .and_then(|position| position.checked_add(table))
+            .and_then(|position| position.checked_add(rom_offset))
+            .ok_or(LoadError::Invalid)?;
+        let mut segment = [0; PROGRAM_HEADER_SIZE];
+        read_rom(&mut segment, position);
+        if read_u32(&segment, 0) != PT_LOAD {
+            continue;
+        }
+
+        let offset = read_u32(&segment, 4)
+            .checked_add(rom_offset)
+            .ok_or(LoadError::Invalid)?;
+        let address = read_u32(&segment, 8) as usize;
+        let file_size = read_u32(&segment, 16) as usize;
+        let memory_size = read_u32(&segment, 20) as usize;
+        copy_segment(offset, address, file_size, memory_size);
+    }
+
+    Ok(Program { entry })
+}
+
+/// Load a raw image stored at a ROM offset to an address, with its entry point at the start.
+///
+/// # Safety
+///
+/// The image must not overlap any memory in use, including the running program and its stack.
+pub unsafe fn load_raw(rom_offset: u32, len: usize, address: usize) -> Program {
+    copy_segment(rom_offset, address, len, len);
+
+    Program { entry: address }
+}
+
+/// Copy `file_size` bytes from the ROM to an address and clear the rest of `memory_size`.
+pub(crate) unsafe fn copy_segment(
+    rom_offset: u32,
+    address: usize,
+    file_size: usize,
+    memory_size: usize,
+) {
+    if memory_size == 0 {
+        return;
+    }
+
+    let memory = core::slice::from_raw_parts_mut(address as *mut u8, memory_size);
+    let (file, bss) = memory.split_at_mut(file_size.min(memory_size));
+    read_rom(file, rom_offset);
+    bss.fill(0);
+
+    writeback_dcache(memory.as_ptr(), memory.len());
+    invalidate_icache(memory.as_ptr(), memory.len());
+}
+
+impl Program {
+    /// Address of the entry point
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// Call the entry point as an `extern "C" fn(usize) -> usize`, returning its result.
+    ///
+    /// # Safety
+    ///
+    /// The program must still be loaded, and its entry point must have that signature.
+    pub unsafe fn run(&self, arg: usize) -> usize {
+        let entry: extern "C" fn(usize) -> usize = core::mem::transmute(self.entry);
+
+        entry(arg)
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let bytes = &bytes[offset..offset + 4];
+
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
@@ -0,0 +1,206 @@
+//! Translated strings.
+//!
+//! Text shown to the player is looked up by key with [`tr!`](crate::tr) in the catalog of the
+//! current language, instead of being written as literals across the program:
+//!
+//! ```text
+//! rrt0::string_table! {
+//!     "en" => "lang/en.txt",
+//!     "fr" => "lang/fr.txt",
+//! }
+//!
+//! locale::set_language("fr");
+//! draw_text(8, 8, tr!("menu.start"));
+//! ```
+//!
+//! A catalog has one `key = value` line per string. Blank lines and lines starting with `#` are
+//! ignored, and spaces around keys and values are trimmed:
+//!
+//! ```text
+//! # Main menu
+//! menu.start = Démarrer
+//! menu.options = Options
+//! ```
+//!
+//! The catalogs declared with [`string_table!`](crate::string_table) are included in the program
+//! at compile time, and the first is the fallback for keys missing from the others. Catalogs can
+//! also stay in the ROM, e.g. as files in the [`romfs`](crate::romfs), and be loaded on demand
+//! into a static buffer with [`Catalog::read`], then installed with [`set_catalog`]:
+//!
+//! ```text
+//! static mut CATALOG: [u8; 16 * 1024] = [0; 16 * 1024];
+//!
+//! let mut file = romfs::open("lang/de.txt")?;
+//! let catalog = Catalog::read(&mut file, unsafe { &mut CATALOG }).ok_or(Error::Catalog)?;
+//! locale::set_catalog(Some(catalog));
+//! ```
+//!
+//! Keys missing from every catalog are shown as themselves.
+//!
+//! Lookups scan the catalog line by line, so text drawn every frame is best looked up once.
+
+use crate::interrupt::Mutex;
+use crate::io;
+
+/// Declare the catalog of each language, from files relative to the crate root. Use it once, at
+/// the top level of the program.
+#[macro_export]
+macro_rules! string_table {
+    ($($language:literal => $path:literal),+ $(,)?) => {
+        #[no_mangle]
+        #[used]
+        pub static RRT0_STRING_TABLE: $crate::locale::StringTable = $crate::locale::StringTable(&[
+            $($crate::locale::Language {
+                name: $language,
+                catalog: $crate::locale::Catalog::new(::core::include_str!(::core::concat!(
+                    ::core::env!("CARGO_MANIFEST_DIR"),
+                    "/",
+                    $path,
+                ))),
+            }),+
+        ]);
+    };
+}
+
+/// Look up the translation of a key in the current language.
+///
+/// e.g. `tr!("menu.start")`
+#[macro_export]
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::locale::tr($key)
+    };
+}
+
+/// The text of a catalog, with one `key = value` line per string.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Catalog<'a> {
+    text: &'a str,
+}
+
+impl<'a> Catalog<'a> {
+    pub const fn new(text: &'a str) -> Self {
+        Self { text }
+    }
+
+    /// Read a catalog to the end of a stream, into a buffer it is then kept in.
+    ///
+    /// Returns `None` if reading fails, the catalog does not fit in the buffer, or it is not valid
+    /// UTF-8.
+    pub fn read<R: io::Read>(reader: &mut R, buffer: &'a mut [u8]) -> Option<Self> {
+        let mut len = 0;
+        loop {
+            match reader.read(&mut buffer[len..]).ok()? {
+                0 => break,
+                read => len += read,
+            }
+            if len == buffer.len() {
+                // Full, so the catalog only fits if the stream ends here
+                if reader.read(&mut [0]).ok()? != 0 {
+                    return None;
+                }
+                break;
+            }
+        }
+
+        let buffer: &'a [u8] = buffer;
+        core::str::from_utf8(&buffer[..len]).ok().map(Self::new)
+    }
+
+    /// Look up the value of a key.
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.entries()
+            .find(|(entry, _)| *entry == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Iterate over the keys and values, in the order of the text.
+    pub fn entries(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.text.lines().filter_map(|line| {
+            let line = line.trim();
+            if line.starts_with('#') {
+                return None;
+            }
+            let (key, value) = line.split_once('=')?;
+
+            Some((key.trim(), value.trim()))
+        })
+    }
+}
+
+/// A language, named e.g. by its ISO 639-1 code, and its catalog.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Language {
+    pub name: &'static str,
+    pub catalog: Catalog<'static>,
+}
+
+/// The languages declared with [`string_table!`](crate::string_table).
+#[derive(Debug)]
+pub struct StringTable(pub &'static [Language]);
+
+struct LocaleState {
+    /// Index of the current language in the string table
+    language: usize,
+    /// Catalog installed with `set_catalog`, used before the string table
+    catalog: Option<Catalog<'static>>,
+}
+
+static LOCALE: Mutex<LocaleState> = Mutex::new(LocaleState {
+    language: 0,
+    catalog: None,
+});
+
+fn with_locale<R>(f: impl FnOnce(&mut LocaleState) -> R) -> R {
+    LOCALE.lock(f)
+}
+
+/// The languages declared with [`string_table!`](crate::string_table), if any.
+pub fn languages() -> &'static [Language] {
+    extern "Rust" {
+        // Null when no string table is declared
+        #[linkage = "extern_weak"]
+        static RRT0_STRING_TABLE: *const StringTable;
+    }
+
+    unsafe { RRT0_STRING_TABLE.as_ref() }.map_or(&[], |table| table.0)
+}
+
+/// The current language, if any are declared.
+pub fn language() -> Option<&'static Language> {
+    languages().get(with_locale(|locale| locale.language))
+}
+
+/// Switch to a language by name. Returns `false` if it is not declared.
+pub fn set_language(name: &str) -> bool {
+    match languages()
+        .iter()
+        .position(|language| language.name == name)
+    {
+        Some(index) => {
+            with_locale(|locale| locale.language = index);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Install a catalog loaded at runtime, which is searched before the string table. `None` removes
+/// it.
+pub fn set_catalog(catalog: Option<Catalog<'static>>) {
+    with_locale(|locale| locale.catalog = catalog);
+}
+
+/// Look up the translation of a key: in the installed catalog, then the current language, then
+/// the first language. Returns the key if none has it.
+pub fn tr(key: &'static str) -> &'static str {
+    let (catalog, language) = with_locale(|locale| (locale.catalog, locale.language));
+    let languages = languages();
+
+    catalog
+        .into_iter()
+        .chain(languages.get(language).map(|language| language.catalog))
+        .chain(languages.first().map(|language| language.catalog))
+        .find_map(|catalog| catalog.get(key))
+        .unwrap_or(key)
+}
@@ -46,4 +46,34 @@ no_mangle! {
     fn fmodf(x: f32, y: f32) -> f32;
     fn fma(x: f64, y: f64, z: f64) -> f64;
     fn fmaf(x: f32, y: f32, z: f32) -> f32;
+    fn acosf(x: f32) -> f32;
+    fn asinf(x: f32) -> f32;
+    fn atanf(x: f32) -> f32;
+    fn atan2f(x: f32, y: f32) -> f32;
+    fn cbrtf(x: f32) -> f32;
+    fn coshf(x: f32) -> f32;
+    fn expm1f(x: f32) -> f32;
+    fn hypotf(x: f32, y: f32) -> f32;
+    fn log1pf(x: f32) -> f32;
+    fn sinhf(x: f32) -> f32;
+    fn tanf(x: f32) -> f32;
+    fn tanhf(x: f32) -> f32;
+    fn sqrt(x: f64) -> f64;
+    fn sqrtf(x: f32) -> f32;
+    fn floor(x: f64) -> f64;
+    fn floorf(x: f32) -> f32;
+    fn ceil(x: f64) -> f64;
+    fn ceilf(x: f32) -> f32;
+    fn trunc(x: f64) -> f64;
+    fn truncf(x: f32) -> f32;
+    fn fabs(x: f64) -> f64;
+    fn fabsf(x: f32) -> f32;
+    fn fmin(x: f64, y: f64) -> f64;
+    fn fminf(x: f32, y: f32) -> f32;
+    fn fmax(x: f64, y: f64) -> f64;
+    fn fmaxf(x: f32, y: f32) -> f32;
+    fn copysign(x: f64, y: f64) -> f64;
+    fn copysignf(x: f32, y: f32) -> f32;
+    fn ldexp(x: f64, n: i32) -> f64;
+    fn ldexpf(x: f32, n: i32) -> f32;
 }
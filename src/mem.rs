@@ -0,0 +1,612 @@
+//! Memory utilities.
+//!
+//! Bulk copies with DMA, cache-line aligned [`DmaBuffer`]s, and data cache maintenance for
+//! sharing memory with the RCP. [`Cached`] and [`Uncached`] pointers keep track of which segment
+//! memory is accessed through, so shared memory is only accessed the way its segment needs, and
+//! cached memory only gives out its physical address for DMA once it is written back. Addresses
+//! are KSEG0 (cached) virtual addresses, as used by ordinary Rust references.
+//!
+//! [`map`] names the regions of the N64 memory map, and converts addresses between segments.
+//!
+//! On platforms without DMA these fall back to CPU copies, and cache maintenance does nothing.
+
+pub mod map;
+
+use core::ptr::{self, NonNull};
+
+/// Copies smaller than this (in bytes) are always done by the CPU, since setting up a DMA
+/// transfer costs more than it saves.
+pub const DMA_THRESHOLD: usize = 256;
+
+/// Data cache line size (in bytes)
+pub const DCACHE_LINE_SIZE: usize = 16;
+
+/// Instruction cache line size (in bytes)
+pub const ICACHE_LINE_SIZE: usize = 32;
+
+/// Size of the RDRAM built into the console (in bytes)
+pub const RDRAM_SIZE: usize = 0x40_0000;
+
+/// Size of RDRAM with an Expansion Pak installed (in bytes)
+pub const EXPANDED_RDRAM_SIZE: usize = 0x80_0000;
+
+/// Detect the installed RDRAM size (in bytes), either [`RDRAM_SIZE`] or [`EXPANDED_RDRAM_SIZE`].
+///
+/// The size is normally read from `osMemSize`, which the boot code stores in low memory. Loaders
+/// that leave it unset are handled by probing for memory beyond the first 4 MiB instead.
+#[cfg(rrt0_platform = "n64")]
+pub fn detect_memory_size() -> usize {
+    match platform::os_mem_size() {
+        size @ (RDRAM_SIZE | EXPANDED_RDRAM_SIZE) => size,
+        _ if platform::probe_expansion() => EXPANDED_RDRAM_SIZE,
+        _ => RDRAM_SIZE,
+    }
+}
+
+/// A value aligned to and padded to whole data cache lines, for sharing with the RCP by DMA.
+///
+/// Since no other data shares its cache lines, the cache can be written back or invalidated
+/// without affecting anything else, and the alignment satisfies both SP and PI DMA.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, align(16))]
+pub struct DmaBuffer<T> {
+    value: T,
+}
+
+impl<T> DmaBuffer<T> {
+    pub const fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        &self.value
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        &mut self.value
+    }
+
+    /// The value through the uncached KSEG1 segment, after writing it back and discarding it from
+    /// the cache.
+    pub fn to_uncached(&mut self) -> Uncached<T> {
+        Cached::new(self.as_mut_ptr()).to_uncached()
+    }
+
+    fn cached(&self) -> Cached<Self> {
+        Cached::new(self as *const Self as *mut Self)
+    }
+
+    /// Write the value back to RDRAM, e.g. before the RCP reads it. Returns its physical address,
+    /// for the RCP to read it from.
+    pub fn writeback(&self) -> u32 {
+        self.cached().writeback()
+    }
+
+    /// Discard the cached value, e.g. after the RCP writes it. Any changes made by the CPU that
+    /// were not written back are lost.
+    ///
+    /// # Safety
+    ///
+    /// The value is read from RDRAM afterward, so RDRAM must hold a valid `T`, e.g. one written
+    /// by the RCP or written back by the CPU before.
+    pub unsafe fn invalidate(&mut self) {
+        // The buffer covers whole cache lines, so no other data is affected
+        self.cached().invalidate();
+    }
+
+    /// Write the value back to RDRAM and discard it from the cache, e.g. before the RCP writes it.
+    /// Returns its physical address, for the RCP to write to.
+    pub fn writeback_invalidate(&mut self) -> u32 {
+        self.cached().writeback_invalidate()
+    }
+}
+
+impl<T> core::ops::Deref for DmaBuffer<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> core::ops::DerefMut for DmaBuffer<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+/// A pointer to a `T` through the cached KSEG0 segment, for memory only the CPU uses, or that is
+/// written back before the RCP reads it.
+///
+/// Accesses are ordinary loads and stores, which may be served from the data cache. Use
+/// [`Uncached`] for memory the RCP reads or writes while the CPU uses it.
+///
+/// The physical address, for DMA, is only returned by the methods writing the cache back, so the
+/// RCP never reads stale data from RDRAM.
+#[derive(Debug, Eq, Hash, PartialEq)]
+pub struct Cached<T: ?Sized>(*mut T);
+
+/// A pointer to a `T` through the uncached KSEG1 segment, for memory shared with the RCP.
+///
+/// Accesses are volatile, and bypass the data cache, so they are seen by the RCP immediately and
+/// see what the RCP wrote.
+#[derive(Debug, Eq, Hash, PartialEq)]
+pub struct Uncached<T>(*mut T);
+
+// Not derived, which would require `T: Copy`
+impl<T: ?Sized> Clone for Cached<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Cached<T> {}
+
+impl<T> Clone for Uncached<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Uncached<T> {}
+
+impl<T: ?Sized> Cached<T> {
+    pub fn as_ptr(self) -> *mut T {
+        self.0
+    }
+
+    /// Physical address, as used by DMA registers
+    fn physical_addr(self) -> u32 {
+        map::physical(self.0.cast::<u8>() as usize) as u32
+    }
+}
+
+impl<T> Cached<T> {
+    /// Point to an address in KSEG0 or KSEG1 through KSEG0.
+    pub fn new(ptr: *mut T) -> Self {
+        Self(platform::cached(ptr))
+    }
+
+    /// Point to the `count`-th next `T`.
+    ///
+    /// # Safety
+    ///
+    /// As for the `add` method of raw pointers.
+    pub unsafe fn add(self, count: usize) -> Self {
+        Self(self.0.add(count))
+    }
+
+    /// # Safety
+    ///
+    /// As for the `read` method of raw pointers.
+    pub unsafe fn read(self) -> T {
+        self.0.read()
+    }
+
+    /// # Safety
+    ///
+    /// As for the `write` method of raw pointers.
+    pub unsafe fn write(self, value: T) {
+        self.0.write(value)
+    }
+
+    /// # Safety
+    ///
+    /// As for the `as_mut` method of raw pointers, and the value must be valid.
+    pub unsafe fn as_mut(&mut self) -> &mut T {
+        &mut *self.0
+    }
+
+    /// Write the value back to RDRAM, e.g. before the RCP reads it. Returns its physical address,
+    /// for the RCP to read it from.
+    pub fn writeback(self) -> u32 {
+        writeback_dcache(self.0.cast(), core::mem::size_of::<T>());
+        self.physical_addr()
+    }
+
+    /// Write the value back to RDRAM and discard it from the cache, e.g. before the RCP writes it.
+    /// Returns its physical address, for the RCP to write to.
+    pub fn writeback_invalidate(self) -> u32 {
+        writeback_invalidate_dcache(self.0.cast(), core::mem::size_of::<T>());
+        self.physical_addr()
+    }
+
+    /// Discard the cached value, e.g. after the RCP writes it.
+    ///
+    /// # Safety
+    ///
+    /// As for [`invalidate_dcache`].
+    pub unsafe fn invalidate(self) {
+        invalidate_dcache(self.0.cast(), core::mem::size_of::<T>());
+    }
+
+    /// The same value through KSEG1. It is written back and discarded from the cache first, so
+    /// uncached accesses don't see stale data, and stale cache lines don't overwrite them later.
+    pub fn to_uncached(self) -> Uncached<T> {
+        writeback_invalidate_dcache(self.0.cast(), core::mem::size_of::<T>());
+        Uncached(platform::uncached(self.0))
+    }
+}
+
+impl<T> Cached<[T]> {
+    /// Point to `len` values at an address in KSEG0 or KSEG1 through KSEG0.
+    pub fn new_slice(ptr: *mut T, len: usize) -> Self {
+        Self(ptr::slice_from_raw_parts_mut(platform::cached(ptr), len))
+    }
+
+    /// Number of values
+    pub fn len(self) -> usize {
+        NonNull::new(self.0).map_or(0, |ptr| ptr.len())
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    fn size(self) -> usize {
+        self.len() * core::mem::size_of::<T>()
+    }
+
+    /// Write the values back to RDRAM, e.g. before the RCP reads them. Returns their physical
+    /// address, for the RCP to read them from.
+    pub fn writeback(self) -> u32 {
+        writeback_dcache(self.0.cast(), self.size());
+        self.physical_addr()
+    }
+
+    /// Write the values back to RDRAM and discard them from the cache, e.g. before the RCP writes
+    /// them. Returns their physical address, for the RCP to write to.
+    pub fn writeback_invalidate(self) -> u32 {
+        writeback_invalidate_dcache(self.0.cast(), self.size());
+        self.physical_addr()
+    }
+
+    /// Discard the cached values, e.g. after the RCP writes them.
+    ///
+    /// # Safety
+    ///
+    /// As for [`invalidate_dcache`].
+    pub unsafe fn invalidate(self) {
+        invalidate_dcache(self.0.cast(), self.size());
+    }
+}
+
+impl<T> Uncached<T> {
+    /// Point to an address in KSEG0 or KSEG1 through KSEG1. The caller is responsible for the
+    /// cache lines covering it: see [`Cached::to_uncached`].
+    pub fn new(ptr: *mut T) -> Self {
+        Self(platform::uncached(ptr))
+    }
+
+    pub fn as_ptr(self) -> *mut T {
+        self.0
+    }
+
+    /// Physical address, as used by DMA registers
+    pub fn physical_addr(self) -> u32 {
+        map::physical(self.0 as usize) as u32
+    }
+
+    /// Point to the `count`-th next `T`.
+    ///
+    /// # Safety
+    ///
+    /// As for the `add` method of raw pointers.
+    pub unsafe fn add(self, count: usize) -> Self {
+        Self(self.0.add(count))
+    }
+
+    /// # Safety
+    ///
+    /// As for the `read_volatile` method of raw pointers.
+    pub unsafe fn read(self) -> T {
+        self.0.read_volatile()
+    }
+
+    /// # Safety
+    ///
+    /// As for the `write_volatile` method of raw pointers.
+    pub unsafe fn write(self, value: T) {
+        self.0.write_volatile(value)
+    }
+
+    /// The same value through KSEG0, discarding any stale cache lines covering it first.
+    ///
+    /// # Safety
+    ///
+    /// As for [`invalidate_dcache`].
+    pub unsafe fn to_cached(self) -> Cached<T> {
+        let cached = platform::cached(self.0);
+        invalidate_dcache(cached.cast(), core::mem::size_of::<T>());
+        Cached(cached)
+    }
+}
+
+/// Copy `src` to `dst` within RDRAM, with SP DMA for large 8-byte aligned copies. Only the whole
+/// data cache lines of `dst` are transferred, and the bytes around them are copied by the CPU.
+///
+/// DMA transfers are staged through the RSP's DMEM, so they are only used when the RSP is
+/// halted. The previous contents of DMEM are lost.
+///
+/// # Panics
+///
+/// Panics if the slices have different lengths.
+pub fn dma_copy(dst: &mut [u8], src: &[u8]) {
+    assert_eq!(dst.len(), src.len(), "Slice lengths must match");
+
+    let aligned = (dst.as_ptr() as usize | src.as_ptr() as usize) % 8 == 0;
+    if !aligned || dst.len() < DMA_THRESHOLD || !platform::rsp_halted() {
+        dst.copy_from_slice(src);
+        return;
+    }
+
+    // Both are 8-byte aligned, so src stays aligned at the first whole cache line of dst
+    let head = dst.as_ptr().align_offset(DCACHE_LINE_SIZE).min(dst.len());
+    let len = (dst.len() - head) & !(DCACHE_LINE_SIZE - 1);
+    let (dst_head, dst) = dst.split_at_mut(head);
+    let (dst, dst_tail) = dst.split_at_mut(len);
+    let (src_head, src) = src.split_at(head);
+    let (src, src_tail) = src.split_at(len);
+
+    dst_head.copy_from_slice(src_head);
+    dst_tail.copy_from_slice(src_tail);
+
+    let src = Cached::new_slice(src.as_ptr() as *mut u8, len).writeback();
+    let dst = Cached::new_slice(dst.as_mut_ptr(), len);
+    platform::sp_dma_copy(dst.writeback_invalidate(), src, len);
+
+    // The lines only hold dst, so discarding any cached during the transfer loses nothing
+    unsafe { dst.invalidate() };
+}
+
+/// Copy bytes from the cartridge ROM at the given offset into `dst`, with PI DMA for large
+/// copies. Only the whole data cache lines of `dst` are transferred, and the bytes around them
+/// are read by the CPU.
+#[cfg(rrt0_platform = "n64")]
+pub fn read_rom(dst: &mut [u8], offset: u32) {
+    // Read up to the first whole cache line in dst with the CPU
+    let head = dst.as_ptr().align_offset(DCACHE_LINE_SIZE).min(dst.len());
+    let (head, dst) = dst.split_at_mut(head);
+    platform::read_rom_cpu(head, offset);
+    let offset = offset + head.len() as u32;
+
+    // The PI needs an even ROM address and length
+    let len = dst.len() & !(DCACHE_LINE_SIZE - 1);
+    if len < DMA_THRESHOLD || offset % 2 != 0 {
+        platform::read_rom_cpu(dst, offset);
+        return;
+    }
+
+    let (dst, tail) = dst.split_at_mut(len);
+    platform::read_rom_cpu(tail, offset + len as u32);
+
+    let dst = Cached::new_slice(dst.as_mut_ptr(), len);
+    platform::pi_dma_read(dst.writeback_invalidate(), offset, len);
+
+    // As in `dma_copy`, no other data shares these lines
+    unsafe { dst.invalidate() };
+}
+
+/// Write dirty data cache lines covering a range back to RDRAM, e.g. before the RCP reads it.
+pub fn writeback_dcache(ptr: *const u8, len: usize) {
+    platform::cache_op::<{ platform::HIT_WRITEBACK_D }>(ptr, len);
+}
+
+/// Discard data cache lines covering a range without writing them back, e.g. after the RCP
+/// writes it.
+///
+/// # Safety
+///
+/// Any unwritten changes in the covered cache lines are lost, including bytes outside of the
+/// range that share a line with it.
+pub unsafe fn invalidate_dcache(ptr: *const u8, len: usize) {
+    platform::cache_op::<{ platform::HIT_INVALIDATE_D }>(ptr, len);
+}
+
+/// Write back and discard data cache lines covering a range, e.g. before the RCP writes it.
+pub fn writeback_invalidate_dcache(ptr: *const u8, len: usize) {
+    platform::cache_op::<{ platform::HIT_WRITEBACK_INVALIDATE_D }>(ptr, len);
+}
+
+/// Discard instruction cache lines covering a range, e.g. after writing code to memory. The code
+/// must be written back from the data cache first.
+pub fn invalidate_icache(ptr: *const u8, len: usize) {
+    platform::invalidate_icache(ptr, len);
+}
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use super::{map, DCACHE_LINE_SIZE, EXPANDED_RDRAM_SIZE, ICACHE_LINE_SIZE, RDRAM_SIZE};
+    use crate::mmio::{PiStatusWrite, PI, SP};
+    use core::arch::asm;
+
+    pub const HIT_INVALIDATE_D: u32 = 0x11;
+    pub const HIT_WRITEBACK_INVALIDATE_D: u32 = 0x15;
+    pub const HIT_WRITEBACK_D: u32 = 0x19;
+
+    const DMEM_SIZE: usize = map::SP_MEM_SIZE;
+
+    /// Boot value holding the installed RDRAM size
+    const OS_MEM_SIZE: *const usize = map::to_kseg0(map::OS_MEM_SIZE) as *const usize;
+
+    // The probed words are above the initial stack pointer, so they are never in use
+
+    /// Uncached address of the last word in the Expansion Pak
+    const EXPANSION_PROBE: *mut u32 =
+        map::to_kseg1(map::RDRAM + EXPANDED_RDRAM_SIZE - 4) as *mut u32;
+    /// Uncached address of the last word in the built-in RDRAM
+    const BASE_PROBE: *mut u32 = map::to_kseg1(map::RDRAM + RDRAM_SIZE - 4) as *mut u32;
+
+    /// Uncached address of the start of the cartridge ROM
+    const ROM_BASE: usize = map::to_kseg1(map::ROM);
+    /// Physical address of the start of the cartridge ROM
+    const ROM_PHYS: u32 = map::ROM as u32;
+
+    /// Perform a data cache operation on every line covering a range.
+    pub fn cache_op<const OP: u32>(ptr: *const u8, len: usize) {
+        if len == 0 {
+            return;
+        }
+
+        let start = ptr as usize & !(DCACHE_LINE_SIZE - 1);
+        let end = ptr as usize + len;
+        for line in (start..end).step_by(DCACHE_LINE_SIZE) {
+            unsafe {
+                match OP {
+                    HIT_INVALIDATE_D => asm!("cache 0x11, 0($4)", in("$4") line, options(nostack)),
+                    HIT_WRITEBACK_INVALIDATE_D => {
+                        asm!("cache 0x15, 0($4)", in("$4") line, options(nostack))
+                    }
+                    _ => asm!("cache 0x19, 0($4)", in("$4") line, options(nostack)),
+                }
+            }
+        }
+    }
+
+    pub fn invalidate_icache(ptr: *const u8, len: usize) {
+        if len == 0 {
+            return;
+        }
+
+        let start = ptr as usize & !(ICACHE_LINE_SIZE - 1);
+        let end = ptr as usize + len;
+        for line in (start..end).step_by(ICACHE_LINE_SIZE) {
+            unsafe { asm!("cache 0x10, 0($4)", in("$4") line, options(nostack)) };
+        }
+    }
+
+    pub fn rsp_halted() -> bool {
+        SP.status.read().halt()
+    }
+
+    /// Convert a KSEG1 pointer to the same address in KSEG0.
+    pub fn cached<T>(ptr: *mut T) -> *mut T {
+        map::to_kseg0(ptr as usize) as *mut T
+    }
+
+    /// Convert a KSEG0 pointer to the same address in KSEG1.
+    pub fn uncached<T>(ptr: *mut T) -> *mut T {
+        map::to_kseg1(ptr as usize) as *mut T
+    }
+
+    pub fn os_mem_size() -> usize {
+        unsafe { OS_MEM_SIZE.read_volatile() }
+    }
+
+    /// Check whether the last word of the Expansion Pak holds written values, without aliasing
+    /// the built-in RDRAM.
+    pub fn probe_expansion() -> bool {
+        unsafe {
+            let saved = (EXPANSION_PROBE.read_volatile(), BASE_PROBE.read_volatile());
+
+            let present = [0x5555_AAAA, 0xAAAA_5555].iter().all(|&pattern| {
+                BASE_PROBE.write_volatile(!pattern);
+                EXPANSION_PROBE.write_volatile(pattern);
+                EXPANSION_PROBE.read_volatile() == pattern && BASE_PROBE.read_volatile() == !pattern
+            });
+
+            EXPANSION_PROBE.write_volatile(saved.0);
+            BASE_PROBE.write_volatile(saved.1);
+
+            present
+        }
+    }
+
+    fn sp_dma_wait() {
+        loop {
+            let status = SP.status.read();
+            if !status.dma_busy() && !status.dma_full() {
+                break;
+            }
+        }
+    }
+
+    /// Copy 8-byte aligned whole words between physical addresses, through DMEM.
+    pub fn sp_dma_copy(dst: u32, src: u32, len: usize) {
+        for offset in (0..len).step_by(DMEM_SIZE) {
+            let chunk = (len - offset).min(DMEM_SIZE) as u32 - 1;
+
+            sp_dma_wait();
+            SP.mem_addr.write(0);
+            SP.dram_addr.write(src + offset as u32);
+            SP.rd_len.write(chunk);
+
+            sp_dma_wait();
+            SP.mem_addr.write(0);
+            SP.dram_addr.write(dst + offset as u32);
+            SP.wr_len.write(chunk);
+        }
+
+        sp_dma_wait();
+    }
+
+    fn pi_wait() {
+        loop {
+            let status = PI.status.read();
+            if !status.dma_busy() && !status.io_busy() {
+                break;
+            }
+        }
+    }
+
+    /// DMA from ROM to an 8-byte aligned physical address, with an even offset and length.
+    pub fn pi_dma_read(dst: u32, offset: u32, len: usize) {
+        pi_wait();
+        PI.dram_addr.write(dst);
+        PI.cart_addr.write(ROM_PHYS + offset);
+        PI.wr_len.write(len as u32 - 1);
+        pi_wait();
+        PI.status
+            .write(PiStatusWrite::default().with_clear_intr(true));
+    }
+
+    /// Read from ROM with 32-bit uncached loads.
+    pub fn read_rom_cpu(dst: &mut [u8], offset: u32) {
+        if dst.is_empty() {
+            return;
+        }
+
+        pi_wait();
+        let mut word = (usize::MAX, [0; 4]);
+        for (i, byte) in dst.iter_mut().enumerate() {
+            let addr = ROM_BASE + offset as usize + i;
+            if addr & !3 != word.0 {
+                let value = unsafe { ((addr & !3) as *const u32).read_volatile() };
+                word = (addr & !3, value.to_be_bytes());
+            }
+            *byte = word.1[addr & 3];
+        }
+    }
+}
+
+#[cfg(not(rrt0_platform = "n64"))]
+mod platform {
+    pub const HIT_INVALIDATE_D: u32 = 0;
+    pub const HIT_WRITEBACK_INVALIDATE_D: u32 = 0;
+    pub const HIT_WRITEBACK_D: u32 = 0;
+
+    pub fn cache_op<const OP: u32>(_ptr: *const u8, _len: usize) {}
+
+    pub fn invalidate_icache(_ptr: *const u8, _len: usize) {}
+
+    pub fn rsp_halted() -> bool {
+        false
+    }
+
+    pub fn cached<T>(ptr: *mut T) -> *mut T {
+        ptr
+    }
+
+    pub fn uncached<T>(ptr: *mut T) -> *mut T {
+        ptr
+    }
+
+    /// Never called, since there is no RSP to be halted.
+    pub fn sp_dma_copy(_dst: u32, _src: u32, _len: usize) {
+        unreachable!("no RSP to copy with")
+    }
+}
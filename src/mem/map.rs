@@ -0,0 +1,129 @@
+//! The N64 memory map.
+//!
+//! Physical addresses of RDRAM, the RCP and its interfaces, the cartridge domains, and the PIF,
+//! with functions converting between the segments the CPU sees them through:
+//!
+//! * KSEG0, from `0x8000_0000`: cached, where code and data run from
+//! * KSEG1, from `0xA000_0000`: uncached, for registers and memory shared with the RCP
+//! * Physical, from `0x0000_0000`: as used by DMA registers
+//!
+//! KSEG0 and KSEG1 map the first 512 MiB of the physical address space directly, so any address
+//! in either converts to the other by changing its top bits.
+//!
+//! e.g. `map::to_kseg1(map::PI_REGS)` is the uncached address of the PI registers.
+
+/// Size of each of KSEG0 and KSEG1, and of the physical address space they map (in bytes)
+pub const SEGMENT_SIZE: usize = 0x2000_0000;
+
+/// Start of the cached, directly mapped segment
+pub const KSEG0: usize = 0x8000_0000;
+/// Start of the uncached, directly mapped segment
+pub const KSEG1: usize = 0xA000_0000;
+
+/// RDRAM, up to 8 MiB with an Expansion Pak
+pub const RDRAM: usize = 0x0000_0000;
+
+/// TLB refill exception vector
+pub const TLB_REFILL_VECTOR: usize = 0x0000_0000;
+/// 64-bit TLB refill exception vector
+pub const XTLB_REFILL_VECTOR: usize = 0x0000_0080;
+/// General exception vector
+pub const GENERAL_VECTOR: usize = 0x0000_0180;
+
+/// Boot information word holding the TV type (0: PAL, 1: NTSC, 2: MPAL), set by the IPL3
+pub const OS_TV_TYPE: usize = 0x0000_0300;
+/// Boot information word holding the installed RDRAM size (in bytes), set by the IPL3 and the
+/// entrypoint
+pub const OS_MEM_SIZE: usize = 0x0000_0318;
+/// Boot information word holding the ROM offset of the filesystem, set by the entrypoint
+pub const FS_START: usize = 0x0000_031C;
+/// Boot information word holding the end of the program, where the heap starts, set by the
+/// entrypoint
+pub const HEAP_START: usize = 0x0000_0320;
+/// Start of the program code, after the exception vectors and boot information
+pub const TEXT_START: usize = 0x0000_0400;
+/// RDRAM configuration registers
+pub const RDRAM_REGS: usize = 0x03F0_0000;
+
+/// RSP data memory
+pub const SP_DMEM: usize = 0x0400_0000;
+/// RSP instruction memory
+pub const SP_IMEM: usize = 0x0400_1000;
+/// Size of each of DMEM and IMEM (in bytes)
+pub const SP_MEM_SIZE: usize = 0x1000;
+/// RSP interface registers
+pub const SP_REGS: usize = 0x0404_0000;
+/// RSP program counter
+pub const SP_PC: usize = 0x0408_0000;
+
+/// RDP command interface registers
+pub const DP_REGS: usize = 0x0410_0000;
+/// RDP span interface registers
+pub const DPS_REGS: usize = 0x0420_0000;
+/// MIPS interface registers
+pub const MI_REGS: usize = 0x0430_0000;
+/// Video interface registers
+pub const VI_REGS: usize = 0x0440_0000;
+/// Audio interface registers
+pub const AI_REGS: usize = 0x0450_0000;
+/// Peripheral interface registers
+pub const PI_REGS: usize = 0x0460_0000;
+/// RDRAM interface registers
+pub const RI_REGS: usize = 0x0470_0000;
+/// Serial interface registers
+pub const SI_REGS: usize = 0x0480_0000;
+
+/// Cartridge domain 2, address 1: 64DD registers
+pub const CART_DOM2_ADDR1: usize = 0x0500_0000;
+/// Cartridge domain 1, address 1: 64DD IPL ROM
+pub const CART_DOM1_ADDR1: usize = 0x0600_0000;
+/// Cartridge domain 2, address 2: SRAM or FlashRAM saves
+pub const CART_DOM2_ADDR2: usize = 0x0800_0000;
+/// Cartridge domain 1, address 2: the cartridge ROM
+pub const CART_DOM1_ADDR2: usize = 0x1000_0000;
+/// Cartridge domain 1, address 3
+pub const CART_DOM1_ADDR3: usize = 0x1FD0_0000;
+
+/// 64drive registers, in cartridge domain 1, address 2
+pub const SIXTY_FOUR_DRIVE_REGS: usize = 0x1800_0000;
+/// EverDrive-64 X series registers, in cartridge domain 1, address 2
+pub const EVERDRIVE_REGS: usize = 0x1F80_0000;
+/// SummerCart64 SD sector buffer, in cartridge domain 1, address 2
+pub const SUMMERCART_BUFFER: usize = 0x1FFE_0000;
+/// SummerCart64 registers, in cartridge domain 1, address 2
+pub const SUMMERCART_REGS: usize = 0x1FFF_0000;
+
+/// The cartridge ROM
+pub const ROM: usize = CART_DOM1_ADDR2;
+
+/// PIF boot ROM, readable only until the PIF locks it after boot
+pub const PIF_ROM: usize = 0x1FC0_0000;
+/// PIF RAM, for joybus commands
+pub const PIF_RAM: usize = 0x1FC0_07C0;
+/// Size of PIF RAM (in bytes)
+pub const PIF_RAM_SIZE: usize = 64;
+
+/// Physical address of an address in KSEG0 or KSEG1. Physical addresses are returned as they are.
+pub const fn physical(addr: usize) -> usize {
+    addr & (SEGMENT_SIZE - 1)
+}
+
+/// The same address in KSEG0.
+pub const fn to_kseg0(addr: usize) -> usize {
+    KSEG0 | physical(addr)
+}
+
+/// The same address in KSEG1.
+pub const fn to_kseg1(addr: usize) -> usize {
+    KSEG1 | physical(addr)
+}
+
+/// Whether an address is in KSEG0.
+pub const fn is_kseg0(addr: usize) -> bool {
+    addr & !(SEGMENT_SIZE - 1) == KSEG0
+}
+
+/// Whether an address is in KSEG1.
+pub const fn is_kseg1(addr: usize) -> bool {
+    addr & !(SEGMENT_SIZE - 1) == KSEG1
+}
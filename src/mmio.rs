@@ -0,0 +1,479 @@
+//! Memory-mapped I/O registers.
+//!
+//! Each RCP interface has a register block constant, like [`VI`] or [`PI`], with a typed [`Reg`]
+//! field per register. Status and control registers have bitfield types with an accessor per
+//! field, so drivers read like `PI.status.read().dma_busy()` instead of masking magic numbers.
+//!
+//! Registers that accept different bits on write than they report on read, like the set and clear
+//! commands of `SP_STATUS`, have a separate write type.
+//!
+//! On Unix and Windows, registers are simulated by the [`sim`] module, so driver logic can be
+//! tested on the host.
+
+use crate::mem::map;
+use core::marker::PhantomData;
+
+#[cfg(any(unix, windows))]
+pub mod sim;
+
+/// A value that can be read from or written to a 32-bit register.
+pub trait RegisterValue: Copy {
+    fn from_bits(bits: u32) -> Self;
+    fn to_bits(self) -> u32;
+}
+
+impl RegisterValue for u32 {
+    fn from_bits(bits: u32) -> Self {
+        bits
+    }
+
+    fn to_bits(self) -> u32 {
+        self
+    }
+}
+
+/// A 32-bit register that reads as `R` and is written as `W`.
+pub struct Reg<R, W = R> {
+    addr: usize,
+    value: PhantomData<(R, W)>,
+}
+
+impl<R: RegisterValue, W: RegisterValue> Reg<R, W> {
+    /// Create a register at an uncached address.
+    ///
+    /// # Safety
+    ///
+    /// The address must be a register, and `R` and `W` must describe it.
+    pub const unsafe fn new(addr: usize) -> Self {
+        Self {
+            addr,
+            value: PhantomData,
+        }
+    }
+
+    /// Address of the register
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub fn read(&self) -> R {
+        R::from_bits(unsafe { (self.addr as *const u32).read_volatile() })
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub fn write(&self, value: W) {
+        unsafe { (self.addr as *mut u32).write_volatile(value.to_bits()) };
+    }
+
+    #[cfg(any(unix, windows))]
+    pub fn read(&self) -> R {
+        R::from_bits(sim::read(self.addr))
+    }
+
+    #[cfg(any(unix, windows))]
+    pub fn write(&self, value: W) {
+        sim::write(self.addr, value.to_bits());
+    }
+}
+
+impl<T: RegisterValue> Reg<T> {
+    /// Read the register, change the value, and write it back.
+    pub fn modify(&self, f: impl FnOnce(T) -> T) {
+        self.write(f(self.read()));
+    }
+}
+
+/// Define a 32-bit bitfield type. Each field is a single bit read as `bool`, or an inclusive
+/// range of bits read as `u32`.
+macro_rules! bitfield {
+    (
+        $(#[$meta:meta])*
+        $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident, $with:ident: $lo:literal $(..= $hi:literal)?;
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+        pub struct $name(u32);
+
+        impl $name {
+            pub const fn from_bits(bits: u32) -> Self {
+                Self(bits)
+            }
+
+            pub const fn bits(self) -> u32 {
+                self.0
+            }
+
+            $(
+                bitfield!(@field $(#[$field_meta])* $field, $with, $lo $(, $hi)?);
+            )*
+        }
+
+        impl RegisterValue for $name {
+            fn from_bits(bits: u32) -> Self {
+                Self(bits)
+            }
+
+            fn to_bits(self) -> u32 {
+                self.0
+            }
+        }
+    };
+
+    (@field $(#[$field_meta:meta])* $field:ident, $with:ident, $bit:literal) => {
+        $(#[$field_meta])*
+        pub const fn $field(self) -> bool {
+            self.0 & 1 << $bit != 0
+        }
+
+        #[doc = concat!("Set [`", stringify!($field), "`](Self::", stringify!($field), ").")]
+        pub const fn $with(self, value: bool) -> Self {
+            Self(self.0 & !(1 << $bit) | (value as u32) << $bit)
+        }
+    };
+
+    (@field $(#[$field_meta:meta])* $field:ident, $with:ident, $lo:literal, $hi:literal) => {
+        $(#[$field_meta])*
+        pub const fn $field(self) -> u32 {
+            self.0 >> $lo & (u32::MAX >> (31 - $hi + $lo))
+        }
+
+        #[doc = concat!("Set [`", stringify!($field), "`](Self::", stringify!($field), ").")]
+        pub const fn $with(self, value: u32) -> Self {
+            let mask = (u32::MAX >> (31 - $hi + $lo)) << $lo;
+            Self(self.0 & !mask | value << $lo & mask)
+        }
+    };
+}
+
+/// Define a register block type, with a field per register at an offset from the base address.
+/// The base is the physical address of the block in the [`map`], accessed through KSEG1.
+macro_rules! registers {
+    (
+        $(#[$meta:meta])*
+        $name:ident @ $base:path {
+            $(
+                $(#[$field_meta:meta])*
+                $offset:literal => $field:ident: $read:ty $(, $write:ty)?;
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        pub struct $name {
+            $(
+                $(#[$field_meta])*
+                pub $field: Reg<$read $(, $write)?>,
+            )*
+        }
+
+        impl $name {
+            /// Base address of the register block
+            pub const BASE: usize = map::to_kseg1($base);
+
+            const fn new() -> Self {
+                unsafe {
+                    Self {
+                        $($field: Reg::new(Self::BASE + $offset),)*
+                    }
+                }
+            }
+        }
+    };
+}
+
+/// RSP interface registers
+pub const SP: Sp = Sp::new();
+/// RDP command interface registers
+pub const DP: Dp = Dp::new();
+/// MIPS interface registers
+pub const MI: Mi = Mi::new();
+/// Video interface registers
+pub const VI: Vi = Vi::new();
+/// Audio interface registers
+pub const AI: Ai = Ai::new();
+/// Peripheral interface registers
+pub const PI: Pi = Pi::new();
+/// Serial interface registers
+pub const SI: Si = Si::new();
+
+registers! {
+    /// RSP interface register block
+    Sp @ map::SP_REGS {
+        /// DMEM or IMEM address for DMA
+        0x00 => mem_addr: u32;
+        /// RDRAM address for DMA
+        0x04 => dram_addr: u32;
+        /// Start a DMA from RDRAM to SP memory
+        0x08 => rd_len: u32;
+        /// Start a DMA from SP memory to RDRAM
+        0x0C => wr_len: u32;
+        0x10 => status: SpStatus, SpStatusWrite;
+        0x14 => dma_full: u32;
+        0x18 => dma_busy: u32;
+        0x1C => semaphore: u32;
+    }
+}
+
+registers! {
+    /// RDP command interface register block
+    Dp @ map::DP_REGS {
+        /// Start of the command buffer
+        0x00 => start: u32;
+        /// End of the command buffer
+        0x04 => end: u32;
+        /// Address of the command being processed
+        0x08 => current: u32;
+        0x0C => status: DpStatus, DpStatusWrite;
+        0x10 => clock: u32;
+        0x14 => buf_busy: u32;
+        0x18 => pipe_busy: u32;
+        0x1C => tmem: u32;
+    }
+}
+
+registers! {
+    /// MIPS interface register block
+    Mi @ map::MI_REGS {
+        0x00 => mode: u32;
+        0x04 => version: u32;
+        /// Pending interrupts
+        0x08 => interrupt: MiInterrupts;
+        /// Enabled interrupts
+        0x0C => mask: MiInterrupts, MiMaskWrite;
+    }
+}
+
+registers! {
+    /// Video interface register block
+    Vi @ map::VI_REGS {
+        0x00 => ctrl: ViCtrl;
+        /// RDRAM address of the framebuffer
+        0x04 => origin: u32;
+        /// Framebuffer width (in pixels)
+        0x08 => width: u32;
+        /// Half-line that raises the VI interrupt
+        0x0C => v_intr: u32;
+        /// Current half-line. Writing clears the VI interrupt.
+        0x10 => v_current: u32;
+        0x14 => burst: u32;
+        0x18 => v_sync: u32;
+        0x1C => h_sync: u32;
+        0x20 => h_sync_leap: u32;
+        0x24 => h_video: u32;
+        0x28 => v_video: u32;
+        0x2C => v_burst: u32;
+        0x30 => x_scale: u32;
+        0x34 => y_scale: u32;
+    }
+}
+
+registers! {
+    /// Audio interface register block
+    Ai @ map::AI_REGS {
+        /// RDRAM address of the next sample buffer
+        0x00 => dram_addr: u32;
+        /// Length of the next sample buffer. Writing queues it.
+        0x04 => len: u32;
+        0x08 => control: u32;
+        /// Writing clears the AI interrupt
+        0x0C => status: AiStatus, u32;
+        0x10 => dacrate: u32;
+        0x14 => bitrate: u32;
+    }
+}
+
+registers! {
+    /// Peripheral interface register block
+    Pi @ map::PI_REGS {
+        /// RDRAM address for DMA
+        0x00 => dram_addr: u32;
+        /// Cartridge bus address for DMA
+        0x04 => cart_addr: u32;
+        /// Start a DMA from RDRAM to the cartridge bus
+        0x08 => rd_len: u32;
+        /// Start a DMA from the cartridge bus to RDRAM
+        0x0C => wr_len: u32;
+        0x10 => status: PiStatus, PiStatusWrite;
+        0x14 => bsd_dom1_lat: u32;
+        0x18 => bsd_dom1_pwd: u32;
+        0x1C => bsd_dom1_pgs: u32;
+        0x20 => bsd_dom1_rls: u32;
+        0x24 => bsd_dom2_lat: u32;
+        0x28 => bsd_dom2_pwd: u32;
+        0x2C => bsd_dom2_pgs: u32;
+        0x30 => bsd_dom2_rls: u32;
+    }
+}
+
+registers! {
+    /// Serial interface register block
+    Si @ map::SI_REGS {
+        /// RDRAM address for DMA
+        0x00 => dram_addr: u32;
+        /// Start a 64-byte DMA from PIF RAM to RDRAM
+        0x04 => pif_ad_rd64b: u32;
+        /// Start a 4-byte write to PIF RAM
+        0x08 => pif_ad_wr4b: u32;
+        /// Start a 64-byte DMA from RDRAM to PIF RAM
+        0x10 => pif_ad_wr64b: u32;
+        /// Start a 4-byte read from PIF RAM
+        0x14 => pif_ad_rd4b: u32;
+        /// Writing clears the SI interrupt
+        0x18 => status: SiStatus, u32;
+    }
+}
+
+bitfield! {
+    /// `SP_STATUS` as read
+    SpStatus {
+        halt, with_halt: 0;
+        broke, with_broke: 1;
+        dma_busy, with_dma_busy: 2;
+        dma_full, with_dma_full: 3;
+        io_busy, with_io_busy: 4;
+        single_step, with_single_step: 5;
+        intr_break, with_intr_break: 6;
+        /// Signal bits 0 to 7
+        signals, with_signals: 7..=14;
+    }
+}
+
+bitfield! {
+    /// `SP_STATUS` commands
+    SpStatusWrite {
+        clear_halt, with_clear_halt: 0;
+        set_halt, with_set_halt: 1;
+        clear_broke, with_clear_broke: 2;
+        clear_intr, with_clear_intr: 3;
+        set_intr, with_set_intr: 4;
+        clear_single_step, with_clear_single_step: 5;
+        set_single_step, with_set_single_step: 6;
+        clear_intr_break, with_clear_intr_break: 7;
+        set_intr_break, with_set_intr_break: 8;
+    }
+}
+
+bitfield! {
+    /// `DP_STATUS` as read
+    DpStatus {
+        /// Commands are read from DMEM instead of RDRAM
+        xbus_dmem_dma, with_xbus_dmem_dma: 0;
+        freeze, with_freeze: 1;
+        flush, with_flush: 2;
+        start_gclk, with_start_gclk: 3;
+        tmem_busy, with_tmem_busy: 4;
+        pipe_busy, with_pipe_busy: 5;
+        cmd_busy, with_cmd_busy: 6;
+        cbuf_ready, with_cbuf_ready: 7;
+        dma_busy, with_dma_busy: 8;
+        end_valid, with_end_valid: 9;
+        start_valid, with_start_valid: 10;
+    }
+}
+
+bitfield! {
+    /// `DP_STATUS` commands
+    DpStatusWrite {
+        clear_xbus_dmem_dma, with_clear_xbus_dmem_dma: 0;
+        set_xbus_dmem_dma, with_set_xbus_dmem_dma: 1;
+        clear_freeze, with_clear_freeze: 2;
+        set_freeze, with_set_freeze: 3;
+        clear_flush, with_clear_flush: 4;
+        set_flush, with_set_flush: 5;
+        clear_tmem_ctr, with_clear_tmem_ctr: 6;
+        clear_pipe_ctr, with_clear_pipe_ctr: 7;
+        clear_cmd_ctr, with_clear_cmd_ctr: 8;
+        clear_clock_ctr, with_clear_clock_ctr: 9;
+    }
+}
+
+bitfield! {
+    /// One bit per RCP interrupt source, as in `MI_INTERRUPT` and `MI_MASK`
+    MiInterrupts {
+        sp, with_sp: 0;
+        si, with_si: 1;
+        ai, with_ai: 2;
+        vi, with_vi: 3;
+        pi, with_pi: 4;
+        dp, with_dp: 5;
+    }
+}
+
+bitfield! {
+    /// `MI_MASK` commands
+    MiMaskWrite {
+        clear_sp, with_clear_sp: 0;
+        set_sp, with_set_sp: 1;
+        clear_si, with_clear_si: 2;
+        set_si, with_set_si: 3;
+        clear_ai, with_clear_ai: 4;
+        set_ai, with_set_ai: 5;
+        clear_vi, with_clear_vi: 6;
+        set_vi, with_set_vi: 7;
+        clear_pi, with_clear_pi: 8;
+        set_pi, with_set_pi: 9;
+        clear_dp, with_clear_dp: 10;
+        set_dp, with_set_dp: 11;
+    }
+}
+
+bitfield! {
+    /// `VI_CTRL`
+    ViCtrl {
+        /// Pixel format: 0 for blank, 2 for 16-bit, 3 for 32-bit
+        pixel_type, with_pixel_type: 0..=1;
+        gamma_dither, with_gamma_dither: 2;
+        gamma, with_gamma: 3;
+        divot, with_divot: 4;
+        vbus_clock, with_vbus_clock: 5;
+        serrate, with_serrate: 6;
+        test_mode, with_test_mode: 7;
+        aa_mode, with_aa_mode: 8..=9;
+        kill_we, with_kill_we: 11;
+        pixel_advance, with_pixel_advance: 12..=15;
+        dedither, with_dedither: 16;
+    }
+}
+
+bitfield! {
+    /// `AI_STATUS` as read
+    AiStatus {
+        enabled, with_enabled: 25;
+        busy, with_busy: 30;
+        full, with_full: 31;
+    }
+}
+
+bitfield! {
+    /// `PI_STATUS` as read
+    PiStatus {
+        dma_busy, with_dma_busy: 0;
+        io_busy, with_io_busy: 1;
+        error, with_error: 2;
+        interrupt, with_interrupt: 3;
+    }
+}
+
+bitfield! {
+    /// `PI_STATUS` commands
+    PiStatusWrite {
+        reset, with_reset: 0;
+        clear_intr, with_clear_intr: 1;
+    }
+}
+
+bitfield! {
+    /// `SI_STATUS` as read
+    SiStatus {
+        dma_busy, with_dma_busy: 0;
+        io_busy, with_io_busy: 1;
+        read_pending, with_read_pending: 2;
+        dma_error, with_dma_error: 3;
+        interrupt, with_interrupt: 12;
+    }
+}
@@ -0,0 +1,111 @@
+//! Simulated registers, for testing driver logic on the host.
+//!
+//! On Unix and Windows, [`Reg`](super::Reg) reads and writes go to an in-process table instead of
+//! memory. Each register holds the last value written to it, starting at 0, unless hooks are set
+//! to model the hardware, e.g. a status register that reports DMA completion after being polled.
+//! Hooks are called with the stored value, which they can change.
+//!
+//! The table is shared by the whole process, so tests using it must not run concurrently.
+
+use crate::collections::LinearMap;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Most registers that can be simulated at once
+pub const MAX_REGISTERS: usize = 64;
+
+/// Called when a register is read, returning the value read.
+pub type ReadHook = fn(stored: &mut u32) -> u32;
+
+/// Called when a register is written, instead of storing the written value.
+pub type WriteHook = fn(stored: &mut u32, value: u32);
+
+#[derive(Clone, Copy, Default)]
+struct Register {
+    value: u32,
+    read: Option<ReadHook>,
+    write: Option<WriteHook>,
+}
+
+struct Registers {
+    locked: AtomicBool,
+    map: UnsafeCell<LinearMap<usize, Register, MAX_REGISTERS>>,
+}
+
+// The map is only accessed while locked
+unsafe impl Sync for Registers {}
+
+static REGISTERS: Registers = Registers {
+    locked: AtomicBool::new(false),
+    map: UnsafeCell::new(LinearMap::new()),
+};
+
+/// Forget all register values and hooks.
+pub fn reset() {
+    with_registers(|map| map.clear());
+}
+
+/// Get the stored value of a register, without calling hooks.
+pub fn get(addr: usize) -> u32 {
+    with_registers(|map| map.get(&addr).map_or(0, |register| register.value))
+}
+
+/// Set the stored value of a register, without calling hooks.
+pub fn set(addr: usize, value: u32) {
+    with_register(addr, |register| register.value = value);
+}
+
+/// Set the function called when a register is read.
+pub fn on_read(addr: usize, hook: ReadHook) {
+    with_register(addr, |register| register.read = Some(hook));
+}
+
+/// Set the function called when a register is written.
+pub fn on_write(addr: usize, hook: WriteHook) {
+    with_register(addr, |register| register.write = Some(hook));
+}
+
+pub(super) fn read(addr: usize) -> u32 {
+    // Hooks run unlocked, so they can access other registers
+    let register = with_register(addr, |register| *register);
+    let mut value = register.value;
+    let read = register.read.map_or(value, |hook| hook(&mut value));
+    set(addr, value);
+
+    read
+}
+
+pub(super) fn write(addr: usize, value: u32) {
+    let register = with_register(addr, |register| *register);
+    let mut stored = register.value;
+    match register.write {
+        Some(hook) => hook(&mut stored, value),
+        None => stored = value,
+    }
+    set(addr, stored);
+}
+
+fn with_register<R>(addr: usize, f: impl FnOnce(&mut Register) -> R) -> R {
+    with_registers(|map| {
+        if !map.contains_key(&addr) && map.insert(addr, Register::default()).is_err() {
+            panic!("Too many simulated registers");
+        }
+
+        f(map.get_mut(&addr).unwrap())
+    })
+}
+
+fn with_registers<R>(f: impl FnOnce(&mut LinearMap<usize, Register, MAX_REGISTERS>) -> R) -> R {
+    while REGISTERS
+        .locked
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+
+    let result = f(unsafe { &mut *REGISTERS.map.get() });
+    REGISTERS.locked.store(false, Ordering::Release);
+
+    result
+}
@@ -0,0 +1,167 @@
+//! Code overlays.
+//!
+//! An overlay is a segment of the program linked to run at an address range that other overlays
+//! share, and stored in the ROM instead of being loaded at boot. Programs larger than RDRAM can put
+//! rarely used code, like each game mode, in overlays and [`load`] them by name when needed.
+//!
+//! The overlay table is declared in the program with [`overlay_table!`](crate::overlay_table), or
+//! registered at runtime with [`set_table`]. Loading an overlay unloads the others that overlap it.
+//!
+//! ```text
+//! rrt0::overlay_table! {
+//!     Overlay::new("battle", 0x0010_0000, 0x8020_0000, 0x4_0000, 0x8000),
+//!     Overlay::new("menu", 0x0014_0000, 0x8020_0000, 0x1_0000, 0x2000),
+//! }
+//!
+//! unsafe { overlay::load("battle")? };
+//! ```
+//!
+//! Where each segment ends up in the ROM depends on the linker script and the tool building the
+//! ROM, so the values come from the program's build, e.g. a build step reading them from the
+//! linker map. This crate does not generate them.
+//!
+//! Only available on N64.
+
+use crate::interrupt::without_interrupts;
+use crate::loader::copy_segment;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Errors that can occur while loading an overlay.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OverlayError {
+    /// No overlay has the name.
+    NotFound,
+}
+
+impl fmt::Display for OverlayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NotFound => "overlay not found",
+        })
+    }
+}
+
+/// Declare the overlay table of the program, from [`Overlay`]s. Use it once, at the top level of
+/// the program.
+#[macro_export]
+macro_rules! overlay_table {
+    ($($overlay:expr),* $(,)?) => {
+        #[no_mangle]
+        #[used]
+        pub static RRT0_OVERLAY_TABLE: $crate::overlay::OverlayTable = {
+            static OVERLAYS: [$crate::overlay::Overlay; [$(::core::stringify!($overlay)),*].len()] =
+                [$($overlay),*];
+
+            $crate::overlay::OverlayTable(&OVERLAYS)
+        };
+    };
+}
+
+/// The overlays declared with [`overlay_table!`](crate::overlay_table).
+#[derive(Debug)]
+pub struct OverlayTable(pub &'static [Overlay]);
+
+/// An overlay segment.
+#[derive(Debug)]
+pub struct Overlay {
+    pub name: &'static str,
+    /// Offset of the segment data in the ROM
+    pub rom_offset: u32,
+    /// Address the segment is linked to run at
+    pub address: usize,
+    /// Size of the segment data (in bytes)
+    pub len: usize,
+    /// Size of the segment's `.bss` after the data (in bytes)
+    pub bss_len: usize,
+    loaded: AtomicBool,
+}
+
+impl Overlay {
+    pub const fn new(
+        name: &'static str,
+        rom_offset: u32,
+        address: usize,
+        len: usize,
+        bss_len: usize,
+    ) -> Self {
+        Self {
+            name,
+            rom_offset,
+            address,
+            len,
+            bss_len,
+            loaded: AtomicBool::new(false),
+        }
+    }
+
+    /// Check if the overlay is loaded.
+    pub fn is_loaded(&self) -> bool {
+        self.loaded.load(Ordering::Relaxed)
+    }
+
+    fn end(&self) -> usize {
+        self.address + self.len + self.bss_len
+    }
+}
+
+struct Table(UnsafeCell<Option<&'static [Overlay]>>);
+
+// Only accessed with interrupts disabled
+unsafe impl Sync for Table {}
+
+static TABLE: Table = Table(UnsafeCell::new(None));
+
+/// Register an overlay table, used instead of the one declared with
+/// [`overlay_table!`](crate::overlay_table).
+pub fn set_table(table: &'static [Overlay]) {
+    without_interrupts(|| unsafe { *TABLE.0.get() = Some(table) });
+}
+
+/// Get the registered overlay table, or else the declared one, if any.
+pub fn table() -> &'static [Overlay] {
+    extern "Rust" {
+        // Null when no overlay table is declared
+        #[linkage = "extern_weak"]
+        static RRT0_OVERLAY_TABLE: *const OverlayTable;
+    }
+
+    without_interrupts(|| unsafe { *TABLE.0.get() })
+        .or_else(|| unsafe { RRT0_OVERLAY_TABLE.as_ref() }.map(|table| table.0))
+        .unwrap_or(&[])
+}
+
+/// Find an overlay by name.
+pub fn get(name: &str) -> Option<&'static Overlay> {
+    table().iter().find(|overlay| overlay.name == name)
+}
+
+/// Load an overlay, unless it is already loaded.
+///
+/// # Safety
+///
+/// No code or data in the overlays it replaces can be in use, e.g. by a caller further up the
+/// stack.
+pub unsafe fn load(name: &str) -> Result<&'static Overlay, OverlayError> {
+    let overlay = get(name).ok_or(OverlayError::NotFound)?;
+    if overlay.is_loaded() {
+        return Ok(overlay);
+    }
+
+    for other in table() {
+        if other.address < overlay.end() && overlay.address < other.end() {
+            other.loaded.store(false, Ordering::Relaxed);
+        }
+    }
+
+    copy_segment(
+        overlay.rom_offset,
+        overlay.address,
+        overlay.len,
+        overlay.len + overlay.bss_len,
+    );
+    overlay.loaded.store(true, Ordering::Relaxed);
+
+    Ok(overlay)
+}
@@ -0,0 +1,162 @@
+//! Platform plumbing for consoles supported outside this crate.
+//!
+//! A platform crate implements [`Platform`] and registers it once, at the top level of the program
+//! or in the platform crate itself, with [`register_platform!`](crate::register_platform):
+//!
+//! ```text
+//! struct Jaguar;
+//!
+//! impl Platform for Jaguar {
+//!     fn init(&self) {
+//!         // Configure the hardware the runtime depends on
+//!     }
+//!
+//!     fn output(&self) -> Option<fn(&str)> {
+//!         Some(jaguar::serial_output)
+//!     }
+//! }
+//!
+//! rrt0::register_platform!(Jaguar);
+//! ```
+//!
+//! Its startup code sets the stack pointer, clears `.bss` and jumps to [`start`], which initializes
+//! the platform and calls the program's `main`. Its panic handler calls [`panic`]:
+//!
+//! ```text
+//! #[panic_handler]
+//! fn panic(info: &PanicInfo<'_>) -> ! {
+//!     rrt0::platform::panic(info)
+//! }
+//! ```
+//!
+//! On the platforms this crate supports, the registered platform, if any, is only asked for its
+//! panic sink, by the panic handler in the [`prelude`](crate::prelude).
+
+use core::panic::PanicInfo;
+
+/// Register the platform the program runs on. Use it once, in the program or its platform crate.
+#[macro_export]
+macro_rules! register_platform {
+    ($platform:expr) => {
+        #[no_mangle]
+        #[used]
+        pub static RRT0_PLATFORM: &'static dyn $crate::platform::Platform = &$platform;
+    };
+}
+
+/// What a region of the address space holds.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RegionKind {
+    Ram,
+    Rom,
+    Mmio,
+}
+
+/// A region of the address space of a platform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Region {
+    pub name: &'static str,
+    pub start: usize,
+    /// Size (in bytes)
+    pub len: usize,
+    pub kind: RegionKind,
+}
+
+impl Region {
+    pub const fn new(name: &'static str, start: usize, len: usize, kind: RegionKind) -> Self {
+        Self {
+            name,
+            start,
+            len,
+            kind,
+        }
+    }
+
+    pub fn end(&self) -> usize {
+        self.start + self.len
+    }
+
+    pub fn contains(&self, addr: usize) -> bool {
+        (self.start..self.end()).contains(&addr)
+    }
+}
+
+/// Hooks a platform provides to the runtime. Every method has a default that does nothing.
+pub trait Platform: Sync {
+    /// Early hardware initialization, called by [`start`] before `main`.
+    fn init(&self) {}
+
+    /// The memory map, e.g. for diagnostics or checking pointers.
+    fn memory_map(&self) -> &'static [Region] {
+        &[]
+    }
+
+    /// The default text output, which [`start`] registers with
+    /// [`test::set_output`](crate::test::set_output).
+    fn output(&self) -> Option<fn(&str)> {
+        None
+    }
+
+    /// Called on panic, after the [test runner](crate::test) reports the failure, e.g. to show
+    /// the message or reset the console.
+    fn panic(&self, _info: &PanicInfo<'_>) {}
+}
+
+/// Get the platform registered with [`register_platform!`](crate::register_platform), if one is.
+pub fn get() -> Option<&'static dyn Platform> {
+    extern "Rust" {
+        // Null when no platform is registered
+        #[linkage = "extern_weak"]
+        static RRT0_PLATFORM: *const &'static dyn Platform;
+    }
+
+    unsafe { RRT0_PLATFORM.as_ref() }.copied()
+}
+
+/// Find the region of the registered platform's memory map holding an address.
+pub fn region(addr: usize) -> Option<Region> {
+    get()?
+        .memory_map()
+        .iter()
+        .find(|region| region.contains(addr))
+        .copied()
+}
+
+/// Initialize the registered platform and call `main`, ending the program if it returns. Called by
+/// the startup code of platforms supported outside this crate.
+pub fn start() -> ! {
+    extern "Rust" {
+        fn main();
+    }
+
+    if let Some(platform) = get() {
+        platform.init();
+        if let Some(output) = platform.output() {
+            crate::test::set_output(output);
+        }
+    }
+
+    unsafe { main() };
+    crate::panic_main()
+}
+
+/// Write a panic to the test output, report it to the test runner and the registered platform, and
+/// halt.
+///
+/// With the `terse-panics` feature, release builds only write the location of the panic to the
+/// test output, without formatting, and the platform is not called.
+pub fn panic(info: &PanicInfo<'_>) -> ! {
+    #[cfg(all(feature = "terse-panics", not(debug_assertions)))]
+    crate::test::fail_terse(info);
+
+    #[cfg(not(all(feature = "terse-panics", not(debug_assertions))))]
+    {
+        crate::test::fail(info);
+        if let Some(platform) = get() {
+            platform.panic(info);
+        }
+    }
+
+    #[allow(clippy::empty_loop)]
+    loop {}
+}
@@ -1,8 +1,139 @@
-#[cfg(any(unix, windows, target_vendor = "nintendo64"))]
+#[cfg(any(
+    unix,
+    windows,
+    rrt0_platform = "n64",
+    rrt0_platform = "ps2",
+    target_os = "psp",
+    rrt0_platform = "dreamcast",
+    rrt0_platform = "saturn",
+    all(
+        any(target_arch = "riscv32", target_arch = "riscv64"),
+        target_os = "none"
+    )
+))]
 pub use crate::math::*;
 
-#[cfg(target_vendor = "nintendo64")]
+#[cfg(any(
+    rrt0_platform = "n64",
+    rrt0_platform = "gba",
+    rrt0_platform = "n3ds",
+    rrt0_platform = "ps2",
+    target_os = "psp",
+    all(
+        any(target_arch = "riscv32", target_arch = "riscv64"),
+        target_os = "none"
+    )
+))]
 use core::arch::global_asm;
 
-#[cfg(target_vendor = "nintendo64")]
+#[cfg(all(
+    rrt0_platform = "n64",
+    not(feature = "cic-6105"),
+    not(feature = "ique")
+))]
 global_asm!(include_str!("platforms/n64/entrypoint.s"));
+
+#[cfg(all(rrt0_platform = "n64", feature = "cic-6105", not(feature = "ique")))]
+global_asm!(concat!(
+    ".set CIC_6105, 1\n",
+    include_str!("platforms/n64/entrypoint.s")
+));
+
+// The iQue Player has no CIC
+#[cfg(all(rrt0_platform = "n64", feature = "ique"))]
+global_asm!(concat!(
+    ".set IQUE, 1\n",
+    include_str!("platforms/n64/entrypoint.s")
+));
+
+#[cfg(rrt0_platform = "n64")]
+global_asm!(include_str!("platforms/n64/mem.s"));
+
+#[cfg(rrt0_platform = "n64")]
+global_asm!(include_str!("platforms/n64/exception.s"));
+
+#[cfg(rrt0_platform = "n64")]
+global_asm!(include_str!("platforms/n64/chain.s"));
+
+#[cfg(rrt0_platform = "gba")]
+global_asm!(include_str!("platforms/gba/crt0.s"));
+
+#[cfg(rrt0_platform = "n3ds")]
+global_asm!(include_str!("platforms/n3ds/crt0.s"));
+
+#[cfg(rrt0_platform = "ps2")]
+global_asm!(include_str!("platforms/ps2/crt0.s"));
+
+#[cfg(rrt0_platform = "ps2")]
+global_asm!(include_str!("platforms/ps2/mem.s"));
+
+#[cfg(target_os = "psp")]
+global_asm!(include_str!("platforms/psp/crt0.s"));
+
+// The N64 memory functions only use MIPS I instructions
+#[cfg(target_os = "psp")]
+global_asm!(include_str!("platforms/n64/mem.s"));
+
+// The RISC-V startup code is configured by assembler symbols for the register size, the
+// privilege mode and the floating point registers of the target
+#[cfg(all(
+    any(target_arch = "riscv32", target_arch = "riscv64"),
+    target_os = "none"
+))]
+mod riscv {
+    #[cfg(target_arch = "riscv32")]
+    macro_rules! xlen {
+        () => {
+            ""
+        };
+    }
+
+    #[cfg(target_arch = "riscv64")]
+    macro_rules! xlen {
+        () => {
+            ".set RV64, 1\n"
+        };
+    }
+
+    #[cfg(not(feature = "sbi"))]
+    macro_rules! mode {
+        () => {
+            ""
+        };
+    }
+
+    #[cfg(feature = "sbi")]
+    macro_rules! mode {
+        () => {
+            ".set SBI, 1\n"
+        };
+    }
+
+    #[cfg(not(target_feature = "f"))]
+    macro_rules! float {
+        () => {
+            ""
+        };
+    }
+
+    #[cfg(all(target_feature = "f", not(target_feature = "d")))]
+    macro_rules! float {
+        () => {
+            ".set FLOAT, 1\n"
+        };
+    }
+
+    #[cfg(target_feature = "d")]
+    macro_rules! float {
+        () => {
+            ".set FLOAT, 1\n.set DOUBLE, 1\n"
+        };
+    }
+
+    super::global_asm!(concat!(
+        xlen!(),
+        mode!(),
+        float!(),
+        include_str!("platforms/riscv/crt0.s")
+    ));
+}
@@ -0,0 +1,90 @@
+// Game Boy Advance startup
+//
+// The linker script (gba.ld) places .crt0 at the start of the ROM (0x08000000), and provides:
+//   __data_lma, __data_start, __data_end: .data, copied from ROM to IWRAM
+//   __ewram_lma, __ewram_start, __ewram_end: .ewram, copied from ROM to EWRAM
+//   __bss_start, __bss_end: .bss in IWRAM, cleared
+
+.section .crt0, "ax"
+.global _start
+.arm
+
+// CPSR modes, with IRQ and FIQ disabled
+.set MODE_IRQ,  0xD2
+.set MODE_SVC,  0xD3
+.set MODE_SYS,  0xDF
+
+// Stack tops in IWRAM, below the BIOS work area
+.set STACK_IRQ, 0x03007FA0
+.set STACK_SVC, 0x03007FE0
+.set STACK_SYS, 0x03007F00
+
+_start:
+    b 1f
+
+    // Cartridge header, completed by a tool like gbafix
+    .space 156                  // Nintendo logo
+    .space 12                   // Title
+    .space 4                    // Game code
+    .space 2                    // Maker code
+    .byte 0x96                  // Fixed value
+    .byte 0                     // Main unit code
+    .byte 0                     // Device type
+    .space 7                    // Reserved
+    .byte 0                     // Software version
+    .byte 0                     // Header checksum
+    .space 2                    // Reserved
+
+1:
+    // Initialize stacks
+    mov r0, #MODE_IRQ
+    msr cpsr_c, r0
+    ldr sp, =STACK_IRQ
+    mov r0, #MODE_SVC
+    msr cpsr_c, r0
+    ldr sp, =STACK_SVC
+    mov r0, #MODE_SYS
+    msr cpsr_c, r0
+    ldr sp, =STACK_SYS
+
+    // Copy .data and .ewram sections
+    ldr r0, =__data_lma
+    ldr r1, =__data_start
+    ldr r2, =__data_end
+    bl copy
+    ldr r0, =__ewram_lma
+    ldr r1, =__ewram_start
+    ldr r2, =__ewram_end
+    bl copy
+
+    // Clear .bss section
+    ldr r1, =__bss_start
+    ldr r2, =__bss_end
+    mov r3, #0
+2:
+    cmp r1, r2
+    strlo r3, [r1], #4
+    blo 2b
+
+    // Enable IRQs in the CPU; the IME register still masks them
+    mov r0, #(MODE_SYS & ~0x80)
+    msr cpsr_c, r0
+
+    // Jump to Rust, which may be Thumb code
+    ldr r0, =main
+    mov lr, pc
+    bx r0
+
+    // Panic if main returns
+    ldr r0, =panic_main
+    bx r0
+
+// Copy words from r0 to r1 until r2
+copy:
+    cmp r1, r2
+    ldrlo r3, [r0], #4
+    strlo r3, [r1], #4
+    blo copy
+    bx lr
+
+.pool
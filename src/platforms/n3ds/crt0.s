@@ -0,0 +1,112 @@
+// Nintendo 3DS bare-metal startup, for the ARM11
+//
+// The program is loaded as the ARM11 section of a FIRM, and the linker script provides:
+//   __bss_start, __bss_end: .bss, cleared
+//
+// CPU interrupts stay disabled until the program installs its exception handlers.
+
+.section .crt0, "ax"
+.global _start
+.arm
+
+// CPSR modes, with IRQ and FIQ disabled
+.set MODE_IRQ,  0xD2
+.set MODE_SVC,  0xD3
+.set MODE_SYS,  0xDF
+
+// Stack tops at the end of FCRAM
+.set STACK_SYS, 0x28000000
+.set STACK_IRQ, 0x27FF0000
+.set STACK_SVC, 0x27FE8000
+
+// Translation table section entries: 1 MiB, full access, and either strongly ordered or normal
+// memory with write-back caching
+.set SECTION_UNCACHED,  0x00000C02
+.set SECTION_CACHED,    0x00001C0E
+
+// System control register bits: MMU, data cache, branch prediction, instruction cache, and the
+// ARMv6 translation table format
+.set SCTLR_ENABLE,      0x00801805
+
+_start:
+    cpsid aif
+
+    // Initialize stacks
+    mov r0, #MODE_IRQ
+    msr cpsr_c, r0
+    ldr sp, =STACK_IRQ
+    mov r0, #MODE_SVC
+    msr cpsr_c, r0
+    ldr sp, =STACK_SVC
+    mov r0, #MODE_SYS
+    msr cpsr_c, r0
+    ldr sp, =STACK_SYS
+
+    // Clear .bss section
+    ldr r1, =__bss_start
+    ldr r2, =__bss_end
+    mov r3, #0
+1:
+    cmp r1, r2
+    strlo r3, [r1], #4
+    blo 1b
+
+    bl mmu_init
+
+    // Jump to Rust, which may be Thumb code
+    ldr r0, =main
+    blx r0
+
+    // Panic if main returns
+    ldr r0, =panic_main
+    bx r0
+
+// Map the address space flat, caching AXI WRAM and FCRAM
+mmu_init:
+    ldr r0, =mmu_table
+    mov r1, #0
+    ldr r2, =SECTION_UNCACHED
+    ldr r3, =SECTION_CACHED
+1:
+    // AXI WRAM is in the section at 0x1FF00000, and FCRAM at 0x20000000 to 0x28000000
+    ldr r4, =0x1FF
+    cmp r1, r4
+    blo 2f
+    cmp r1, #0x280
+    orrlo r4, r3, r1, lsl #20
+    blo 3f
+2:
+    orr r4, r2, r1, lsl #20
+3:
+    str r4, [r0, r1, lsl #2]
+    add r1, r1, #1
+    cmp r1, #0x1000
+    blo 1b
+
+    // Invalidate the caches and TLB, and use the table for all addresses
+    mov r1, #0
+    mcr p15, 0, r1, c7, c7, 0
+    mcr p15, 0, r1, c8, c7, 0
+    mcr p15, 0, r1, c2, c0, 2
+    mcr p15, 0, r0, c2, c0, 0
+
+    // Client access to domain 0, checking the permissions in the table
+    mov r1, #1
+    mcr p15, 0, r1, c3, c0, 0
+
+    // Enable the MMU and caches
+    mrc p15, 0, r1, c1, c0, 0
+    ldr r2, =SCTLR_ENABLE
+    orr r1, r1, r2
+    mcr p15, 0, r1, c1, c0, 0
+    mov r1, #0
+    mcr p15, 0, r1, c7, c5, 4
+    bx lr
+
+.pool
+
+// The translation table, aligned to its size
+.section .bss.mmu_table, "aw", %nobits
+.align 14
+mmu_table:
+    .space 0x4000
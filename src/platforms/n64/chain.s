@@ -0,0 +1,69 @@
+// Boot chaining trampoline. `boot::chain` copies this code to IMEM and runs it uncached from
+// there, since the copy overwrites the program that started it.
+//
+// $a0: Physical RDRAM address to copy the boot code to
+// $a1: Physical cartridge address of the boot code
+// $a2: Length of the boot code, minus one
+// $a3: Entry point
+.section .rodata.chain, "a"
+.global rrt0_chain_start
+.global rrt0_chain_end
+.set noreorder
+.set noat
+
+// Peripheral interface registers
+.set PI_BASE,           0xA4600000
+.set PI_DRAM_ADDR,      0x00
+.set PI_CART_ADDR,      0x04
+.set PI_WR_LEN,         0x0C
+.set PI_STATUS,         0x10
+.set PI_BUSY,           0x03
+
+// Cache sizes and the index store tag operations
+.set ICACHE_SIZE,       0x4000
+.set ICACHE_LINE,       32
+.set DCACHE_SIZE,       0x2000
+.set DCACHE_LINE,       16
+.set INDEX_STORE_TAG_I, 0x08
+.set INDEX_STORE_TAG_D, 0x09
+
+    .align 2
+rrt0_chain_start:
+    li $t0, PI_BASE
+1:
+    lw $t1, PI_STATUS($t0)
+    andi $t1, $t1, PI_BUSY
+    bnez $t1, 1b
+    nop
+
+    sw $a0, PI_DRAM_ADDR($t0)
+    sw $a1, PI_CART_ADDR($t0)
+    sw $a2, PI_WR_LEN($t0)
+2:
+    lw $t1, PI_STATUS($t0)
+    andi $t1, $t1, PI_BUSY
+    bnez $t1, 2b
+    nop
+
+    // Invalidate both caches without writing back, clearing each line's tag
+    mtc0 $zero, $28
+    mtc0 $zero, $29
+    lui $t0, 0x8000
+    ori $t1, $t0, ICACHE_SIZE
+3:
+    cache INDEX_STORE_TAG_I, 0($t0)
+    addiu $t0, $t0, ICACHE_LINE
+    bne $t0, $t1, 3b
+    nop
+
+    lui $t0, 0x8000
+    ori $t1, $t0, DCACHE_SIZE
+4:
+    cache INDEX_STORE_TAG_D, 0($t0)
+    addiu $t0, $t0, DCACHE_LINE
+    bne $t0, $t1, 4b
+    nop
+
+    jr $a3
+    nop
+rrt0_chain_end:
@@ -2,6 +2,9 @@
 .global _start
 .set noreorder
 
+// CP0 registers
+.set COUNT,     $9
+
 // Floating Point GPRs
 .set FPC_CSR,   $31
 
@@ -11,6 +14,8 @@
 
 // N64 PIF/OS pointers
 .set OS_MEM_SIZE,           0x80000318
+.set OS_MEM_SIZE_6105,      0x800003F0
+.set IQUE_MEM_SIZE,         0x00800000
 .set PIF_ENTRY_POINT,       0xBFC00000
 .set PIF_CONTROL,           0x07FC
 
@@ -18,10 +23,36 @@
 .set FS_START,              0x8000031C
 .set HEAP_START,            0x80000320
 
+// Where IPL3 stores the memory size, which CIC-6105 IPL3 moves
+.ifdef CIC_6105
+.set IPL3_MEM_SIZE,         OS_MEM_SIZE_6105
+.else
+.set IPL3_MEM_SIZE,         OS_MEM_SIZE
+.endif
+
 _start:
-    // Initialize stack
-    li $t0, OS_MEM_SIZE
+    // Record the boot timings in saved registers until .bss is cleared
+    mfc0 $s0, COUNT
+
+.ifdef IQUE
+    // The iQue Player secure kernel boots without IPL3, and gives programs 8 MiB
+    li $t0, IQUE_MEM_SIZE
+.else
+    // Copy the memory size to where the runtime reads it
+    li $t0, IPL3_MEM_SIZE
     lw $t0, 0($t0)
+.endif
+    li $t1, OS_MEM_SIZE
+    sw $t0, 0($t1)
+
+    // Initialize stack, below the memory reserved at the top by the layout, if there is one
+    .weak RRT0_MEMORY_LAYOUT
+    la $t2, RRT0_MEMORY_LAYOUT
+    beqz $t2, 1f
+    nop
+    lw $t2, 0($t2)
+    subu $t0, $t0, $t2
+1:
     li $t1, 0x7FFFFFF0
     addu $sp, $t0, $t1
 
@@ -36,15 +67,18 @@ _start:
     b 1b
     nop
 2:
+    mfc0 $s1, COUNT
 
     // Configure Floating Point Unit
     li $t0, (FPCSR_FS | FPCSR_EV)
     ctc1 $t0, FPC_CSR
 
-    // Enable PIF NMI
+.ifndef IQUE
+    // Enable PIF NMI; the iQue Player has no PIF
     li $t0, PIF_ENTRY_POINT
     ori $t1, $zero, 8
     sw $t1, PIF_CONTROL($t0)
+.endif
 
     // Store the FS location for the OS
     la $t0, __rom_end
@@ -56,6 +90,13 @@ _start:
     li $t1, HEAP_START
     sw $t0, 0($t1)
 
+    // Store the boot timings
+    mfc0 $s2, COUNT
+    la $t0, rrt0_boot_timings
+    sw $s0, 0($t0)
+    sw $s1, 4($t0)
+    sw $s2, 8($t0)
+
     // Jump to Rust
     jal main
     nop
@@ -63,3 +104,10 @@ _start:
     // Panic if main returns
     j panic_main
     nop
+
+// Count register values at entry, after clearing .bss, and after initializing I/O
+.section .bss
+.global rrt0_boot_timings
+.align 2
+rrt0_boot_timings:
+    .space 12
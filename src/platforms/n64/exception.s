@@ -0,0 +1,203 @@
+// General exception handler. Saves the interrupted context in an `exception::Frame` on the
+// current stack, calls `rrt0_exception` with it, then restores the (possibly modified) context and
+// returns to the exception PC. `install` points the exception vectors here.
+.section .text.exception, "ax"
+.global rrt0_exception_entry
+.set noreorder
+.set noat
+.set mips3
+
+// Frame layout, matching `exception::Frame`
+.set FRAME_GPR,         0
+.set FRAME_HI,          256
+.set FRAME_LO,          264
+.set FRAME_FPR,         272
+.set FRAME_FCSR,        528
+.set FRAME_STATUS,      532
+.set FRAME_CAUSE,       536
+.set FRAME_EPC,         540
+.set FRAME_BADVADDR,    544
+.set FRAME_SIZE,        552
+
+// Argument save area required by the calling convention, below the frame
+.set ARGS_SIZE,         16
+
+// Status register FR bit: all 32 FPRs are 64-bit
+.set STATUS_FR,         0x04000000
+
+rrt0_exception_entry:
+    addiu $sp, $sp, -(ARGS_SIZE + FRAME_SIZE)
+
+    // Save GPRs, except $zero, $k0, $k1 (reserved for the handler) and $sp
+    sd $1, (ARGS_SIZE + FRAME_GPR + 8)($sp)
+    sd $2, (ARGS_SIZE + FRAME_GPR + 16)($sp)
+    sd $3, (ARGS_SIZE + FRAME_GPR + 24)($sp)
+    sd $4, (ARGS_SIZE + FRAME_GPR + 32)($sp)
+    sd $5, (ARGS_SIZE + FRAME_GPR + 40)($sp)
+    sd $6, (ARGS_SIZE + FRAME_GPR + 48)($sp)
+    sd $7, (ARGS_SIZE + FRAME_GPR + 56)($sp)
+    sd $8, (ARGS_SIZE + FRAME_GPR + 64)($sp)
+    sd $9, (ARGS_SIZE + FRAME_GPR + 72)($sp)
+    sd $10, (ARGS_SIZE + FRAME_GPR + 80)($sp)
+    sd $11, (ARGS_SIZE + FRAME_GPR + 88)($sp)
+    sd $12, (ARGS_SIZE + FRAME_GPR + 96)($sp)
+    sd $13, (ARGS_SIZE + FRAME_GPR + 104)($sp)
+    sd $14, (ARGS_SIZE + FRAME_GPR + 112)($sp)
+    sd $15, (ARGS_SIZE + FRAME_GPR + 120)($sp)
+    sd $16, (ARGS_SIZE + FRAME_GPR + 128)($sp)
+    sd $17, (ARGS_SIZE + FRAME_GPR + 136)($sp)
+    sd $18, (ARGS_SIZE + FRAME_GPR + 144)($sp)
+    sd $19, (ARGS_SIZE + FRAME_GPR + 152)($sp)
+    sd $20, (ARGS_SIZE + FRAME_GPR + 160)($sp)
+    sd $21, (ARGS_SIZE + FRAME_GPR + 168)($sp)
+    sd $22, (ARGS_SIZE + FRAME_GPR + 176)($sp)
+    sd $23, (ARGS_SIZE + FRAME_GPR + 184)($sp)
+    sd $24, (ARGS_SIZE + FRAME_GPR + 192)($sp)
+    sd $25, (ARGS_SIZE + FRAME_GPR + 200)($sp)
+    sd $28, (ARGS_SIZE + FRAME_GPR + 224)($sp)
+    sd $30, (ARGS_SIZE + FRAME_GPR + 240)($sp)
+    sd $31, (ARGS_SIZE + FRAME_GPR + 248)($sp)
+    addiu $k0, $sp, (ARGS_SIZE + FRAME_SIZE)
+    sd $k0, (ARGS_SIZE + FRAME_GPR + 232)($sp)
+
+    mfhi $k0
+    sd $k0, (ARGS_SIZE + FRAME_HI)($sp)
+    mflo $k0
+    sd $k0, (ARGS_SIZE + FRAME_LO)($sp)
+
+    mfc0 $k0, $13
+    sw $k0, (ARGS_SIZE + FRAME_CAUSE)($sp)
+    mfc0 $k0, $14
+    sw $k0, (ARGS_SIZE + FRAME_EPC)($sp)
+    mfc0 $k0, $8
+    sw $k0, (ARGS_SIZE + FRAME_BADVADDR)($sp)
+    mfc0 $k0, $12
+    sw $k0, (ARGS_SIZE + FRAME_STATUS)($sp)
+
+    // Save FPRs. Odd registers are only separate with the FR bit set.
+    cfc1 $k1, $31
+    sw $k1, (ARGS_SIZE + FRAME_FCSR)($sp)
+    sdc1 $f0, (ARGS_SIZE + FRAME_FPR + 0)($sp)
+    sdc1 $f2, (ARGS_SIZE + FRAME_FPR + 16)($sp)
+    sdc1 $f4, (ARGS_SIZE + FRAME_FPR + 32)($sp)
+    sdc1 $f6, (ARGS_SIZE + FRAME_FPR + 48)($sp)
+    sdc1 $f8, (ARGS_SIZE + FRAME_FPR + 64)($sp)
+    sdc1 $f10, (ARGS_SIZE + FRAME_FPR + 80)($sp)
+    sdc1 $f12, (ARGS_SIZE + FRAME_FPR + 96)($sp)
+    sdc1 $f14, (ARGS_SIZE + FRAME_FPR + 112)($sp)
+    sdc1 $f16, (ARGS_SIZE + FRAME_FPR + 128)($sp)
+    sdc1 $f18, (ARGS_SIZE + FRAME_FPR + 144)($sp)
+    sdc1 $f20, (ARGS_SIZE + FRAME_FPR + 160)($sp)
+    sdc1 $f22, (ARGS_SIZE + FRAME_FPR + 176)($sp)
+    sdc1 $f24, (ARGS_SIZE + FRAME_FPR + 192)($sp)
+    sdc1 $f26, (ARGS_SIZE + FRAME_FPR + 208)($sp)
+    sdc1 $f28, (ARGS_SIZE + FRAME_FPR + 224)($sp)
+    sdc1 $f30, (ARGS_SIZE + FRAME_FPR + 240)($sp)
+    li $k1, STATUS_FR
+    and $k0, $k0, $k1
+    beqz $k0, 1f
+    nop
+    sdc1 $f1, (ARGS_SIZE + FRAME_FPR + 8)($sp)
+    sdc1 $f3, (ARGS_SIZE + FRAME_FPR + 24)($sp)
+    sdc1 $f5, (ARGS_SIZE + FRAME_FPR + 40)($sp)
+    sdc1 $f7, (ARGS_SIZE + FRAME_FPR + 56)($sp)
+    sdc1 $f9, (ARGS_SIZE + FRAME_FPR + 72)($sp)
+    sdc1 $f11, (ARGS_SIZE + FRAME_FPR + 88)($sp)
+    sdc1 $f13, (ARGS_SIZE + FRAME_FPR + 104)($sp)
+    sdc1 $f15, (ARGS_SIZE + FRAME_FPR + 120)($sp)
+    sdc1 $f17, (ARGS_SIZE + FRAME_FPR + 136)($sp)
+    sdc1 $f19, (ARGS_SIZE + FRAME_FPR + 152)($sp)
+    sdc1 $f21, (ARGS_SIZE + FRAME_FPR + 168)($sp)
+    sdc1 $f23, (ARGS_SIZE + FRAME_FPR + 184)($sp)
+    sdc1 $f25, (ARGS_SIZE + FRAME_FPR + 200)($sp)
+    sdc1 $f27, (ARGS_SIZE + FRAME_FPR + 216)($sp)
+    sdc1 $f29, (ARGS_SIZE + FRAME_FPR + 232)($sp)
+    sdc1 $f31, (ARGS_SIZE + FRAME_FPR + 248)($sp)
+1:
+
+    jal rrt0_exception
+    addiu $a0, $sp, ARGS_SIZE
+
+    // Restore FPRs
+    lw $k0, (ARGS_SIZE + FRAME_STATUS)($sp)
+    li $k1, STATUS_FR
+    and $k0, $k0, $k1
+    beqz $k0, 2f
+    nop
+    ldc1 $f1, (ARGS_SIZE + FRAME_FPR + 8)($sp)
+    ldc1 $f3, (ARGS_SIZE + FRAME_FPR + 24)($sp)
+    ldc1 $f5, (ARGS_SIZE + FRAME_FPR + 40)($sp)
+    ldc1 $f7, (ARGS_SIZE + FRAME_FPR + 56)($sp)
+    ldc1 $f9, (ARGS_SIZE + FRAME_FPR + 72)($sp)
+    ldc1 $f11, (ARGS_SIZE + FRAME_FPR + 88)($sp)
+    ldc1 $f13, (ARGS_SIZE + FRAME_FPR + 104)($sp)
+    ldc1 $f15, (ARGS_SIZE + FRAME_FPR + 120)($sp)
+    ldc1 $f17, (ARGS_SIZE + FRAME_FPR + 136)($sp)
+    ldc1 $f19, (ARGS_SIZE + FRAME_FPR + 152)($sp)
+    ldc1 $f21, (ARGS_SIZE + FRAME_FPR + 168)($sp)
+    ldc1 $f23, (ARGS_SIZE + FRAME_FPR + 184)($sp)
+    ldc1 $f25, (ARGS_SIZE + FRAME_FPR + 200)($sp)
+    ldc1 $f27, (ARGS_SIZE + FRAME_FPR + 216)($sp)
+    ldc1 $f29, (ARGS_SIZE + FRAME_FPR + 232)($sp)
+    ldc1 $f31, (ARGS_SIZE + FRAME_FPR + 248)($sp)
+2:
+    ldc1 $f0, (ARGS_SIZE + FRAME_FPR + 0)($sp)
+    ldc1 $f2, (ARGS_SIZE + FRAME_FPR + 16)($sp)
+    ldc1 $f4, (ARGS_SIZE + FRAME_FPR + 32)($sp)
+    ldc1 $f6, (ARGS_SIZE + FRAME_FPR + 48)($sp)
+    ldc1 $f8, (ARGS_SIZE + FRAME_FPR + 64)($sp)
+    ldc1 $f10, (ARGS_SIZE + FRAME_FPR + 80)($sp)
+    ldc1 $f12, (ARGS_SIZE + FRAME_FPR + 96)($sp)
+    ldc1 $f14, (ARGS_SIZE + FRAME_FPR + 112)($sp)
+    ldc1 $f16, (ARGS_SIZE + FRAME_FPR + 128)($sp)
+    ldc1 $f18, (ARGS_SIZE + FRAME_FPR + 144)($sp)
+    ldc1 $f20, (ARGS_SIZE + FRAME_FPR + 160)($sp)
+    ldc1 $f22, (ARGS_SIZE + FRAME_FPR + 176)($sp)
+    ldc1 $f24, (ARGS_SIZE + FRAME_FPR + 192)($sp)
+    ldc1 $f26, (ARGS_SIZE + FRAME_FPR + 208)($sp)
+    ldc1 $f28, (ARGS_SIZE + FRAME_FPR + 224)($sp)
+    ldc1 $f30, (ARGS_SIZE + FRAME_FPR + 240)($sp)
+    lw $k1, (ARGS_SIZE + FRAME_FCSR)($sp)
+    ctc1 $k1, $31
+
+    ld $k0, (ARGS_SIZE + FRAME_HI)($sp)
+    mthi $k0
+    ld $k0, (ARGS_SIZE + FRAME_LO)($sp)
+    mtlo $k0
+
+    lw $k0, (ARGS_SIZE + FRAME_EPC)($sp)
+    mtc0 $k0, $14
+
+    // Restore GPRs
+    ld $1, (ARGS_SIZE + FRAME_GPR + 8)($sp)
+    ld $2, (ARGS_SIZE + FRAME_GPR + 16)($sp)
+    ld $3, (ARGS_SIZE + FRAME_GPR + 24)($sp)
+    ld $4, (ARGS_SIZE + FRAME_GPR + 32)($sp)
+    ld $5, (ARGS_SIZE + FRAME_GPR + 40)($sp)
+    ld $6, (ARGS_SIZE + FRAME_GPR + 48)($sp)
+    ld $7, (ARGS_SIZE + FRAME_GPR + 56)($sp)
+    ld $8, (ARGS_SIZE + FRAME_GPR + 64)($sp)
+    ld $9, (ARGS_SIZE + FRAME_GPR + 72)($sp)
+    ld $10, (ARGS_SIZE + FRAME_GPR + 80)($sp)
+    ld $11, (ARGS_SIZE + FRAME_GPR + 88)($sp)
+    ld $12, (ARGS_SIZE + FRAME_GPR + 96)($sp)
+    ld $13, (ARGS_SIZE + FRAME_GPR + 104)($sp)
+    ld $14, (ARGS_SIZE + FRAME_GPR + 112)($sp)
+    ld $15, (ARGS_SIZE + FRAME_GPR + 120)($sp)
+    ld $16, (ARGS_SIZE + FRAME_GPR + 128)($sp)
+    ld $17, (ARGS_SIZE + FRAME_GPR + 136)($sp)
+    ld $18, (ARGS_SIZE + FRAME_GPR + 144)($sp)
+    ld $19, (ARGS_SIZE + FRAME_GPR + 152)($sp)
+    ld $20, (ARGS_SIZE + FRAME_GPR + 160)($sp)
+    ld $21, (ARGS_SIZE + FRAME_GPR + 168)($sp)
+    ld $22, (ARGS_SIZE + FRAME_GPR + 176)($sp)
+    ld $23, (ARGS_SIZE + FRAME_GPR + 184)($sp)
+    ld $24, (ARGS_SIZE + FRAME_GPR + 192)($sp)
+    ld $25, (ARGS_SIZE + FRAME_GPR + 200)($sp)
+    ld $28, (ARGS_SIZE + FRAME_GPR + 224)($sp)
+    ld $30, (ARGS_SIZE + FRAME_GPR + 240)($sp)
+    ld $31, (ARGS_SIZE + FRAME_GPR + 248)($sp)
+
+    addiu $sp, $sp, (ARGS_SIZE + FRAME_SIZE)
+    nop
+    eret
@@ -0,0 +1,122 @@
+// Memory functions required by compiled Rust code. These are normally provided by
+// compiler_builtins only when it is built with the `mem` feature, so they are defined here as weak
+// symbols to link either way.
+.section .text.mem, "ax"
+.set noreorder
+
+// void *memcpy(void *dst, const void *src, size_t len)
+.weak memcpy
+memcpy:
+    move $v0, $a0
+
+    // Copy words while both pointers are word aligned
+    or $t0, $a0, $a1
+    andi $t0, $t0, 3
+    bnez $t0, 2f
+    nop
+1:
+    sltiu $t0, $a2, 4
+    bnez $t0, 2f
+    nop
+    lw $t1, 0($a1)
+    addiu $a1, $a1, 4
+    sw $t1, 0($a0)
+    addiu $a0, $a0, 4
+    b 1b
+    addiu $a2, $a2, -4
+
+    // Copy the remaining bytes
+2:
+    beqz $a2, 3f
+    nop
+    lbu $t1, 0($a1)
+    addiu $a1, $a1, 1
+    sb $t1, 0($a0)
+    addiu $a0, $a0, 1
+    b 2b
+    addiu $a2, $a2, -1
+3:
+    jr $ra
+    nop
+
+// void *memmove(void *dst, const void *src, size_t len)
+.weak memmove
+memmove:
+    // Copy forwards unless dst overlaps the end of src
+    sltu $t0, $a1, $a0
+    beqz $t0, memcpy
+    addu $t2, $a1, $a2
+    sltu $t0, $a0, $t2
+    beqz $t0, memcpy
+    nop
+
+    // Copy backwards
+    move $v0, $a0
+    addu $a0, $a0, $a2
+1:
+    beqz $a2, 2f
+    nop
+    addiu $t2, $t2, -1
+    lbu $t1, 0($t2)
+    addiu $a0, $a0, -1
+    sb $t1, 0($a0)
+    b 1b
+    addiu $a2, $a2, -1
+2:
+    jr $ra
+    nop
+
+// void *memset(void *dst, int c, size_t len)
+.weak memset
+memset:
+    move $v0, $a0
+    andi $a1, $a1, 0xFF
+
+    // Fill words while the pointer is word aligned
+    andi $t0, $a0, 3
+    bnez $t0, 2f
+    sll $t0, $a1, 8
+    or $t0, $t0, $a1
+    sll $t1, $t0, 16
+    or $t0, $t0, $t1
+1:
+    sltiu $t1, $a2, 4
+    bnez $t1, 2f
+    nop
+    sw $t0, 0($a0)
+    addiu $a0, $a0, 4
+    b 1b
+    addiu $a2, $a2, -4
+
+    // Fill the remaining bytes
+2:
+    beqz $a2, 3f
+    nop
+    sb $a1, 0($a0)
+    addiu $a0, $a0, 1
+    b 2b
+    addiu $a2, $a2, -1
+3:
+    jr $ra
+    nop
+
+// int memcmp(const void *a, const void *b, size_t len)
+// int bcmp(const void *a, const void *b, size_t len)
+.weak memcmp
+.weak bcmp
+memcmp:
+bcmp:
+1:
+    beqz $a2, 2f
+    move $v0, $zero
+    lbu $t0, 0($a0)
+    lbu $t1, 0($a1)
+    addiu $a0, $a0, 1
+    addiu $a1, $a1, 1
+    beq $t0, $t1, 1b
+    addiu $a2, $a2, -1
+    jr $ra
+    subu $v0, $t0, $t1
+2:
+    jr $ra
+    nop
@@ -0,0 +1,60 @@
+// PlayStation 2 Emotion Engine startup
+//
+// The program is an ELF loaded by the BIOS or a loader like ps2link. The linker script provides:
+//   _gp: the global pointer
+//   __bss_start, __bss_end: .bss, cleared
+
+.section .text.crt0, "ax"
+.global _start
+.set noreorder
+
+// EE kernel syscalls, numbered in $v1
+.set SYSCALL_SETUP_THREAD,  60
+.set SYSCALL_SETUP_HEAP,    61
+.set SYSCALL_FLUSH_CACHE,   100
+
+// Size of the stack, like `layout::DEFAULT_STACK_SIZE`
+.set STACK_SIZE,            0x10000
+
+_start:
+    // Clear .bss section
+    la $t0, __bss_start
+    la $t1, __bss_end
+1:
+    sltu $t2, $t0, $t1
+    beqz $t2, 2f
+    nop
+    sw $zero, 0($t0)
+    b 1b
+    addiu $t0, $t0, 4
+2:
+
+    // Let the kernel place the stack at the end of memory, and set up the main thread
+    la $gp, _gp
+    move $a0, $gp
+    li $a1, -1
+    li $a2, STACK_SIZE
+    move $a3, $zero
+    move $t0, $zero
+    li $v1, SYSCALL_SETUP_THREAD
+    syscall
+    move $sp, $v0
+
+    // Give the kernel heap the rest of memory after the program
+    la $a0, __bss_end
+    li $a1, -1
+    li $v1, SYSCALL_SETUP_HEAP
+    syscall
+
+    // Write back the data cache and invalidate the instruction cache
+    move $a0, $zero
+    li $v1, SYSCALL_FLUSH_CACHE
+    syscall
+
+    // Jump to Rust
+    jal main
+    nop
+
+    // Panic if main returns
+    j panic_main
+    nop
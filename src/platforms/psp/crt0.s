@@ -0,0 +1,89 @@
+// PSP startup
+//
+// The program is a PRX, linked with the script of the mipsel-sony-psp target, which starts it at
+// module_start. The loader sets $gp and clears .bss. The program provides the module info with
+// `rrt0::psp_module_info!`, which the syslib exports point to.
+.set noreorder
+
+// Bounds of the import and export tables, for the module info
+.section .lib.stub.top, "a", @progbits
+.global __lib_stub_top
+__lib_stub_top:
+
+.section .lib.stub.btm, "a", @progbits
+.global __lib_stub_bottom
+__lib_stub_bottom:
+
+.section .lib.ent.top, "a", @progbits
+.global __lib_ent_top
+__lib_ent_top:
+
+.section .lib.ent.btm, "a", @progbits
+.global __lib_ent_bottom
+__lib_ent_bottom:
+
+// syslib exports: module_start and module_info, by NID
+.section .rodata.sceResident, "a", @progbits
+__syslib_exports:
+    .word 0xD632ACDB            // module_start
+    .word 0xF01D73A7            // module_info
+    .word module_start
+    .word module_info
+
+.section .lib.ent, "a", @progbits
+    .word 0                     // Name, none for syslib
+    .hword 0                    // Version
+    .hword 0x8000               // Attributes
+    .byte 4                     // Entry size (in words)
+    .byte 1                     // Variable count
+    .hword 1                    // Function count
+    .word __syslib_exports
+
+// Imports, which the loader patches to call the kernel
+.macro import_module name, flags, count
+.section .rodata.sceResident, "a", @progbits
+__stub_name_\name:
+    .asciz "\name"
+    .align 2
+.section .lib.stub, "aR", @progbits
+    .word __stub_name_\name
+    .word \flags                // Version and attributes
+    .byte 5                     // Entry size (in words)
+    .byte 0                     // Variable count
+    .hword \count               // Function count
+    .word __stub_nids_\name
+    .word __stub_text_\name
+.section .rodata.sceNid, "a", @progbits
+__stub_nids_\name:
+.section .sceStub.text, "ax", @progbits
+__stub_text_\name:
+.endm
+
+.macro import_function name, nid
+.section .rodata.sceNid, "a", @progbits
+    .word \nid
+.section .sceStub.text, "ax", @progbits
+.global \name
+\name:
+    jr $ra
+    nop
+.endm
+
+import_module Kernel_Library, 0x00010000, 2
+import_function sceKernelCpuSuspendIntr, 0x092968F4
+import_function sceKernelCpuResumeIntr, 0x5F10D406
+
+import_module sceDisplay, 0x40010000, 2
+import_function sceDisplaySetMode, 0x0E20F177
+import_function sceDisplaySetFrameBuf, 0x289D82FE
+
+.section .text.crt0, "ax", @progbits
+.global module_start
+module_start:
+    // Jump to Rust
+    jal main
+    nop
+
+    // Panic if main returns
+    j panic_main
+    nop
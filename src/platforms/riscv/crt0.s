@@ -0,0 +1,231 @@
+// RISC-V startup
+//
+// Runs in machine mode, or in supervisor mode under an SBI implementation like OpenSBI when SBI is
+// set (by the `sbi` feature). RV64 is set on 64-bit targets, FLOAT on targets with the F extension
+// and DOUBLE on those with the D extension too. The linker script provides:
+//   __stack_top: the top of the stack of hart 0, or of the hart started by SBI
+//   __bss_start, __bss_end: .bss, cleared
+
+.section .text.init, "ax"
+.global _start
+
+.ifdef RV64
+.set REGBYTES, 8
+.macro STORE reg, index
+    sd \reg, \index*REGBYTES(sp)
+.endm
+.macro LOAD reg, index
+    ld \reg, \index*REGBYTES(sp)
+.endm
+.else
+.set REGBYTES, 4
+.macro STORE reg, index
+    sw \reg, \index*REGBYTES(sp)
+.endm
+.macro LOAD reg, index
+    lw \reg, \index*REGBYTES(sp)
+.endm
+.endif
+
+.ifdef DOUBLE
+.set FREGBYTES, 8
+.macro FSTORE reg, index
+    fsd \reg, \index*FREGBYTES(sp)
+.endm
+.macro FLOAD reg, index
+    fld \reg, \index*FREGBYTES(sp)
+.endm
+.else
+.set FREGBYTES, 4
+.macro FSTORE reg, index
+    fsw \reg, \index*FREGBYTES(sp)
+.endm
+.macro FLOAD reg, index
+    flw \reg, \index*FREGBYTES(sp)
+.endm
+.endif
+
+// Access the supervisor or machine mode version of a CSR
+.macro CSRR reg, csr
+.ifdef SBI
+    csrr \reg, s\csr
+.else
+    csrr \reg, m\csr
+.endif
+.endm
+.macro CSRW csr, reg
+.ifdef SBI
+    csrw s\csr, \reg
+.else
+    csrw m\csr, \reg
+.endif
+.endm
+
+// Size of the trap frame: the caller-saved registers, epc, cause, tval, and padding for the
+// 16 byte stack alignment
+.set FRAME_SIZE, 20 * REGBYTES
+
+// Size of the floating point frame below it: the caller-saved registers, fcsr, and padding
+.set FP_FRAME_SIZE, 22 * 8
+
+// FS field of the status register, for the state of the floating point unit
+.set STATUS_FS_INITIAL, 0x2000
+
+_start:
+    .option push
+    .option norelax
+    la gp, __global_pointer$
+    .option pop
+
+.ifndef SBI
+    // Park all harts except hart 0; SBI only starts one
+    csrr t0, mhartid
+    bnez t0, park
+.endif
+
+    la sp, __stack_top
+
+.ifdef FLOAT
+    // Turn on the floating point unit, which starts off and makes its instructions trap
+    li t0, STATUS_FS_INITIAL
+.ifdef SBI
+    csrs sstatus, t0
+.else
+    csrs mstatus, t0
+.endif
+    fscsr zero
+.endif
+
+    // Handle traps in direct mode
+    la t0, rrt0_trap_entry
+    CSRW tvec, t0
+
+    // Clear .bss section
+    la t0, __bss_start
+    la t1, __bss_end
+1:
+    bgeu t0, t1, 2f
+    sw zero, 0(t0)
+    addi t0, t0, 4
+    j 1b
+2:
+
+    // Jump to Rust
+    call main
+
+    // Panic if main returns
+    tail panic_main
+
+park:
+    wfi
+    j park
+
+// Save the caller-saved registers and trap CSRs in a frame, and pass it to Rust. The caller-saved
+// floating point registers, which Rust code may use as well, are saved below the frame.
+.align 2
+rrt0_trap_entry:
+    addi sp, sp, -FRAME_SIZE
+    STORE ra, 0
+    STORE t0, 1
+    STORE t1, 2
+    STORE t2, 3
+    STORE t3, 4
+    STORE t4, 5
+    STORE t5, 6
+    STORE t6, 7
+    STORE a0, 8
+    STORE a1, 9
+    STORE a2, 10
+    STORE a3, 11
+    STORE a4, 12
+    STORE a5, 13
+    STORE a6, 14
+    STORE a7, 15
+    CSRR t0, epc
+    STORE t0, 16
+    CSRR t0, cause
+    STORE t0, 17
+    CSRR t0, tval
+    STORE t0, 18
+    mv a0, sp
+
+.ifdef FLOAT
+    addi sp, sp, -FP_FRAME_SIZE
+    FSTORE ft0, 0
+    FSTORE ft1, 1
+    FSTORE ft2, 2
+    FSTORE ft3, 3
+    FSTORE ft4, 4
+    FSTORE ft5, 5
+    FSTORE ft6, 6
+    FSTORE ft7, 7
+    FSTORE ft8, 8
+    FSTORE ft9, 9
+    FSTORE ft10, 10
+    FSTORE ft11, 11
+    FSTORE fa0, 12
+    FSTORE fa1, 13
+    FSTORE fa2, 14
+    FSTORE fa3, 15
+    FSTORE fa4, 16
+    FSTORE fa5, 17
+    FSTORE fa6, 18
+    FSTORE fa7, 19
+    frcsr t0
+    sw t0, 20*FREGBYTES(sp)
+.endif
+
+    call rrt0_trap
+
+.ifdef FLOAT
+    lw t0, 20*FREGBYTES(sp)
+    fscsr t0
+    FLOAD ft0, 0
+    FLOAD ft1, 1
+    FLOAD ft2, 2
+    FLOAD ft3, 3
+    FLOAD ft4, 4
+    FLOAD ft5, 5
+    FLOAD ft6, 6
+    FLOAD ft7, 7
+    FLOAD ft8, 8
+    FLOAD ft9, 9
+    FLOAD ft10, 10
+    FLOAD ft11, 11
+    FLOAD fa0, 12
+    FLOAD fa1, 13
+    FLOAD fa2, 14
+    FLOAD fa3, 15
+    FLOAD fa4, 16
+    FLOAD fa5, 17
+    FLOAD fa6, 18
+    FLOAD fa7, 19
+    addi sp, sp, FP_FRAME_SIZE
+.endif
+
+    // The handler may have changed the return address
+    LOAD t0, 16
+    CSRW epc, t0
+    LOAD ra, 0
+    LOAD t0, 1
+    LOAD t1, 2
+    LOAD t2, 3
+    LOAD t3, 4
+    LOAD t4, 5
+    LOAD t5, 6
+    LOAD t6, 7
+    LOAD a0, 8
+    LOAD a1, 9
+    LOAD a2, 10
+    LOAD a3, 11
+    LOAD a4, 12
+    LOAD a5, 13
+    LOAD a6, 14
+    LOAD a7, 15
+    addi sp, sp, FRAME_SIZE
+
+.ifdef SBI
+    sret
+.else
+    mret
+.endif
@@ -0,0 +1,83 @@
+! Sega Saturn startup, for the master SH-2
+!
+! rustc has no inline assembly for SuperH, so this file is not included by the crate: the program's
+! build assembles it with the sh-elf toolchain (`sh-elf-as -big`) and links it. Symbols defined in C
+! or Rust carry the leading underscore of the sh-elf ABI.
+!
+! The BIOS loads the program to the first read address in the boot header (0x06004000 by default)
+! and sets the stack pointer from it. The linker script provides:
+!   __bss_start, __bss_end: .bss, cleared
+
+    .section .text.crt0, "ax"
+    .global start
+
+start:
+    ! Mask interrupts
+    mov.l sr_init, r0
+    ldc r0, sr
+
+    ! Clear .bss section
+    mov.l bss_start, r0
+    mov.l bss_end, r1
+    mov #0, r2
+1:
+    cmp/hs r1, r0
+    bt 2f
+    mov.l r2, @r0
+    bra 1b
+    add #4, r0
+2:
+
+    ! Unmask interrupts
+    mov #0, r0
+    ldc r0, sr
+
+    ! Jump to Rust
+    mov.l main_address, r0
+    jsr @r0
+    nop
+
+    ! Panic if main returns
+    mov.l panic_main_address, r0
+    jmp @r0
+    nop
+
+    .align 2
+sr_init:
+    .long 0x000000F0
+bss_start:
+    .long __bss_start
+bss_end:
+    .long __bss_end
+main_address:
+    .long _main
+panic_main_address:
+    .long _panic_main
+
+! u32 rrt0_interrupts_disable(void): mask all interrupts, returning the previous SR
+    .global _rrt0_interrupts_disable
+_rrt0_interrupts_disable:
+    stc sr, r0
+    mov r0, r1
+    mov.l imask, r2
+    or r2, r1
+    ldc r1, sr
+    rts
+    nop
+
+! void rrt0_interrupts_restore(u32 sr): restore the interrupt mask from a previous SR
+    .global _rrt0_interrupts_restore
+_rrt0_interrupts_restore:
+    stc sr, r0
+    mov.l imask, r2
+    not r2, r1
+    and r1, r0
+    and r2, r4
+    or r4, r0
+    ldc r0, sr
+    rts
+    nop
+
+    .align 2
+imask:
+    .long 0x000000F0
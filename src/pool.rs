@@ -0,0 +1,209 @@
+//! Object pools.
+//!
+//! A [`Pool`] stores up to `N` values of one type in place, with constant time insertion and
+//! removal and no allocator. Values are referred to by [`Handle`]s, which stay valid until the
+//! value is removed. A stale handle never refers to a newer value in the same slot.
+
+/// A reference to a value in a [`Pool`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+impl Handle {
+    /// Index of the value's slot in the pool
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+enum Entry<T> {
+    Occupied(T),
+    /// Index of the next free slot, or `N` at the end of the free list
+    Vacant(usize),
+}
+
+struct Slot<T> {
+    /// Incremented each time the slot is freed
+    generation: u32,
+    entry: Entry<T>,
+}
+
+/// A fixed-capacity pool of `N` values.
+pub struct Pool<T, const N: usize> {
+    slots: [Slot<T>; N],
+    /// Head of the free list, or `N` if the pool is full
+    free: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> Pool<T, N> {
+    /// Create an empty pool.
+    pub fn new() -> Self {
+        let mut index = 0;
+        let slots = [(); N].map(|_| {
+            index += 1;
+            Slot {
+                generation: 0,
+                entry: Entry::Vacant(index),
+            }
+        });
+
+        Self {
+            slots,
+            free: 0,
+            len: 0,
+        }
+    }
+
+    /// Number of values in the pool
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the pool contains no values
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the pool has no room for more values
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Maximum number of values in the pool
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Add a value to the pool. Returns the value back if the pool is full.
+    pub fn insert(&mut self, value: T) -> Result<Handle, T> {
+        let index = self.free;
+        let slot = match self.slots.get_mut(index) {
+            Some(slot) => slot,
+            None => return Err(value),
+        };
+
+        if let Entry::Vacant(next) = slot.entry {
+            self.free = next;
+        }
+        slot.entry = Entry::Occupied(value);
+        self.len += 1;
+
+        Ok(Handle {
+            index: index as u32,
+            generation: slot.generation,
+        })
+    }
+
+    /// Remove a value from the pool. Returns `None` if the handle is stale.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        self.get(handle)?;
+
+        let index = handle.index();
+        let slot = &mut self.slots[index];
+        slot.generation = slot.generation.wrapping_add(1);
+        let entry = core::mem::replace(&mut slot.entry, Entry::Vacant(self.free));
+        self.free = index;
+        self.len -= 1;
+
+        match entry {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    /// Whether the handle refers to a value in the pool.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Get a value. Returns `None` if the handle is stale.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        match self.slots.get(handle.index()) {
+            Some(Slot {
+                generation,
+                entry: Entry::Occupied(value),
+            }) if *generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Get a value mutably. Returns `None` if the handle is stale.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        match self.slots.get_mut(handle.index()) {
+            Some(Slot {
+                generation,
+                entry: Entry::Occupied(value),
+            }) if *generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Remove all values, invalidating all handles.
+    pub fn clear(&mut self) {
+        for slot in &mut self.slots {
+            if let Entry::Occupied(_) = slot.entry {
+                slot.generation = slot.generation.wrapping_add(1);
+            }
+        }
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            slot.entry = Entry::Vacant(index + 1);
+        }
+
+        self.free = 0;
+        self.len = 0;
+    }
+
+    /// Iterate over the values in slot order, with their handles.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| match &slot.entry {
+                Entry::Occupied(value) => Some((Self::handle(index, slot), value)),
+                Entry::Vacant(_) => None,
+            })
+    }
+
+    /// Iterate mutably over the values in slot order, with their handles.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let handle = Self::handle(index, slot);
+                match &mut slot.entry {
+                    Entry::Occupied(value) => Some((handle, value)),
+                    Entry::Vacant(_) => None,
+                }
+            })
+    }
+
+    /// Remove the values for which `f` returns `false`.
+    pub fn retain(&mut self, mut f: impl FnMut(Handle, &mut T) -> bool) {
+        for index in 0..N {
+            let slot = &mut self.slots[index];
+            let handle = Self::handle(index, slot);
+            if let Entry::Occupied(value) = &mut slot.entry {
+                if !f(handle, value) {
+                    self.remove(handle);
+                }
+            }
+        }
+    }
+
+    fn handle(index: usize, slot: &Slot<T>) -> Handle {
+        Handle {
+            index: index as u32,
+            generation: slot.generation,
+        }
+    }
+}
+
+impl<T, const N: usize> Default for Pool<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
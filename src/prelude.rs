@@ -1,9 +1,24 @@
+pub use crate::collections::{LinearMap, String, Vec};
 use core::panic::PanicInfo;
 
 /// This function is called on panic.
-#[cfg_attr(target_vendor = "nintendo64", panic_handler)]
+#[cfg_attr(
+    any(
+        rrt0_platform = "n64",
+        rrt0_platform = "gba",
+        rrt0_platform = "n3ds",
+        rrt0_platform = "ps2",
+        target_os = "psp",
+        rrt0_platform = "dreamcast",
+        rrt0_platform = "saturn",
+        all(
+            any(target_arch = "riscv32", target_arch = "riscv64"),
+            target_os = "none"
+        )
+    ),
+    panic_handler
+)]
 #[no_mangle]
-fn panic(_panic_info: &PanicInfo<'_>) -> ! {
-    #[allow(clippy::empty_loop)]
-    loop {}
+fn panic(panic_info: &PanicInfo<'_>) -> ! {
+    crate::platform::panic(panic_info)
 }
@@ -0,0 +1,324 @@
+//! Profiling.
+//!
+//! [`profile_scope!`](crate::profile_scope) times the rest of the enclosing scope with the cycle
+//! counter, aggregating the calls, total, minimum and maximum time for each label. Call
+//! [`end_frame`] once per frame; the statistics for the previous frame can then be read with
+//! [`for_each_scope`] or written as text with [`report`], e.g. to draw them on screen. Up to
+//! [`MAX_SCOPES`] labels are tracked, and timings for others are ignored.
+//!
+//! The sampling profiler records the interrupted PC from a timer interrupt at a fixed rate. Samples
+//! are kept in a buffer of [`MAX_SAMPLES`] until they are read with [`drain_samples`], e.g. once
+//! per frame to stream them to the host, where they can be symbolized and turned into a flamegraph.
+//! Samples taken while the buffer is full are counted by [`dropped_samples`].
+//!
+//! The timer is the COP0 `Compare` interrupt, so sampling enables CPU interrupts. Other interrupts
+//! are passed on to the interrupt handler that was registered when sampling started.
+//!
+//! On other platforms, no samples are taken.
+
+use crate::exception::{self, ExceptionCode, Frame};
+use crate::interrupt::Mutex;
+use crate::time::{self, Instant, COUNTER_FREQUENCY};
+use core::fmt;
+use core::time::Duration;
+
+/// Time the rest of the enclosing scope under a label.
+///
+/// e.g. `profile_scope!("physics");`
+#[macro_export]
+macro_rules! profile_scope {
+    ($label:expr) => {
+        let _scope = $crate::profile::Scope::start($label);
+    };
+}
+
+/// Most labels timed by [`profile_scope!`](crate::profile_scope)
+pub const MAX_SCOPES: usize = 32;
+
+/// Timing statistics for a label over one frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ScopeStats {
+    pub label: &'static str,
+    /// Number of times the scope ran
+    pub calls: u32,
+    /// Counter ticks spent in the scope
+    total: u32,
+    min: u32,
+    max: u32,
+}
+
+impl ScopeStats {
+    const fn new(label: &'static str) -> Self {
+        Self {
+            label,
+            calls: 0,
+            total: 0,
+            min: u32::MAX,
+            max: 0,
+        }
+    }
+
+    fn record(&mut self, ticks: u32) {
+        self.calls += 1;
+        self.total = self.total.saturating_add(ticks);
+        self.min = self.min.min(ticks);
+        self.max = self.max.max(ticks);
+    }
+
+    /// Total time spent in the scope.
+    pub fn total(&self) -> Duration {
+        time::ticks_to_duration(self.total)
+    }
+
+    pub fn min(&self) -> Duration {
+        time::ticks_to_duration(self.min.min(self.max))
+    }
+
+    pub fn max(&self) -> Duration {
+        time::ticks_to_duration(self.max)
+    }
+
+    pub fn average(&self) -> Duration {
+        time::ticks_to_duration(self.total / self.calls.max(1))
+    }
+}
+
+struct ScopesState {
+    current: [Option<ScopeStats>; MAX_SCOPES],
+    previous: [Option<ScopeStats>; MAX_SCOPES],
+}
+
+static SCOPES: Mutex<ScopesState> = Mutex::new(ScopesState {
+    current: [None; MAX_SCOPES],
+    previous: [None; MAX_SCOPES],
+});
+
+/// A running [`profile_scope!`](crate::profile_scope), recording its time when dropped.
+pub struct Scope {
+    label: &'static str,
+    start: Instant,
+}
+
+impl Scope {
+    pub fn start(label: &'static str) -> Self {
+        Self {
+            label,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let ticks = Instant::now().ticks_since(self.start);
+        with_scopes(|scopes| {
+            let mut free = None;
+            for slot in scopes.current.iter_mut() {
+                match slot {
+                    Some(stats) if stats.label == self.label => return stats.record(ticks),
+                    Some(_) => {}
+                    None => free = free.or(Some(slot)),
+                }
+            }
+
+            if let Some(slot) = free {
+                slot.insert(ScopeStats::new(self.label)).record(ticks);
+            }
+        });
+    }
+}
+
+/// Finish the current frame, making its scope statistics available and starting new ones.
+pub fn end_frame() {
+    with_scopes(|scopes| {
+        scopes.previous = scopes.current;
+        for stats in scopes.current.iter_mut().flatten() {
+            *stats = ScopeStats::new(stats.label);
+        }
+    });
+}
+
+/// Call a function with the scope statistics for the previous frame, in order of first use. Only
+/// labels timed in that frame are included.
+pub fn for_each_scope(mut f: impl FnMut(&ScopeStats)) {
+    let previous = with_scopes(|scopes| scopes.previous);
+    previous
+        .iter()
+        .flatten()
+        .filter(|stats| stats.calls > 0)
+        .for_each(&mut f);
+}
+
+/// Write a line for each label with the scope statistics for the previous frame, in microseconds.
+pub fn report(out: &mut impl fmt::Write) -> fmt::Result {
+    let mut result = Ok(());
+    for_each_scope(|stats| {
+        result = result.and_then(|_| {
+            writeln!(
+                out,
+                "{} x{} total {} min {} avg {} max {}",
+                stats.label,
+                stats.calls,
+                stats.total().as_micros(),
+                stats.min().as_micros(),
+                stats.average().as_micros(),
+                stats.max().as_micros(),
+            )
+        });
+    });
+
+    result
+}
+
+fn with_scopes<R>(f: impl FnOnce(&mut ScopesState) -> R) -> R {
+    SCOPES.lock(f)
+}
+
+/// Capacity of the sample buffer
+pub const MAX_SAMPLES: usize = 1024;
+
+/// `Cause` register bit for a pending timer interrupt
+const CAUSE_IP7: u32 = 0x8000;
+
+struct SamplerState {
+    samples: [u32; MAX_SAMPLES],
+    /// Index of the oldest sample
+    start: usize,
+    len: usize,
+    dropped: u32,
+    /// Counter ticks between samples
+    period: u32,
+    /// Interrupt handler to pass other interrupts to
+    next: Option<exception::Handler>,
+}
+
+static SAMPLER: Mutex<SamplerState> = Mutex::new(SamplerState {
+    samples: [0; MAX_SAMPLES],
+    start: 0,
+    len: 0,
+    dropped: 0,
+    period: 0,
+    next: None,
+});
+
+/// Start sampling at a rate (in Hz). Also installs the exception handler.
+pub fn start_sampling(rate: u32) {
+    exception::install();
+    let period = with_sampler(|sampler| {
+        if sampler.period == 0 {
+            sampler.next = exception::handler(ExceptionCode::Interrupt);
+        }
+        sampler.period = (COUNTER_FREQUENCY / rate.max(1)).max(1);
+        exception::set_handler(ExceptionCode::Interrupt, Some(handle_interrupt));
+
+        sampler.period
+    });
+
+    // Changes the Status register, so it can't be done with interrupts disabled
+    platform::start_timer(period);
+}
+
+/// Stop sampling. Samples already taken can still be read.
+pub fn stop_sampling() {
+    platform::stop_timer();
+    with_sampler(|sampler| {
+        if sampler.period != 0 {
+            exception::set_handler(ExceptionCode::Interrupt, sampler.next.take());
+            sampler.period = 0;
+        }
+    });
+}
+
+/// Remove the samples taken so far, passing their PCs to a function, oldest first.
+pub fn drain_samples(mut f: impl FnMut(usize)) {
+    // Copied out in chunks, so `f` runs with interrupts enabled
+    let mut chunk = [0; 64];
+    loop {
+        let len = with_sampler(|sampler| {
+            let len = sampler.len.min(chunk.len());
+            for (index, sample) in chunk[..len].iter_mut().enumerate() {
+                *sample = sampler.samples[(sampler.start + index) % MAX_SAMPLES];
+            }
+            sampler.start = (sampler.start + len) % MAX_SAMPLES;
+            sampler.len -= len;
+
+            len
+        });
+        if len == 0 {
+            break;
+        }
+
+        chunk[..len].iter().for_each(|&pc| f(pc as usize));
+    }
+}
+
+/// Number of samples lost because the buffer was full, since the last call.
+pub fn dropped_samples() -> u32 {
+    with_sampler(|sampler| core::mem::take(&mut sampler.dropped))
+}
+
+fn with_sampler<R>(f: impl FnOnce(&mut SamplerState) -> R) -> R {
+    SAMPLER.lock(f)
+}
+
+fn handle_interrupt(frame: &mut Frame) {
+    if frame.cause & CAUSE_IP7 == 0 {
+        // Passed on outside the lock, in case the next handler reads the samples
+        match with_sampler(|sampler| sampler.next) {
+            Some(next) => next(frame),
+            None => panic!("Unhandled interrupt"),
+        }
+        return;
+    }
+
+    with_sampler(|sampler| {
+        platform::next_timer(sampler.period);
+        if sampler.len == MAX_SAMPLES {
+            sampler.dropped = sampler.dropped.saturating_add(1);
+        } else {
+            sampler.samples[(sampler.start + sampler.len) % MAX_SAMPLES] = frame.pc() as u32;
+            sampler.len += 1;
+        }
+    });
+}
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use core::arch::asm;
+
+    /// `Status` register bits enabling the timer interrupt and interrupts in general
+    const STATUS_IM7: u32 = 0x8000;
+    const STATUS_IE: u32 = 0x0001;
+
+    pub fn start_timer(period: u32) {
+        next_timer(period);
+        unsafe {
+            let status: u32;
+            asm!("mfc0 $2, $12", out("$2") status, options(nomem, nostack));
+            asm!("mtc0 $2, $12", in("$2") status | STATUS_IM7 | STATUS_IE, options(nostack));
+        }
+    }
+
+    pub fn stop_timer() {
+        unsafe {
+            let status: u32;
+            asm!("mfc0 $2, $12", out("$2") status, options(nomem, nostack));
+            asm!("mtc0 $2, $12", in("$2") status & !STATUS_IM7, options(nostack));
+        }
+    }
+
+    /// Schedule the next timer interrupt, acknowledging the current one.
+    pub fn next_timer(period: u32) {
+        let compare = crate::time::counter().wrapping_add(period);
+        unsafe { asm!("mtc0 $2, $11", in("$2") compare, options(nostack)) };
+    }
+}
+
+#[cfg(not(rrt0_platform = "n64"))]
+mod platform {
+    pub fn start_timer(_period: u32) {}
+
+    pub fn stop_timer() {}
+
+    pub fn next_timer(_period: u32) {}
+}
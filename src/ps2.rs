@@ -0,0 +1,56 @@
+//! PlayStation 2 support.
+//!
+//! [`Sio`] writes text to the serial port of the Emotion Engine, for printing diagnostics, e.g. as
+//! the output of the [test runner](crate::test::set_output):
+//!
+//! ```text
+//! rrt0::test::set_output(rrt0::ps2::sio_output);
+//! ```
+//!
+//! PCSX2 shows the output in its EE console log, and on hardware it appears on the SIO pins of the
+//! expansion connector.
+//!
+//! Only available on PS2.
+
+use core::fmt;
+
+/// SIO interrupt status register
+const SIO_ISR: *const u32 = 0x1000_F130 as *const u32;
+
+/// SIO transmit FIFO
+const SIO_TXFIFO: *mut u8 = 0x1000_F180 as *mut u8;
+
+/// Transmit FIFO fill level in `SIO_ISR`, and its value when the FIFO is full
+const SIO_ISR_TX_LEVEL: u32 = 0xF000;
+const SIO_ISR_TX_FULL: u32 = 0x8000;
+
+/// Text output to the EE serial port.
+#[derive(Debug, Default)]
+pub struct Sio;
+
+impl Sio {
+    /// Write a byte, waiting for room in the transmit FIFO.
+    pub fn write_byte(&mut self, byte: u8) {
+        while unsafe { SIO_ISR.read_volatile() } & SIO_ISR_TX_LEVEL == SIO_ISR_TX_FULL {}
+
+        unsafe { SIO_TXFIFO.write_volatile(byte) };
+    }
+}
+
+impl fmt::Write for Sio {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+
+        Ok(())
+    }
+}
+
+/// Write text to the EE serial port, with the signature of an output function for the
+/// [test runner](crate::test::set_output).
+pub fn sio_output(s: &str) {
+    for byte in s.bytes() {
+        Sio.write_byte(byte);
+    }
+}
@@ -0,0 +1,172 @@
+//! PSP support.
+//!
+//! A PSP program is a PRX module, which the loader only starts with a module info. The program
+//! provides it once, at the top level, with [`psp_module_info!`](crate::psp_module_info):
+//!
+//! ```text
+//! rrt0::psp_module_info!("hello");
+//! ```
+//!
+//! [`DebugScreen`] draws text to the display with a [`TextConsole`], for printing diagnostics,
+//! e.g. as the output of the [test runner](crate::test::set_output):
+//!
+//! ```text
+//! let mut screen = DebugScreen::new(FrameBuffer, &FONT);
+//! screen.init();
+//! writeln!(screen, "hello from rrt0")?;
+//! ```
+//!
+//! Only available on PSP.
+
+pub use crate::text::FONT_SIZE;
+use crate::text::{Surface, TextConsole};
+
+/// Length of the module name, including the terminating zero (in bytes)
+const MODULE_NAME_LEN: usize = 28;
+
+/// Declare the module info of the program. Use it once, at the top level of the program.
+#[macro_export]
+macro_rules! psp_module_info {
+    ($name:expr) => {
+        #[no_mangle]
+        #[used]
+        #[link_section = ".rodata.sceModuleInfo"]
+        pub static module_info: $crate::psp::ModuleInfo = {
+            extern "C" {
+                static _gp: u8;
+                static __lib_ent_top: u8;
+                static __lib_ent_bottom: u8;
+                static __lib_stub_top: u8;
+                static __lib_stub_bottom: u8;
+            }
+
+            unsafe {
+                $crate::psp::ModuleInfo::new(
+                    $name,
+                    &_gp,
+                    [&__lib_ent_top, &__lib_ent_bottom],
+                    [&__lib_stub_top, &__lib_stub_bottom],
+                )
+            }
+        };
+    };
+}
+
+/// Module info of a PRX, as the loader reads it.
+#[derive(Debug)]
+#[repr(C)]
+pub struct ModuleInfo {
+    attributes: u16,
+    version: [u8; 2],
+    name: [u8; MODULE_NAME_LEN],
+    gp: *const u8,
+    exports: [*const u8; 2],
+    imports: [*const u8; 2],
+}
+
+// Only holds addresses in the program, which are never written through
+unsafe impl Sync for ModuleInfo {}
+
+impl ModuleInfo {
+    /// Create the module info of a user mode module. Use
+    /// [`psp_module_info!`](crate::psp_module_info) instead, which provides the addresses from the
+    /// linker.
+    ///
+    /// # Panics
+    ///
+    /// Panics (at compile time, in a static) if the name is longer than 27 bytes.
+    pub const fn new(
+        name: &str,
+        gp: *const u8,
+        exports: [*const u8; 2],
+        imports: [*const u8; 2],
+    ) -> Self {
+        let bytes = name.as_bytes();
+        assert!(bytes.len() < MODULE_NAME_LEN, "Module name is too long");
+
+        let mut module_name = [0; MODULE_NAME_LEN];
+        let mut index = 0;
+        while index < bytes.len() {
+            module_name[index] = bytes[index];
+            index += 1;
+        }
+
+        Self {
+            attributes: 0,
+            version: [1, 1],
+            name: module_name,
+            gp,
+            exports,
+            imports,
+        }
+    }
+}
+
+extern "C" {
+    fn sceDisplaySetMode(mode: u32, width: u32, height: u32) -> i32;
+    fn sceDisplaySetFrameBuf(top: *const u32, buffer_width: u32, format: u32, sync: u32) -> i32;
+}
+
+/// The frame buffer in VRAM, through the uncached mirror
+const VRAM: *mut u32 = 0x4400_0000 as *mut u32;
+
+/// Size of the screen (in pixels)
+pub const SCREEN_WIDTH: usize = 480;
+pub const SCREEN_HEIGHT: usize = 272;
+
+/// Width of a line in the frame buffer (in pixels)
+const BUFFER_WIDTH: usize = 512;
+
+/// 32-bit ABGR pixels, and updating the display at the next vertical blank
+const PIXEL_FORMAT_8888: u32 = 3;
+const SETBUF_NEXTFRAME: u32 = 1;
+
+/// The frame buffer at the start of VRAM, as a surface for a [`TextConsole`].
+#[derive(Debug)]
+pub struct FrameBuffer;
+
+impl FrameBuffer {
+    /// Show the frame buffer on the display.
+    pub fn show(&mut self) {
+        unsafe {
+            sceDisplaySetMode(0, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
+            sceDisplaySetFrameBuf(
+                VRAM,
+                BUFFER_WIDTH as u32,
+                PIXEL_FORMAT_8888,
+                SETBUF_NEXTFRAME,
+            );
+        }
+    }
+}
+
+/// Colors are 0xAABBGGRR.
+impl Surface for FrameBuffer {
+    type Color = u32;
+
+    const WIDTH: usize = SCREEN_WIDTH;
+    const HEIGHT: usize = SCREEN_HEIGHT;
+
+    const FOREGROUND: u32 = 0xFFFF_FFFF;
+    const BACKGROUND: u32 = 0xFF00_0000;
+
+    fn read(&self, x: usize, y: usize) -> u32 {
+        unsafe { VRAM.add(y * BUFFER_WIDTH + x).read_volatile() }
+    }
+
+    fn write(&mut self, x: usize, y: usize, color: u32) {
+        unsafe { VRAM.add(y * BUFFER_WIDTH + x).write_volatile(color) };
+    }
+}
+
+/// A text console drawn to the frame buffer at the start of VRAM. Scrolls up when the screen is
+/// full.
+pub type DebugScreen = TextConsole<FrameBuffer>;
+
+impl DebugScreen {
+    /// Clear the screen and show the frame buffer on the display.
+    pub fn init(&mut self) {
+        self.clear();
+        self.surface_mut().show();
+    }
+}
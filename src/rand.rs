@@ -0,0 +1,155 @@
+//! Pseudorandom number generation.
+//!
+//! [`Xoshiro128PlusPlus`] is a small, fast generator that only needs 32-bit arithmetic. It
+//! implements [`RngCore`] and [`SeedableRng`], so it works with the `rand` crate and anything else
+//! built on `rand_core`.
+//!
+//! [`global`] returns a handle to a shared generator that is seeded automatically on first use
+//! from the [`entropy`](crate::entropy) pool, so sequences differ between boots.
+//! The generator is not cryptographically secure.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+pub use rand_core::{Error, RngCore, SeedableRng};
+
+/// The xoshiro128++ generator by David Blackman and Sebastiano Vigna.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Xoshiro128PlusPlus {
+    s: [u32; 4],
+}
+
+impl Xoshiro128PlusPlus {
+    /// Advance the state, returning the next output.
+    fn step(s: &mut [u32; 4]) -> u32 {
+        let result = s[0].wrapping_add(s[3]).rotate_left(7).wrapping_add(s[0]);
+        let t = s[1] << 9;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(11);
+
+        result
+    }
+}
+
+impl RngCore for Xoshiro128PlusPlus {
+    fn next_u32(&mut self) -> u32 {
+        Self::step(&mut self.s)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Xoshiro128PlusPlus {
+    type Seed = [u8; 16];
+
+    /// Create a generator from a seed. The all-zero seed, which would only ever produce zeros, is
+    /// replaced with a fixed nonzero seed.
+    fn from_seed(seed: Self::Seed) -> Self {
+        if seed == [0; 16] {
+            return Self::seed_from_u64(0);
+        }
+
+        let mut s = [0; 4];
+        for (word, bytes) in s.iter_mut().zip(seed.chunks_exact(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        Self { s }
+    }
+}
+
+/// State of the global generator. All zeros means it has not been seeded.
+static STATE: [AtomicU32; 4] = [
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+];
+
+/// A handle to the global generator, returned by [`global`].
+///
+/// The state is updated without disabling interrupts, so numbers generated concurrently from an
+/// interrupt handler and the main program may repeat.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GlobalRng;
+
+/// Get a handle to the global generator. It is seeded on first use unless [`seed`] is called
+/// first.
+pub fn global() -> GlobalRng {
+    GlobalRng
+}
+
+/// Reseed the global generator, e.g. to replay a deterministic sequence.
+pub fn seed(seed: u64) {
+    store(&Xoshiro128PlusPlus::seed_from_u64(seed).s);
+}
+
+impl RngCore for GlobalRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut s = load();
+        if s == [0; 4] {
+            s = Xoshiro128PlusPlus::seed_from_u64(boot_seed()).s;
+        }
+        let result = Xoshiro128PlusPlus::step(&mut s);
+        store(&s);
+
+        result
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+fn load() -> [u32; 4] {
+    let mut s = [0; 4];
+    for (value, word) in s.iter_mut().zip(&STATE) {
+        *value = word.load(Ordering::Relaxed);
+    }
+
+    s
+}
+
+fn store(s: &[u32; 4]) {
+    for (word, value) in STATE.iter().zip(s) {
+        word.store(*value, Ordering::Relaxed);
+    }
+}
+
+/// Seed for the global generator, from the entropy pool.
+fn boot_seed() -> u64 {
+    crate::entropy::seed()
+}
+
+#[cfg(all(feature = "getrandom", rrt0_platform = "n64"))]
+getrandom::register_custom_getrandom!(fill_random);
+
+/// Backend for the `getrandom` crate, so crates that depend on it work on N64.
+#[cfg(all(feature = "getrandom", rrt0_platform = "n64"))]
+fn fill_random(dest: &mut [u8]) -> Result<(), getrandom::Error> {
+    global().fill_bytes(dest);
+    Ok(())
+}
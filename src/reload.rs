@@ -0,0 +1,174 @@
+//! Replacing assets at runtime, with data pushed from the host.
+//!
+//! During development, a host tool can send a changed asset (a texture, level data) over a debug
+//! channel, and the program swaps it in without being rebuilt or rebooted. The channel is read one
+//! byte at a time by a function like the one given to [`test::set_input`](crate::test::set_input),
+//! e.g. one polling a flashcart's USB FIFO.
+//!
+//! An asset is sent as a header line, followed by its data:
+//!
+//! ```text
+//! ##rrt0-asset <name> <length> <crc32>
+//! ```
+//!
+//! The length is in decimal, and the CRC-32 of the data in hexadecimal. Other lines are ignored.
+//! [`Receiver::poll`] reads what is available, e.g. once per frame, into a buffer, and calls a
+//! function with each asset received whole with a matching checksum:
+//!
+//! ```text
+//! receiver.poll(read_usb, |name, data| match name {
+//!     "levels/1.bin" => level.load(data),
+//!     _ => {}
+//! });
+//! ```
+
+use crate::checksum::crc32;
+
+/// Start of the header line of an asset
+pub const MARKER: &str = "##rrt0-asset";
+
+/// Longest asset name (in bytes). Assets with longer names are dropped, and their data skipped.
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Longest header line: the marker, name, length, and checksum with separators (in bytes)
+const MAX_HEADER_LEN: usize = MARKER.len() + MAX_NAME_LEN + 10 + 8 + 3;
+
+/// Receives assets from the host into a buffer.
+#[derive(Debug)]
+pub struct Receiver<'a> {
+    buffer: &'a mut [u8],
+    state: State,
+    line: [u8; MAX_HEADER_LEN],
+    line_len: usize,
+    /// Whether the line is longer than any header, and lost bytes after the marker
+    line_overflow: bool,
+    name: [u8; MAX_NAME_LEN],
+    name_len: usize,
+    dropped: usize,
+}
+
+#[derive(Debug)]
+enum State {
+    /// Reading a header line
+    Header,
+    /// Reading the data of an asset into the buffer
+    Data {
+        len: usize,
+        received: usize,
+        crc: u32,
+    },
+    /// Skipping the data of a dropped asset
+    Skip { remaining: usize },
+}
+
+impl<'a> Receiver<'a> {
+    /// Create a receiver, with a buffer as large as the largest asset.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer,
+            state: State::Header,
+            line: [0; MAX_HEADER_LEN],
+            line_len: 0,
+            line_overflow: false,
+            name: [0; MAX_NAME_LEN],
+            name_len: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Read bytes until the input has none available, calling a function with each asset received.
+    pub fn poll(&mut self, mut input: impl FnMut() -> Option<u8>, mut f: impl FnMut(&str, &[u8])) {
+        while let Some(byte) = input() {
+            self.state = match self.state {
+                State::Header => self.header(byte, &mut f),
+                State::Data { len, received, crc } => {
+                    self.buffer[received] = byte;
+                    match received + 1 {
+                        received if received < len => State::Data { len, received, crc },
+                        _ => {
+                            self.finish(len, crc, &mut f);
+                            State::Header
+                        }
+                    }
+                }
+                State::Skip { remaining: 1 } => State::Header,
+                State::Skip { remaining } => State::Skip {
+                    remaining: remaining - 1,
+                },
+            };
+        }
+    }
+
+    /// Number of assets dropped, because they did not fit in the buffer, had a name that was too
+    /// long, or had a checksum that did not match
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    fn header(&mut self, byte: u8, f: &mut impl FnMut(&str, &[u8])) -> State {
+        if byte != b'\n' {
+            // Lines longer than any header keep the marker with its separator, and their end,
+            // which has the length and checksum of an asset with a name that is too long
+            if self.line_len == self.line.len() {
+                self.line.copy_within(MARKER.len() + 2.., MARKER.len() + 1);
+                self.line_len -= 1;
+                self.line_overflow = true;
+            }
+            self.line[self.line_len] = byte;
+            self.line_len += 1;
+            return State::Header;
+        }
+
+        let line_len = core::mem::take(&mut self.line_len);
+        let overflow = core::mem::take(&mut self.line_overflow);
+        // Split as bytes, since a line that overflowed may start in the middle of a character
+        let mut fields = self.line[..line_len]
+            .split(u8::is_ascii_whitespace)
+            .filter(|field| !field.is_empty());
+        if fields.next() != Some(MARKER.as_bytes()) {
+            return State::Header;
+        }
+        let (name, len, crc) = match (fields.next(), fields.next_back(), fields.next_back()) {
+            (Some(name), Some(crc), Some(len)) => (name, len, crc),
+            _ => return State::Header,
+        };
+        let field = |field| core::str::from_utf8(field).unwrap_or("");
+        let (len, crc) = match (field(len).parse(), u32::from_str_radix(field(crc), 16)) {
+            (Ok(len), Ok(crc)) => (len, crc),
+            _ => return State::Header,
+        };
+
+        if overflow || name.len() > MAX_NAME_LEN || len > self.buffer.len() {
+            self.dropped += 1;
+            return match len {
+                0 => State::Header,
+                remaining => State::Skip { remaining },
+            };
+        }
+        self.name[..name.len()].copy_from_slice(name);
+        self.name_len = name.len();
+
+        match len {
+            0 => {
+                self.finish(0, crc, f);
+                State::Header
+            }
+            len => State::Data {
+                len,
+                received: 0,
+                crc,
+            },
+        }
+    }
+
+    fn finish(&mut self, len: usize, crc: u32, f: &mut impl FnMut(&str, &[u8])) {
+        let data = &self.buffer[..len];
+        if crc32(data) != crc {
+            self.dropped += 1;
+            return;
+        }
+
+        let name = core::str::from_utf8(&self.name[..self.name_len]).unwrap_or("");
+        f(name, data);
+    }
+}
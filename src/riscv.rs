@@ -0,0 +1,151 @@
+//! Generic bare-metal RISC-V support, for FPGA cores and handheld devices.
+//!
+//! The startup code runs the program in machine mode on hart 0, parking the other harts. With the
+//! `sbi` feature, it runs in supervisor mode on the hart started by an SBI implementation like
+//! OpenSBI instead.
+//!
+//! Traps are passed to the handler registered with [`set_trap_handler`], which can inspect and
+//! modify the interrupted context in a [`TrapFrame`]. Traps without a handler panic. On targets
+//! with floating point registers, the startup code enables them, and the trap entry saves them
+//! around the handler.
+//!
+//! [`Uart16550`] writes text to a 16550 compatible UART, the most common serial port on these
+//! systems, and `sbi_output` (with the `sbi` feature) to the SBI console, for printing
+//! diagnostics, e.g. as the output of the [test runner](crate::test::set_output):
+//!
+//! ```text
+//! fn output(s: &str) {
+//!     Uart16550::new(0x1000_0000, 0).write_str(s).ok();
+//! }
+//!
+//! rrt0::test::set_output(output);
+//! ```
+//!
+//! Only available on RISC-V targets without an OS.
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A function handling a trap.
+pub type TrapHandler = fn(&mut TrapFrame);
+
+/// The context saved by the trap entry, with the caller-saved registers and trap CSRs.
+#[derive(Debug)]
+#[repr(C)]
+pub struct TrapFrame {
+    pub ra: usize,
+    /// `t0` to `t6`
+    pub t: [usize; 7],
+    /// `a0` to `a7`
+    pub a: [usize; 8],
+    /// Where execution resumes when the handler returns
+    pub epc: usize,
+    pub cause: usize,
+    /// Trap value, e.g. the faulting address
+    pub tval: usize,
+    padding: usize,
+}
+
+/// Bit of the cause set for interrupts
+const CAUSE_INTERRUPT: usize = 1 << (usize::BITS - 1);
+
+impl TrapFrame {
+    /// Whether the trap is an interrupt, rather than an exception.
+    pub fn is_interrupt(&self) -> bool {
+        self.cause & CAUSE_INTERRUPT != 0
+    }
+
+    /// The interrupt or exception code.
+    pub fn code(&self) -> usize {
+        self.cause & !CAUSE_INTERRUPT
+    }
+
+    /// Resume after the instruction that caused the exception, e.g. an `ecall`.
+    pub fn skip_instruction(&mut self) {
+        // 32-bit instructions have both low bits set, and compressed ones do not
+        let low = unsafe { (self.epc as *const u16).read_volatile() };
+        self.epc += if low & 0b11 == 0b11 { 4 } else { 2 };
+    }
+}
+
+/// The handler as a function pointer
+static TRAP_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+/// Register the trap handler, replacing any previous one. `None` removes it.
+pub fn set_trap_handler(handler: Option<TrapHandler>) {
+    let handler = handler.map_or(0, |handler| handler as usize);
+    TRAP_HANDLER.store(handler, Ordering::Release);
+}
+
+#[no_mangle]
+extern "C" fn rrt0_trap(frame: &mut TrapFrame) {
+    let handler = match TRAP_HANDLER.load(Ordering::Acquire) {
+        0 => panic!("Unhandled trap"),
+        // Only function pointers are stored
+        handler => unsafe { core::mem::transmute::<usize, TrapHandler>(handler) },
+    };
+
+    handler(frame);
+}
+
+/// Receive buffer/transmit holding, and line status register indices
+const UART_THR: usize = 0;
+const UART_LSR: usize = 5;
+
+/// Transmit holding register empty flag in the line status register
+const UART_LSR_THRE: u8 = 0x20;
+
+/// Text output to a 16550 compatible UART.
+#[derive(Debug)]
+pub struct Uart16550 {
+    base: usize,
+    shift: u32,
+}
+
+impl Uart16550 {
+    /// Access a UART at a base address, with registers `1 << shift` bytes apart. The UART is
+    /// expected to be configured by the firmware or the program.
+    pub const fn new(base: usize, shift: u32) -> Self {
+        Self { base, shift }
+    }
+
+    fn register(&self, index: usize) -> *mut u8 {
+        (self.base + (index << self.shift)) as *mut u8
+    }
+
+    /// Write a byte, waiting for room in the transmitter.
+    pub fn write_byte(&mut self, byte: u8) {
+        while unsafe { self.register(UART_LSR).read_volatile() } & UART_LSR_THRE == 0 {}
+
+        unsafe { self.register(UART_THR).write_volatile(byte) };
+    }
+}
+
+impl fmt::Write for Uart16550 {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+
+        Ok(())
+    }
+}
+
+/// Write text to the SBI console, with the signature of an output function for the
+/// [test runner](crate::test::set_output).
+#[cfg(feature = "sbi")]
+pub fn sbi_output(s: &str) {
+    /// Legacy console putchar extension
+    const SBI_CONSOLE_PUTCHAR: usize = 0x01;
+
+    for byte in s.bytes() {
+        unsafe {
+            core::arch::asm!(
+                "ecall",
+                inout("a0") byte as usize => _,
+                in("a7") SBI_CONSOLE_PUTCHAR,
+                options(nostack),
+            );
+        }
+    }
+}
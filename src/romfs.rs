@@ -0,0 +1,318 @@
+//! A read-only filesystem stored in the cartridge ROM.
+//!
+//! Assets embedded with `include_bytes!` are loaded into RDRAM with the rest of the program. A
+//! filesystem image instead stays in the ROM, and files are read from it on demand with PI DMA:
+//!
+//! ```text
+//! romfs::mount(ASSETS_ROM_OFFSET)?;
+//! let mut hero = romfs::open("sprites/hero.tga")?;
+//! hero.read(&mut buffer);
+//! ```
+//!
+//! The image is built on the host, e.g. by the program that packs the ROM, from a list of assets
+//! collected with [`include_assets!`](crate::include_assets) and encoded with [`encode`]. It is
+//! then appended to the ROM image at an offset the program is built to know.
+//!
+//! The encoded image is big-endian: the magic `ROMF`, the number of files, then for each file its
+//! data offset and length, and name offset and length (as `offset << 8 | length`), sorted by name,
+//! followed by the names, then the data of each file aligned to 8 bytes for DMA. Offsets are from
+//! the start of the image.
+
+use core::fmt;
+
+const MAGIC: &[u8; 4] = b"ROMF";
+const HEADER_SIZE: usize = 8;
+const ENTRY_SIZE: usize = 12;
+
+/// Alignment of file data in an image (in bytes)
+const DATA_ALIGN: usize = 8;
+
+/// Longest file name stored in an image (in bytes). Longer names are truncated.
+pub const MAX_NAME_LEN: usize = 0xFF;
+
+/// Largest offset of a name in an image, which is stored in 24 bits
+const MAX_NAME_OFFSET: usize = 0xFF_FFFF;
+
+/// Collect a list of files, relative to the crate root, into an array of [`Asset`]s named by
+/// their paths.
+///
+/// e.g. `let mut assets = include_assets!("sprites/hero.tga", "music/title.xm");`
+#[macro_export]
+macro_rules! include_assets {
+    ($($path:literal),* $(,)?) => {
+        [$($crate::romfs::Asset {
+            name: $path,
+            data: ::core::include_bytes!(::core::concat!(
+                ::core::env!("CARGO_MANIFEST_DIR"),
+                "/",
+                $path,
+            )),
+        }),*]
+    };
+}
+
+/// A file to store in an image.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Asset<'a> {
+    pub name: &'a str,
+    pub data: &'a [u8],
+}
+
+/// Errors that can occur while encoding an image.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EncodeError {
+    /// The output buffer is smaller than [`encoded_size`].
+    Capacity,
+    /// The names do not all start in the first 16 MiB of the image.
+    NamesTooLarge,
+    /// Two assets have the same name, once truncated to [`MAX_NAME_LEN`].
+    DuplicateName,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Capacity => "output buffer too small",
+            Self::NamesTooLarge => "file names too large",
+            Self::DuplicateName => "duplicate file name",
+        })
+    }
+}
+
+/// Size of the image [`encode`] creates from a list of assets (in bytes).
+pub fn encoded_size(assets: &[Asset<'_>]) -> usize {
+    let names: usize = assets.iter().map(|asset| name_len(asset.name)).sum();
+    let data: usize = assets.iter().map(|asset| align(asset.data.len())).sum();
+
+    align(HEADER_SIZE + assets.len() * ENTRY_SIZE + names) + data
+}
+
+/// Encode a list of assets as an image, sorting them by name. Returns the size of the image,
+/// which is [`encoded_size`].
+pub fn encode(assets: &mut [Asset<'_>], out: &mut [u8]) -> Result<usize, EncodeError> {
+    let size = encoded_size(assets);
+    let out = out.get_mut(..size).ok_or(EncodeError::Capacity)?;
+    assets.sort_unstable_by_key(|asset| stored_name(asset.name));
+
+    if assets
+        .windows(2)
+        .any(|pair| stored_name(pair[0].name) == stored_name(pair[1].name))
+    {
+        return Err(EncodeError::DuplicateName);
+    }
+
+    // The names are stored in order, so the last one starts furthest
+    let names: usize = assets.iter().map(|asset| name_len(asset.name)).sum();
+    let last_name = assets.last().map_or(0, |asset| name_len(asset.name));
+    if HEADER_SIZE + assets.len() * ENTRY_SIZE + names - last_name > MAX_NAME_OFFSET {
+        return Err(EncodeError::NamesTooLarge);
+    }
+
+    out.fill(0);
+
+    out[..4].copy_from_slice(MAGIC);
+    out[4..8].copy_from_slice(&(assets.len() as u32).to_be_bytes());
+
+    let mut name_offset = HEADER_SIZE + assets.len() * ENTRY_SIZE;
+    let data: usize = assets.iter().map(|asset| align(asset.data.len())).sum();
+    let mut data_offset = size - data;
+    for (index, asset) in assets.iter().enumerate() {
+        let len = name_len(asset.name);
+        out[name_offset..name_offset + len].copy_from_slice(&asset.name.as_bytes()[..len]);
+        out[data_offset..data_offset + asset.data.len()].copy_from_slice(asset.data);
+
+        let entry = &mut out[HEADER_SIZE + index * ENTRY_SIZE..][..ENTRY_SIZE];
+        entry[0..4].copy_from_slice(&(data_offset as u32).to_be_bytes());
+        entry[4..8].copy_from_slice(&(asset.data.len() as u32).to_be_bytes());
+        entry[8..12].copy_from_slice(&((name_offset << 8 | len) as u32).to_be_bytes());
+        name_offset += len;
+        data_offset += align(asset.data.len());
+    }
+
+    Ok(size)
+}
+
+/// Length of a name once stored, truncated on a character boundary.
+fn name_len(name: &str) -> usize {
+    let mut len = name.len().min(MAX_NAME_LEN);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    len
+}
+
+/// A name as stored, truncated to [`MAX_NAME_LEN`].
+fn stored_name(name: &str) -> &[u8] {
+    &name.as_bytes()[..name_len(name)]
+}
+
+fn align(len: usize) -> usize {
+    (len + DATA_ALIGN - 1) & !(DATA_ALIGN - 1)
+}
+
+#[cfg(rrt0_platform = "n64")]
+pub use self::platform::*;
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use super::{name_len, ENTRY_SIZE, HEADER_SIZE, MAGIC, MAX_NAME_LEN};
+    use crate::interrupt::without_interrupts;
+    use crate::io::{self, SeekFrom};
+    use crate::mem::read_rom;
+    use core::cell::UnsafeCell;
+    use core::convert::Infallible;
+    use core::fmt;
+
+    /// Errors that can occur while opening a file.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum RomfsError {
+        /// The ROM offset does not hold an image.
+        Invalid,
+        /// No image is mounted.
+        NotMounted,
+        /// No file has the name.
+        NotFound,
+    }
+
+    impl fmt::Display for RomfsError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(match self {
+                Self::Invalid => "not a filesystem image",
+                Self::NotMounted => "no filesystem mounted",
+                Self::NotFound => "file not found",
+            })
+        }
+    }
+
+    /// A mounted image: its ROM offset and number of files.
+    struct Mount(UnsafeCell<Option<(u32, u32)>>);
+
+    // Only accessed with interrupts disabled
+    unsafe impl Sync for Mount {}
+
+    static MOUNT: Mount = Mount(UnsafeCell::new(None));
+
+    /// Mount the image stored at a ROM offset, replacing any mounted image.
+    pub fn mount(rom_offset: u32) -> Result<(), RomfsError> {
+        let mut header = [0; HEADER_SIZE];
+        read_rom(&mut header, rom_offset);
+        if header[..4] != *MAGIC {
+            return Err(RomfsError::Invalid);
+        }
+        let count = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+
+        without_interrupts(|| unsafe { *MOUNT.0.get() = Some((rom_offset, count)) });
+
+        Ok(())
+    }
+
+    /// Open a file in the mounted image by name.
+    pub fn open(name: &str) -> Result<File, RomfsError> {
+        let (image, count) =
+            without_interrupts(|| unsafe { *MOUNT.0.get() }).ok_or(RomfsError::NotMounted)?;
+        let name = &name.as_bytes()[..name_len(name)];
+
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let mid = (low + high) / 2;
+            let mut entry = [0; ENTRY_SIZE];
+            read_rom(
+                &mut entry,
+                image + HEADER_SIZE as u32 + mid * ENTRY_SIZE as u32,
+            );
+            let [offset, len, name_field] = [0, 4, 8].map(|at| {
+                u32::from_be_bytes([entry[at], entry[at + 1], entry[at + 2], entry[at + 3]])
+            });
+
+            let mut buffer = [0; MAX_NAME_LEN];
+            let entry_name = &mut buffer[..(name_field & 0xFF) as usize];
+            read_rom(entry_name, image + (name_field >> 8));
+
+            match (*entry_name).cmp(name) {
+                core::cmp::Ordering::Less => low = mid + 1,
+                core::cmp::Ordering::Greater => high = mid,
+                core::cmp::Ordering::Equal => {
+                    return Ok(File {
+                        rom_offset: image + offset,
+                        len,
+                        position: 0,
+                    })
+                }
+            }
+        }
+
+        Err(RomfsError::NotFound)
+    }
+
+    /// An open file, read from the ROM as needed.
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub struct File {
+        rom_offset: u32,
+        len: u32,
+        position: u32,
+    }
+
+    impl File {
+        /// Offset of the file data in the ROM, for streaming it with DMA directly
+        pub fn rom_offset(&self) -> u32 {
+            self.rom_offset
+        }
+
+        /// Size of the file (in bytes)
+        pub fn len(&self) -> usize {
+            self.len as usize
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len == 0
+        }
+
+        /// Current position (in bytes)
+        pub fn position(&self) -> usize {
+            self.position as usize
+        }
+
+        /// Move to a position, up to the end of the file.
+        pub fn seek(&mut self, position: usize) {
+            self.position = position.min(self.len()) as u32;
+        }
+
+        /// Read bytes from the current position, advancing it. Returns the number of bytes read,
+        /// which is less than the buffer length at the end of the file.
+        pub fn read(&mut self, buffer: &mut [u8]) -> usize {
+            let len = self.read_at(buffer, self.position as usize);
+            self.position += len as u32;
+
+            len
+        }
+
+        /// Read bytes from a position, without changing the current position. Returns the number
+        /// of bytes read.
+        pub fn read_at(&self, buffer: &mut [u8], position: usize) -> usize {
+            let len = buffer.len().min(self.len().saturating_sub(position));
+            read_rom(&mut buffer[..len], self.rom_offset + position as u32);
+
+            len
+        }
+    }
+
+    impl io::ErrorType for File {
+        type Error = Infallible;
+    }
+
+    impl io::Read for File {
+        fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+            Ok(File::read(self, buffer))
+        }
+    }
+
+    impl io::Seek for File {
+        fn seek(&mut self, position: SeekFrom) -> Result<u64, Self::Error> {
+            let position = io::seek_position(position, self.position as u64, self.len as u64);
+            self.position = position as u32;
+
+            Ok(position)
+        }
+    }
+}
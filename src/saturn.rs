@@ -0,0 +1,157 @@
+//! Sega Saturn support.
+//!
+//! The BIOS only boots a disc whose first sectors hold an IP.BIN: a boot header, followed by the
+//! security code and area code objects from the Sega SDK, which this crate cannot provide. The
+//! program declares the header once, at the top level, with
+//! [`saturn_boot_header!`](crate::saturn_boot_header), and its linker script places the
+//! `.boot_header` section at the start of IP.BIN, before the Sega objects:
+//!
+//! ```text
+//! rrt0::saturn_boot_header!(BootHeader::new("T-99901G", "HELLO FROM RRT0", 0x1800));
+//! ```
+//!
+//! [`Sci`] writes text to the serial interface of the master SH-2, for printing diagnostics, e.g.
+//! as the output of the [test runner](crate::test::set_output).
+//!
+//! rustc has no inline assembly for SuperH, so the startup code in
+//! `src/platforms/saturn/crt0.s` is assembled and linked by the program's build.
+//!
+//! Only available on Saturn.
+
+use core::fmt;
+
+/// Size of the boot header (in bytes)
+pub const BOOT_HEADER_SIZE: usize = 0x100;
+
+/// Where the BIOS loads the program, and the stack pointer of the master SH-2, by default
+pub const DEFAULT_LOAD_ADDRESS: u32 = 0x0600_4000;
+pub const DEFAULT_STACK: u32 = 0x0600_2000;
+
+/// Declare the boot header of the program. Use it once, at the top level of the program.
+#[macro_export]
+macro_rules! saturn_boot_header {
+    ($header:expr) => {
+        #[no_mangle]
+        #[used]
+        #[link_section = ".boot_header"]
+        pub static RRT0_BOOT_HEADER: $crate::saturn::BootHeader = $header;
+    };
+}
+
+/// The boot header at the start of IP.BIN.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct BootHeader([u8; BOOT_HEADER_SIZE]);
+
+impl BootHeader {
+    /// Create a boot header for all regions, which loads the program to [`DEFAULT_LOAD_ADDRESS`].
+    /// `product` is the product number (up to 10 bytes), `title` the game title (up to 112
+    /// bytes), and `ip_size` the size of the whole IP.BIN (in bytes). Longer text is cut.
+    pub const fn new(product: &str, title: &str, ip_size: u32) -> Self {
+        let mut bytes = [b' '; BOOT_HEADER_SIZE];
+        bytes = copy(bytes, 0x00, 16, "SEGA SEGASATURN ");
+        bytes = copy(bytes, 0x10, 16, "SEGA TP RRT0");
+        bytes = copy(bytes, 0x20, 10, product);
+        bytes = copy(bytes, 0x2A, 6, "V1.000");
+        bytes = copy(bytes, 0x30, 8, "20000101");
+        bytes = copy(bytes, 0x38, 8, "CD-1/1");
+        bytes = copy(bytes, 0x40, 16, "JTUBKAEL");
+        bytes = copy(bytes, 0x50, 16, "J");
+        bytes = copy(bytes, 0x60, 112, title);
+
+        let mut header = Self(bytes);
+        header.0 = word(header.0, 0xE0, ip_size);
+        header.0 = word(header.0, 0xE4, 0);
+        header.0 = word(header.0, 0xE8, DEFAULT_STACK);
+        header.0 = word(header.0, 0xEC, 0);
+        header.0 = word(header.0, 0xF0, DEFAULT_LOAD_ADDRESS);
+        header.0 = word(header.0, 0xF4, 0);
+        header.0 = word(header.0, 0xF8, 0);
+        header.0 = word(header.0, 0xFC, 0);
+
+        header
+    }
+}
+
+/// Copy text into a field, padded with spaces.
+const fn copy(
+    mut bytes: [u8; BOOT_HEADER_SIZE],
+    offset: usize,
+    len: usize,
+    text: &str,
+) -> [u8; BOOT_HEADER_SIZE] {
+    let text = text.as_bytes();
+    let mut index = 0;
+    while index < len && index < text.len() {
+        bytes[offset + index] = text[index];
+        index += 1;
+    }
+
+    bytes
+}
+
+/// Store a big-endian word.
+const fn word(
+    mut bytes: [u8; BOOT_HEADER_SIZE],
+    offset: usize,
+    value: u32,
+) -> [u8; BOOT_HEADER_SIZE] {
+    let value = value.to_be_bytes();
+    let mut index = 0;
+    while index < 4 {
+        bytes[offset + index] = value[index];
+        index += 1;
+    }
+
+    bytes
+}
+
+/// Serial mode, bit rate, control, transmit data and status registers of the SH-2 SCI
+const SMR: *mut u8 = 0xFFFF_FE00 as *mut u8;
+const BRR: *mut u8 = 0xFFFF_FE01 as *mut u8;
+const SCR: *mut u8 = 0xFFFF_FE02 as *mut u8;
+const TDR: *mut u8 = 0xFFFF_FE03 as *mut u8;
+const SSR: *mut u8 = 0xFFFF_FE04 as *mut u8;
+
+/// Transmit enable bit in `SCR`, and transmit data register empty flag in `SSR`
+const SCR_TE: u8 = 0x20;
+const SSR_TDRE: u8 = 0x80;
+
+/// Text output to the serial interface of the master SH-2.
+#[derive(Debug)]
+pub struct Sci(());
+
+impl Sci {
+    /// Configure the interface for asynchronous 8N1 transmission. The bit rate register value is
+    /// `Pφ / (32 * baud) - 1`, e.g. 45 for 9600 baud with the 14.3 MHz peripheral clock.
+    pub fn init(bit_rate: u8) -> Self {
+        unsafe {
+            SCR.write_volatile(0);
+            SMR.write_volatile(0);
+            BRR.write_volatile(bit_rate);
+            SCR.write_volatile(SCR_TE);
+        }
+
+        Self(())
+    }
+
+    /// Write a byte, waiting for the previous one to be sent.
+    pub fn write_byte(&mut self, byte: u8) {
+        while unsafe { SSR.read_volatile() } & SSR_TDRE == 0 {}
+
+        unsafe {
+            TDR.write_volatile(byte);
+            SSR.write_volatile(SSR.read_volatile() & !SSR_TDRE);
+        }
+    }
+}
+
+impl fmt::Write for Sci {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+//! Scene stack for the game loop.
+//!
+//! A program defines its scenes, like the title screen, gameplay and a pause menu, as the variants
+//! of one type implementing [`Scene`], and runs a [`SceneStack`] of them as its
+//! [`App`](crate::app::App). Only the top scene is updated, and it moves between scenes by
+//! returning a [`Transition`]:
+//!
+//! ```text
+//! enum Game {
+//!     Title,
+//!     Level(Level),
+//!     Paused,
+//! }
+//!
+//! impl Scene for Game {
+//!     fn update(&mut self, dt: Duration, input: &Input) -> Transition<Self> {
+//!         match self {
+//!             Game::Title if input.is_pressed(BUTTON_START) => {
+//!                 Transition::Replace(Game::Level(Level::new(1)))
+//!             }
+//!             Game::Level(_) if input.is_pressed(BUTTON_START) => Transition::Push(Game::Paused),
+//!             Game::Level(level) => level.update(dt, input),
+//!             Game::Paused if input.is_pressed(BUTTON_START) => Transition::Pop,
+//!             _ => Transition::None,
+//!         }
+//!     }
+//!     ...
+//! }
+//!
+//! rrt0::app::run(SceneStack::<Game, 4>::new(Game::Title), &mut backend);
+//! ```
+//!
+//! The stack holds up to `N` scenes in place, so it needs no allocator. The loop ends when the
+//! last scene is popped.
+
+use crate::app::{App, Frame, Input};
+use crate::collections::Vec;
+use core::time::Duration;
+
+/// What the top scene does after an update.
+#[derive(Debug)]
+pub enum Transition<S> {
+    /// Stay in the scene
+    None,
+    /// Enter a scene on top of this one, e.g. a pause menu
+    Push(S),
+    /// Leave this scene, returning to the one below
+    Pop,
+    /// Leave this scene for another
+    Replace(S),
+    /// Leave all scenes, ending the loop
+    Quit,
+}
+
+/// A scene of a [`SceneStack`]. `P` is the pixel type of the backend's frame buffers.
+pub trait Scene<P = u16>: Sized {
+    /// Called when the scene becomes the top of the stack, the first time or after the scene on
+    /// top of it is popped.
+    fn enter(&mut self) {}
+
+    /// Called when the scene stops being the top of the stack, before it is removed or another
+    /// scene is pushed on top of it.
+    fn exit(&mut self) {}
+
+    fn update(&mut self, dt: Duration, input: &Input) -> Transition<Self>;
+
+    fn draw(&mut self, frame: &mut Frame<'_, P>);
+
+    /// Whether the scene below is drawn first, e.g. for a pause menu over the paused game.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// A stack of up to `N` scenes.
+pub struct SceneStack<S, const N: usize> {
+    scenes: Vec<S, N>,
+}
+
+impl<S, const N: usize> SceneStack<S, N> {
+    /// Create a stack and enter its first scene.
+    pub fn new<P>(scene: S) -> Self
+    where
+        S: Scene<P>,
+    {
+        let mut stack = Self { scenes: Vec::new() };
+        stack.push(scene);
+
+        stack
+    }
+
+    /// Number of scenes on the stack
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    /// The top scene
+    pub fn top(&self) -> Option<&S> {
+        self.scenes.last()
+    }
+
+    pub fn top_mut(&mut self) -> Option<&mut S> {
+        self.scenes.last_mut()
+    }
+
+    /// Exit the top scene, and enter a scene on top of it. Panics if the stack is full.
+    pub fn push<P>(&mut self, mut scene: S)
+    where
+        S: Scene<P>,
+    {
+        assert!(!self.scenes.is_full(), "scene stack is full");
+
+        if let Some(top) = self.scenes.last_mut() {
+            top.exit();
+        }
+        scene.enter();
+        self.scenes.push(scene).ok();
+    }
+
+    /// Exit and remove the top scene, and enter the one below it.
+    pub fn pop<P>(&mut self) -> Option<S>
+    where
+        S: Scene<P>,
+    {
+        let mut scene = self.scenes.pop()?;
+        scene.exit();
+        if let Some(top) = self.scenes.last_mut() {
+            top.enter();
+        }
+
+        Some(scene)
+    }
+
+    /// Exit and remove the top scene, and enter another in its place.
+    pub fn replace<P>(&mut self, mut scene: S) -> Option<S>
+    where
+        S: Scene<P>,
+    {
+        let mut previous = self.scenes.pop();
+        if let Some(previous) = &mut previous {
+            previous.exit();
+        }
+        scene.enter();
+        self.scenes.push(scene).ok();
+
+        previous
+    }
+
+    /// Exit and remove all scenes, from the top.
+    pub fn clear<P>(&mut self)
+    where
+        S: Scene<P>,
+    {
+        while let Some(mut scene) = self.scenes.pop() {
+            scene.exit();
+        }
+    }
+}
+
+impl<S: Scene<P>, P, const N: usize> App<P> for SceneStack<S, N> {
+    fn update(&mut self, dt: Duration, input: &Input) {
+        let transition = match self.scenes.last_mut() {
+            Some(top) => top.update(dt, input),
+            None => return,
+        };
+
+        match transition {
+            Transition::None => {}
+            Transition::Push(scene) => self.push::<P>(scene),
+            Transition::Pop => {
+                self.pop::<P>();
+            }
+            Transition::Replace(scene) => {
+                self.replace::<P>(scene);
+            }
+            Transition::Quit => self.clear::<P>(),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame<'_, P>) {
+        // Draw the top scene over the overlays below it, and the first scene that is not one
+        let first = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        for scene in &mut self.scenes[first..] {
+            scene.draw(frame);
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.scenes.is_empty()
+    }
+}
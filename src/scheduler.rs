@@ -0,0 +1,128 @@
+//! Periodic tasks.
+//!
+//! A [`Scheduler`] runs callbacks at fixed rates, every frame, every few frames, or at an interval
+//! of time, so housekeeping like autosaving or polling accessories needs no counters of its own.
+//! The program advances it once per frame, with the time the frame took:
+//!
+//! ```text
+//! let mut scheduler = Scheduler::<Game, 8>::new();
+//! scheduler.add(Rate::Interval(Duration::from_secs(60)), Game::autosave)?;
+//! scheduler.add(Rate::Frames(2), Game::poll_rumble_pak)?;
+//!
+//! // In the update of the game loop
+//! scheduler.tick(dt, &mut game);
+//! ```
+//!
+//! Time comes from the caller, e.g. the `dt` of the [game loop](crate::app), or
+//! [`Instant::elapsed`](crate::time::Instant::elapsed) since the previous tick. A task that falls
+//! more than one interval behind runs once, and skips the runs it missed.
+//!
+//! Callbacks get a mutable context of type `C`, the state they work on, in place of closures.
+
+use crate::collections::CapacityError;
+use crate::pool::{Handle, Pool};
+use core::time::Duration;
+
+/// How often a task runs.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Rate {
+    EveryFrame,
+    /// Every `n` frames, starting with the `n`-th
+    Frames(u32),
+    /// Whenever the interval has passed since the task last ran, or was added
+    Interval(Duration),
+}
+
+struct Task<C> {
+    rate: Rate,
+    callback: fn(&mut C),
+    /// Frames or time since the task last ran
+    frames: u32,
+    elapsed: Duration,
+}
+
+/// Up to `N` periodic tasks on a context of type `C`.
+pub struct Scheduler<C, const N: usize> {
+    tasks: Pool<Task<C>, N>,
+}
+
+impl<C, const N: usize> Scheduler<C, N> {
+    pub fn new() -> Self {
+        Self { tasks: Pool::new() }
+    }
+
+    /// Add a task. Fails if the scheduler is full.
+    pub fn add(&mut self, rate: Rate, callback: fn(&mut C)) -> Result<Handle, CapacityError> {
+        self.tasks
+            .insert(Task {
+                rate,
+                callback,
+                frames: 0,
+                elapsed: Duration::ZERO,
+            })
+            .map_err(|_| CapacityError)
+    }
+
+    /// Remove a task. Returns `false` if the handle is stale.
+    pub fn remove(&mut self, handle: Handle) -> bool {
+        self.tasks.remove(handle).is_some()
+    }
+
+    /// Change the rate of a task, counting from now. Returns `false` if the handle is stale.
+    pub fn set_rate(&mut self, handle: Handle, rate: Rate) -> bool {
+        match self.tasks.get_mut(handle) {
+            Some(task) => {
+                task.rate = rate;
+                task.frames = 0;
+                task.elapsed = Duration::ZERO;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of tasks
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Advance by one frame that took `dt`, and run the tasks that are due, in the order they
+    /// were added to free slots.
+    pub fn tick(&mut self, dt: Duration, context: &mut C) {
+        for (_, task) in self.tasks.iter_mut() {
+            task.frames = task.frames.saturating_add(1);
+            task.elapsed += dt;
+
+            let due = match task.rate {
+                Rate::EveryFrame => true,
+                Rate::Frames(n) => task.frames >= n.max(1),
+                Rate::Interval(interval) if task.elapsed >= interval => {
+                    // Keep the phase, but drop the runs that were missed
+                    task.elapsed = match interval.is_zero() {
+                        true => Duration::ZERO,
+                        false => Duration::from_nanos(
+                            (task.elapsed.as_nanos() % interval.as_nanos()) as u64,
+                        ),
+                    };
+                    true
+                }
+                Rate::Interval(_) => false,
+            };
+
+            if due {
+                task.frames = 0;
+                (task.callback)(context);
+            }
+        }
+    }
+}
+
+impl<C, const N: usize> Default for Scheduler<C, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,134 @@
+//! SD card block access.
+//!
+//! Storage is accessed in 512-byte sectors through the [`BlockDevice`] trait, which the
+//! [`fat`](crate::fat) filesystem is built on. On N64, [`SummerCart64`] reads and writes the SD
+//! card of a SummerCart64 through its command registers.
+//!
+//! The EverDrive-64 SD interface is not supported: its command sequence is not documented well
+//! enough to implement without the hardware. Other devices can implement [`BlockDevice`].
+
+use core::fmt;
+
+/// Size of a sector (in bytes)
+pub const SECTOR_SIZE: usize = 512;
+
+/// A block device failed to read or write.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BlockError;
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("block device error")
+    }
+}
+
+/// Storage read and written in sectors.
+pub trait BlockDevice {
+    /// Read a sector.
+    fn read(&mut self, sector: u32, buffer: &mut [u8; SECTOR_SIZE]) -> Result<(), BlockError>;
+
+    /// Write a sector.
+    fn write(&mut self, sector: u32, buffer: &[u8; SECTOR_SIZE]) -> Result<(), BlockError>;
+}
+
+#[cfg(rrt0_platform = "n64")]
+pub use self::platform::*;
+
+#[cfg(rrt0_platform = "n64")]
+mod platform {
+    use super::{BlockDevice, BlockError, SECTOR_SIZE};
+    use crate::env::{self, Flashcart};
+    use crate::mem::{map, read_rom};
+    use crate::mmio::PI;
+
+    /// `SCR` register: the command when written, the status when read
+    const COMMAND: *mut u32 = map::to_kseg1(map::SUMMERCART_REGS) as *mut u32;
+    const DATA0: *mut u32 = map::to_kseg1(map::SUMMERCART_REGS + 0x04) as *mut u32;
+    const DATA1: *mut u32 = map::to_kseg1(map::SUMMERCART_REGS + 0x08) as *mut u32;
+
+    const STATUS_BUSY: u32 = 1 << 31;
+    const STATUS_ERROR: u32 = 1 << 30;
+
+    const SD_CARD_OP: u8 = b'i';
+    const SD_SECTOR_SET: u8 = b's';
+    const SD_READ: u8 = b'S';
+    const SD_WRITE: u8 = b'D';
+
+    /// `SD_CARD_OP` operation initializing the card
+    const SD_CARD_INIT: u32 = 1;
+
+    /// Physical address of the buffer that sectors are transferred through
+    const BUFFER: u32 = map::SUMMERCART_BUFFER as u32;
+    /// Offset of the buffer from the start of the ROM
+    const BUFFER_ROM_OFFSET: u32 = (map::SUMMERCART_BUFFER - map::ROM) as u32;
+    /// Uncached address of the buffer, for writes
+    const BUFFER_UNCACHED: *mut u32 = map::to_kseg1(map::SUMMERCART_BUFFER) as *mut u32;
+
+    /// The SD card of a SummerCart64.
+    #[derive(Debug)]
+    pub struct SummerCart64 {
+        _private: (),
+    }
+
+    impl SummerCart64 {
+        /// Initialize the SD card. Fails if the program is not running from a SummerCart64, or
+        /// no card is inserted.
+        pub fn init() -> Result<Self, BlockError> {
+            // Detection also unlocks the registers
+            if env::detect().flashcart != Some(Flashcart::SummerCart64) {
+                return Err(BlockError);
+            }
+            command(SD_CARD_OP, 0, SD_CARD_INIT)?;
+
+            Ok(Self { _private: () })
+        }
+    }
+
+    impl BlockDevice for SummerCart64 {
+        fn read(&mut self, sector: u32, buffer: &mut [u8; SECTOR_SIZE]) -> Result<(), BlockError> {
+            command(SD_SECTOR_SET, sector, 0)?;
+            command(SD_READ, BUFFER, 1)?;
+            read_rom(buffer, BUFFER_ROM_OFFSET);
+
+            Ok(())
+        }
+
+        fn write(&mut self, sector: u32, buffer: &[u8; SECTOR_SIZE]) -> Result<(), BlockError> {
+            for (index, word) in buffer.chunks_exact(4).enumerate() {
+                let word = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+                wait_for_pi();
+                unsafe { BUFFER_UNCACHED.add(index).write_volatile(word) };
+            }
+            command(SD_SECTOR_SET, sector, 0)?;
+            command(SD_WRITE, BUFFER, 1)
+        }
+    }
+
+    /// Run a command and wait for it to finish.
+    fn command(command: u8, data0: u32, data1: u32) -> Result<(), BlockError> {
+        wait_for_pi();
+        unsafe { DATA0.write_volatile(data0) };
+        wait_for_pi();
+        unsafe { DATA1.write_volatile(data1) };
+        wait_for_pi();
+        unsafe { COMMAND.write_volatile(command as u32) };
+
+        loop {
+            wait_for_pi();
+            let status = unsafe { COMMAND.read_volatile() };
+            if status & STATUS_BUSY == 0 {
+                return match status & STATUS_ERROR {
+                    0 => Ok(()),
+                    _ => Err(BlockError),
+                };
+            }
+        }
+    }
+
+    fn wait_for_pi() {
+        while {
+            let status = PI.status.read();
+            status.dma_busy() || status.io_busy()
+        } {}
+    }
+}
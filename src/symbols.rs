@@ -0,0 +1,280 @@
+//! Symbol tables, for turning code addresses into function names.
+//!
+//! A symbol table is built on the host after linking, from the output of `nm -S` for the program
+//! ELF, with [`parse_nm_line`] and [`encode`]. It can then be appended to the ROM image and read
+//! into memory with [`read_rom`](crate::mem::read_rom) at startup, or when a crash screen needs
+//! it. [`SymbolTable::lookup`] finds the function containing an address, like those returned by
+//! [`backtrace::capture`](crate::backtrace::capture).
+//!
+//! Rust symbol names are mangled. [`Demangle`] formats a name like
+//! `_ZN4game6player6update17h0123456789abcdefE` as `game::player::update`. Names can be demangled
+//! either before encoding, to save the work at runtime, or after lookup.
+//!
+//! The encoded table is big-endian: the magic `SYMB`, the number of symbols, then for each symbol
+//! its address, size, and name offset and length (as `offset << 8 | length`), sorted by address,
+//! followed by the names.
+
+use crate::collections::CapacityError;
+use core::fmt;
+
+const MAGIC: &[u8; 4] = b"SYMB";
+const HEADER_SIZE: usize = 8;
+const ENTRY_SIZE: usize = 12;
+
+/// Longest name stored in a table (in bytes). Longer names are truncated.
+pub const MAX_NAME_LEN: usize = 0xFF;
+
+/// A named range of code.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Symbol<'a> {
+    pub address: u32,
+    /// Size (in bytes), or 0 if unknown
+    pub size: u32,
+    pub name: &'a str,
+}
+
+/// Parse a code symbol from a line of `nm -S` output, e.g.
+/// `80001234 00000040 T _ZN4game4main17h0123456789abcdefE`. Returns `None` for other lines.
+pub fn parse_nm_line(line: &str) -> Option<Symbol<'_>> {
+    let mut fields = line.split_whitespace();
+    let address = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let (size, kind) = match fields.next()? {
+        kind if kind.len() == 1 => (0, kind),
+        size => (u32::from_str_radix(size, 16).ok()?, fields.next()?),
+    };
+    let name = fields.next()?;
+
+    matches!(kind, "T" | "t" | "W" | "w").then(|| Symbol {
+        address,
+        size,
+        name,
+    })
+}
+
+/// Size of the table [`encode`] creates from a list of symbols (in bytes).
+pub fn encoded_size(symbols: &[Symbol<'_>]) -> usize {
+    let names: usize = symbols.iter().map(|symbol| name_len(symbol.name)).sum();
+
+    HEADER_SIZE + symbols.len() * ENTRY_SIZE + names
+}
+
+/// Encode a list of symbols as a table, sorting them by address. Returns the size of the table,
+/// which is [`encoded_size`].
+pub fn encode(symbols: &mut [Symbol<'_>], out: &mut [u8]) -> Result<usize, CapacityError> {
+    let size = encoded_size(symbols);
+    let out = out.get_mut(..size).ok_or(CapacityError)?;
+    symbols.sort_unstable_by_key(|symbol| symbol.address);
+
+    out[..4].copy_from_slice(MAGIC);
+    out[4..8].copy_from_slice(&(symbols.len() as u32).to_be_bytes());
+
+    let (entries, names) = out[HEADER_SIZE..].split_at_mut(symbols.len() * ENTRY_SIZE);
+    let mut offset = 0;
+    for (symbol, entry) in symbols.iter().zip(entries.chunks_exact_mut(ENTRY_SIZE)) {
+        let len = name_len(symbol.name);
+        names[offset..offset + len].copy_from_slice(&symbol.name.as_bytes()[..len]);
+
+        entry[0..4].copy_from_slice(&symbol.address.to_be_bytes());
+        entry[4..8].copy_from_slice(&symbol.size.to_be_bytes());
+        entry[8..12].copy_from_slice(&((offset << 8 | len) as u32).to_be_bytes());
+        offset += len;
+    }
+
+    Ok(size)
+}
+
+/// Length of a name once stored, truncated on a character boundary.
+fn name_len(name: &str) -> usize {
+    let mut len = name.len().min(MAX_NAME_LEN);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    len
+}
+
+/// An encoded symbol table.
+#[derive(Clone, Copy, Debug)]
+pub struct SymbolTable<'a> {
+    entries: &'a [u8],
+    names: &'a [u8],
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Read a table created by [`encode`]. Returns `None` if it is not a valid table.
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.get(..4)? != MAGIC {
+            return None;
+        }
+
+        let count = read_u32(bytes, 4)? as usize;
+        let end = count
+            .checked_mul(ENTRY_SIZE)
+            .and_then(|size| size.checked_add(HEADER_SIZE))?;
+        let entries = bytes.get(HEADER_SIZE..end)?;
+        let names = &bytes[HEADER_SIZE + entries.len()..];
+
+        Some(Self { entries, names })
+    }
+
+    /// Number of symbols
+    pub fn len(&self) -> usize {
+        self.entries.len() / ENTRY_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get a symbol by index, in address order.
+    pub fn get(&self, index: usize) -> Option<Symbol<'a>> {
+        let entry = self
+            .entries
+            .get(index.checked_mul(ENTRY_SIZE)?..)?
+            .get(..ENTRY_SIZE)?;
+        let name = read_u32(entry, 8)? as usize;
+        let name = self.names.get(name >> 8..(name >> 8) + (name & 0xFF))?;
+
+        Some(Symbol {
+            address: read_u32(entry, 0)?,
+            size: read_u32(entry, 4)?,
+            name: core::str::from_utf8(name).ok()?,
+        })
+    }
+
+    /// Find the symbol containing an address. Symbols with an unknown size are assumed to extend
+    /// to the next symbol.
+    pub fn lookup(&self, address: usize) -> Option<Symbol<'a>> {
+        let address = address as u32;
+
+        // Find the last symbol starting at or before the address
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.get(mid)?.address <= address {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        let symbol = self.get(low.checked_sub(1)?)?;
+        (symbol.size == 0 || address - symbol.address < symbol.size).then(|| symbol)
+    }
+
+    /// Iterate over the symbols, in address order.
+    pub fn iter(&self) -> impl Iterator<Item = Symbol<'a>> + '_ {
+        (0..self.len()).filter_map(move |index| self.get(index))
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// A symbol name, demangled from the legacy Rust mangling scheme by `Display` when it is mangled.
+/// The hash suffix is left out.
+#[derive(Clone, Copy, Debug)]
+pub struct Demangle<'a>(pub &'a str);
+
+impl fmt::Display for Demangle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Mangled names are ASCII, so anything else is shown as it is
+        let mut rest = match self.0.strip_prefix("_ZN") {
+            Some(rest) if rest.ends_with('E') && rest.is_ascii() => &rest[..rest.len() - 1],
+            _ => return f.write_str(self.0),
+        };
+
+        // Check the whole name is well-formed before writing anything
+        let mut components = 0;
+        let mut check = rest;
+        while !check.is_empty() {
+            match split_component(check) {
+                Some((_, next)) => check = next,
+                None => return f.write_str(self.0),
+            }
+            components += 1;
+        }
+
+        for index in 0..components {
+            let (component, next) = split_component(rest).unwrap();
+            rest = next;
+            if index + 1 == components && is_hash(component) {
+                break;
+            }
+            if index > 0 {
+                f.write_str("::")?;
+            }
+            write_component(f, component)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Split a length-prefixed component off the front of a mangled name.
+fn split_component(name: &str) -> Option<(&str, &str)> {
+    let digits = name.bytes().take_while(u8::is_ascii_digit).count();
+    let len: usize = name[..digits].parse().ok()?;
+    let rest = &name[digits..];
+
+    Some((rest.get(..len)?, &rest[len..]))
+}
+
+/// Check if a component is the `h` and 16 hex digit hash suffix.
+fn is_hash(component: &str) -> bool {
+    component.len() == 17
+        && component.starts_with('h')
+        && component[1..].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Write a component, replacing its escapes.
+fn write_component(f: &mut fmt::Formatter<'_>, component: &str) -> fmt::Result {
+    // Components starting with an escape are prefixed with an underscore
+    let mut rest = match component.strip_prefix('_') {
+        Some(escaped) if escaped.starts_with('$') => escaped,
+        _ => component,
+    };
+
+    while !rest.is_empty() {
+        if let Some(next) = rest.strip_prefix("..") {
+            f.write_str("::")?;
+            rest = next;
+        } else if let Some((escape, next)) = rest
+            .strip_prefix('$')
+            .and_then(|escaped| escaped.split_once('$'))
+        {
+            match escape {
+                "SP" => f.write_str("@")?,
+                "BP" => f.write_str("*")?,
+                "RF" => f.write_str("&")?,
+                "LT" => f.write_str("<")?,
+                "GT" => f.write_str(">")?,
+                "LP" => f.write_str("(")?,
+                "RP" => f.write_str(")")?,
+                "C" => f.write_str(",")?,
+                _ => {
+                    let c = escape
+                        .strip_prefix('u')
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .and_then(char::from_u32);
+                    match c {
+                        Some(c) => fmt::Write::write_char(f, c)?,
+                        None => write!(f, "${}$", escape)?,
+                    }
+                }
+            }
+            rest = next;
+        } else {
+            let len = rest[1..]
+                .find(['$', '.'])
+                .map_or(rest.len(), |index| index + 1);
+            f.write_str(&rest[..len])?;
+            rest = &rest[len..];
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,265 @@
+//! A test runner for running unit tests on target, with the unstable `custom_test_frameworks`
+//! feature:
+//!
+//! ```text
+//! #![feature(custom_test_frameworks)]
+//! #![test_runner(rrt0::test::runner)]
+//! #![reexport_test_harness_main = "test_main"]
+//!
+//! #[test_case]
+//! fn addition() {
+//!     assert_eq!(1 + 1, 2);
+//! }
+//! ```
+//!
+//! Results are written line by line, in the same format as the standard test harness, to the
+//! function registered with [`set_output`]. Panics abort, so a failing test ends the run: the
+//! panic handler in the [`prelude`](crate::prelude) writes the panic and reports the test as failed
+//! before halting. With the `terse-panics` feature, release builds skip the message and the
+//! summary, and only write the panic location and the exit line.
+//!
+//! A run ends with an exit line that runners can wait for and parse to decide whether it passed,
+//! `##rrt0-exit status=<code> passed=<count> failed=<count> total=<count>`. The status is 0 when
+//! every test passed, and 101 (like the standard test harness) when one failed. Programs other
+//! than test runs can end the same way with [`exit`].
+//!
+//! When a function reading input from the host is registered with [`set_input`], the host selects
+//! which tests run. The runner first announces the tests, one line each, and waits for a reply:
+//!
+//! ```text
+//! ##rrt0-list <count>
+//! ##rrt0-test <index> <name>
+//! ##rrt0-select
+//! ```
+//!
+//! The reply is a line with either `all` or the indices of the tests to run, separated by spaces.
+//! Only the first [`MAX_SELECTED`] tests can be selected by index.
+
+use crate::interrupt::Mutex;
+use core::fmt::{self, Write};
+
+/// Start of the line ending a run
+pub const EXIT_MARKER: &str = "##rrt0-exit";
+
+/// Exit status of a run with a failed test
+pub const FAILURE_STATUS: u8 = 101;
+
+/// Number of tests that can be selected by index
+pub const MAX_SELECTED: usize = 256;
+
+/// Longest selection line read from the host (in bytes). Longer lines are truncated.
+const MAX_SELECTION_LEN: usize = 1024;
+
+/// A test function.
+pub trait Test {
+    fn name(&self) -> &'static str;
+    fn run(&self);
+}
+
+impl<T: Fn()> Test for T {
+    fn name(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+
+    fn run(&self) {
+        self();
+    }
+}
+
+struct RunnerState {
+    output: Option<fn(&str)>,
+    input: Option<fn() -> Option<u8>>,
+    /// Name of the running test
+    running: Option<&'static str>,
+    total: usize,
+    passed: usize,
+}
+
+static RUNNER: Mutex<RunnerState> = Mutex::new(RunnerState {
+    output: None,
+    input: None,
+    running: None,
+    total: 0,
+    passed: 0,
+});
+
+/// Register the function that test results are written to, e.g. one drawing to the screen.
+/// Without one, results are discarded.
+pub fn set_output(output: fn(&str)) {
+    with_runner(|runner| runner.output = Some(output));
+}
+
+/// Register the function that reads the host's test selection, one byte at a time. It returns
+/// `None` when no byte is available yet.
+pub fn set_input(input: fn() -> Option<u8>) {
+    with_runner(|runner| runner.input = Some(input));
+}
+
+/// Run a list of tests, reporting each result and a summary.
+pub fn runner(tests: &[&dyn Test]) {
+    let selected = select(tests);
+    let selected = |index: usize| match selected {
+        Some(selected) => index < MAX_SELECTED && selected[index / 32] & 1 << (index % 32) != 0,
+        None => true,
+    };
+    let total = (0..tests.len()).filter(|&index| selected(index)).count();
+
+    with_runner(|runner| {
+        runner.total = total;
+        runner.passed = 0;
+    });
+    print(format_args!("running {} tests\n", total));
+
+    let tests = tests
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| selected(index));
+    for (_, test) in tests {
+        with_runner(|runner| runner.running = Some(test.name()));
+        print(format_args!("test {} ... ", test.name()));
+        test.run();
+        print(format_args!("ok\n"));
+        with_runner(|runner| {
+            runner.running = None;
+            runner.passed += 1;
+        });
+    }
+
+    summary(true);
+    exit(0);
+}
+
+/// Announce the tests and read the host's selection, as a bitset of test indices. Returns `None`
+/// when all tests run.
+fn select(tests: &[&dyn Test]) -> Option<[u32; MAX_SELECTED / 32]> {
+    let input = with_runner(|runner| runner.input)?;
+
+    print(format_args!("##rrt0-list {}\n", tests.len()));
+    for (index, test) in tests.iter().enumerate() {
+        print(format_args!("##rrt0-test {} {}\n", index, test.name()));
+    }
+    print(format_args!("##rrt0-select\n"));
+
+    let mut line = [0; MAX_SELECTION_LEN];
+    let mut len = 0;
+    loop {
+        match input() {
+            Some(b'\n') => break,
+            Some(byte) if len < line.len() => {
+                line[len] = byte;
+                len += 1;
+            }
+            _ => {}
+        }
+    }
+
+    let line = core::str::from_utf8(&line[..len]).unwrap_or("");
+    let mut selected = [0; MAX_SELECTED / 32];
+    for word in line.split_whitespace() {
+        match word.parse::<usize>() {
+            Ok(index) if index < MAX_SELECTED => selected[index / 32] |= 1 << (index % 32),
+            Ok(_) => {}
+            Err(_) if word == "all" => return None,
+            Err(_) => {}
+        }
+    }
+
+    Some(selected)
+}
+
+/// Write a panic's message and location, and report the running test as failed, if there is one.
+/// Called by the panic handler.
+#[cfg(not(all(feature = "terse-panics", not(debug_assertions))))]
+pub(crate) fn fail(info: &core::panic::PanicInfo<'_>) {
+    print(format_args!("{}\n", info));
+    if let Some(name) = with_runner(|runner| runner.running.take()) {
+        print(format_args!("FAILED\n\nfailures:\n    {}\n\n", name));
+        summary(false);
+        exit(FAILURE_STATUS);
+    }
+}
+
+/// Write the location of a panic, and report the running test as failed with the exit line,
+/// without `core::fmt`. Called by the panic handler with the `terse-panics` feature.
+#[cfg(all(feature = "terse-panics", not(debug_assertions)))]
+pub(crate) fn fail_terse(info: &core::panic::PanicInfo<'_>) {
+    use crate::fmt::u32_to_str;
+
+    let (output, running, passed, total) = with_runner(|runner| {
+        let running = runner.running.take();
+        (runner.output, running, runner.passed, runner.total)
+    });
+    let output = match output {
+        Some(output) => output,
+        None => return,
+    };
+
+    let mut buffer = [0; 10];
+    if let Some(location) = info.location() {
+        output("panicked at ");
+        output(location.file());
+        output(":");
+        output(u32_to_str(location.line(), &mut buffer));
+        output(":");
+        output(u32_to_str(location.column(), &mut buffer));
+        output("\n");
+    }
+    if running.is_some() {
+        output("FAILED\n");
+        output(EXIT_MARKER);
+        output(" status=");
+        output(u32_to_str(FAILURE_STATUS as u32, &mut buffer));
+        output(" passed=");
+        output(u32_to_str(passed as u32, &mut buffer));
+        output(" failed=1 total=");
+        output(u32_to_str(total as u32, &mut buffer));
+        output("\n");
+    }
+}
+
+/// Write the exit line with a status code and the test counts, then halt.
+pub fn exit(status: u8) -> ! {
+    let (total, passed, failed) = with_runner(|runner| {
+        let failed = (status != 0 && runner.passed < runner.total) as usize;
+        (runner.total, runner.passed, failed)
+    });
+    print(format_args!(
+        "{} status={} passed={} failed={} total={}\n",
+        EXIT_MARKER, status, passed, failed, total
+    ));
+    crate::leak::exit();
+
+    #[allow(clippy::empty_loop)]
+    loop {}
+}
+
+fn summary(ok: bool) {
+    let (total, passed) = with_runner(|runner| (runner.total, runner.passed));
+    print(format_args!(
+        "test result: {}. {} passed; {} failed; {} not run\n",
+        if ok { "ok" } else { "FAILED" },
+        passed,
+        !ok as usize,
+        total - passed - !ok as usize,
+    ));
+}
+
+/// Write formatted text to the output, one piece at a time.
+fn print(args: fmt::Arguments<'_>) {
+    struct Output(fn(&str));
+
+    impl Write for Output {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            (self.0)(s);
+            Ok(())
+        }
+    }
+
+    if let Some(output) = with_runner(|runner| runner.output) {
+        let _ = Output(output).write_fmt(args);
+    }
+}
+
+fn with_runner<R>(f: impl FnOnce(&mut RunnerState) -> R) -> R {
+    RUNNER.lock(f)
+}
@@ -0,0 +1,523 @@
+//! Text layout, and text consoles drawn to frame buffers.
+//!
+//! [`Layout`] places the glyphs of text for any font described by its [`Metrics`]: it breaks lines
+//! at newlines, and word wraps them to a width. Text can be measured with it before it is drawn,
+//! and its lines aligned within a width with [`Align`]:
+//!
+//! ```text
+//! let mut layout = Layout::new(metrics, Some(rect.width));
+//! layout.lines(text, |line| {
+//!     let x = rect.x + Align::Center.offset(rect.width, line.width) + line.x;
+//!     draw_line(x, rect.y + line.y, line.text);
+//! });
+//! ```
+//!
+//! [`TextConsole`] draws text with an 8x8 font to a [`Surface`], for printing diagnostics, e.g. as
+//! the output of the [test runner](crate::test::set_output). The platforms provide surfaces for
+//! their displays, like the [GBA](crate::gba) mode 3 bitmap and the [PSP](crate::psp) frame
+//! buffer:
+//!
+//! ```text
+//! let mut text = TextConsole::new(Mode3, &FONT);
+//! text.clear();
+//! writeln!(text, "hello from rrt0")?;
+//! ```
+//!
+//! The font is supplied by the program: 96 glyphs of 8x8 pixels for the characters from `' '` to
+//! `'\x7f'`, each 8 bytes with one byte per row and the leftmost pixel in the top bit. Other
+//! characters are drawn as `'?'`. Lines are word wrapped to the width of the surface.
+
+use core::fmt;
+
+/// Sizes of the glyphs of a font, for laying out text.
+pub trait Metrics {
+    /// Glyph height (in pixels)
+    fn height(&self) -> usize;
+
+    /// Horizontal space between adjacent glyphs (in pixels)
+    fn spacing(&self) -> usize;
+
+    /// Vertical space between adjacent lines (in pixels)
+    fn leading(&self) -> usize;
+
+    /// Width of the glyph for a character (in pixels)
+    fn glyph_width(&self, ch: char) -> usize;
+}
+
+impl<M: Metrics + ?Sized> Metrics for &M {
+    fn height(&self) -> usize {
+        (**self).height()
+    }
+
+    fn spacing(&self) -> usize {
+        (**self).spacing()
+    }
+
+    fn leading(&self) -> usize {
+        (**self).leading()
+    }
+
+    fn glyph_width(&self, ch: char) -> usize {
+        (**self).glyph_width(ch)
+    }
+}
+
+/// Size of a block of text (in pixels)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Extent {
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Horizontal alignment of each line of text within a width
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    /// Offset (in pixels) of a line of the given width within the available width.
+    pub fn offset(self, available: usize, width: usize) -> usize {
+        match self {
+            Self::Left => 0,
+            Self::Center => available.saturating_sub(width) / 2,
+            Self::Right => available.saturating_sub(width),
+        }
+    }
+}
+
+/// A line of text with glyphs, laid out by [`Layout::lines`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Line<'a> {
+    /// The text from the first glyph to the last
+    pub text: &'a str,
+    /// Offset of the first glyph (in pixels)
+    pub x: usize,
+    pub y: usize,
+    /// Width from the start of the line to the end of the last glyph (in pixels)
+    pub width: usize,
+}
+
+/// Text layout state, shared across consecutive runs of text.
+///
+/// Lines are broken at newlines, and at spaces when `max_width` is given. Words wider than
+/// `max_width` are broken between characters. At least one glyph is always placed on each line,
+/// so that layout makes progress.
+#[derive(Clone, Debug)]
+pub struct Layout<M: Metrics> {
+    metrics: M,
+    max_width: usize,
+    x: usize,
+    row: usize,
+    widest: usize,
+    empty: bool,
+}
+
+impl<M: Metrics> Layout<M> {
+    pub const fn new(metrics: M, max_width: Option<usize>) -> Self {
+        Self {
+            metrics,
+            max_width: match max_width {
+                Some(max_width) => max_width,
+                None => usize::MAX,
+            },
+            x: 0,
+            row: 0,
+            widest: 0,
+            empty: true,
+        }
+    }
+
+    /// Continue the layout with another run of text, calling `f` with the offset (in pixels) of
+    /// each glyph relative to the top-left corner of the text.
+    pub fn push(&mut self, string: &str, mut f: impl FnMut(usize, usize, char)) {
+        self.place(string, |x, y, ch, _, _| f(x, y, ch));
+    }
+
+    /// Continue the layout with another run of text, calling `f` with each of its lines that has
+    /// glyphs. Each line is passed once it is complete, and the last at the end of the run.
+    pub fn lines<'a>(&mut self, string: &'a str, mut f: impl FnMut(Line<'a>)) {
+        // The current line, as its first glyph and the end of its last
+        let mut current: Option<(Line<'a>, usize)> = None;
+        self.place(string, |x, y, ch, index, width| {
+            let end = index + ch.len_utf8();
+            match &mut current {
+                Some((line, start)) if line.y == y => {
+                    line.text = &string[*start..end];
+                    line.width = x + width;
+                }
+                _ => {
+                    if let Some((line, _)) = current.take() {
+                        f(line);
+                    }
+                    let line = Line {
+                        text: &string[index..end],
+                        x,
+                        y,
+                        width: x + width,
+                    };
+                    current = Some((line, index));
+                }
+            }
+        });
+        if let Some((line, _)) = current {
+            f(line);
+        }
+    }
+
+    /// Extent of all text laid out so far.
+    pub fn extent(&self) -> Extent {
+        if self.empty {
+            return Extent::default();
+        }
+
+        Extent {
+            width: self.widest,
+            height: (self.row + 1) * (self.metrics.height() + self.metrics.leading())
+                - self.metrics.leading(),
+        }
+    }
+
+    /// Lay out a run of text, calling `f` with the offset, character, byte index, and width of
+    /// each glyph.
+    fn place(&mut self, string: &str, mut f: impl FnMut(usize, usize, char, usize, usize)) {
+        self.empty &= string.is_empty();
+
+        let line_height = self.metrics.height() + self.metrics.leading();
+        let spacing = self.metrics.spacing();
+        let index_of = |part: &str| part.as_ptr() as usize - string.as_ptr() as usize;
+
+        for (i, line) in string.split('\n').enumerate() {
+            if i > 0 {
+                self.new_line();
+            }
+
+            for (j, word) in line.split(' ').enumerate() {
+                if j > 0 {
+                    self.x += self.metrics.glyph_width(' ') + spacing;
+                }
+
+                // Move the whole word to the next line if it doesn't fit on this one
+                if !word.is_empty() && self.x > 0 && self.x + self.measure(word) > self.max_width {
+                    self.new_line();
+                }
+
+                for (index, ch) in word.char_indices() {
+                    let width = self.metrics.glyph_width(ch);
+                    if self.x > 0 && self.x + width > self.max_width {
+                        self.new_line();
+                    }
+
+                    f(
+                        self.x,
+                        self.row * line_height,
+                        ch,
+                        index_of(word) + index,
+                        width,
+                    );
+                    self.x += width;
+                    self.widest = self.widest.max(self.x);
+                    self.x += spacing;
+                }
+            }
+        }
+    }
+
+    /// Width of a word (in pixels).
+    fn measure(&self, word: &str) -> usize {
+        let spacing = self.metrics.spacing();
+        let width: usize = word
+            .chars()
+            .map(|ch| self.metrics.glyph_width(ch) + spacing)
+            .sum();
+
+        width.saturating_sub(spacing)
+    }
+
+    fn new_line(&mut self) {
+        self.row += 1;
+        self.x = 0;
+    }
+}
+
+/// Size of a glyph (in pixels)
+pub const GLYPH_SIZE: usize = 8;
+
+/// Size of a font (in bytes)
+pub const FONT_SIZE: usize = 96 * GLYPH_SIZE;
+
+/// Pixels that text is drawn to, like a frame buffer.
+pub trait Surface {
+    type Color: Copy;
+
+    /// Size (in pixels)
+    const WIDTH: usize;
+    const HEIGHT: usize;
+
+    /// Color of text on a new console
+    const FOREGROUND: Self::Color;
+    /// Color behind text on a new console
+    const BACKGROUND: Self::Color;
+
+    fn read(&self, x: usize, y: usize) -> Self::Color;
+
+    fn write(&mut self, x: usize, y: usize, color: Self::Color);
+}
+
+/// Metrics of the 8x8 glyphs of a console, which sit next to each other in rows and columns
+#[derive(Clone, Copy, Debug)]
+pub struct Cells;
+
+impl Metrics for Cells {
+    fn height(&self) -> usize {
+        GLYPH_SIZE
+    }
+
+    fn spacing(&self) -> usize {
+        0
+    }
+
+    fn leading(&self) -> usize {
+        0
+    }
+
+    fn glyph_width(&self, _ch: char) -> usize {
+        GLYPH_SIZE
+    }
+}
+
+/// A text console drawn to a surface, in rows and columns of glyphs. Scrolls up when the surface is
+/// full.
+#[derive(Debug)]
+pub struct TextConsole<S: Surface> {
+    surface: S,
+    font: &'static [u8; FONT_SIZE],
+    pub foreground: S::Color,
+    pub background: S::Color,
+    layout: Layout<Cells>,
+    /// Row of the layout at the top of the surface
+    top: usize,
+}
+
+impl<S: Surface> TextConsole<S> {
+    /// Number of rows of glyphs on the surface
+    const ROWS: usize = S::HEIGHT / GLYPH_SIZE;
+
+    /// Create a console in the surface's default colors, at its top left.
+    pub const fn new(surface: S, font: &'static [u8; FONT_SIZE]) -> Self {
+        Self {
+            surface,
+            font,
+            foreground: S::FOREGROUND,
+            background: S::BACKGROUND,
+            layout: Layout::new(Cells, Some(S::WIDTH)),
+            top: 0,
+        }
+    }
+
+    pub fn surface(&self) -> &S {
+        &self.surface
+    }
+
+    pub fn surface_mut(&mut self) -> &mut S {
+        &mut self.surface
+    }
+
+    /// Fill the surface with the background color and move to the top left.
+    pub fn clear(&mut self) {
+        for y in 0..S::HEIGHT {
+            for x in 0..S::WIDTH {
+                self.surface.write(x, y, self.background);
+            }
+        }
+        self.layout = Layout::new(Cells, Some(S::WIDTH));
+        self.top = 0;
+    }
+
+    /// Draw a character after the text so far.
+    pub fn put_char(&mut self, c: char) {
+        self.write_text(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Draw text after the text so far.
+    pub fn write_text(&mut self, text: &str) {
+        let mut layout = self.layout.clone();
+        layout.push(text, |x, y, ch| {
+            let row = y / GLYPH_SIZE;
+            if row >= self.top + Self::ROWS {
+                self.scroll(row + 1 - Self::ROWS - self.top);
+            }
+            self.draw_glyph(x, (row - self.top) * GLYPH_SIZE, ch);
+        });
+        self.layout = layout;
+    }
+
+    fn draw_glyph(&mut self, x: usize, y: usize, ch: char) {
+        let index = match ch {
+            ' '..='\x7f' => ch as usize - ' ' as usize,
+            _ => '?' as usize - ' ' as usize,
+        };
+        let glyph = &self.font[index * GLYPH_SIZE..(index + 1) * GLYPH_SIZE];
+        for (dy, bits) in glyph.iter().enumerate() {
+            for dx in 0..GLYPH_SIZE {
+                let color = match bits & (0x80 >> dx) {
+                    0 => self.background,
+                    _ => self.foreground,
+                };
+                self.surface.write(x + dx, y + dy, color);
+            }
+        }
+    }
+
+    /// Scroll up some rows of glyphs, and clear the rows below.
+    fn scroll(&mut self, rows: usize) {
+        let distance = rows * GLYPH_SIZE;
+        for y in 0..S::HEIGHT {
+            for x in 0..S::WIDTH {
+                let color = match y + distance {
+                    source if source < S::HEIGHT => self.surface.read(x, source),
+                    _ => self.background,
+                };
+                self.surface.write(x, y, color);
+            }
+        }
+        self.top += rows;
+    }
+}
+
+impl<S: Surface> fmt::Write for TextConsole<S> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_text(s);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    /// 3 columns and 2 rows of glyphs, in memory
+    struct Pixels([[bool; 3 * GLYPH_SIZE]; 2 * GLYPH_SIZE]);
+
+    impl Surface for Pixels {
+        type Color = bool;
+
+        const WIDTH: usize = 3 * GLYPH_SIZE;
+        const HEIGHT: usize = 2 * GLYPH_SIZE;
+
+        const FOREGROUND: bool = true;
+        const BACKGROUND: bool = false;
+
+        fn read(&self, x: usize, y: usize) -> bool {
+            self.0[y][x]
+        }
+
+        fn write(&mut self, x: usize, y: usize, color: bool) {
+            self.0[y][x] = color;
+        }
+    }
+
+    /// A font where each glyph has its top row set, and its index in the rows below
+    static FONT: [u8; FONT_SIZE] = {
+        let mut font = [0; FONT_SIZE];
+        let mut index = 0;
+        while index < 96 {
+            font[index * GLYPH_SIZE] = 0xFF;
+            font[index * GLYPH_SIZE + 1] = index as u8;
+            index += 1;
+        }
+        font
+    };
+
+    /// The glyph index drawn at a column and row, if any.
+    fn glyph(console: &TextConsole<Pixels>, column: usize, row: usize) -> Option<u8> {
+        let pixels = &console.surface().0;
+        let (x, y) = (column * GLYPH_SIZE, row * GLYPH_SIZE);
+        if !pixels[y][x] {
+            return None;
+        }
+
+        Some((0..GLYPH_SIZE).fold(0, |index, bit| index << 1 | pixels[y + 1][x + bit] as u8))
+    }
+
+    /// Proportional metrics, where `i` is narrower
+    struct Narrow;
+
+    impl Metrics for Narrow {
+        fn height(&self) -> usize {
+            8
+        }
+
+        fn spacing(&self) -> usize {
+            1
+        }
+
+        fn leading(&self) -> usize {
+            2
+        }
+
+        fn glyph_width(&self, ch: char) -> usize {
+            match ch {
+                'i' => 2,
+                _ => 6,
+            }
+        }
+    }
+
+    #[test]
+    fn layout_lines() {
+        let mut layout = Layout::new(Narrow, Some(40));
+        let mut lines = [Line {
+            text: "",
+            x: 0,
+            y: 0,
+            width: 0,
+        }; 5];
+        let mut count = 0;
+        layout.lines("hi there\n  in\nmmmmmmm", |line| {
+            lines[count] = line;
+            count += 1;
+        });
+
+        assert_eq!(count, 5);
+        assert_eq!((lines[0].text, lines[0].x, lines[0].width), ("hi", 0, 9));
+        assert_eq!(
+            (lines[1].text, lines[1].y, lines[1].width),
+            ("there", 10, 34)
+        );
+        // Leading spaces count towards the width, and long words break between characters
+        assert_eq!((lines[2].text, lines[2].x, lines[2].width), ("in", 14, 23));
+        assert_eq!((lines[3].text, lines[3].width), ("mmmmm", 34));
+        assert_eq!((lines[4].text, lines[4].y), ("mm", 40));
+        assert_eq!(
+            layout.extent(),
+            Extent {
+                width: 34,
+                height: 48
+            }
+        );
+        assert_eq!(Align::Center.offset(30, 9), 10);
+        assert_eq!(Align::Right.offset(30, 34), 0);
+    }
+
+    #[test]
+    fn wraps_and_scrolls() {
+        let mut console = TextConsole::new(Pixels([[false; 24]; 16]), &FONT);
+        console.clear();
+        write!(console, "abcd").unwrap();
+        assert_eq!(glyph(&console, 0, 0), Some(b'a' - b' '));
+        assert_eq!(glyph(&console, 0, 1), Some(b'd' - b' '));
+        assert_eq!(glyph(&console, 1, 1), None);
+
+        // Scrolls up a row to start the third line
+        write!(console, "\ne\u{e9}").unwrap();
+        assert_eq!(glyph(&console, 0, 0), Some(b'd' - b' '));
+        assert_eq!(glyph(&console, 0, 1), Some(b'e' - b' '));
+        assert_eq!(glyph(&console, 1, 1), Some(b'?' - b' '));
+    }
+}
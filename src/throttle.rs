@@ -0,0 +1,101 @@
+//! Call-site throttling.
+//!
+//! [`once!`](crate::once) runs code only the first time its call site is reached,
+//! [`every_nth!`](crate::every_nth) on every n-th time, and [`rate_limit!`](crate::rate_limit) at
+//! most once per interval. This keeps diagnostics in per-frame code, like a warning written to an
+//! on-screen terminal, from flooding their output.
+
+use crate::interrupt;
+use crate::time::{self, Instant};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::time::Duration;
+
+/// Run code only the first time this call site is reached.
+///
+/// e.g. `once!(terminal.write_str("MISSING TEXTURE\n"));`
+#[macro_export]
+macro_rules! once {
+    ($($body:tt)*) => {{
+        static THROTTLE: $crate::throttle::Throttle = $crate::throttle::Throttle::new();
+        if THROTTLE.once() {
+            $($body)*
+        }
+    }};
+}
+
+/// Run code the first time this call site is reached, and every `n` times after that.
+///
+/// e.g. `every_nth!(60, draw_text!(8, 8, RED, "SLOW FRAME"));` in a 60 Hz loop
+#[macro_export]
+macro_rules! every_nth {
+    ($n:expr, $($body:tt)*) => {{
+        static THROTTLE: $crate::throttle::Throttle = $crate::throttle::Throttle::new();
+        if THROTTLE.every_nth($n) {
+            $($body)*
+        }
+    }};
+}
+
+/// Run code the first time this call site is reached, and then at most once per interval.
+///
+/// e.g. `rate_limit!(Duration::from_secs(1), report_dropped_samples());`
+#[macro_export]
+macro_rules! rate_limit {
+    ($interval:expr, $($body:tt)*) => {{
+        static THROTTLE: $crate::throttle::Throttle = $crate::throttle::Throttle::new();
+        if THROTTLE.interval($interval) {
+            $($body)*
+        }
+    }};
+}
+
+/// State for one throttled call site.
+///
+/// Updates are not atomic as a whole, so a call site reached from both an interrupt handler and
+/// the main program may run slightly more often than requested.
+pub struct Throttle {
+    started: AtomicBool,
+    count: AtomicU32,
+    /// Counter ticks when the call site last ran
+    last: AtomicU32,
+}
+
+impl Throttle {
+    pub const fn new() -> Self {
+        Self {
+            started: AtomicBool::new(false),
+            count: AtomicU32::new(0),
+            last: AtomicU32::new(0),
+        }
+    }
+
+    /// Whether this is the first call.
+    pub fn once(&self) -> bool {
+        !interrupt::swap(&self.started, true)
+    }
+
+    /// Whether this call is the first of a run of `n`.
+    pub fn every_nth(&self, n: u32) -> bool {
+        interrupt::fetch_add(&self.count, 1) % n.max(1) == 0
+    }
+
+    /// Whether this is the first call, or `interval` has passed since the last time this returned
+    /// `true`. Intervals longer than the cycle counter period are shortened to it.
+    pub fn interval(&self, interval: Duration) -> bool {
+        let now = Instant::now().ticks();
+        let ticks = time::duration_to_ticks(interval);
+
+        if self.once() || now.wrapping_sub(self.last.load(Ordering::Relaxed)) >= ticks {
+            self.last.store(now, Ordering::Relaxed);
+            return true;
+        }
+
+        false
+    }
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,74 @@
+//! Time measurement with the CPU cycle counter.
+//!
+//! On Nintendo 64, this is the CP0 `Count` register, which increments at half the CPU clock rate
+//! and wraps around roughly every 91 seconds. Intervals are computed with wrapping arithmetic, so
+//! they are correct as long as they are shorter than one full period of the counter.
+//!
+//! On other platforms the counter does not advance.
+
+use core::time::Duration;
+
+/// Cycle counter frequency (in Hz)
+pub const COUNTER_FREQUENCY: u32 = 46_875_000;
+
+/// Read the raw cycle counter.
+#[cfg(rrt0_platform = "n64")]
+pub fn counter() -> u32 {
+    let count: u32;
+    unsafe {
+        core::arch::asm!("mfc0 $2, $9", out("$2") count, options(nomem, nostack));
+    }
+
+    count
+}
+
+/// Read the raw cycle counter.
+#[cfg(not(rrt0_platform = "n64"))]
+pub fn counter() -> u32 {
+    0
+}
+
+/// Convert a number of counter ticks to a duration.
+pub fn ticks_to_duration(ticks: u32) -> Duration {
+    let nanos = ticks as u64 * 1_000_000_000 / COUNTER_FREQUENCY as u64;
+
+    Duration::from_nanos(nanos)
+}
+
+/// Convert a duration to a number of counter ticks, saturating at the counter period.
+pub fn duration_to_ticks(duration: Duration) -> u32 {
+    let ticks = duration.as_nanos() * COUNTER_FREQUENCY as u128 / 1_000_000_000;
+
+    ticks.min(u32::MAX as u128) as u32
+}
+
+/// A point in time, as measured by the cycle counter.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Instant(u32);
+
+impl Instant {
+    /// The current time.
+    pub fn now() -> Self {
+        Self(counter())
+    }
+
+    /// Raw counter value at this instant.
+    pub fn ticks(&self) -> u32 {
+        self.0
+    }
+
+    /// Number of counter ticks from `earlier` to this instant.
+    pub fn ticks_since(&self, earlier: Instant) -> u32 {
+        self.0.wrapping_sub(earlier.0)
+    }
+
+    /// Time elapsed from `earlier` to this instant.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        ticks_to_duration(self.ticks_since(earlier))
+    }
+
+    /// Time elapsed since this instant.
+    pub fn elapsed(&self) -> Duration {
+        Self::now().duration_since(*self)
+    }
+}
@@ -0,0 +1,401 @@
+//! Vector and matrix math.
+//!
+//! [`Vec2`], [`Vec3`], [`Vec4`] and [`Mat4`] are plain structs of scalars with no SIMD paths. They
+//! default to `f32` components, and also work with [`Fx16_16`] for fixed point math.
+//!
+//! Matrices are column-major and transform column vectors, so `a * b` applies `b` first.
+
+use crate::fixed::Fx16_16;
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Component type for vectors and matrices.
+pub trait Scalar:
+    Copy
+    + Default
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const FRAC_PI_2: Self;
+
+    fn sqrt(self) -> Self;
+
+    /// Sine and cosine of an angle in radians.
+    fn sin_cos(self) -> (Self, Self);
+}
+
+impl Scalar for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+    const FRAC_PI_2: Self = core::f32::consts::FRAC_PI_2;
+
+    fn sqrt(self) -> Self {
+        libm::sqrtf(self)
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        libm::sincosf(self)
+    }
+}
+
+impl Scalar for Fx16_16 {
+    const ZERO: Self = Fx16_16::ZERO;
+    const ONE: Self = Fx16_16::ONE;
+    const FRAC_PI_2: Self = Fx16_16::FRAC_PI_2;
+
+    fn sqrt(self) -> Self {
+        Fx16_16::sqrt(self)
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        Fx16_16::sin_cos(self)
+    }
+}
+
+macro_rules! vector {
+    ($(#[$attr:meta])* $name:ident { $($field:ident),+ }) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+        pub struct $name<T = f32> {
+            $(pub $field: T,)+
+        }
+
+        impl<T: Scalar> $name<T> {
+            pub const ZERO: Self = Self { $($field: T::ZERO),+ };
+            pub const ONE: Self = Self { $($field: T::ONE),+ };
+
+            pub const fn new($($field: T),+) -> Self {
+                Self { $($field),+ }
+            }
+
+            /// Create a vector with all components set to `value`.
+            pub const fn splat(value: T) -> Self {
+                Self { $($field: value),+ }
+            }
+
+            pub fn dot(self, rhs: Self) -> T {
+                T::ZERO $(+ self.$field * rhs.$field)+
+            }
+
+            pub fn length_squared(self) -> T {
+                self.dot(self)
+            }
+
+            pub fn length(self) -> T {
+                self.length_squared().sqrt()
+            }
+
+            /// Scale to unit length. Returns zero for a zero-length vector.
+            pub fn normalize(self) -> Self {
+                let length = self.length();
+                if length > T::ZERO {
+                    self / length
+                } else {
+                    Self::ZERO
+                }
+            }
+
+            pub fn distance(self, rhs: Self) -> T {
+                (rhs - self).length()
+            }
+
+            /// Component-wise minimum.
+            pub fn min(self, rhs: Self) -> Self {
+                Self { $($field: if rhs.$field < self.$field { rhs.$field } else { self.$field }),+ }
+            }
+
+            /// Component-wise maximum.
+            pub fn max(self, rhs: Self) -> Self {
+                Self { $($field: if rhs.$field > self.$field { rhs.$field } else { self.$field }),+ }
+            }
+
+            /// Component-wise product.
+            pub fn mul_elements(self, rhs: Self) -> Self {
+                Self { $($field: self.$field * rhs.$field),+ }
+            }
+        }
+
+        impl<T: Scalar> Add for $name<T> {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self {
+                Self { $($field: self.$field + rhs.$field),+ }
+            }
+        }
+
+        impl<T: Scalar> Sub for $name<T> {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                Self { $($field: self.$field - rhs.$field),+ }
+            }
+        }
+
+        impl<T: Scalar> Mul<T> for $name<T> {
+            type Output = Self;
+
+            fn mul(self, rhs: T) -> Self {
+                Self { $($field: self.$field * rhs),+ }
+            }
+        }
+
+        impl<T: Scalar> Div<T> for $name<T> {
+            type Output = Self;
+
+            fn div(self, rhs: T) -> Self {
+                Self { $($field: self.$field / rhs),+ }
+            }
+        }
+
+        impl<T: Scalar> Neg for $name<T> {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self { $($field: -self.$field),+ }
+            }
+        }
+
+        impl<T: Scalar> AddAssign for $name<T> {
+            fn add_assign(&mut self, rhs: Self) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl<T: Scalar> SubAssign for $name<T> {
+            fn sub_assign(&mut self, rhs: Self) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl<T: Scalar> MulAssign<T> for $name<T> {
+            fn mul_assign(&mut self, rhs: T) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl<T: Scalar> DivAssign<T> for $name<T> {
+            fn div_assign(&mut self, rhs: T) {
+                *self = *self / rhs;
+            }
+        }
+    };
+}
+
+vector! {
+    /// A 2D vector
+    Vec2 { x, y }
+}
+
+vector! {
+    /// A 3D vector
+    Vec3 { x, y, z }
+}
+
+vector! {
+    /// A 4D vector, or a 3D point in homogeneous coordinates
+    Vec4 { x, y, z, w }
+}
+
+impl<T: Scalar> Vec2<T> {
+    /// Z component of the cross product of the vectors extended to 3D.
+    pub fn perp_dot(self, rhs: Self) -> T {
+        self.x * rhs.y - self.y * rhs.x
+    }
+
+    /// Rotate 90 degrees counter-clockwise.
+    pub fn perp(self) -> Self {
+        Self::new(-self.y, self.x)
+    }
+
+    pub fn extend(self, z: T) -> Vec3<T> {
+        Vec3::new(self.x, self.y, z)
+    }
+}
+
+impl<T: Scalar> Vec3<T> {
+    pub fn cross(self, rhs: Self) -> Self {
+        Self::new(
+            self.y * rhs.z - self.z * rhs.y,
+            self.z * rhs.x - self.x * rhs.z,
+            self.x * rhs.y - self.y * rhs.x,
+        )
+    }
+
+    pub fn extend(self, w: T) -> Vec4<T> {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
+
+    pub fn truncate(self) -> Vec2<T> {
+        Vec2::new(self.x, self.y)
+    }
+}
+
+impl<T: Scalar> Vec4<T> {
+    pub fn truncate(self) -> Vec3<T> {
+        Vec3::new(self.x, self.y, self.z)
+    }
+}
+
+/// A 4x4 matrix stored as four column vectors.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Mat4<T = f32> {
+    pub cols: [Vec4<T>; 4],
+}
+
+impl<T: Scalar> Mat4<T> {
+    pub const ZERO: Self = Self::from_cols([Vec4::ZERO; 4]);
+    pub const IDENTITY: Self = Self::from_cols([
+        Vec4::new(T::ONE, T::ZERO, T::ZERO, T::ZERO),
+        Vec4::new(T::ZERO, T::ONE, T::ZERO, T::ZERO),
+        Vec4::new(T::ZERO, T::ZERO, T::ONE, T::ZERO),
+        Vec4::new(T::ZERO, T::ZERO, T::ZERO, T::ONE),
+    ]);
+
+    pub const fn from_cols(cols: [Vec4<T>; 4]) -> Self {
+        Self { cols }
+    }
+
+    pub fn translation(offset: Vec3<T>) -> Self {
+        let mut m = Self::IDENTITY;
+        m.cols[3] = offset.extend(T::ONE);
+        m
+    }
+
+    pub fn scale(scale: Vec3<T>) -> Self {
+        let mut m = Self::IDENTITY;
+        m.cols[0].x = scale.x;
+        m.cols[1].y = scale.y;
+        m.cols[2].z = scale.z;
+        m
+    }
+
+    /// Rotation around the X axis (in radians).
+    pub fn rotation_x(angle: T) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let mut m = Self::IDENTITY;
+        m.cols[1] = Vec4::new(T::ZERO, cos, sin, T::ZERO);
+        m.cols[2] = Vec4::new(T::ZERO, -sin, cos, T::ZERO);
+        m
+    }
+
+    /// Rotation around the Y axis (in radians).
+    pub fn rotation_y(angle: T) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let mut m = Self::IDENTITY;
+        m.cols[0] = Vec4::new(cos, T::ZERO, -sin, T::ZERO);
+        m.cols[2] = Vec4::new(sin, T::ZERO, cos, T::ZERO);
+        m
+    }
+
+    /// Rotation around the Z axis (in radians).
+    pub fn rotation_z(angle: T) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let mut m = Self::IDENTITY;
+        m.cols[0] = Vec4::new(cos, sin, T::ZERO, T::ZERO);
+        m.cols[1] = Vec4::new(-sin, cos, T::ZERO, T::ZERO);
+        m
+    }
+
+    /// Right-handed perspective projection with a vertical field of view (in radians), mapping
+    /// depth to `[-1, 1]` in clip space.
+    pub fn perspective(fov_y: T, aspect: T, near: T, far: T) -> Self {
+        let two = T::ONE + T::ONE;
+        let (sin, cos) = (fov_y / two).sin_cos();
+        let f = cos / sin;
+        let depth = near - far;
+
+        Self::from_cols([
+            Vec4::new(f / aspect, T::ZERO, T::ZERO, T::ZERO),
+            Vec4::new(T::ZERO, f, T::ZERO, T::ZERO),
+            Vec4::new(T::ZERO, T::ZERO, (far + near) / depth, -T::ONE),
+            Vec4::new(T::ZERO, T::ZERO, two * far * near / depth, T::ZERO),
+        ])
+    }
+
+    /// Right-handed orthographic projection, mapping the box to `[-1, 1]` on every axis.
+    pub fn orthographic(left: T, right: T, bottom: T, top: T, near: T, far: T) -> Self {
+        let two = T::ONE + T::ONE;
+        let (width, height, depth) = (right - left, top - bottom, far - near);
+
+        Self::from_cols([
+            Vec4::new(two / width, T::ZERO, T::ZERO, T::ZERO),
+            Vec4::new(T::ZERO, two / height, T::ZERO, T::ZERO),
+            Vec4::new(T::ZERO, T::ZERO, -two / depth, T::ZERO),
+            Vec4::new(
+                -(right + left) / width,
+                -(top + bottom) / height,
+                -(far + near) / depth,
+                T::ONE,
+            ),
+        ])
+    }
+
+    /// Right-handed view matrix for a camera at `eye` looking at `target`.
+    pub fn look_at(eye: Vec3<T>, target: Vec3<T>, up: Vec3<T>) -> Self {
+        let forward = (target - eye).normalize();
+        let side = forward.cross(up).normalize();
+        let up = side.cross(forward);
+
+        Self::from_cols([
+            Vec4::new(side.x, up.x, -forward.x, T::ZERO),
+            Vec4::new(side.y, up.y, -forward.y, T::ZERO),
+            Vec4::new(side.z, up.z, -forward.z, T::ZERO),
+            Vec4::new(-side.dot(eye), -up.dot(eye), forward.dot(eye), T::ONE),
+        ])
+    }
+
+    /// Row `index` as a vector.
+    pub fn row(&self, index: usize) -> Vec4<T> {
+        let [a, b, c, d] = self.cols.map(|col| [col.x, col.y, col.z, col.w][index]);
+        Vec4::new(a, b, c, d)
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self::from_cols([self.row(0), self.row(1), self.row(2), self.row(3)])
+    }
+
+    /// Transform a point, applying translation.
+    pub fn transform_point(&self, point: Vec3<T>) -> Vec3<T> {
+        (*self * point.extend(T::ONE)).truncate()
+    }
+
+    /// Transform a point and divide by `w`, e.g. to project from clip space to normalized device
+    /// coordinates.
+    pub fn project_point(&self, point: Vec3<T>) -> Vec3<T> {
+        let v = *self * point.extend(T::ONE);
+        v.truncate() / v.w
+    }
+
+    /// Transform a direction, ignoring translation.
+    pub fn transform_vector(&self, vector: Vec3<T>) -> Vec3<T> {
+        (*self * vector.extend(T::ZERO)).truncate()
+    }
+}
+
+impl<T: Scalar> Mul<Vec4<T>> for Mat4<T> {
+    type Output = Vec4<T>;
+
+    fn mul(self, rhs: Vec4<T>) -> Vec4<T> {
+        let [a, b, c, d] = self.cols;
+        a * rhs.x + b * rhs.y + c * rhs.z + d * rhs.w
+    }
+}
+
+impl<T: Scalar> Mul for Mat4<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::from_cols(rhs.cols.map(|col| self * col))
+    }
+}
+
+impl<T: Scalar> MulAssign for Mat4<T> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}